@@ -1,94 +1,887 @@
-use clap::{App, Arg};
-use plagiarismbasic_lib::{AppSettings, Metric};
+use clap::{App, AppSettings as ClapSettings, Arg, ArgMatches, SubCommand};
+use plagiarismbasic_lib::{
+    is_zip, AnnotationFormat, AppSettings, ConfidenceRules, Config, FragmentMode, Metric,
+    PipelineConfig, PseudonymConfig, SectionSplitter,
+};
+use std::fs;
+use std::path::Path;
 
-pub fn get_cli_input() -> AppSettings {
-        let app = App::new("Basic Plagiarism Checker")
+/// What the binary was asked to do, along with the settings for it
+pub enum Command {
+    /// Check the untrusted text and print the results
+    Check(AppSettings),
+    /// Read and clean the trusted texts once and save them to an index file
+    IndexBuild {
+        settings: AppSettings,
+        output: String,
+    },
+    /// Check the untrusted text against the trusted texts of an index file
+    IndexQuery {
+        settings: AppSettings,
+        index: String,
+    },
+    /// Fragment the untrusted texts and save only their hashes to a file
+    IndexFingerprint {
+        settings: AppSettings,
+        output: String,
+    },
+    /// Check the untrusted text and write the results as a HTML report
+    ReportHtml {
+        settings: AppSettings,
+        output: String,
+        open_after: bool,
+    },
+    /// Check the untrusted text and write the results as JSON
+    ReportJson {
+        settings: AppSettings,
+        output: String,
+    },
+    /// Check the untrusted text and write the results as JUnit XML
+    ReportJunit {
+        settings: AppSettings,
+        output: String,
+    },
+    /// Compare all untrusted texts with each other and write their similarity matrix as CSV
+    ReportMatrix {
+        settings: AppSettings,
+        output: String,
+    },
+    /// Print the ngrams shared by the most texts, with their owner counts
+    ReportNgrams { settings: AppSettings, top: usize },
+    /// Print the expected comparisons, runtime and memory of the checks
+    ReportCost(AppSettings),
+    /// Print the thresholds suggested from random pairs at a false-positive rate
+    ReportTune {
+        settings: AppSettings,
+        false_positive_rate: f64,
+        samples: usize,
+    },
+    /// Check the untrusted text and print both texts of every result side by side
+    ReportDiff { settings: AppSettings, color: bool },
+    /// Check the untrusted text and write every untrusted text with its matches marked
+    ReportAnnotated {
+        settings: AppSettings,
+        output: String,
+        format: AnnotationFormat,
+    },
+    /// Run the check and output described by a config file
+    Run(Config),
+    /// Serve the HTTP API on an address, preloading the trusted texts
+    #[cfg(feature = "serve")]
+    Serve { settings: AppSettings, addr: String },
+    /// Triage the results in a terminal UI and write the decisions to a file
+    #[cfg(feature = "review")]
+    Review {
+        settings: AppSettings,
+        output: String,
+    },
+    /// Check new and changed files of the untrusted folder as they arrive, appending the results to a file
+    #[cfg(feature = "watch")]
+    Watch {
+        settings: AppSettings,
+        output: String,
+    },
+}
+
+pub fn get_cli_input() -> Command {
+    let app = App::new("Basic Plagiarism Checker")
         .about("Checks for plagiarism using very basic metrics between different text files")
         .author("Sriram Sami (@frizensami on GitHub)")
-        .arg(Arg::with_name("untrusted-directory")
+        .setting(ClapSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("check")
+                .about("Checks an untrusted text against the trusted texts and prints the results")
+                .arg(untrusted_arg())
+                .arg(trusted_arg())
+                .args(&check_args())
+                .args(&severity_args())
+                .args(&cleaning_args()))
+        .subcommand(SubCommand::with_name("index")
+                .about("Builds or queries an index of cleaned trusted texts")
+                .setting(ClapSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("build")
+                        .about("Reads and cleans the trusted texts once and saves them to an index file")
+                        .arg(trusted_arg().required(true))
+                        .arg(output_arg("Sets the index file to write").required(true))
+                        .arg(sensitivity_arg())
+                        .args(&cleaning_args()))
+                .subcommand(SubCommand::with_name("query")
+                        .about("Checks an untrusted text against the trusted texts of an index file and prints the results")
+                        .arg(untrusted_arg())
+                        .arg(Arg::with_name("index")
+                                .long("index")
+                                .help("Sets the index file written by `index build`. Use the same sensitivity and cleaning options it was built with.")
+                                .takes_value(true)
+                                .required(true))
+                        .args(&check_args())
+                        .args(&severity_args())
+                        .args(&cleaning_args()))
+                .subcommand(SubCommand::with_name("fingerprint")
+                        .about("Writes only the hashed fragments of the untrusted texts to a file, to share them as sources without sharing their text")
+                        .arg(untrusted_arg())
+                        .arg(output_arg("Sets the fingerprint file to write").required(true))
+                        .arg(sensitivity_arg())
+                        .args(&cleaning_args())))
+        .subcommand(SubCommand::with_name("report")
+                .about("Checks an untrusted text against the trusted texts and writes a report")
+                .setting(ClapSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("html")
+                        .about("Writes the results as report.html into the output directory")
+                        .arg(untrusted_arg())
+                        .arg(trusted_arg())
+                        .arg(output_arg("Sets the directory to write the HTML report to").default_value("./www/"))
+                        .arg(Arg::with_name("open-html")
+                                .long("open")
+                                .help("If the HTML file should be opened automatically after writing"))
+                        .args(&check_args())
+                        .args(&severity_args())
+                        .args(&cleaning_args()))
+                .subcommand(SubCommand::with_name("json")
+                        .about("Writes the results as a JSON file")
+                        .arg(untrusted_arg())
+                        .arg(trusted_arg())
+                        .arg(output_arg("Sets the JSON file to write").default_value("./report.json"))
+                        .args(&check_args())
+                        .args(&severity_args())
+                        .args(&cleaning_args()))
+                .subcommand(SubCommand::with_name("junit")
+                        .about("Writes the results as a JUnit XML file for CI, with every result as a failed test case")
+                        .arg(untrusted_arg())
+                        .arg(trusted_arg())
+                        .arg(output_arg("Sets the XML file to write").default_value("./report.xml"))
+                        .args(&check_args())
+                        .args(&severity_args())
+                        .args(&cleaning_args()))
+                .subcommand(SubCommand::with_name("matrix")
+                        .about("Writes the pairwise similarity of all untrusted texts as a CSV matrix, e.g. for a heatmap")
+                        .arg(untrusted_arg())
+                        .arg(output_arg("Sets the CSV file to write").default_value("./matrix.csv"))
+                        .args(&check_args())
+                        .args(&cleaning_args()))
+                .subcommand(SubCommand::with_name("ngrams")
+                        .about("Prints the ngrams shared by the most texts, e.g. to find prompt phrases to ignore")
+                        .arg(untrusted_arg())
+                        .arg(trusted_arg())
+                        .arg(Arg::with_name("top")
+                                .long("top")
+                                .help("Sets how many ngrams to print")
+                                .takes_value(true)
+                                .default_value("20"))
+                        .args(&check_args())
+                        .args(&cleaning_args()))
+                .subcommand(SubCommand::with_name("cost")
+                        .about("Prints the expected comparisons, runtime and memory of checking the texts, without running the check")
+                        .arg(untrusted_arg())
+                        .arg(trusted_arg())
+                        .args(&check_args())
+                        .args(&cleaning_args()))
+                .subcommand(SubCommand::with_name("tune")
+                        .about("Prints the similarity threshold and score that random pairs of texts exceed at a false-positive rate, to choose -s and --warn-above")
+                        .arg(untrusted_arg())
+                        .arg(trusted_arg())
+                        .arg(Arg::with_name("false-positive-rate")
+                                .long("false-positive-rate")
+                                .help("Sets the fraction of random pairs allowed to exceed the suggested thresholds")
+                                .takes_value(true)
+                                .default_value("0.01"))
+                        .arg(Arg::with_name("samples")
+                                .long("samples")
+                                .help("Sets how many random pairs of texts to compare")
+                                .takes_value(true)
+                                .default_value("1000"))
+                        .args(&check_args())
+                        .args(&cleaning_args()))
+                .subcommand(SubCommand::with_name("diff")
+                        .about("Prints both texts of every result side by side, with the matches highlighted")
+                        .arg(untrusted_arg())
+                        .arg(trusted_arg())
+                        .arg(Arg::with_name("no-color")
+                                .long("no-color")
+                                .help("Marks matches with [brackets] instead of terminal colors"))
+                        .args(&check_args())
+                        .args(&severity_args())
+                        .args(&cleaning_args()))
+                .subcommand(SubCommand::with_name("annotated")
+                        .about("Writes every untrusted text with [[match:owner:score]] markers around its matches, e.g. to import them into other review tools")
+                        .arg(untrusted_arg())
+                        .arg(trusted_arg())
+                        .arg(output_arg("Sets the directory to write the annotated texts to").default_value("./annotated/"))
+                        .arg(Arg::with_name("html")
+                                .long("html")
+                                .help("Writes HTML pages with <mark> tags around the matches instead of marked plain text"))
+                        .args(&check_args())
+                        .args(&severity_args())
+                        .args(&cleaning_args())))
+        .subcommand(SubCommand::with_name("run")
+                .about("Runs the check and writes the output described by a TOML config file")
+                .arg(Arg::with_name("config")
+                        .short("c")
+                        .long("config")
+                        .help("Sets the config file to use")
+                        .takes_value(true)
+                        .default_value("plagiarism.toml")));
+    #[cfg(feature = "serve")]
+    let app = app.subcommand(
+        SubCommand::with_name("serve")
+            .about("Serves a HTTP API to upload texts, run checks and download the results")
+            .arg(
+                Arg::with_name("address")
+                    .long("address")
+                    .help("Sets the address to listen on")
+                    .takes_value(true)
+                    .default_value("127.0.0.1:8080"),
+            )
+            .arg(trusted_arg())
+            .args(&check_args())
+            .args(&cleaning_args()),
+    );
+    #[cfg(feature = "review")]
+    let app = app.subcommand(
+        SubCommand::with_name("review")
+            .about("Lists the results in a terminal UI to mark them as confirmed or dismissed")
+            .arg(untrusted_arg())
+            .arg(trusted_arg())
+            .arg(
+                output_arg("Sets the JSON file to write the decisions to")
+                    .default_value("./decisions.json"),
+            )
+            .args(&check_args())
+            .args(&cleaning_args()),
+    );
+    #[cfg(feature = "watch")]
+        let app = app.subcommand(SubCommand::with_name("watch")
+                .about("Watches the untrusted folder and checks every new or changed file against the trusted and untrusted texts, appending the results to a JSON Lines file")
+                .arg(untrusted_arg())
+                .arg(trusted_arg())
+                .arg(output_arg("Sets the JSON Lines file to append the results to").default_value("./results.jsonl"))
+                .args(&check_args())
+                .args(&cleaning_args()));
+
+    let matches = app.get_matches();
+    match matches.subcommand() {
+        ("check", Some(matches)) => Command::Check(get_settings(matches)),
+        ("index", Some(index_matches)) => match index_matches.subcommand() {
+            ("build", Some(matches)) => Command::IndexBuild {
+                settings: get_settings(matches),
+                output: get_output(matches),
+            },
+            ("query", Some(matches)) => Command::IndexQuery {
+                settings: get_settings(matches),
+                index: matches
+                    .value_of("index")
+                    .expect("Index file not provided!")
+                    .to_string(),
+            },
+            ("fingerprint", Some(matches)) => Command::IndexFingerprint {
+                settings: get_settings(matches),
+                output: get_output(matches),
+            },
+            _ => panic!("Incorrect index subcommand given!"),
+        },
+        ("report", Some(report_matches)) => match report_matches.subcommand() {
+            ("html", Some(matches)) => Command::ReportHtml {
+                settings: get_settings(matches),
+                output: get_output(matches),
+                open_after: matches.is_present("open-html"),
+            },
+            ("json", Some(matches)) => Command::ReportJson {
+                settings: get_settings(matches),
+                output: get_output(matches),
+            },
+            ("junit", Some(matches)) => Command::ReportJunit {
+                settings: get_settings(matches),
+                output: get_output(matches),
+            },
+            ("matrix", Some(matches)) => Command::ReportMatrix {
+                settings: get_settings(matches),
+                output: get_output(matches),
+            },
+            ("ngrams", Some(matches)) => Command::ReportNgrams {
+                settings: get_settings(matches),
+                top: matches
+                    .value_of("top")
+                    .expect("Ngram count not provided!")
+                    .parse()
+                    .expect("Ngram count provided was not an integer!"),
+            },
+            ("cost", Some(matches)) => Command::ReportCost(get_settings(matches)),
+            ("tune", Some(matches)) => Command::ReportTune {
+                settings: get_settings(matches),
+                false_positive_rate: matches
+                    .value_of("false-positive-rate")
+                    .expect("False-positive rate not provided!")
+                    .parse()
+                    .expect("False-positive rate provided was not a number!"),
+                samples: matches
+                    .value_of("samples")
+                    .expect("Sample count not provided!")
+                    .parse()
+                    .expect("Sample count provided was not an integer!"),
+            },
+            ("diff", Some(matches)) => Command::ReportDiff {
+                settings: get_settings(matches),
+                color: !matches.is_present("no-color"),
+            },
+            ("annotated", Some(matches)) => Command::ReportAnnotated {
+                settings: get_settings(matches),
+                output: get_output(matches),
+                format: if matches.is_present("html") {
+                    AnnotationFormat::Html
+                } else {
+                    AnnotationFormat::Markers
+                },
+            },
+            _ => panic!("Incorrect report subcommand given!"),
+        },
+        #[cfg(feature = "serve")]
+        ("serve", Some(matches)) => Command::Serve {
+            settings: get_settings(matches),
+            addr: matches
+                .value_of("address")
+                .expect("Address not provided!")
+                .to_string(),
+        },
+        #[cfg(feature = "review")]
+        ("review", Some(matches)) => Command::Review {
+            settings: get_settings(matches),
+            output: get_output(matches),
+        },
+        #[cfg(feature = "watch")]
+        ("watch", Some(matches)) => Command::Watch {
+            settings: get_settings(matches),
+            output: get_output(matches),
+        },
+        ("run", Some(matches)) => {
+            let path = matches
+                .value_of("config")
+                .expect("Config file not provided!");
+            Command::Run(Config::from_path(path).unwrap_or_else(|err| {
+                panic!("{} cannot be read as a config file! ({})", path, err)
+            }))
+        }
+        _ => panic!("Incorrect subcommand given!"),
+    }
+}
+
+fn untrusted_arg() -> Arg<'static, 'static> {
+    Arg::with_name("untrusted")
                 .short("u")
                 .long("untrusted")
-                .help("Sets the directory containing untrusted text files. Each file will be treated as a separate submission by a separate person.")
+                .help("Sets the untrusted text file to check, or a directory of untrusted files. Each file in the directory will be treated as a separate submission by a separate person. A ZIP archive (e.g. an LMS export) is read like a directory, with the files in each top-level folder as one submission (needs the `zip` feature).")
                 .takes_value(true)
-                .required(true))
-        .arg(Arg::with_name("trusted-directory")
+                .required_unless("manifest")
+}
+
+fn trusted_arg() -> Arg<'static, 'static> {
+    Arg::with_name("trusted-directory")
                 .short("t")
                 .long("trusted")
                 .help("Sets the directory containing trusted text files. Each file will be treated as a separate possible plagiarism source text.")
-                .takes_value(true))
-        .arg(Arg::with_name("ignore-directory")
-                .short("i")
-                .long("ignore")
-                .help("Sets the directory containing text files with content to be ignored from plagiarism checks.")
-                .takes_value(true))
-        .arg(Arg::with_name("metric")
-                .short("m")
-                .long("metric")
-                .help("Sets the metric (function) used for similarity testing. Equal checks that both strings are equal, and lev uses the Levenshtein distance")
                 .takes_value(true)
-                .required(true)
-                .possible_values(&["equal", "lev"]))
-        .arg(Arg::with_name("sensitivity")
+}
+
+fn output_arg(help: &'static str) -> Arg<'static, 'static> {
+    Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .help(help)
+        .takes_value(true)
+}
+
+fn sensitivity_arg() -> Arg<'static, 'static> {
+    Arg::with_name("sensitivity")
                 .short("n")
                 .long("sensitivity")
-                .help("Sets the number of words required to form a unit of plagiarism checking")
-                .takes_value(true)
-                .required(true))
-        .arg(Arg::with_name("similarity")
-                .short("s")
-                .long("similarity")
-                .help("Sets the threshold value for plagiarism to be detected by a chosen metric")
+                .help("Sets the number of words required to form a unit of plagiarism checking. Several comma-separated sizes (e.g. 3,7) are all compared in one run.")
                 .takes_value(true)
-                .required(true))
-        .arg(Arg::with_name("output-cli")
-                .long("cli")
-                .help("If the results should be printed to the command line"))
-        .arg(Arg::with_name("output-html")
-                .long("html")
-                .help("If the results should be printed to a HTML file"))
-        .arg(Arg::with_name("open-html")
-                .long("openhtml")
-                .help("If the HTML file should be opened automatically after writing")) ;
+                .use_delimiter(true)
+                .required(true)
+}
+
+/// Arguments of every subcommand that runs a check
+fn check_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+                Arg::with_name("manifest")
+                        .long("manifest")
+                        .help("Also adds the texts listed in a manifest file, or on stdin with `-`: a JSON array or JSON Lines of {\"owner_id\", \"trusted\", \"path\" or \"text\"} objects, or TSV lines of owner ID, `trusted` or `untrusted`, and a path or `text:` followed by the text")
+                        .takes_value(true),
+                Arg::with_name("metric")
+                        .short("m")
+                        .long("metric")
+                        .help("Sets the metric (function) used for similarity testing. Equal checks that both strings are equal, lev uses the Levenshtein distance, simhash the number of differing SimHash bits and embedding how many percent the cosine similarity of the averaged word vectors is below 100")
+                        .takes_value(true)
+                        .required(true)
+                        .possible_values(&["equal", "lev", "simhash", "embedding", "lcs"]),
+                Arg::with_name("extra-metric")
+                        .long("extra-metric")
+                        .help("Also runs this metric, with the same similarity threshold, on every pair of texts the main metric reports, to compare e.g. exact with fuzzy matches. Can be given several times.")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .possible_values(&["equal", "lev", "simhash", "embedding", "lcs"]),
+                sensitivity_arg(),
+                Arg::with_name("similarity")
+                        .short("s")
+                        .long("similarity")
+                        .help("Sets the threshold value for plagiarism to be detected by a chosen metric")
+                        .takes_value(true)
+                        .required(true),
+                Arg::with_name("threads")
+                        .long("threads")
+                        .help("Sets the number of threads to split the comparisons over")
+                        .takes_value(true)
+                        .default_value("1"),
+                Arg::with_name("min-matches")
+                        .long("min-matches")
+                        .help("Only reports texts that share at least this many matching fragments")
+                        .takes_value(true)
+                        .default_value("1"),
+                Arg::with_name("min-density")
+                        .long("min-density")
+                        .help("Only reports texts whose matches are at least this clustered (0 to 1) in one of them, rather than scattered")
+                        .takes_value(true)
+                        .default_value("0"),
+                Arg::with_name("idf")
+                        .long("idf")
+                        .help("Weights every match by how rare its fragment is in the corpus, so stock phrases count less"),
+                Arg::with_name("min-weighted-score")
+                        .long("min-weighted-score")
+                        .help("Only reports texts whose IDF weighted coverage is at least this high (0 to 1) in one of them, implies --idf")
+                        .takes_value(true)
+                        .default_value("0"),
+                Arg::with_name("prefilter")
+                        .long("prefilter")
+                        .help("Only compares the texts that this cheap metric finds a match between with the (slower) main metric")
+                        .takes_value(true)
+                        .possible_values(&["equal", "simhash"]),
+                Arg::with_name("prefilter-cutoff")
+                        .long("prefilter-cutoff")
+                        .help("Sets the cutoff of the prefilter metric (bits for simhash)")
+                        .takes_value(true)
+                        .default_value("0"),
+                Arg::with_name("alignments")
+                        .long("alignments")
+                        .help("Adds the word-by-word alignment of every match to the results of non-equal metrics, e.g. for JSON reports"),
+                Arg::with_name("snippets")
+                        .long("snippets")
+                        .help("Adds a snippet of every match in both texts, with this many words before and after it, to the results, so JSON consumers can show previews without the texts")
+                        .takes_value(true),
+                Arg::with_name("chunk-words")
+                        .long("chunk-words")
+                        .help("Compares texts longer than this many words (e.g. books) in chunks of that many words, which are spread over the threads like separate texts. Results show the chunks they matched in.")
+                        .takes_value(true),
+                Arg::with_name("fingerprints")
+                        .long("fingerprints")
+                        .help("Adds the texts of a fingerprint file written by `index fingerprint` as trusted sources. Only the equal metric matches them. Can be given several times.")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                Arg::with_name("pseudonymize")
+                        .long("pseudonymize")
+                        .help("Replaces the untrusted owner IDs in all outputs with codes like S001 (sequential) or keyed hashes like anon-3f9a1c2b (hash), for a blind review")
+                        .takes_value(true)
+                        .possible_values(&["sequential", "hash"]),
+                Arg::with_name("pseudonym-key")
+                        .long("pseudonym-key")
+                        .help("Sets the secret key of the pseudonyms. The same key gives the same hash pseudonyms in every run.")
+                        .takes_value(true)
+                        .requires("pseudonymize"),
+                Arg::with_name("pseudonym-map")
+                        .long("pseudonym-map")
+                        .help("Sets the JSON file to write the mapping from pseudonyms back to the real owner IDs to")
+                        .takes_value(true)
+                        .default_value("./pseudonyms.json"),
+        ]
+}
 
-        // Get options for algorithm
-        let matches = app.get_matches();
-        let n: usize = matches
-                .value_of("sensitivity")
-                .expect("Sensitivity value was not provided!")
-                .parse()
-                .expect("Sensitivity value provided was not a an integer!");
-        let s: usize = matches
-                .value_of("similarity")
-                .expect("Similarity value was not provided!")
-                .parse()
-                .expect("Similarity value provided was not a an integer!");
-        let metricarg: &str = matches.value_of("metric").expect("Metric not provided!");
-        let metric: Metric = match metricarg {
-                "equal" => Metric::Equal,
-                "lev" => Metric::Lev,
-                _ => panic!("Incorrect metric argument given!"),
-        };
+/// Checkpoints and thresholds of the highest score that set the exit code,
+///     for the subcommands that check and report
+fn severity_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+                Arg::with_name("checkpoint")
+                        .long("checkpoint")
+                        .help("Writes the results and the compared texts to this file every --checkpoint-interval seconds, so an interrupted run can be resumed")
+                        .takes_value(true),
+                Arg::with_name("resume")
+                        .long("resume")
+                        .help("Resumes an interrupted run from its checkpoint file, only comparing the texts it hadn't compared yet, and keeps writing checkpoints to it (or to --checkpoint)")
+                        .takes_value(true),
+                Arg::with_name("checkpoint-interval")
+                        .long("checkpoint-interval")
+                        .help("Sets the number of seconds between two checkpoints")
+                        .takes_value(true)
+                        .default_value("60"),
+                Arg::with_name("warn-above")
+                        .long("warn-above")
+                        .help("Exits with code 1 if the highest score (0 to 1) of any result is above this")
+                        .takes_value(true),
+                Arg::with_name("fail-above")
+                        .long("fail-above")
+                        .help("Exits with code 2 if the highest score (0 to 1) of any result is above this")
+                        .takes_value(true),
+        ]
+}
+
+/// Arguments that change how texts are read and cleaned
+fn cleaning_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+                Arg::with_name("ignore-directory")
+                        .short("i")
+                        .long("ignore")
+                        .help("Sets the directory containing text files with content to be ignored from plagiarism checks.")
+                        .takes_value(true),
+                Arg::with_name("prompt")
+                        .long("prompt")
+                        .help("Sets the file with the assignment prompt: its ngrams are ignored like the ignore directory, and every result shows how much of both texts is just the prompt")
+                        .takes_value(true),
+                Arg::with_name("strip-diacritics")
+                        .long("strip-diacritics")
+                        .help("If accented characters should be treated as their base letters (e.g. \"é\" as \"e\")"),
+                Arg::with_name("ignore-quotes")
+                        .long("ignore-quotes")
+                        .help("If text inside double quotation marks should be left out of plagiarism checks"),
+                Arg::with_name("ignore-citations")
+                        .long("ignore-citations")
+                        .help("If citation markers like \"(Smith, 2020)\" or \"[3]\" should be left out of plagiarism checks"),
+                Arg::with_name("exclude-pattern")
+                        .long("exclude-pattern")
+                        .help("Sets a regex whose matches (e.g. \"(?ms)^References$.*\" for a bibliography) are dropped from all texts. Can be given several times.")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                Arg::with_name("preserve-case")
+                        .long("preserve-case")
+                        .help("If words should be compared case-sensitively"),
+                Arg::with_name("strip-numbers")
+                        .long("strip-numbers")
+                        .help("If digits should be removed from the text before comparison"),
+                Arg::with_name("normalize-numbers")
+                        .long("normalize-numbers")
+                        .help("If numbers should be replaced with <NUM>, so that changed values still match"),
+                Arg::with_name("normalize-dates")
+                        .long("normalize-dates")
+                        .help("If dates should be replaced with <DATE>"),
+                Arg::with_name("normalize-entities")
+                        .long("normalize-entities")
+                        .help("If capitalized names inside sentences should be replaced with <ENT>"),
+                Arg::with_name("keep-confusables")
+                        .long("keep-confusables")
+                        .help("If invisible characters and Cyrillic or Greek letters that look like Latin ones should be kept as they are, instead of being dropped or folded"),
+                Arg::with_name("keep-punctuation")
+                        .long("keep-punctuation")
+                        .help("If punctuation inside words (e.g. apostrophes) should be kept for comparison"),
+                Arg::with_name("join-hyphens")
+                        .long("join-hyphens")
+                        .help("If hyphenated words like \"well-known\" should be treated as a single word"),
+                Arg::with_name("stopwords")
+                        .long("stopwords")
+                        .help("Sets a comma-separated list of words to leave out of all texts")
+                        .takes_value(true)
+                        .use_delimiter(true),
+                Arg::with_name("strip-markup")
+                        .long("strip-markup")
+                        .help("If HTML tags and Markdown syntax should be removed from trusted files that look like HTML or Markdown"),
+                Arg::with_name("sentences")
+                        .long("sentences")
+                        .help("Compares whole sentences of at least <sensitivity> words instead of runs of <sensitivity> words"),
+                Arg::with_name("skip")
+                        .long("skip")
+                        .help("Also compares runs of <sensitivity> words that skip up to this many words in between, to catch inserted filler words")
+                        .takes_value(true)
+                        .default_value("0"),
+                Arg::with_name("stride")
+                        .long("stride")
+                        .help("Only makes a run of <sensitivity> words at every this many words, which is faster on large corpora but misses some matches")
+                        .takes_value(true)
+                        .default_value("1"),
+                Arg::with_name("within-sentences")
+                        .long("within-sentences")
+                        .help("Only makes runs of <sensitivity> words within one sentence, not across the end of one"),
+                Arg::with_name("sections")
+                        .long("sections")
+                        .help("Splits every text into sections at lines that look like headings (e.g. \"# Methods\", \"Chapter 3\", \"2.1 Related Work\") and lists the matches in each section"),
+                Arg::with_name("section-pattern")
+                        .long("section-pattern")
+                        .help("Splits every text into sections at every match of this regex instead, e.g. \"(?m)^Chapter \\d+\"")
+                        .takes_value(true),
+                Arg::with_name("code")
+                        .long("code")
+                        .help("Compares the texts as source code of the given language, ignoring comments and renamed identifiers and literals")
+                        .takes_value(true)
+                        .possible_values(&["rust", "python", "c", "cpp", "java", "javascript"]),
+                Arg::with_name("word-vectors")
+                        .long("word-vectors")
+                        .help("Sets the GloVe or fastText word vector file used by the embedding metric")
+                        .takes_value(true)
+                        .required_if("metric", "embedding"),
+                Arg::with_name("synonyms")
+                        .long("synonyms")
+                        .help("Sets a file of comma-separated synonym sets (one per line) whose words are all treated as the first word of their set")
+                        .takes_value(true),
+                Arg::with_name("translations")
+                        .long("translations")
+                        .help("Sets a file of \"word, translation\" pairs (one per line) that translate texts to one language before comparing them, to find translated copies")
+                        .takes_value(true),
+                Arg::with_name("detect-language")
+                        .long("detect-language")
+                        .help("Detects the language of every text and only compares texts in the same language"),
+                Arg::with_name("cjk-dictionary")
+                        .long("cjk-dictionary")
+                        .requires("detect-language")
+                        .help("Sets a word list (one word per line) that splits texts detected to be in Chinese or Japanese into words. Without one, they are split into pairs of characters.")
+                        .takes_value(true),
+                Arg::with_name("stem")
+                        .long("stem")
+                        .requires("detect-language")
+                        .help("Reduces words to their stems with the stemmer of the detected language, e.g. \"connecting\" to \"connect\""),
+        ]
+}
 
-        // Get info from directories
-        let utext: &str = matches
-                .value_of("untrusted-directory")
-                .expect("Untrusted directory not provided!");
-        let tdir: Option<String> = matches.value_of("trusted-directory").map(|x| x.to_string());
-        let idir: Option<String> = matches.value_of("ignore-directory").map(|x| x.to_string());
+fn get_output(matches: &ArgMatches) -> String {
+    matches
+        .value_of("output")
+        .expect("Output path not provided!")
+        .to_string()
+}
+
+/// Reads the settings shared by all subcommands. Arguments a subcommand
+///     doesn't have (e.g. the metric for `index build`) get default values.
+fn get_settings(matches: &ArgMatches) -> AppSettings {
+    // Get options for algorithm
+    let ngram_sizes: Vec<usize> = matches
+        .values_of("sensitivity")
+        .expect("Sensitivity value was not provided!")
+        .map(|x| {
+            x.parse()
+                .expect("Sensitivity value provided was not a an integer!")
+        })
+        .collect();
+    let n: usize = *ngram_sizes
+        .iter()
+        .min()
+        .expect("Sensitivity value was not provided!");
+    let s: usize = matches
+        .value_of("similarity")
+        .unwrap_or("0")
+        .parse()
+        .expect("Similarity value provided was not a an integer!");
+    let metric: Metric = matches
+        .value_of("metric")
+        .unwrap_or("equal")
+        .parse()
+        .unwrap_or_else(|err| panic!("Incorrect metric argument given! ({})", err));
+    let extra_metrics: Vec<Metric> = matches
+        .values_of("extra-metric")
+        .map(|metrics| {
+            metrics
+                .map(|x| {
+                    x.parse()
+                        .unwrap_or_else(|err| panic!("Incorrect metric argument given! ({})", err))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let threads: usize = matches
+        .value_of("threads")
+        .unwrap_or("1")
+        .parse()
+        .expect("Thread count provided was not a an integer!");
+    let min_matches: usize = matches
+        .value_of("min-matches")
+        .unwrap_or("1")
+        .parse()
+        .expect("Minimum match count provided was not an integer!");
+    let min_density: f32 = matches
+        .value_of("min-density")
+        .unwrap_or("0")
+        .parse()
+        .expect("Minimum density provided was not a number!");
+    let idf_weighting = matches.is_present("idf");
+    let min_weighted_score: f32 = matches
+        .value_of("min-weighted-score")
+        .unwrap_or("0")
+        .parse()
+        .expect("Minimum weighted score provided was not a number!");
+    let manifest: Option<String> = matches.value_of("manifest").map(|x| x.to_string());
+    let checkpoint: Option<String> = matches.value_of("checkpoint").map(|x| x.to_string());
+    let resume: Option<String> = matches.value_of("resume").map(|x| x.to_string());
+    let checkpoint_interval: u64 = matches
+        .value_of("checkpoint-interval")
+        .unwrap_or("60")
+        .parse()
+        .expect("Checkpoint interval provided was not an integer!");
+    let warn_above: Option<f32> = matches.value_of("warn-above").map(|x| {
+        x.parse()
+            .expect("Warning threshold provided was not a number!")
+    });
+    let fail_above: Option<f32> = matches.value_of("fail-above").map(|x| {
+        x.parse()
+            .expect("Failure threshold provided was not a number!")
+    });
+    let skip: usize = matches
+        .value_of("skip")
+        .unwrap_or("0")
+        .parse()
+        .expect("Skip count provided was not an integer!");
+    let stride: usize = matches
+        .value_of("stride")
+        .unwrap_or("1")
+        .parse()
+        .expect("Stride provided was not an integer!");
+    let within_sentences = matches.is_present("within-sentences");
 
-        // Get flag options
-        //     let output_cli = matches.is_present("output-cli");
-        //     let output_html = matches.is_present("output-html");
-        //     let open_html_after = matches.is_present("open-html");
-        AppSettings {
-                n,
-                s,
-                metric,
-                utext: utext.to_string(),
-                tdir,
-                idir,
-                // output_cli,
-                // output_html,
-                // open_html_after,
+    // Get info from files and directories
+    let (utext, udir): (String, Option<String>) = match matches.value_of("untrusted") {
+        Some(path) if Path::new(path).is_dir() || is_zip(Path::new(path)) => {
+            (String::new(), Some(path.to_string()))
         }
+        Some(path) => (
+            fs::read_to_string(path).unwrap_or_else(|err| {
+                panic!("{} cannot be read as an UTF-8 file! ({})", path, err)
+            }),
+            None,
+        ),
+        None => (String::new(), None),
+    };
+    let tdir: Option<String> = matches.value_of("trusted-directory").map(|x| x.to_string());
+    let idir: Option<String> = matches.value_of("ignore-directory").map(|x| x.to_string());
+    let prompt: Option<String> = matches.value_of("prompt").map(|x| x.to_string());
+
+    // Get flag options
+    let strip_diacritics = matches.is_present("strip-diacritics");
+    let exclude_quotes = matches.is_present("ignore-quotes");
+    let exclude_citations = matches.is_present("ignore-citations");
+    let exclude_patterns: Vec<String> = matches
+        .values_of("exclude-pattern")
+        .map(|patterns| patterns.map(|x| x.to_string()).collect())
+        .unwrap_or_default();
+    let preserve_case = matches.is_present("preserve-case");
+    let keep_numbers = !matches.is_present("strip-numbers");
+    let normalize_numbers = matches.is_present("normalize-numbers");
+    let normalize_dates = matches.is_present("normalize-dates");
+    let normalize_entities = matches.is_present("normalize-entities");
+    let fold_confusables = !matches.is_present("keep-confusables");
+    let keep_punctuation = matches.is_present("keep-punctuation");
+    let split_hyphens = !matches.is_present("join-hyphens");
+    let stopwords: Vec<String> = matches
+        .values_of("stopwords")
+        .map(|words| words.map(|x| x.to_string()).collect())
+        .unwrap_or_default();
+    let strip_markup = matches.is_present("strip-markup");
+    let detect_language = matches.is_present("detect-language");
+    let stem = matches.is_present("stem");
+    let code_language: Option<String> = matches.value_of("code").map(|x| x.to_string());
+    let word_vectors: Option<String> = matches.value_of("word-vectors").map(|x| x.to_string());
+    let synonyms: Option<String> = matches.value_of("synonyms").map(|x| x.to_string());
+    let translations: Option<String> = matches.value_of("translations").map(|x| x.to_string());
+    let cjk_dictionary: Option<String> = matches.value_of("cjk-dictionary").map(|x| x.to_string());
+    let fingerprints: Vec<String> = matches
+        .values_of("fingerprints")
+        .map(|paths| paths.map(|x| x.to_string()).collect())
+        .unwrap_or_default();
+    let alignments = matches.is_present("alignments");
+    let snippet_words: Option<usize> = matches.value_of("snippets").map(|x| {
+        x.parse()
+            .expect("Snippet word count provided was not an integer!")
+    });
+    let chunk_words: Option<usize> = matches.value_of("chunk-words").map(|x| {
+        x.parse()
+            .expect("Chunk word count provided was not an integer!")
+    });
+    let pipeline: Option<PipelineConfig> =
+        matches
+            .value_of("prefilter")
+            .map(|prefilter| PipelineConfig {
+                prefilter: prefilter
+                    .parse()
+                    .unwrap_or_else(|err| panic!("Incorrect prefilter argument given! ({})", err)),
+                cutoff: matches
+                    .value_of("prefilter-cutoff")
+                    .unwrap_or("0")
+                    .parse()
+                    .expect("Prefilter cutoff provided was not an integer!"),
+                ..PipelineConfig::default()
+            });
+    let pseudonyms: Option<PseudonymConfig> =
+        matches
+            .value_of("pseudonymize")
+            .map(|scheme| PseudonymConfig {
+                scheme: match scheme {
+                    "hash" => plagiarismbasic_lib::PseudonymScheme::Hash,
+                    _ => plagiarismbasic_lib::PseudonymScheme::Sequential,
+                },
+                key: matches
+                    .value_of("pseudonym-key")
+                    .unwrap_or_default()
+                    .to_string(),
+                mapping: matches
+                    .value_of("pseudonym-map")
+                    .expect("Pseudonym mapping file not provided!")
+                    .to_string(),
+            });
+    let sections: Option<SectionSplitter> = match matches.value_of("section-pattern") {
+        Some(pattern) => Some(SectionSplitter::Pattern(pattern.to_string())),
+        None if matches.is_present("sections") => Some(SectionSplitter::Headings),
+        None => None,
+    };
+    let fragment_mode = if matches.is_present("sentences") {
+        FragmentMode::Sentence
+    } else {
+        FragmentMode::Ngram
+    };
+    AppSettings {
+        n,
+        s,
+        metric,
+        extra_metrics,
+        utext,
+        udir,
+        tdir,
+        idir,
+        prompt,
+        strip_diacritics,
+        exclude_quotes,
+        exclude_citations,
+        exclude_patterns,
+        preserve_case,
+        keep_numbers,
+        normalize_numbers,
+        normalize_dates,
+        normalize_entities,
+        fold_confusables,
+        keep_punctuation,
+        split_hyphens,
+        stopwords,
+        strip_markup,
+        code_language,
+        fragment_mode,
+        threads,
+        min_matches,
+        min_density,
+        idf_weighting,
+        min_weighted_score,
+        ngram_sizes,
+        skip,
+        stride,
+        within_sentences,
+        word_vectors,
+        synonyms,
+        translations,
+        cjk_dictionary,
+        fingerprints,
+        alignments,
+        snippet_words,
+        chunk_words,
+        confidence: ConfidenceRules::default(),
+        pipeline,
+        pseudonyms,
+        sections,
+        detect_language,
+        stem,
+        checkpoint,
+        resume,
+        checkpoint_interval,
+        manifest,
+        warn_above,
+        fail_above,
+    }
 }
@@ -1,9 +1,198 @@
 mod cli_input;
-use plagiarismbasic_lib::{run_plagiarism_checks, AppSettings};
+#[cfg(feature = "review")]
+mod review;
+#[cfg(feature = "watch")]
+mod watch;
+use plagiarismbasic_lib::results::Severity;
+use plagiarismbasic_lib::{
+    build_database, print_results, run_trusted_check, write_annotated_texts, write_html_report,
+    write_json_report, write_junit_report, write_similarity_csv, AppSettings, OutputFormat,
+    PlagiarismResult,
+};
 
-use cli_input::get_cli_input;
+use cli_input::{get_cli_input, Command};
 fn main() {
-    // Read settings for algorithm from cli
-    let appsettings: AppSettings = get_cli_input();
-    run_plagiarism_checks(appsettings);
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+    // Read the subcommand and settings for algorithm from cli
+    match get_cli_input() {
+        Command::Check(settings) => {
+            let db = build_database(&settings);
+            let mut results: Vec<PlagiarismResult> = run_trusted_check(&db, &settings);
+            print_results(&mut results);
+            exit_with_severity(&results, &settings);
+        }
+        Command::IndexBuild { settings, output } => {
+            let db = build_database(&settings);
+            db.save_trusted_index(&output)
+                .unwrap_or_else(|err| panic!("Cannot write index {}! ({})", output, err));
+        }
+        Command::IndexQuery { settings, index } => {
+            let mut db = build_database(&settings);
+            db.load_trusted_index(&index)
+                .unwrap_or_else(|err| panic!("Cannot read index {}! ({})", index, err));
+            let mut results: Vec<PlagiarismResult> = run_trusted_check(&db, &settings);
+            print_results(&mut results);
+            exit_with_severity(&results, &settings);
+        }
+        Command::IndexFingerprint { settings, output } => {
+            let db = build_database(&settings);
+            db.save_fingerprints(&output, false)
+                .unwrap_or_else(|err| panic!("Cannot write fingerprints {}! ({})", output, err));
+        }
+        Command::ReportHtml {
+            settings,
+            output,
+            open_after,
+        } => {
+            let db = build_database(&settings);
+            let mut results: Vec<PlagiarismResult> = run_trusted_check(&db, &settings);
+            write_html_report(&mut results, &db, &output, open_after);
+            exit_with_severity(&results, &settings);
+        }
+        Command::ReportJson { settings, output } => {
+            let db = build_database(&settings);
+            let mut results: Vec<PlagiarismResult> = run_trusted_check(&db, &settings);
+            write_json_report(&mut results, &output)
+                .unwrap_or_else(|err| panic!("Cannot write report {}! ({})", output, err));
+            exit_with_severity(&results, &settings);
+        }
+        Command::ReportJunit { settings, output } => {
+            let db = build_database(&settings);
+            let mut results: Vec<PlagiarismResult> = run_trusted_check(&db, &settings);
+            write_junit_report(&mut results, &output)
+                .unwrap_or_else(|err| panic!("Cannot write report {}! ({})", output, err));
+            exit_with_severity(&results, &settings);
+        }
+        Command::ReportMatrix { settings, output } => {
+            let db = build_database(&settings);
+            write_similarity_csv(&db.similarity_matrix(), &output)
+                .unwrap_or_else(|err| panic!("Cannot write matrix {}! ({})", output, err));
+        }
+        Command::ReportNgrams { settings, top } => {
+            let db = build_database(&settings);
+            for shared in db.shared_ngrams(top) {
+                println!("{}\t{}", shared.owners, shared.ngram);
+            }
+        }
+        Command::ReportCost(settings) => {
+            let db = build_database(&settings);
+            let estimate = db.estimate_check_cost();
+            let checks = [
+                ("Trusted", estimate.trusted),
+                ("Untrusted", estimate.untrusted),
+            ];
+            for (check, cost) in checks {
+                println!(
+                    "{} check: {} pairs, {} fragment comparisons, about {:.1}s",
+                    check,
+                    cost.pairs,
+                    cost.fragment_comparisons,
+                    cost.runtime.as_secs_f64()
+                );
+            }
+            println!(
+                "Memory of the texts: about {:.1} MB",
+                estimate.memory_bytes as f64 / 1_000_000.0
+            );
+        }
+        Command::ReportTune {
+            settings,
+            false_positive_rate,
+            samples,
+        } => {
+            let db = build_database(&settings);
+            let suggestion = db.auto_tune(false_positive_rate, samples);
+            println!(
+                "Sampled {} text pairs and {} fragment pairs",
+                suggestion.sampled_pairs, suggestion.sampled_fragments
+            );
+            if let Some(s) = suggestion.s {
+                println!("Suggested similarity threshold (-s): {}", s);
+            }
+            println!(
+                "Suggested score threshold (--warn-above): {:.3}",
+                suggestion.score_threshold
+            );
+        }
+        Command::ReportDiff { settings, color } => {
+            let db = build_database(&settings);
+            let results: Vec<PlagiarismResult> = run_trusted_check(&db, &settings);
+            for result in &results {
+                if color {
+                    println!("{}", result.render_diff_ansi(&db));
+                } else {
+                    println!("{}", result.render_diff(&db));
+                }
+            }
+            exit_with_severity(&results, &settings);
+        }
+        Command::ReportAnnotated {
+            settings,
+            output,
+            format,
+        } => {
+            let db = build_database(&settings);
+            let results = run_trusted_check(&db, &settings);
+            write_annotated_texts(&results, &db, &settings, &output, format).unwrap_or_else(
+                |err| panic!("Cannot write annotated texts to {}! ({})", output, err),
+            );
+            exit_with_severity(&results, &settings);
+        }
+        Command::Run(config) => {
+            let settings = config
+                .app_settings()
+                .unwrap_or_else(|err| panic!("Cannot read {}! ({})", config.untrusted, err));
+            let db = build_database(&settings);
+            let mut results: Vec<PlagiarismResult> = run_trusted_check(&db, &settings);
+            let output = config.output.path();
+            match config.output.format {
+                OutputFormat::Cli => print_results(&mut results),
+                OutputFormat::Html => {
+                    write_html_report(&mut results, &db, output, config.output.open)
+                }
+                OutputFormat::Json => write_json_report(&mut results, output)
+                    .unwrap_or_else(|err| panic!("Cannot write report {}! ({})", output, err)),
+                OutputFormat::Junit => write_junit_report(&mut results, output)
+                    .unwrap_or_else(|err| panic!("Cannot write report {}! ({})", output, err)),
+            }
+            exit_with_severity(&results, &settings);
+        }
+        #[cfg(feature = "serve")]
+        Command::Serve { settings, addr } => {
+            let addr = addr
+                .parse()
+                .unwrap_or_else(|err| panic!("{} is not a valid address! ({})", addr, err));
+            plagiarismbasic_lib::serve(build_database(&settings), addr)
+                .unwrap_or_else(|err| panic!("Cannot serve on {}! ({})", addr, err));
+        }
+        #[cfg(feature = "review")]
+        Command::Review { settings, output } => {
+            let db = build_database(&settings);
+            review::review(&db, run_trusted_check(&db, &settings), &output)
+                .unwrap_or_else(|err| panic!("Cannot review the results! ({})", err));
+        }
+        #[cfg(feature = "watch")]
+        Command::Watch { settings, output } => {
+            watch::watch(&settings, &output).unwrap_or_else(|err| {
+                panic!(
+                    "Cannot watch {}! ({})",
+                    settings.udir.unwrap_or_default(),
+                    err
+                )
+            })
+        }
+    }
+}
+
+/// Exits with code 1 or 2 if the highest score of the results is above
+///     `--warn-above` or `--fail-above`
+fn exit_with_severity(results: &[PlagiarismResult], settings: &AppSettings) {
+    let severity = Severity::of(results, settings.warn_above, settings.fail_above);
+    if severity != Severity::Pass {
+        std::process::exit(severity.exit_code());
+    }
 }
@@ -0,0 +1,214 @@
+use plagiarismbasic_lib::results::{write_decisions, Decision, Verdict};
+use plagiarismbasic_lib::{sort_results, PlagiarismDatabase, PlagiarismResult};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashSet;
+use std::io;
+
+const HELP: &str =
+        " up/down: select  c: confirm  d: dismiss  u: undo  pgup/pgdn: scroll  e: export  q: export and quit ";
+
+/// Results being triaged, with the verdict given to each so far
+struct Review<'a> {
+    db: &'a PlagiarismDatabase,
+    results: Vec<PlagiarismResult>,
+    verdicts: Vec<Option<Verdict>>,
+    list: ListState,
+    /// Lines scrolled down in both texts
+    scroll: u16,
+    output: &'a str,
+    status: String,
+}
+
+/// Lists the results most significant first, shows the texts of the selected
+///     one side by side with the matches highlighted, and lets the reviewer
+///     mark each as confirmed or dismissed. The decisions are written to
+///     `output` as JSON on export and on quitting.
+pub fn review(
+    db: &PlagiarismDatabase,
+    mut results: Vec<PlagiarismResult>,
+    output: &str,
+) -> io::Result<()> {
+    sort_results(&mut results);
+    let mut list = ListState::default();
+    if !results.is_empty() {
+        list.select(Some(0));
+    }
+    let mut review = Review {
+        db,
+        verdicts: vec![None; results.len()],
+        results,
+        list,
+        scroll: 0,
+        output,
+        status: String::new(),
+    };
+    let mut terminal = ratatui::init();
+    let outcome = review.run(&mut terminal);
+    ratatui::restore();
+    outcome?;
+    review.export()
+}
+
+impl Review<'_> {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.list.select_previous();
+                    self.scroll = 0;
+                }
+                KeyCode::Char('c') => self.decide(Some(Verdict::Confirmed)),
+                KeyCode::Char('d') => self.decide(Some(Verdict::Dismissed)),
+                KeyCode::Char('u') => self.decide(None),
+                KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+                KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+                KeyCode::Char('e') => {
+                    self.status = match self.export() {
+                        Ok(()) => format!("Exported decisions to {}", self.output),
+                        Err(err) => format!("Cannot export to {}! ({})", self.output, err),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn select_next(&mut self) {
+        if let Some(selected) = self.list.selected() {
+            self.list.select(Some(
+                (selected + 1).min(self.results.len().saturating_sub(1)),
+            ));
+            self.scroll = 0;
+        }
+    }
+
+    /// Gives the selected result a verdict (or takes it back) and moves on
+    fn decide(&mut self, verdict: Option<Verdict>) {
+        if let Some(selected) = self.list.selected() {
+            self.verdicts[selected] = verdict;
+            if verdict.is_some() {
+                self.select_next();
+            }
+        }
+    }
+
+    fn export(&self) -> io::Result<()> {
+        let decisions: Vec<Decision> = self
+            .results
+            .iter()
+            .zip(&self.verdicts)
+            .filter_map(|(result, verdict)| Some(Decision::new(result, (*verdict)?)))
+            .collect();
+        write_decisions(self.output, &decisions).map_err(|err| io::Error::other(err.to_string()))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(30),
+                Constraint::Min(5),
+                Constraint::Length(1),
+            ])
+            .split(frame.area());
+        let items: Vec<ListItem> = self
+            .results
+            .iter()
+            .zip(&self.verdicts)
+            .map(|(result, verdict)| {
+                let (mark, style) = match verdict {
+                    Some(Verdict::Confirmed) => ("[x]", Style::default().fg(Color::Red)),
+                    Some(Verdict::Dismissed) => ("[-]", Style::default().fg(Color::DarkGray)),
+                    None => ("[ ]", Style::default()),
+                };
+                let score = result.coverage_owner1.max(result.coverage_owner2) * 100.0;
+                ListItem::new(format!(
+                    "{} {:>5.1}% {:>4} fragments  {} <-> {} (n = {})",
+                    mark,
+                    score,
+                    result.matching_fragments.len(),
+                    result.owner_id1,
+                    result.owner_id2,
+                    result.n
+                ))
+                .style(style)
+            })
+            .collect();
+        let title = format!(" {} results ", self.results.len());
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, rows[0], &mut self.list);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(rows[1]);
+        if let Some(result) = self
+            .list
+            .selected()
+            .and_then(|selected| self.results.get(selected))
+        {
+            let (matched1, matched2) = result.matched_word_indices();
+            let words1 = self
+                .db
+                .get_clean_words(&result.owner_id1, result.trusted_owner1);
+            let words2 = self
+                .db
+                .get_clean_words(&result.owner_id2, result.trusted_owner2);
+            self.draw_text(frame, columns[0], &result.owner_id1, words1, &matched1);
+            self.draw_text(frame, columns[1], &result.owner_id2, words2, &matched2);
+        }
+        let footer = if self.status.is_empty() {
+            HELP
+        } else {
+            self.status.as_str()
+        };
+        frame.render_widget(Paragraph::new(footer), rows[2]);
+    }
+
+    /// Draws the cleaned words of a text, with the matched ones highlighted
+    fn draw_text(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        owner: &str,
+        words: &[String],
+        matched: &HashSet<usize>,
+    ) {
+        let highlight = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+        let spans: Vec<Span> = words
+            .iter()
+            .enumerate()
+            .map(|(idx, word)| {
+                let text = format!("{} ", word);
+                if matched.contains(&idx) {
+                    Span::styled(text, highlight)
+                } else {
+                    Span::raw(text)
+                }
+            })
+            .collect();
+        let text = Paragraph::new(Line::from(spans))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" {} ", owner)),
+            )
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(text, area);
+    }
+}
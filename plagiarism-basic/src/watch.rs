@@ -0,0 +1,98 @@
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecursiveMode, Watcher};
+use plagiarismbasic_lib::results::append_json_lines;
+use plagiarismbasic_lib::{build_database, AppSettings, TextOwnerID};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How long to wait for more changes before checking, since editors and
+///     uploads often write a file in several steps
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Checks the texts of the untrusted folder once, then watches the folder
+///     and (re-)adds every new or changed file as an untrusted text, checking
+///     only it against the trusted texts and all other untrusted texts. Every
+///     result is appended to `output` as one line of JSON (see
+///     `results::append_json_lines`). Runs until the watcher fails.
+pub fn watch(settings: &AppSettings, output: &str) -> io::Result<()> {
+    let udir = settings.udir.as_deref().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "watch needs an untrusted directory (-u)",
+        )
+    })?;
+    let mut db = build_database(settings);
+    let mut results = db.check_trusted_plagiarism();
+    results.extend(db.check_untrusted_plagiarism());
+    append_json_lines(output, &results).map_err(io::Error::other)?;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+    watcher
+        .watch(Path::new(udir), RecursiveMode::NonRecursive)
+        .map_err(watch_error)?;
+    eprintln!("Watching {} for new submissions...", udir);
+    loop {
+        let mut changed: BTreeSet<PathBuf> = BTreeSet::new();
+        // Block for the first change, then collect until no more arrive
+        let mut event = receiver.recv().map_err(watch_error)?;
+        loop {
+            changed.extend(changed_files(event.map_err(watch_error)?));
+            event = match receiver.recv_timeout(SETTLE_TIME) {
+                Ok(event) => event,
+                Err(_) => break,
+            };
+        }
+        let mut owners: Vec<TextOwnerID> = Vec::new();
+        for path in changed {
+            match db.add_untrusted_file(&path) {
+                Ok(()) => owners.push(file_owner(&path)),
+                Err(err) => eprintln!("Skipping {}! ({})", path.display(), err),
+            }
+        }
+        if owners.is_empty() {
+            continue;
+        }
+        let results = db.check_new_untrusted(&owners).map_err(io::Error::other)?;
+        append_json_lines(output, &results).map_err(io::Error::other)?;
+        eprintln!(
+            "Checked {}: {} result(s) appended to {}",
+            owners.join(", "),
+            results.len(),
+            output
+        );
+    }
+}
+
+/// Regular, non-hidden files created or written to by an event
+fn changed_files(event: notify::Event) -> Vec<PathBuf> {
+    match event.kind {
+        EventKind::Create(_)
+        | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any) => event
+            .paths
+            .into_iter()
+            .filter(|path| path.is_file() && !is_hidden(path))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Editors and uploads keep temporary files like `.essay.txt.swp`
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Owner ID of an untrusted file, its file name like `add_untrusted_file`
+fn file_owner(path: &Path) -> TextOwnerID {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn watch_error<E: std::fmt::Display>(err: E) -> io::Error {
+    io::Error::other(format!("Cannot watch for changes: {}", err))
+}
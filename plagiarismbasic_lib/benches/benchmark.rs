@@ -1,5 +1,8 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use plagiarismbasic_lib::{run_plagiarism_checks, AppSettings, Metric};
+use plagiarismbasic_lib::{
+    run_plagiarism_checks, AppSettings, ConfidenceRules, FragmentMode, Metric,
+};
+use std::fs;
 use std::time::Duration;
 
 pub fn criterion_benchmark(c: &mut Criterion) {
@@ -8,20 +11,67 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     group.measurement_time(Duration::from_secs(60));
     group.sample_size(10);
 
-    let settings = AppSettings {
+    let utext = fs::read_to_string("testfiles/cs-corpus/ut/g0pA_taska.txt")
+        .expect("Benchmark untrusted text could not be read!");
+    let settings = || AppSettings {
         n: 10,
         s: 0,
         metric: Metric::Equal,
+        extra_metrics: Vec::new(),
         tdir: Some("testfiles/cs-corpus/t".to_string()),
-        udir: "testfiles/cs-corpus/ut".to_string(),
+        utext: utext.clone(),
+        udir: None,
         idir: None,
-        output_cli: false,
-        output_html: true,
-        open_html_after: false,
+        prompt: None,
+        strip_diacritics: false,
+        exclude_quotes: false,
+        exclude_citations: false,
+        exclude_patterns: Vec::new(),
+        preserve_case: false,
+        keep_numbers: true,
+        normalize_numbers: false,
+        normalize_dates: false,
+        normalize_entities: false,
+        fold_confusables: true,
+        keep_punctuation: false,
+        split_hyphens: true,
+        stopwords: Vec::new(),
+        strip_markup: false,
+        code_language: None,
+        fragment_mode: FragmentMode::Ngram,
+        threads: 1,
+        min_matches: 1,
+        ngram_sizes: Vec::new(),
+        skip: 0,
+        stride: 1,
+        within_sentences: false,
+        fingerprints: Vec::new(),
+        word_vectors: None,
+        synonyms: None,
+        translations: None,
+        cjk_dictionary: None,
+        alignments: false,
+        snippet_words: None,
+        chunk_words: None,
+        min_density: 0.0,
+        idf_weighting: false,
+        min_weighted_score: 0.0,
+        confidence: ConfidenceRules::default(),
+        pseudonyms: None,
+        sections: None,
+        pipeline: None,
+        detect_language: false,
+        stem: false,
+        checkpoint: None,
+        resume: None,
+        checkpoint_interval: 60,
+        manifest: None,
+        warn_above: None,
+        fail_above: None,
     };
     // Long running function
-    group.bench_function("run_plag nocli + html + no-open-html", |b| {
-        b.iter(|| run_plagiarism_checks(black_box(&settings)))
+    group.bench_function("run_plag trusted", |b| {
+        b.iter(|| run_plagiarism_checks(black_box(settings())))
     });
 
     group.finish();
@@ -0,0 +1,109 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use plagiarismbasic_lib::corpus::{synthetic, SyntheticCorpus};
+use plagiarismbasic_lib::{
+    levenshtein_batch, tokenize_text, CleanOptions, Metric, PlagiarismDatabase,
+};
+
+/// Corpus sizes (texts per side) and overlap rate of the synthetic corpora
+const SIZES: [usize; 2] = [10, 40];
+const WORDS_PER_TEXT: usize = 300;
+const OVERLAP: f64 = 0.3;
+
+fn corpus(texts: usize) -> SyntheticCorpus {
+    synthetic(texts, WORDS_PER_TEXT, OVERLAP, 42)
+}
+
+fn database(corpus: &SyntheticCorpus, n: usize, s: usize, metric: Metric) -> PlagiarismDatabase {
+    let mut db = PlagiarismDatabase::new(n, s, metric, Vec::new());
+    corpus.add_to(&mut db);
+    db
+}
+
+/// Trusted checks with each metric
+pub fn metric_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("metrics");
+    group.sample_size(10);
+    for texts in SIZES {
+        let corpus = corpus(texts);
+        for (name, n, s, metric) in [
+            ("equal", 8, 0, Metric::Equal),
+            ("lev", 8, 2, Metric::Lev),
+            ("simhash", 8, 10, Metric::SimHash),
+        ] {
+            let db = database(&corpus, n, s, metric);
+            group.bench_with_input(BenchmarkId::new(name, texts), &db, |b, db| {
+                b.iter(|| db.check_trusted_plagiarism())
+            });
+        }
+    }
+    group.finish();
+}
+
+/// The stages of a run: cleaning, building the database, and the checks
+pub fn pipeline_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pipeline");
+    group.sample_size(10);
+    for texts in SIZES {
+        let corpus = corpus(texts);
+        let options = CleanOptions::default();
+        group.bench_with_input(BenchmarkId::new("tokenize", texts), &corpus, |b, corpus| {
+            b.iter(|| {
+                for (_, text) in &corpus.untrusted {
+                    black_box(tokenize_text(text, &options));
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("build", texts), &corpus, |b, corpus| {
+            b.iter(|| database(corpus, 8, 0, Metric::Equal))
+        });
+        let db = database(&corpus, 8, 0, Metric::Equal);
+        group.bench_with_input(BenchmarkId::new("duplicates", texts), &db, |b, db| {
+            b.iter(|| db.check_exact_duplicates())
+        });
+        group.bench_with_input(BenchmarkId::new("lsh", texts), &db, |b, db| {
+            b.iter(|| db.check_with_lsh(16, 4))
+        });
+    }
+    group.finish();
+}
+
+/// One fragment against many with the scalar Levenshtein distance and the
+///     batched bit-parallel one
+pub fn levenshtein_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("levenshtein");
+    let corpus = corpus(SIZES[0]);
+    for n in [4, 8, 16] {
+        let fragments: Vec<String> = corpus
+            .untrusted
+            .iter()
+            .flat_map(|(_, text)| {
+                let words: Vec<&str> = text.split_whitespace().collect();
+                words
+                    .chunks(n)
+                    .map(|chunk| chunk.join(" "))
+                    .collect::<Vec<String>>()
+            })
+            .collect();
+        let source = &fragments[0];
+        group.bench_with_input(BenchmarkId::new("scalar", n), &fragments, |b, fragments| {
+            b.iter(|| {
+                fragments
+                    .iter()
+                    .map(|target| strsim::levenshtein(source, target))
+                    .collect::<Vec<usize>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("batch", n), &fragments, |b, fragments| {
+            b.iter(|| levenshtein_batch(source, fragments))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    metric_benchmarks,
+    pipeline_benchmarks,
+    levenshtein_benchmarks
+);
+criterion_main!(benches);
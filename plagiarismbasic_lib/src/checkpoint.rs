@@ -0,0 +1,149 @@
+use crate::error::{Error, Result};
+use crate::plagiarism_database::{
+    Fragmentation, PlagiarismDatabase, PlagiarismResult, TextOwnerID,
+};
+use crate::Metric;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Owner pairs compared per thread between two checks of the checkpoint
+///     interval
+const PAIRS_PER_THREAD: usize = 16;
+
+/// Progress of a trusted check saved by `check_with_checkpoint`, so that an
+///     interrupted run can be resumed instead of starting over
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// Settings of the run, which a resumed run must share
+    pub fragmentation: Fragmentation,
+    pub metric: Metric,
+    pub s: usize,
+    /// (trusted owner, untrusted owner) pairs compared so far
+    pub compared: BTreeSet<(TextOwnerID, TextOwnerID)>,
+    /// Results of the compared pairs
+    pub results: Vec<PlagiarismResult>,
+    /// If all owner pairs of the run were compared
+    pub complete: bool,
+}
+
+impl Checkpoint {
+    /// Reads a checkpoint written by `check_with_checkpoint`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Checkpoint> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    /// Writes the checkpoint to a temporary file first and then renames it,
+    ///     so that an interruption never leaves a half-written checkpoint
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&partial, path)?;
+        Ok(())
+    }
+}
+
+impl PlagiarismDatabase {
+    /// Same as `check_trusted_plagiarism`, but writes the results and the
+    ///     owner pairs compared so far to a checkpoint file about every
+    ///     `interval` (and when done). Resuming from a checkpoint of an
+    ///     interrupted run only compares the pairs it doesn't have yet. The
+    ///     database must have the same settings as the resumed run, and
+    ///     texts added since are compared too.
+    pub fn check_with_checkpoint<P: AsRef<Path>>(
+        &self,
+        path: P,
+        interval: Duration,
+        resume: Option<Checkpoint>,
+    ) -> Result<Vec<PlagiarismResult>> {
+        let (metric, s) = self.metric_settings();
+        let mut checkpoint = match resume {
+            Some(checkpoint) => {
+                self.check_fragmentation("Checkpoint was", &checkpoint.fragmentation)?;
+                if (checkpoint.metric, checkpoint.s) != (metric, s) {
+                    return Err(Error::IncompatibleFragments(format!(
+                        "Checkpoint was made with the {:?} metric and s = {}, but the database uses {:?} and s = {}",
+                        checkpoint.metric, checkpoint.s, metric, s
+                    )));
+                }
+                checkpoint
+            }
+            None => Checkpoint {
+                fragmentation: self.fragmentation(),
+                metric,
+                s,
+                compared: BTreeSet::new(),
+                results: Vec::new(),
+                complete: false,
+            },
+        };
+        let mut pairs = self.trusted_owner_pairs();
+        pairs.retain(|pair| !checkpoint.compared.contains(pair));
+        let mut last_saved = Instant::now();
+        for batch in pairs.chunks(self.thread_count() * PAIRS_PER_THREAD) {
+            checkpoint.results.extend(self.check_trusted_pairs(batch));
+            checkpoint.compared.extend(batch.iter().cloned());
+            if last_saved.elapsed() >= interval {
+                checkpoint.write(path.as_ref())?;
+                last_saved = Instant::now();
+            }
+        }
+        checkpoint.complete = true;
+        checkpoint.write(path.as_ref())?;
+        // Same order as `check_trusted_plagiarism`, also for resumed results
+        let mut results = checkpoint.results;
+        results.sort_by(|a, b| (&a.owner_id1, &a.owner_id2).cmp(&(&b.owner_id1, &b.owner_id2)));
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_with_checkpoint() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("book", "the mitochondria is the powerhouse of the cell");
+        db.add_trusted_text("web", "as we all know the water cycle never stops");
+        db.add_untrusted_text("alice", "we know the powerhouse of the cell well");
+        db.add_untrusted_text("bob", "the water cycle never stops they say");
+        let expected = db.check_trusted_plagiarism();
+        let path = std::env::temp_dir().join("plagiarismbasic_test_checkpoint.json");
+
+        // An interrupted run that only got to compare the first pair
+        let first = ("book".to_string(), "alice".to_string());
+        let interrupted = Checkpoint {
+            fragmentation: db.fragmentation(),
+            metric: Metric::Equal,
+            s: 0,
+            compared: BTreeSet::from([first.clone()]),
+            results: db.check_trusted_pairs(&[first]),
+            complete: false,
+        };
+        interrupted.write(&path).unwrap();
+        let resumed = Checkpoint::from_path(&path).unwrap();
+        let results = db
+            .check_with_checkpoint(&path, Duration::ZERO, Some(resumed))
+            .unwrap();
+        assert_eq!(results, expected);
+        let done = Checkpoint::from_path(&path).unwrap();
+        assert!(done.complete);
+        assert_eq!(done.compared.len(), 4);
+
+        let other = PlagiarismDatabase::new(4, 0, Metric::Equal, Vec::new());
+        assert!(matches!(
+            other.check_with_checkpoint(&path, Duration::ZERO, Some(done)),
+            Err(Error::IncompatibleFragments(_))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+}
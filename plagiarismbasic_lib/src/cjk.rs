@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Splits runs of Chinese and Japanese characters, which aren't separated by
+///     spaces, into words. Runs are split into the longest words of a
+///     dictionary from left to right, and the characters no dictionary word
+///     starts at into overlapping pairs (character bigrams), which is all an
+///     empty dictionary does. Used for texts detected to be in Chinese or
+///     Japanese, see `cjk_dictionary` on the builder.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CjkSegmenter {
+    words: HashSet<String>,
+    /// Characters of the longest word
+    longest: usize,
+}
+
+impl CjkSegmenter {
+    /// Builds the dictionary from a list of words
+    pub fn from_words<I: IntoIterator<Item = String>>(words: I) -> CjkSegmenter {
+        let words: HashSet<String> = words
+            .into_iter()
+            .map(|word| word.trim().to_string())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let longest = words
+            .iter()
+            .map(|word| word.chars().count())
+            .max()
+            .unwrap_or(0);
+        CjkSegmenter { words, longest }
+    }
+
+    /// Reads one word per line. Anything after the first whitespace of a
+    ///     line (like the frequencies of jieba or MeCab dictionaries) is
+    ///     ignored, as are empty lines and lines starting with '#'.
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<CjkSegmenter> {
+        let mut words: Vec<String> = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().starts_with('#') {
+                continue;
+            }
+            if let Some(word) = line.split_whitespace().next() {
+                words.push(word.to_string());
+            }
+        }
+        Ok(CjkSegmenter::from_words(words))
+    }
+
+    /// Reads a dictionary file, see `from_reader`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<CjkSegmenter> {
+        CjkSegmenter::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Splits a run of CJK characters into words, with the byte offset of
+    ///     every word in the run. Bigrams of unknown characters overlap.
+    pub fn segment<'a>(&self, run: &'a str) -> Vec<(usize, &'a str)> {
+        let offsets: Vec<usize> = run
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(Some(run.len()))
+            .collect();
+        let chars = offsets.len() - 1;
+        let mut words: Vec<(usize, &str)> = Vec::new();
+        // First character not in a dictionary word since the last one
+        let mut unknown_start = 0;
+        let mut idx = 0;
+        while idx < chars {
+            let known = (1..=self.longest.min(chars - idx)).rev().find(|&length| {
+                self.words
+                    .contains(&run[offsets[idx]..offsets[idx + length]])
+            });
+            match known {
+                Some(length) => {
+                    push_bigrams(run, &offsets[unknown_start..=idx], &mut words);
+                    words.push((offsets[idx], &run[offsets[idx]..offsets[idx + length]]));
+                    idx += length;
+                    unknown_start = idx;
+                }
+                None => idx += 1,
+            }
+        }
+        push_bigrams(run, &offsets[unknown_start..=chars], &mut words);
+        words
+    }
+}
+
+/// Character bigrams of a part of a run given by its character offsets, or
+///     the single character of a part that short
+fn push_bigrams<'a>(run: &'a str, offsets: &[usize], words: &mut Vec<(usize, &'a str)>) {
+    match offsets.len() {
+        0 | 1 => {}
+        2 => words.push((offsets[0], &run[offsets[0]..offsets[1]])),
+        _ => words.extend(
+            offsets
+                .windows(3)
+                .map(|window| (window[0], &run[window[0]..window[2]])),
+        ),
+    }
+}
+
+/// Checks for Han, Hiragana and Katakana characters
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3005}' | '\u{3007}' | '\u{3040}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}'
+        | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF66}'..='\u{FF9F}' | '\u{20000}'..='\u{2FA1F}')
+}
+
+/// Checks if texts in a language (ISO 639-3 code) are written without spaces
+///     between words, in CJK characters
+pub(crate) fn is_cjk_language(language: &str) -> bool {
+    matches!(language, "cmn" | "jpn")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_cjk_segmenter() {
+        let bigrams = CjkSegmenter::default();
+        let words: Vec<&str> = bigrams
+            .segment("我爱北京")
+            .into_iter()
+            .map(|(_, word)| word)
+            .collect();
+        assert_eq!(words, vec!["我爱", "爱北", "北京"]);
+        assert_eq!(bigrams.segment("我"), vec![(0, "我")]);
+
+        let dictionary =
+            CjkSegmenter::from_reader("# words\n北京 100 ns\n天安门\n".as_bytes()).unwrap();
+        assert_eq!(
+            dictionary.segment("我爱北京天安门"),
+            vec![(0, "我爱"), (6, "北京"), (12, "天安门")]
+        );
+
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .detect_language(true)
+            .build();
+        db.add_trusted_text(
+            "source",
+            "水的循环从来不会停止，太阳把海洋里的水变成水蒸气。",
+        );
+        db.add_untrusted_text("essay", "我们都知道水的循环从来不会停止。");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(db.get_language("essay"), Some("cmn"));
+        assert_eq!(results.len(), 1);
+        // "水的循环从来不会停止" is 9 overlapping bigrams
+        assert_eq!(results[0].matching_fragments.len(), 7);
+    }
+}
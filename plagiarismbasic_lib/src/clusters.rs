@@ -0,0 +1,102 @@
+use crate::plagiarism_database::{PlagiarismResult, TextOwnerID};
+use std::collections::HashMap;
+
+/// Groups untrusted owners that matched each other into clusters, so that a
+///     group who all copied from each other shows up once instead of as many
+///     pairs. Owners are linked by every untrusted-vs-untrusted result with at
+///     least `min_fragments` matching fragments; results against trusted
+///     sources are ignored, as they would join everyone who used the same source.
+///     Each cluster is sorted, and clusters are sorted by decreasing size.
+pub fn cluster_results(
+    results: &[PlagiarismResult],
+    min_fragments: usize,
+) -> Vec<Vec<TextOwnerID>> {
+    let mut owner_indices: HashMap<&str, usize> = HashMap::new();
+    let mut owners: Vec<&str> = Vec::new();
+    // Union-find forest over the owner indices
+    let mut parents: Vec<usize> = Vec::new();
+    for result in results {
+        let has_trusted = result.trusted_owner1 || result.trusted_owner2;
+        if has_trusted || result.matching_fragments.len() < min_fragments {
+            continue;
+        }
+        let mut roots = [0; 2];
+        for (root, owner) in roots.iter_mut().zip([&result.owner_id1, &result.owner_id2]) {
+            let idx = *owner_indices.entry(owner).or_insert_with(|| {
+                owners.push(owner);
+                parents.push(parents.len());
+                parents.len() - 1
+            });
+            *root = find_root(&mut parents, idx);
+        }
+        parents[roots[0].max(roots[1])] = roots[0].min(roots[1]);
+    }
+
+    let mut clusters: HashMap<usize, Vec<TextOwnerID>> = HashMap::new();
+    for (idx, owner) in owners.iter().enumerate() {
+        let root = find_root(&mut parents, idx);
+        clusters.entry(root).or_default().push(owner.to_string());
+    }
+    let mut clusters: Vec<Vec<TextOwnerID>> = clusters.into_values().collect();
+    for cluster in &mut clusters {
+        cluster.sort();
+    }
+    clusters.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    clusters
+}
+
+/// Finds the root of an index, shortening the path on the way
+fn find_root(parents: &mut [usize], idx: usize) -> usize {
+    let mut root = idx;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut current = idx;
+    while parents[current] != root {
+        let next = parents[current];
+        parents[current] = root;
+        current = next;
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_cluster_results() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        // Matched by a, b and c, which must not join them
+        db.add_trusted_text(
+            "source",
+            "we know the mitochondria is the powerhouse of the cell and the moon landing was faked",
+        );
+        db.add_untrusted_text("a", "we know the mitochondria is the powerhouse");
+        db.add_untrusted_text(
+            "b",
+            "the powerhouse of the cell and the moon landing was faked",
+        );
+        db.add_untrusted_text("c", "everyone says the moon landing was faked today");
+        db.add_untrusted_text("d", "roses are red and violets are blue");
+        db.add_untrusted_text("e", "roses are red and the sky is grey");
+        db.add_untrusted_text("f", "nothing in common with anything else here");
+        let results = db.check_with_lsh(32, 1);
+        assert!(results
+            .iter()
+            .any(|result| result.trusted_owner1 && result.owner_id2 == "a"));
+        assert_eq!(
+            cluster_results(&results, 1),
+            vec![
+                vec!["b".to_string(), "c".to_string()],
+                vec!["d".to_string(), "e".to_string()],
+            ]
+        );
+        // "d" and "e" only share two fragments
+        assert_eq!(
+            cluster_results(&results, 3),
+            vec![vec!["b".to_string(), "c".to_string()]]
+        );
+    }
+}
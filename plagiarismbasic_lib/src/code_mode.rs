@@ -0,0 +1,355 @@
+use crate::text_utils::{CleanOptions, CleanedText, Tokenizer};
+use std::collections::HashSet;
+
+/// Placeholder token for identifiers that aren't keywords
+pub const IDENTIFIER_TOKEN: &str = "<id>";
+/// Placeholder token for numeric literals
+pub const NUMBER_TOKEN: &str = "<num>";
+/// Placeholder token for string and character literals
+pub const STRING_TOKEN: &str = "<str>";
+
+/// Tokenizes source code instead of prose, so that renaming variables or
+///     changing literal values doesn't hide copied code. Comments are dropped,
+///     keywords and punctuation are kept as they are.
+#[derive(Clone, Debug)]
+pub struct CodeMode {
+    /// Words of the language that are kept instead of replaced by `IDENTIFIER_TOKEN`
+    pub keywords: HashSet<String>,
+    /// Markers that start a comment running to the end of the line, e.g. "//"
+    pub line_comments: Vec<String>,
+    /// (start, end) markers of block comments, e.g. ("/*", "*/")
+    pub block_comments: Vec<(String, String)>,
+    /// Single quotes start strings (Python, JavaScript) rather than character literals
+    pub single_quote_strings: bool,
+    /// Replace identifiers by `IDENTIFIER_TOKEN`
+    pub normalize_identifiers: bool,
+    /// Replace numbers and strings by `NUMBER_TOKEN` and `STRING_TOKEN`
+    pub normalize_literals: bool,
+}
+
+const C_FAMILY_COMMENTS: &[&str] = &["//"];
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "int", "long", "register", "return", "short",
+    "signed", "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned", "void",
+    "volatile", "while",
+];
+
+const JAVA_KEYWORDS: &[&str] = &[
+    "abstract",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "false",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "throws",
+    "true",
+    "try",
+    "void",
+    "while",
+];
+
+const JAVASCRIPT_KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "yield",
+];
+
+impl CodeMode {
+    /// Creates a code mode with C-style comments and the given keywords,
+    ///     normalizing identifiers and literals
+    pub fn new(keywords: &[&str]) -> CodeMode {
+        CodeMode {
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            line_comments: C_FAMILY_COMMENTS.iter().map(|c| c.to_string()).collect(),
+            block_comments: vec![("/*".to_string(), "*/".to_string())],
+            single_quote_strings: false,
+            normalize_identifiers: true,
+            normalize_literals: true,
+        }
+    }
+
+    /// Returns the settings for a known language: "rust", "python", "c",
+    ///     "cpp", "java" or "javascript" (case-insensitive)
+    pub fn for_language(language: &str) -> Option<CodeMode> {
+        let mode = match language.to_lowercase().as_str() {
+            "rust" | "rs" => CodeMode::new(RUST_KEYWORDS),
+            "c" | "cpp" | "c++" => CodeMode::new(C_KEYWORDS),
+            "java" => CodeMode::new(JAVA_KEYWORDS),
+            "javascript" | "js" => CodeMode {
+                single_quote_strings: true,
+                ..CodeMode::new(JAVASCRIPT_KEYWORDS)
+            },
+            "python" | "py" => CodeMode {
+                line_comments: vec!["#".to_string()],
+                block_comments: Vec::new(),
+                single_quote_strings: true,
+                ..CodeMode::new(PYTHON_KEYWORDS)
+            },
+            _ => return None,
+        };
+        Some(mode)
+    }
+
+    /// Splits source code into tokens: keywords, (normalized) identifiers and
+    ///     literals, and single punctuation characters
+    pub fn tokenize(&self, source: &str) -> CleanedText {
+        let chars: Vec<char> = source.chars().collect();
+        // Byte offset of every character, and of the end of the source
+        let offsets: Vec<usize> = source
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(source.len()))
+            .collect();
+        let mut tokens: Vec<String> = Vec::new();
+        let mut byte_ranges: Vec<(usize, usize)> = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let token_start = i;
+            let token_count = tokens.len();
+            let c = chars[i];
+            if c.is_whitespace() {
+                i += 1;
+            } else if let Some(marker) = self
+                .line_comments
+                .iter()
+                .find(|m| starts_with(&chars, i, m))
+            {
+                i += marker.chars().count();
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            } else if let Some((start, end)) = self
+                .block_comments
+                .iter()
+                .find(|(start, _)| starts_with(&chars, i, start))
+            {
+                i += start.chars().count();
+                while i < chars.len() && !starts_with(&chars, i, end) {
+                    i += 1;
+                }
+                i = (i + end.chars().count()).min(chars.len());
+            } else if let Some(end) = self.literal_end(&chars, i) {
+                let literal: String = chars[i..end].iter().collect();
+                tokens.push(self.literal_token(literal, STRING_TOKEN));
+                i = end;
+            } else if c.is_numeric() {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '.' || chars[i] == '_')
+                {
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().collect();
+                tokens.push(self.literal_token(literal, NUMBER_TOKEN));
+            } else if c.is_alphabetic() || c == '_' {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if self.normalize_identifiers && !self.keywords.contains(&word) {
+                    tokens.push(IDENTIFIER_TOKEN.to_string());
+                } else {
+                    tokens.push(word);
+                }
+            } else {
+                tokens.push(c.to_string());
+                i += 1;
+            }
+            if tokens.len() > token_count {
+                byte_ranges.push((offsets[token_start], offsets[i]));
+            }
+        }
+        CleanedText {
+            words: tokens,
+            excluded: Vec::new(),
+            sentence_starts: Vec::new(),
+            byte_ranges,
+            stripped: Vec::new(),
+            suspicious_chars: 0,
+        }
+    }
+
+    /// If a string or character literal starts at index i, returns the index
+    ///     just after its closing quote
+    fn literal_end(&self, chars: &[char], i: usize) -> Option<usize> {
+        let quote = chars[i];
+        let is_string = quote == '"' || (quote == '\'' && self.single_quote_strings);
+        if !is_string && quote != '\'' {
+            return None;
+        }
+        if !is_string {
+            // Character literals hold one character or one escape sequence,
+            //     anything else (e.g. Rust lifetimes like 'a) is punctuation
+            if chars.get(i + 1) != Some(&'\\') {
+                return if chars.get(i + 2) == Some(&quote) {
+                    Some(i + 3)
+                } else {
+                    None
+                };
+            }
+            return (i + 2..chars.len().min(i + 12))
+                .find(|&j| chars[j] == quote)
+                .map(|j| j + 1);
+        }
+        let mut j = i + 1;
+        while j < chars.len() {
+            match chars[j] {
+                '\\' => j += 2,
+                c if c == quote => return Some(j + 1),
+                _ => j += 1,
+            }
+        }
+        None
+    }
+
+    fn literal_token(&self, literal: String, placeholder: &str) -> String {
+        if self.normalize_literals {
+            placeholder.to_string()
+        } else {
+            literal
+        }
+    }
+}
+
+/// Checks if the pattern occurs in chars at index i
+fn starts_with(chars: &[char], i: usize, pattern: &str) -> bool {
+    pattern
+        .chars()
+        .enumerate()
+        .all(|(offset, p)| chars.get(i + offset) == Some(&p))
+}
+
+impl Tokenizer for CodeMode {
+    /// Same as `CodeMode::tokenize`, the cleaning options are not used
+    fn tokenize(&self, text: &str, _options: &CleanOptions) -> CleanedText {
+        CodeMode::tokenize(self, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renamed_code_matches() {
+        let mode = CodeMode::for_language("rust").unwrap();
+        let original = "fn total(items: &[u32]) -> u32 {\n    // Sum everything\n    let mut sum = 0;\n    for x in items { sum += x; }\n    sum\n}";
+        let renamed = "fn add_all<'a>(v: &'a [u32]) -> u32 { /* copied */ let mut acc = 10; for e in v { acc += e; } acc }";
+        let tokens = mode.tokenize(original).words;
+        assert_eq!(
+            tokens[..12],
+            ["fn", "<id>", "(", "<id>", ":", "&", "[", "<id>", "]", ")", "-", ">"]
+        );
+        assert!(tokens.contains(&"<num>".to_string()));
+        assert!(!tokens.iter().any(|t| t == "Sum" || t == "sum"));
+        // Everything from the function body on is identical
+        let body =
+            |tokens: Vec<String>| tokens[tokens.iter().position(|t| t == "{").unwrap()..].to_vec();
+        assert_eq!(body(tokens), body(mode.tokenize(renamed).words));
+        assert_eq!(
+            mode.tokenize("'\\n' 'x' 'ab").words,
+            vec!["<str>", "<str>", "'", "<id>"]
+        );
+        assert_eq!(
+            mode.tokenize("é = \"x\"; // c").byte_ranges,
+            vec![(0, 2), (3, 4), (5, 8), (8, 9)]
+        );
+    }
+
+    #[test]
+    fn test_python_strings_and_comments() {
+        let mode = CodeMode::for_language("Python").unwrap();
+        assert_eq!(
+            mode.tokenize("print('hi # there', \"x\") # done\nx = 3.5")
+                .words,
+            vec!["<id>", "(", "<str>", ",", "<str>", ")", "<id>", "=", "<num>"]
+        );
+        assert!(CodeMode::for_language("cobol").is_none());
+    }
+}
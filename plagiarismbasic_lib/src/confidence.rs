@@ -0,0 +1,159 @@
+use crate::plagiarism_database::{FragmentLocation, PlagiarismResult};
+use serde::{Deserialize, Serialize};
+
+/// How likely a result is real plagiarism, see `ConfidenceRules`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Confidence {
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+/// Minimums a result has to reach, all at once, for a confidence level
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfidenceRule {
+    /// Number of matching fragments
+    pub min_matches: usize,
+    /// Words in the longest run of matched words, in either text
+    pub min_span: usize,
+    /// Coverage (see `PlagiarismResult::coverage_owner1`) of either owner
+    pub min_coverage: f32,
+    /// Only results of the equal metric qualify, not near-matches
+    pub exact_only: bool,
+}
+
+impl Default for ConfidenceRule {
+    fn default() -> ConfidenceRule {
+        ConfidenceRule {
+            min_matches: 1,
+            min_span: 0,
+            min_coverage: 0.0,
+            exact_only: false,
+        }
+    }
+}
+
+impl ConfidenceRule {
+    /// Checks if a result reaches all minimums of the rule
+    pub fn matches(&self, result: &PlagiarismResult) -> bool {
+        result.matching_fragments.len() >= self.min_matches
+            && longest_span(result) >= self.min_span
+            && result.coverage_owner1.max(result.coverage_owner2) >= self.min_coverage
+            && (result.equal_fragments || !self.exact_only)
+    }
+}
+
+/// Labels results `High` confidence if they reach the `high` rule, else
+///     `Medium` if they reach the `medium` rule, else `Low`. Can be set with
+///     `confidence_rules` on the builder or a `[confidence]` table in a
+///     config file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfidenceRules {
+    pub high: ConfidenceRule,
+    pub medium: ConfidenceRule,
+}
+
+impl Default for ConfidenceRules {
+    fn default() -> ConfidenceRules {
+        ConfidenceRules {
+            high: ConfidenceRule {
+                min_matches: 5,
+                min_span: 25,
+                min_coverage: 0.2,
+                exact_only: false,
+            },
+            medium: ConfidenceRule {
+                min_matches: 2,
+                min_span: 10,
+                min_coverage: 0.05,
+                exact_only: false,
+            },
+        }
+    }
+}
+
+impl ConfidenceRules {
+    /// Confidence level of a result
+    pub fn classify(&self, result: &PlagiarismResult) -> Confidence {
+        if self.high.matches(result) {
+            Confidence::High
+        } else if self.medium.matches(result) {
+            Confidence::Medium
+        } else {
+            Confidence::Low
+        }
+    }
+}
+
+/// Number of words in the longest run of overlapping or adjacent matched
+///     fragments of a result, in either text
+pub fn longest_span(result: &PlagiarismResult) -> usize {
+    let locations = &result.matching_fragments_locations;
+    let locations1 = locations
+        .iter()
+        .flat_map(|(locs1, _)| locs1.iter().copied());
+    let locations2 = locations
+        .iter()
+        .flat_map(|(_, locs2)| locs2.iter().copied());
+    longest_run(locations1.collect()).max(longest_run(locations2.collect()))
+}
+
+/// Length of the longest union of overlapping or adjacent (inclusive) ranges
+fn longest_run(mut locations: Vec<FragmentLocation>) -> usize {
+    locations.sort_unstable();
+    let mut longest = 0;
+    let mut current: Option<FragmentLocation> = None;
+    for (start, end) in locations {
+        current = match current {
+            Some((run_start, run_end)) if start <= run_end + 1 => {
+                Some((run_start, run_end.max(end)))
+            }
+            _ => Some((start, end)),
+        };
+        if let Some((run_start, run_end)) = current {
+            longest = longest.max(run_end - run_start + 1);
+        }
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_classify() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text(
+            "book",
+            "the mitochondria is the powerhouse of the cell and it makes energy for everything",
+        );
+        db.add_untrusted_text(
+            "copied",
+            "the mitochondria is the powerhouse of the cell and it",
+        );
+        db.add_untrusted_text("phrase", "as they say the powerhouse of a body never rests");
+        let mut results = db.check_trusted_plagiarism();
+        results.sort_by(|a, b| a.owner_id2.cmp(&b.owner_id2));
+        assert_eq!(longest_span(&results[0]), 10);
+        assert_eq!(longest_span(&results[1]), 3);
+
+        let rules = ConfidenceRules {
+            high: ConfidenceRule {
+                min_span: 10,
+                ..ConfidenceRule::default()
+            },
+            medium: ConfidenceRule {
+                min_matches: 2,
+                ..ConfidenceRule::default()
+            },
+        };
+        assert_eq!(rules.classify(&results[0]), Confidence::High);
+        assert_eq!(rules.classify(&results[1]), Confidence::Low);
+        assert_eq!(results[0].confidence, Confidence::Medium);
+    }
+}
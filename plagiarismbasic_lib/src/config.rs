@@ -0,0 +1,391 @@
+use crate::{
+    is_zip, AppSettings, ConfidenceRules, FragmentMode, Metric, PipelineConfig, PseudonymConfig,
+    SectionSplitter,
+};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Settings of a whole run, read from a TOML file (usually `plagiarism.toml`)
+///     so that repeated runs are reproducible. Relative paths in the file are
+///     relative to the directory of the file itself.
+///
+/// ```toml
+/// untrusted = "submissions/"
+/// trusted = "sources/"
+/// metric = "lev"
+/// n = 8
+/// s = 2
+/// stopwords = ["the", "a"]
+///
+/// [exclusions]
+/// quotes = true
+///
+/// [output]
+/// format = "html"
+/// path = "report/"
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Untrusted text file, or directory with one file per submission
+    pub untrusted: String,
+    /// Directory of trusted source texts
+    pub trusted: Option<String>,
+    /// Directory of texts whose fragments are ignored
+    pub ignore: Option<String>,
+    /// File with the assignment prompt the texts answer
+    pub prompt: Option<String>,
+    #[serde(default = "default_metric")]
+    pub metric: Metric,
+    /// Metrics also run on every pair the metric reports
+    #[serde(default)]
+    pub extra_metrics: Vec<Metric>,
+    /// Number of words per fragment
+    pub n: usize,
+    /// Compare ngrams of all these sizes in one run instead of only n
+    #[serde(default)]
+    pub ngram_sizes: Vec<usize>,
+    /// Ngrams may skip up to this many words in between
+    #[serde(default)]
+    pub skip: usize,
+    /// Only make an ngram at every stride-th word
+    #[serde(default = "default_stride")]
+    pub stride: usize,
+    /// Only make ngrams within one sentence
+    #[serde(default)]
+    pub within_sentences: bool,
+    /// Similarity cutoff of the metric
+    #[serde(default)]
+    pub s: usize,
+    #[serde(default = "default_threads")]
+    pub threads: usize,
+    /// Minimum number of matching fragments for a pair to be reported
+    #[serde(default = "default_min_matches")]
+    pub min_matches: usize,
+    /// Minimum density of the matches in either text for a pair to be reported
+    #[serde(default)]
+    pub min_density: f32,
+    /// Weight the matches by how rare their fragments are in the corpus
+    #[serde(default)]
+    pub idf: bool,
+    /// Minimum weighted coverage of either text for a pair to be reported
+    #[serde(default)]
+    pub min_weighted_score: f32,
+    /// Exit with a warning (1) if the highest score is above this
+    pub warn_above: Option<f32>,
+    /// Exit with a failure (2) if the highest score is above this
+    pub fail_above: Option<f32>,
+    /// Words left out of all texts
+    #[serde(default)]
+    pub stopwords: Vec<String>,
+    /// Compare texts as source code of this language
+    pub code: Option<String>,
+    /// GloVe or fastText word vector file for the embedding metric
+    pub word_vectors: Option<String>,
+    /// File of comma-separated synonym sets, one per line
+    pub synonyms: Option<String>,
+    /// File of "word, translation" pairs to the pivot language, one per line
+    pub translations: Option<String>,
+    /// Word list splitting texts detected to be in Chinese or Japanese
+    pub cjk_dictionary: Option<String>,
+    /// Fingerprint files of other corpora to use as trusted sources
+    #[serde(default)]
+    pub fingerprints: Vec<String>,
+    /// Align the words of matches for non-equal metrics
+    #[serde(default)]
+    pub alignments: bool,
+    /// Words of context around the snippets of every match in the results
+    pub snippets: Option<usize>,
+    /// Compare texts longer than this many words in chunks of that many words
+    pub chunk_words: Option<usize>,
+    /// Cheap first pass to find the pairs compared with the metric
+    pub pipeline: Option<PipelineConfig>,
+    /// `[confidence.high]` and `[confidence.medium]` tables
+    #[serde(default)]
+    pub confidence: ConfidenceRules,
+    /// `[pseudonyms]` table to rename the untrusted owners for a blind review
+    pub pseudonyms: Option<PseudonymConfig>,
+    /// `"headings"` or `{ pattern = "<regex>" }` to count matches per section
+    pub sections: Option<SectionSplitter>,
+    /// "ngram" (default) or "sentence"
+    #[serde(default)]
+    pub fragment_mode: FragmentMode,
+    #[serde(default)]
+    pub exclusions: ExclusionConfig,
+    #[serde(default)]
+    pub cleaning: CleaningConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+}
+
+/// `[exclusions]` table: text left out of plagiarism checks
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExclusionConfig {
+    /// Text inside double quotation marks
+    pub quotes: bool,
+    /// Citation markers like "(Smith, 2020)" or "[3]"
+    pub citations: bool,
+    /// Regexes whose matches are dropped from all texts
+    pub patterns: Vec<String>,
+}
+
+/// `[cleaning]` table: how texts are cleaned, see `CleanOptions`
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct CleaningConfig {
+    pub strip_diacritics: bool,
+    pub preserve_case: bool,
+    pub keep_numbers: bool,
+    pub normalize_numbers: bool,
+    pub normalize_dates: bool,
+    pub normalize_entities: bool,
+    pub fold_confusables: bool,
+    pub keep_punctuation: bool,
+    pub split_hyphens: bool,
+    pub strip_markup: bool,
+    pub detect_language: bool,
+    pub stem: bool,
+}
+
+impl Default for CleaningConfig {
+    fn default() -> CleaningConfig {
+        CleaningConfig {
+            strip_diacritics: false,
+            preserve_case: false,
+            keep_numbers: true,
+            normalize_numbers: false,
+            normalize_dates: false,
+            normalize_entities: false,
+            fold_confusables: true,
+            keep_punctuation: false,
+            split_hyphens: true,
+            strip_markup: false,
+            detect_language: false,
+            stem: false,
+        }
+    }
+}
+
+/// How the results of a run are reported
+#[derive(Deserialize, Copy, Clone, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Print the results on the command line
+    #[default]
+    Cli,
+    /// Write report.html into a directory
+    Html,
+    /// Write a JSON file
+    Json,
+    /// Write a JUnit XML file
+    Junit,
+}
+
+/// `[output]` table
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub format: OutputFormat,
+    /// Output directory (HTML) or file (JSON, JUnit), defaults to "./www/",
+    ///     "./report.json" and "./report.xml"
+    pub path: Option<String>,
+    /// Open the HTML report after writing it
+    pub open: bool,
+}
+
+impl OutputConfig {
+    /// The configured output path, or the default one of the output format
+    pub fn path(&self) -> &str {
+        match (&self.path, self.format) {
+            (Some(path), _) => path,
+            (None, OutputFormat::Json) => "./report.json",
+            (None, OutputFormat::Junit) => "./report.xml",
+            (None, _) => "./www/",
+        }
+    }
+}
+
+fn default_metric() -> Metric {
+    Metric::Equal
+}
+
+fn default_threads() -> usize {
+    1
+}
+
+fn default_stride() -> usize {
+    1
+}
+
+fn default_min_matches() -> usize {
+    1
+}
+
+/// Makes a relative path relative to the base directory instead
+fn resolve_path(base: &Path, path: &mut String) {
+    *path = base.join(&*path).to_string_lossy().into_owned();
+}
+
+impl Config {
+    /// Reads a TOML config file, resolving its relative paths against the
+    ///     directory the file is in
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Config> {
+        let path = path.as_ref();
+        let mut config = Config::from_toml(&fs::read_to_string(path)?).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a valid config file: {}", path.display(), err),
+            )
+        })?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        resolve_path(base, &mut config.untrusted);
+        for path in vec![
+            &mut config.trusted,
+            &mut config.ignore,
+            &mut config.prompt,
+            &mut config.word_vectors,
+            &mut config.synonyms,
+            &mut config.translations,
+            &mut config.cjk_dictionary,
+            &mut config.output.path,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            resolve_path(base, path);
+        }
+        for path in &mut config.fingerprints {
+            resolve_path(base, path);
+        }
+        if let Some(pseudonyms) = &mut config.pseudonyms {
+            resolve_path(base, &mut pseudonyms.mapping);
+        }
+        Ok(config)
+    }
+
+    /// Parses the contents of a TOML config file, leaving paths as they are
+    pub fn from_toml(text: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Settings to build the database with. Reads the untrusted text
+    ///     if `untrusted` is a file rather than a directory or ZIP archive.
+    pub fn app_settings(&self) -> io::Result<AppSettings> {
+        let untrusted = Path::new(&self.untrusted);
+        let (utext, udir) = if untrusted.is_dir() || is_zip(untrusted) {
+            (String::new(), Some(self.untrusted.clone()))
+        } else {
+            (fs::read_to_string(&self.untrusted)?, None)
+        };
+        Ok(AppSettings {
+            n: self.n,
+            s: self.s,
+            metric: self.metric,
+            extra_metrics: self.extra_metrics.clone(),
+            utext,
+            udir,
+            tdir: self.trusted.clone(),
+            idir: self.ignore.clone(),
+            prompt: self.prompt.clone(),
+            strip_diacritics: self.cleaning.strip_diacritics,
+            exclude_quotes: self.exclusions.quotes,
+            exclude_citations: self.exclusions.citations,
+            exclude_patterns: self.exclusions.patterns.clone(),
+            preserve_case: self.cleaning.preserve_case,
+            keep_numbers: self.cleaning.keep_numbers,
+            normalize_numbers: self.cleaning.normalize_numbers,
+            normalize_dates: self.cleaning.normalize_dates,
+            normalize_entities: self.cleaning.normalize_entities,
+            fold_confusables: self.cleaning.fold_confusables,
+            keep_punctuation: self.cleaning.keep_punctuation,
+            split_hyphens: self.cleaning.split_hyphens,
+            stopwords: self.stopwords.clone(),
+            strip_markup: self.cleaning.strip_markup,
+            code_language: self.code.clone(),
+            fragment_mode: self.fragment_mode,
+            threads: self.threads,
+            min_matches: self.min_matches,
+            min_density: self.min_density,
+            idf_weighting: self.idf,
+            min_weighted_score: self.min_weighted_score,
+            ngram_sizes: self.ngram_sizes.clone(),
+            skip: self.skip,
+            stride: self.stride,
+            within_sentences: self.within_sentences,
+            word_vectors: self.word_vectors.clone(),
+            synonyms: self.synonyms.clone(),
+            translations: self.translations.clone(),
+            cjk_dictionary: self.cjk_dictionary.clone(),
+            fingerprints: self.fingerprints.clone(),
+            alignments: self.alignments,
+            snippet_words: self.snippets,
+            chunk_words: self.chunk_words,
+            pipeline: self.pipeline.clone(),
+            confidence: self.confidence.clone(),
+            pseudonyms: self.pseudonyms.clone(),
+            sections: self.sections.clone(),
+            detect_language: self.cleaning.detect_language,
+            stem: self.cleaning.stem,
+            checkpoint: None,
+            resume: None,
+            checkpoint_interval: 60,
+            manifest: None,
+            warn_above: self.warn_above,
+            fail_above: self.fail_above,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let config = Config::from_toml(
+            r#"
+            untrusted = "ut"
+            n = 6
+            metric = "lev"
+            stopwords = ["the"]
+
+            [cleaning]
+            keep_punctuation = true
+
+            [output]
+            format = "json"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.metric, Metric::Lev);
+        assert_eq!(config.s, 0);
+        assert_eq!(config.threads, 1);
+        assert!(config.cleaning.keep_punctuation && config.cleaning.keep_numbers);
+        assert!(!config.exclusions.quotes);
+        assert_eq!(config.output.path(), "./report.json");
+        assert!(Config::from_toml("untrusted = \"ut\"\nn = 6\nsensitivity = 3").is_err());
+        assert!(Config::from_toml("untrusted = \"ut\"").is_err());
+    }
+
+    #[test]
+    fn test_from_path_resolves_paths() {
+        let dir = std::env::temp_dir().join("plagiarismbasic_test_config");
+        fs::create_dir_all(dir.join("ut")).unwrap();
+        fs::write(dir.join("ut").join("a.txt"), "some text").unwrap();
+        fs::write(
+            dir.join("plagiarism.toml"),
+            "untrusted = \"ut\"\ntrusted = \"/abs/t\"\nn = 3\n",
+        )
+        .unwrap();
+        let config = Config::from_path(dir.join("plagiarism.toml")).unwrap();
+        assert_eq!(Path::new(&config.untrusted), dir.join("ut"));
+        assert_eq!(config.trusted.as_deref(), Some("/abs/t"));
+        let settings = config.app_settings().unwrap();
+        assert_eq!(settings.udir, Some(config.untrusted.clone()));
+        assert!(settings.utext.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+use crate::plagiarism_database::{PlagiarismDatabase, TextOwnerID};
+
+/// Number of words copied at a time from a trusted source
+const COPIED_RUN: usize = 12;
+/// Syllables that generated words are made of
+const SYLLABLES: [&str; 16] = [
+    "ka", "lo", "mi", "ne", "ru", "sa", "te", "vo", "bi", "da", "fu", "go", "hi", "ja", "pe", "zu",
+];
+
+/// Trusted sources and untrusted texts that copied parts of them
+#[derive(Debug, Clone)]
+pub struct SyntheticCorpus {
+    pub trusted: Vec<(TextOwnerID, String)>,
+    pub untrusted: Vec<(TextOwnerID, String)>,
+}
+
+impl SyntheticCorpus {
+    /// Adds all texts of the corpus to a database
+    pub fn add_to(&self, db: &mut PlagiarismDatabase) {
+        for (owner, text) in &self.trusted {
+            db.add_trusted_text(owner, text);
+        }
+        for (owner, text) in &self.untrusted {
+            db.add_untrusted_text(owner, text);
+        }
+    }
+}
+
+/// Generates `texts` trusted and `texts` untrusted texts of `words` words each.
+///     Every run of 12 words of an untrusted text is copied from a random
+///     trusted text with probability `overlap` (0.0 to 1.0), and made up
+///     otherwise. The same seed always gives the same corpus.
+pub fn synthetic(texts: usize, words: usize, overlap: f64, seed: u64) -> SyntheticCorpus {
+    let mut rng = SplitMix64(seed);
+    let vocabulary: Vec<String> = (0..2000).map(|_| random_word(&mut rng)).collect();
+    let random_text = |rng: &mut SplitMix64, length: usize| -> Vec<String> {
+        (0..length)
+            .map(|_| vocabulary[rng.below(vocabulary.len())].clone())
+            .collect()
+    };
+
+    let trusted_words: Vec<Vec<String>> =
+        (0..texts).map(|_| random_text(&mut rng, words)).collect();
+    let mut untrusted = Vec::with_capacity(texts);
+    for idx in 0..texts {
+        let mut text: Vec<String> = Vec::with_capacity(words);
+        while text.len() < words {
+            let run = COPIED_RUN.min(words - text.len());
+            let source = &trusted_words[rng.below(texts)];
+            if rng.next_f64() < overlap && source.len() >= run {
+                let start = rng.below(source.len() - run + 1);
+                text.extend_from_slice(&source[start..start + run]);
+            } else {
+                text.extend(random_text(&mut rng, run));
+            }
+        }
+        untrusted.push((format!("untrusted{}", idx), text.join(" ")));
+    }
+    let trusted = trusted_words
+        .into_iter()
+        .enumerate()
+        .map(|(idx, words)| (format!("trusted{}", idx), words.join(" ")))
+        .collect();
+    SyntheticCorpus { trusted, untrusted }
+}
+
+/// A made-up word of two to four syllables
+fn random_word(rng: &mut SplitMix64) -> String {
+    let syllables = 2 + rng.below(3);
+    (0..syllables)
+        .map(|_| SYLLABLES[rng.below(SYLLABLES.len())])
+        .collect()
+}
+
+/// Small seeded random number generator, so corpora don't depend on a crate
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in 0..bound
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Random number in 0.0..1.0
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Metric;
+
+    #[test]
+    fn test_synthetic() {
+        let corpus = synthetic(3, 50, 1.0, 7);
+        assert_eq!(corpus.trusted.len(), 3);
+        assert_eq!(corpus.untrusted[2].1.split(' ').count(), 50);
+        assert_eq!(synthetic(3, 50, 1.0, 7).untrusted, corpus.untrusted);
+
+        let mut db = PlagiarismDatabase::new(COPIED_RUN, 0, Metric::Equal, Vec::new());
+        corpus.add_to(&mut db);
+        assert_eq!(
+            db.summarize_by_owner(&db.check_trusted_plagiarism()).len(),
+            3
+        );
+        let mut db = PlagiarismDatabase::new(COPIED_RUN, 0, Metric::Equal, Vec::new());
+        synthetic(3, 50, 0.0, 7).add_to(&mut db);
+        assert!(db.check_trusted_plagiarism().is_empty());
+    }
+}
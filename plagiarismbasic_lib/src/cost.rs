@@ -0,0 +1,188 @@
+use crate::plagiarism_database::{PlagiarismDatabase, TextOwnerID};
+use crate::Metric;
+use serde::Serialize;
+use std::mem;
+use std::time::{Duration, Instant};
+
+/// Owner pairs compared at most to calibrate the estimate
+const CALIBRATION_PAIRS: usize = 16;
+
+/// Time after which no more calibration pairs are compared
+const CALIBRATION_TIME: Duration = Duration::from_millis(200);
+
+/// Rough size of one fragment of a text in memory: its hash, its list of
+///     locations and one location
+const FRAGMENT_BYTES: usize = 8 + mem::size_of::<Vec<(usize, usize)>>() + 16;
+
+/// Expected work of one kind of check, see `estimate_check_cost`
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct CheckCost {
+    /// Owner pairs compared
+    pub pairs: u64,
+    /// Fragment comparisons over all pairs: lookups for `equal`, fragment
+    ///     pairs for the other metrics and word pairs for `lcs`
+    pub fragment_comparisons: u64,
+    /// Expected time on the configured number of threads
+    pub runtime: Duration,
+}
+
+/// What checking the current corpus is expected to cost, from
+///     `PlagiarismDatabase::estimate_check_cost`
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct CostEstimate {
+    /// `check_trusted_plagiarism`
+    pub trusted: CheckCost,
+    /// `check_untrusted_plagiarism`
+    pub untrusted: CheckCost,
+    /// Rough memory of the texts of the database, which any check needs
+    pub memory_bytes: u64,
+    /// Measured time of one fragment comparison on one thread
+    pub seconds_per_comparison: f64,
+}
+
+impl PlagiarismDatabase {
+    /// Estimates the pair and fragment comparisons of the checks from the
+    ///     sizes of the texts, the metric and the fragmentation, so that
+    ///     settings can be adjusted before a long run. The runtime is
+    ///     calibrated by comparing a few real owner pairs first, which takes
+    ///     up to about a fifth of a second. Pairs skipped by groups, allowed
+    ///     pairs or a prefilter are still counted, so the estimate is high
+    ///     for them.
+    pub fn estimate_check_cost(&self) -> CostEstimate {
+        let (metric, _) = self.metric_settings();
+        let trusted = self.text_sizes(true);
+        let untrusted = self.text_sizes(false);
+        // LCS compares words, the other metrics fragments
+        let size = |&(_, words, fragments): &(&TextOwnerID, usize, usize)| -> u64 {
+            if metric == Metric::Lcs {
+                words as u64
+            } else {
+                fragments as u64
+            }
+        };
+        let pair_cost = |source: u64, against: u64| -> u64 {
+            match metric {
+                // One lookup per fragment of the source
+                Metric::Equal => source,
+                _ => source * against,
+            }
+        };
+
+        // (owner, trusted) of both sides of a pair
+        type Side<'a> = (&'a str, bool);
+        let mut pair_samples: Vec<(Side, Side, u64)> = Vec::new();
+        let mut trusted_comparisons = 0;
+        for source in &trusted {
+            for against in &untrusted {
+                let cost = pair_cost(size(source), size(against));
+                trusted_comparisons += cost;
+                if pair_samples.len() < CALIBRATION_PAIRS {
+                    pair_samples.push(((source.0, true), (against.0, false), cost));
+                }
+            }
+        }
+        let mut untrusted_comparisons = 0;
+        for (idx, source) in untrusted.iter().enumerate() {
+            for against in &untrusted[idx + 1..] {
+                let cost = pair_cost(size(source), size(against));
+                untrusted_comparisons += cost;
+                if pair_samples.len() < CALIBRATION_PAIRS {
+                    pair_samples.push(((source.0, false), (against.0, false), cost));
+                }
+            }
+        }
+
+        // The first pairs of the trusted check, or of the untrusted one
+        //     without trusted texts
+        let started = Instant::now();
+        let mut calibrated = 0;
+        for &(source, against, cost) in &pair_samples {
+            if started.elapsed() >= CALIBRATION_TIME {
+                break;
+            }
+            self.compare_owners(source, against);
+            calibrated += cost;
+        }
+        let seconds_per_comparison = if calibrated == 0 {
+            0.0
+        } else {
+            started.elapsed().as_secs_f64() / calibrated as f64
+        };
+        let threads = self.thread_count().max(1) as f64;
+        let check_cost = |pairs: u64, fragment_comparisons: u64| CheckCost {
+            pairs,
+            fragment_comparisons,
+            runtime: Duration::from_secs_f64(
+                fragment_comparisons as f64 * seconds_per_comparison / threads,
+            ),
+        };
+
+        let count = untrusted.len() as u64;
+        let memory_bytes = trusted
+            .iter()
+            .chain(&untrusted)
+            .map(|&(owner, words, fragments)| {
+                (owner.len() + words * (mem::size_of::<String>() + 8) + fragments * FRAGMENT_BYTES)
+                    as u64
+            })
+            .sum();
+        CostEstimate {
+            trusted: check_cost(trusted.len() as u64 * count, trusted_comparisons),
+            untrusted: check_cost(count * count.saturating_sub(1) / 2, untrusted_comparisons),
+            memory_bytes,
+            seconds_per_comparison,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database with a trusted text of 12 words and untrusted ones of 3, 5
+    ///     and 8 words
+    fn database(n: usize, metric: Metric) -> PlagiarismDatabase {
+        let mut db = PlagiarismDatabase::new(n, 2, metric, Vec::new());
+        db.add_trusted_text(
+            "book",
+            "rivers carry water from the mountains down to the sea every day",
+        );
+        db.add_untrusted_text("short", "the water cycle");
+        db.add_untrusted_text("medium", "the water cycle never stops");
+        db.add_untrusted_text("long", "rivers carry water down to the sea fast");
+        db
+    }
+
+    #[test]
+    fn test_estimate_check_cost() {
+        let estimate = database(3, Metric::Lev).estimate_check_cost();
+        // 10 fragments of the book, then 1, 3 and 6 of the untrusted texts
+        assert_eq!(estimate.trusted.pairs, 3);
+        assert_eq!(estimate.trusted.fragment_comparisons, 10 * (1 + 3 + 6));
+        assert_eq!(estimate.untrusted.pairs, 3);
+        assert_eq!(estimate.untrusted.fragment_comparisons, 3 + 6 + 3 * 6);
+        assert!(estimate.seconds_per_comparison > 0.0);
+        assert!(estimate.memory_bytes > 0);
+
+        // Longer fragments make fewer of them and none of the short text, LCS
+        //     compares words
+        let estimate = database(5, Metric::Lev).estimate_check_cost();
+        assert_eq!(estimate.trusted.fragment_comparisons, 8 * (1 + 4));
+        let estimate = database(3, Metric::Lcs).estimate_check_cost();
+        assert_eq!(estimate.trusted.fragment_comparisons, 12 * (3 + 5 + 8));
+        // One lookup per fragment of the book
+        let estimate = database(3, Metric::Equal).estimate_check_cost();
+        assert_eq!(estimate.trusted.fragment_comparisons, 3 * 10);
+
+        let memory = |texts: &[&str]| {
+            let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+            for (idx, text) in texts.iter().enumerate() {
+                db.add_untrusted_text(&idx.to_string(), text);
+            }
+            db.estimate_check_cost().memory_bytes
+        };
+        let (short, long) = ("the water cycle", "rivers carry water down to the sea fast");
+        assert!(memory(&[short]) < memory(&[long]));
+        assert!(memory(&[long]) < memory(&[short, long]));
+    }
+}
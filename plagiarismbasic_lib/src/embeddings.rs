@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Pre-trained word vectors, e.g. from GloVe or fastText, for the embedding metric
+#[derive(Debug, Clone, Default)]
+pub struct WordVectors {
+    /// Number of values per vector
+    dimensions: usize,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+impl WordVectors {
+    /// Reads word vectors in the text format of GloVe and fastText: one word
+    ///     per line followed by its values, separated by spaces. The
+    ///     "<word count> <dimensions>" header line of fastText files is skipped.
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<WordVectors> {
+        let mut word_vectors = WordVectors::default();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut parts = line.split_whitespace();
+            let word = match parts.next() {
+                Some(word) => word,
+                None => continue,
+            };
+            let values: Vec<f32> = parts
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<_, _>>()
+                .map_err(|err| invalid_data(format!("Line {}: {}", idx + 1, err)))?;
+            if idx == 0 && values.len() == 1 && word.parse::<usize>().is_ok() {
+                continue;
+            }
+            if word_vectors.dimensions == 0 {
+                word_vectors.dimensions = values.len();
+            }
+            if values.len() != word_vectors.dimensions {
+                return Err(invalid_data(format!(
+                    "Line {}: expected {} values but found {}",
+                    idx + 1,
+                    word_vectors.dimensions,
+                    values.len()
+                )));
+            }
+            word_vectors.vectors.insert(word.to_lowercase(), values);
+        }
+        Ok(word_vectors)
+    }
+
+    /// Reads a GloVe or fastText `.vec` file, see `from_reader`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<WordVectors> {
+        WordVectors::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Number of words with a vector
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Average of the vectors of all known words (looked up in lowercase if
+    ///     needed), scaled to unit length. None if no word is known.
+    pub fn average<'a, I: IntoIterator<Item = &'a str>>(&self, words: I) -> Option<Vec<f32>> {
+        let mut sum = vec![0.0; self.dimensions];
+        let mut known = 0;
+        for vector in words.into_iter().filter_map(|word| {
+            self.vectors
+                .get(word)
+                .or_else(|| self.vectors.get(&word.to_lowercase()))
+        }) {
+            for (total, value) in sum.iter_mut().zip(vector) {
+                *total += value;
+            }
+            known += 1;
+        }
+        let length = sum.iter().map(|value| value * value).sum::<f32>().sqrt();
+        if known == 0 || length == 0.0 {
+            return None;
+        }
+        Some(sum.into_iter().map(|value| value / length).collect())
+    }
+}
+
+/// Cosine similarity of two vectors of unit length
+pub fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
+    v1.iter().zip(v2).map(|(a, b)| a * b).sum()
+}
+
+/// Checks if the cosine similarity is at least (100 - cutoff) percent
+pub fn check_embedding(v1: &[f32], v2: &[f32], cutoff: usize) -> bool {
+    cosine_similarity(v1, v2) >= 1.0 - cutoff as f32 / 100.0
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_vectors() {
+        let vectors =
+            WordVectors::from_reader("3 2\nbig 1 0\nlarge 0.9 0.1\nsmall -1 0\n".as_bytes())
+                .unwrap();
+        assert_eq!(vectors.len(), 3);
+        let big = vectors.average(vec!["big", "unknown"]).unwrap();
+        let large = vectors.average(vec!["large"]).unwrap();
+        let small = vectors.average(vec!["small"]).unwrap();
+        assert!(check_embedding(&big, &large, 5));
+        assert!(!check_embedding(&big, &small, 5));
+        assert_eq!(vectors.average(vec!["unknown"]), None);
+        assert!(WordVectors::from_reader("big 1 0\nlarge 1\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::plagiarism_database::TextOwnerID;
+use crate::Metric;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that can go wrong when setting up, filling or querying a database
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read or written
+    Io(io::Error),
+    /// An index or fingerprint file is not valid JSON of the expected shape
+    Json(serde_json::Error),
+    /// A plain text file is not valid UTF-8
+    InvalidUtf8 { path: PathBuf },
+    /// A text has no words left after cleaning
+    EmptyText { owner_id: TextOwnerID },
+    /// A text was added for an owner that already has one, and the database
+    ///     was built with `DuplicateOwnerPolicy::Error`
+    DuplicateOwner { owner_id: TextOwnerID },
+    /// A text was asked for that was never added
+    UnknownOwner { owner_id: TextOwnerID },
+    /// Fragments loaded from a file were made differently than the ones
+    ///     of the database (e.g. with another ngram size), or a result was
+    ///     merged into one of other owners or ngram size
+    IncompatibleFragments(String),
+    /// An exclusion or section pattern is not a valid regular expression
+    InvalidPattern { pattern: String, message: String },
+    /// The embedding metric was chosen without word vectors
+    MissingWordVectors,
+    /// The custom metric was chosen without a `SimilarityMetric`
+    MissingCustomMetric,
+    /// The prefilter of a pipeline is as slow as a full check
+    InvalidPrefilter { metric: Metric },
+    /// A results file was written by a newer version with a format this
+    ///     version cannot read
+    UnsupportedSchema { version: u64 },
+    /// A line of a TSV manifest is malformed
+    InvalidManifest { line: usize, message: String },
+    /// The `Translator` of the database returned another number of words
+    ///     than it was given, for the text of an owner (or a shared text like
+    ///     the assignment prompt)
+    TranslatorMismatch {
+        owner_id: TextOwnerID,
+        expected: usize,
+        got: usize,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Json(err) => write!(f, "Invalid JSON: {}", err),
+            Error::InvalidUtf8 { path } => write!(f, "{} is not valid UTF-8", path.display()),
+            Error::EmptyText { owner_id } => write!(f, "Text {} has no words", owner_id),
+            Error::DuplicateOwner { owner_id } => {
+                write!(f, "A text was already added for {}", owner_id)
+            }
+            Error::UnknownOwner { owner_id } => write!(f, "Text {} was never added", owner_id),
+            Error::IncompatibleFragments(message) => write!(f, "{}", message),
+            Error::InvalidPattern { pattern, message } => {
+                write!(f, "Pattern {} is not a valid regex: {}", pattern, message)
+            }
+            Error::MissingWordVectors => write!(
+                f,
+                "The embedding metric needs word vectors, but none were given"
+            ),
+            Error::MissingCustomMetric => write!(
+                f,
+                "The custom metric needs a SimilarityMetric, but none was given"
+            ),
+            Error::InvalidPrefilter { metric } => write!(
+                f,
+                "The {:?} metric cannot prefilter candidates, only equal and simhash can",
+                metric
+            ),
+            Error::UnsupportedSchema { version } => write!(
+                f,
+                "Results were written in format version {}, but only versions up to {} can be read",
+                version,
+                crate::results::SCHEMA_VERSION
+            ),
+            Error::InvalidManifest { line, message } => {
+                write!(f, "Line {} of the manifest is invalid: {}", line, message)
+            }
+            Error::TranslatorMismatch {
+                owner_id,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Translator returned {} words for the {} words of {}",
+                got, expected, owner_id
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
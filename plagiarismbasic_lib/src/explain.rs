@@ -0,0 +1,381 @@
+use crate::code_mode;
+use crate::confidence::Confidence;
+use crate::plagiarism_database::{
+    FragmentLocation, PlagiarismDatabase, PlagiarismResult, TextCleaning, TextOwnerID,
+};
+use crate::text_utils::{DATE_TOKEN, ENTITY_TOKEN, NUMBER_TOKEN};
+use crate::Metric;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Spans listed at most by `explain`
+const TOP_SPANS: usize = 5;
+
+/// Why the owners of a result were matched, from `PlagiarismResult::explain`,
+///     for answering students who contest a result
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub owner_id1: TextOwnerID,
+    pub owner_id2: TextOwnerID,
+    /// Metric and similarity threshold of the database that found the matches
+    pub metric: Metric,
+    pub s: usize,
+    pub n: usize,
+    /// Extra metrics (see `extra_metrics` on the builder) that matched too
+    pub also_matched_by: Vec<Metric>,
+    pub score: ScoreBreakdown,
+    /// Runs of overlapping or adjacent matches with the most fragments
+    pub top_spans: Vec<ExplainedSpan>,
+    /// Normalizations that changed the matched spans of either text, in
+    ///     cleaning order
+    pub normalizations: Vec<NormalizationEffect>,
+    /// Every normalization configured for the texts (e.g. `stemming`,
+    ///     `stopwords` or `ignored texts`), in cleaning order, including
+    ///     those whose changes can't be told from the clean text
+    pub configured_normalizations: Vec<String>,
+}
+
+/// What the score of a result is made of
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScoreBreakdown {
+    /// `PlagiarismResult::score`, the larger of the coverages
+    pub score: f32,
+    pub matching_fragments: usize,
+    pub coverage_owner1: f32,
+    pub coverage_owner2: f32,
+    pub weighted_coverage_owner1: f32,
+    pub weighted_coverage_owner2: f32,
+    pub density_owner1: f32,
+    pub density_owner2: f32,
+    pub prompt_coverage_owner1: f32,
+    pub prompt_coverage_owner2: f32,
+    pub confidence: Confidence,
+    pub allowed: bool,
+}
+
+/// A run of overlapping or adjacent matching fragments in both texts
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ExplainedSpan {
+    /// Word ranges (both inclusive) of the span in each owner's text
+    pub span1: FragmentLocation,
+    pub span2: FragmentLocation,
+    /// Clean words of the span, None for fingerprints
+    pub text1: Option<String>,
+    pub text2: Option<String>,
+    /// Matching fragments in the span
+    pub fragments: usize,
+    /// Share of all the matching fragments of the result (0.0 to 1.0)
+    pub weight: f32,
+}
+
+/// A normalization that changed the matched spans of at least one owner
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NormalizationEffect {
+    /// e.g. `numbers`, `quotes and citations` or `assignment prompt`
+    pub name: String,
+    /// Words (regions for exclusion patterns) in or right next to the
+    ///     owner's matched spans that it replaced or dropped. None if it
+    ///     wasn't applied to the owner's text.
+    pub changes_owner1: Option<usize>,
+    pub changes_owner2: Option<usize>,
+}
+
+impl PlagiarismResult {
+    /// Explains the result with the settings and texts of the database that
+    ///     found it: the metric, the parts of the score, the spans that
+    ///     contributed most and the normalizations that changed them
+    pub fn explain(&self, db: &PlagiarismDatabase) -> Explanation {
+        let (metric, s) = db.metric_settings();
+        let spans = matched_spans(self);
+        let total: usize = spans.iter().map(|&(_, _, fragments)| fragments).sum();
+        let cleaning1 = db.text_cleaning(&self.owner_id1, self.trusted_owner1);
+        let cleaning2 = db.text_cleaning(&self.owner_id2, self.trusted_owner2);
+        let span_text = |cleaning: &Option<TextCleaning>, (start, end): FragmentLocation| {
+            let words = cleaning.as_ref()?.words.get(start..=end)?;
+            Some(words.join(" "))
+        };
+
+        let mut top_spans: Vec<ExplainedSpan> = spans
+            .iter()
+            .map(|&(span1, span2, fragments)| ExplainedSpan {
+                span1,
+                span2,
+                text1: span_text(&cleaning1, span1),
+                text2: span_text(&cleaning2, span2),
+                fragments,
+                weight: fragments as f32 / total as f32,
+            })
+            .collect();
+        top_spans.sort_by(|a, b| b.fragments.cmp(&a.fragments).then(a.span1.cmp(&b.span1)));
+        top_spans.truncate(TOP_SPANS);
+
+        let spans1: Vec<FragmentLocation> = spans.iter().map(|&(span1, _, _)| span1).collect();
+        let spans2: Vec<FragmentLocation> = spans.iter().map(|&(_, span2, _)| span2).collect();
+        let effects1 = cleaning1
+            .as_ref()
+            .map_or_else(Vec::new, |cleaning| effects(cleaning, &spans1));
+        let effects2 = cleaning2
+            .as_ref()
+            .map_or_else(Vec::new, |cleaning| effects(cleaning, &spans2));
+        let mut configured_normalizations: Vec<String> = Vec::new();
+        let mut normalizations: Vec<NormalizationEffect> = Vec::new();
+        for &(name, _) in effects1.iter().chain(&effects2) {
+            if configured_normalizations.iter().any(|other| other == name) {
+                continue;
+            }
+            configured_normalizations.push(name.to_string());
+            let changes = |effects: &[(&str, Option<usize>)]| {
+                effects
+                    .iter()
+                    .find(|&&(other, _)| other == name)
+                    .and_then(|&(_, changes)| changes)
+            };
+            let (changes_owner1, changes_owner2) = (changes(&effects1), changes(&effects2));
+            if changes_owner1.unwrap_or(0) + changes_owner2.unwrap_or(0) > 0 {
+                normalizations.push(NormalizationEffect {
+                    name: name.to_string(),
+                    changes_owner1,
+                    changes_owner2,
+                });
+            }
+        }
+
+        Explanation {
+            owner_id1: self.owner_id1.clone(),
+            owner_id2: self.owner_id2.clone(),
+            metric,
+            s,
+            n: self.n,
+            also_matched_by: self
+                .metric_results
+                .iter()
+                .filter(|result| !result.matching_fragments_locations.is_empty())
+                .map(|result| result.metric)
+                .collect(),
+            score: ScoreBreakdown {
+                score: self.score(),
+                matching_fragments: self.matching_fragments.len(),
+                coverage_owner1: self.coverage_owner1,
+                coverage_owner2: self.coverage_owner2,
+                weighted_coverage_owner1: self.weighted_coverage_owner1,
+                weighted_coverage_owner2: self.weighted_coverage_owner2,
+                density_owner1: self.density_owner1,
+                density_owner2: self.density_owner2,
+                prompt_coverage_owner1: self.prompt_coverage_owner1,
+                prompt_coverage_owner2: self.prompt_coverage_owner2,
+                confidence: self.confidence,
+                allowed: self.allowed,
+            },
+            top_spans,
+            normalizations,
+            configured_normalizations,
+        }
+    }
+}
+
+/// Merges the matches of a result into (span of owner 1, span of owner 2,
+///     fragments) runs that overlap or touch in both texts. A fragment found
+///     several times is paired location by location, and the extra locations
+///     of one text with the last one of the other.
+fn matched_spans(result: &PlagiarismResult) -> Vec<(FragmentLocation, FragmentLocation, usize)> {
+    let mut pairs: Vec<(FragmentLocation, FragmentLocation)> = result
+        .matching_fragments_locations
+        .iter()
+        .flat_map(|(locs1, locs2)| {
+            (0..locs1.len().max(locs2.len())).filter_map(move |idx| {
+                let loc1 = locs1.get(idx).or(locs1.last())?;
+                let loc2 = locs2.get(idx).or(locs2.last())?;
+                Some((*loc1, *loc2))
+            })
+        })
+        .collect();
+    pairs.sort_unstable();
+    let mut spans: Vec<(FragmentLocation, FragmentLocation, usize)> = Vec::new();
+    for (loc1, loc2) in pairs {
+        match spans.last_mut() {
+            Some((span1, span2, fragments))
+                if loc1.0 <= span1.1 + 1 && loc2.0 <= span2.1 + 1 && loc2.1 + 1 >= span2.0 =>
+            {
+                span1.1 = span1.1.max(loc1.1);
+                span2.0 = span2.0.min(loc2.0);
+                span2.1 = span2.1.max(loc2.1);
+                *fragments += 1;
+            }
+            _ => spans.push((loc1, loc2, 1)),
+        }
+    }
+    spans
+}
+
+/// The normalizations applied to a text with their changes to the matched
+///     spans, if they can be told from the clean text (original words
+///     aren't kept), see `NormalizationEffect`
+fn effects(
+    cleaning: &TextCleaning,
+    spans: &[FragmentLocation],
+) -> Vec<(&'static str, Option<usize>)> {
+    let options = &cleaning.options;
+    let covered: BTreeSet<usize> = spans.iter().flat_map(|&(start, end)| start..=end).collect();
+    let tokens = |tokens: &[&str]| {
+        covered
+            .iter()
+            .filter(|&&idx| {
+                cleaning
+                    .words
+                    .get(idx)
+                    .is_some_and(|word| tokens.contains(&word.as_str()))
+            })
+            .count()
+    };
+    // Dropped words are left out of the spans, so they are only right next
+    //     to them
+    let touches = |idx: usize| {
+        spans
+            .iter()
+            .any(|&(start, end)| idx + 1 >= start && idx <= end + 1)
+    };
+
+    let mut effects: Vec<(&'static str, Option<usize>)> = Vec::new();
+    if cleaning.code_mode {
+        let code_tokens = [
+            code_mode::IDENTIFIER_TOKEN,
+            code_mode::NUMBER_TOKEN,
+            code_mode::STRING_TOKEN,
+        ];
+        effects.push(("code mode", Some(tokens(&code_tokens))));
+    } else if !options.preserve_case {
+        effects.push(("lowercase", None));
+    }
+    if options.strip_diacritics {
+        effects.push(("diacritics", None));
+    }
+    if options.fold_confusables {
+        effects.push(("confusables", None));
+    }
+    if !options.exclude_patterns.is_empty() {
+        let bytes = |idx: usize| cleaning.word_byte_ranges.get(idx).copied();
+        // From the end of the word before a span to the start of the word after it
+        let regions = cleaning
+            .stripped
+            .iter()
+            .filter(|&&(start, end)| {
+                spans.iter().any(|&(first, last)| {
+                    let after = first
+                        .checked_sub(1)
+                        .and_then(bytes)
+                        .map_or(0, |(_, end)| end);
+                    let before = bytes(last + 1).map_or(usize::MAX, |(start, _)| start);
+                    start >= after && end <= before
+                })
+            })
+            .count();
+        effects.push(("exclusion patterns", Some(regions)));
+    }
+    if options.exclude_quotes || options.exclude_citations {
+        let excluded = cleaning
+            .excluded
+            .iter()
+            .flat_map(|&(start, end)| start..=end)
+            .filter(|&idx| touches(idx))
+            .count();
+        effects.push(("quotes and citations", Some(excluded)));
+    }
+    if options.normalize_dates {
+        effects.push(("dates", Some(tokens(&[DATE_TOKEN]))));
+    }
+    if options.normalize_numbers {
+        effects.push(("numbers", Some(tokens(&[NUMBER_TOKEN]))));
+    }
+    if options.normalize_entities {
+        effects.push(("entities", Some(tokens(&[ENTITY_TOKEN]))));
+    }
+    if !options.stopwords.is_empty() {
+        effects.push(("stopwords", None));
+    }
+    if options.synonyms.is_some() {
+        effects.push(("synonyms", None));
+    }
+    if cleaning.translated {
+        effects.push(("translation", None));
+    }
+    if cleaning.stemmed {
+        effects.push(("stemming", None));
+    }
+    if !cleaning.prompt_words.is_empty() {
+        let prompt = cleaning
+            .prompt_words
+            .iter()
+            .filter(|&&idx| touches(idx))
+            .count();
+        effects.push(("assignment prompt", Some(prompt)));
+    }
+    if cleaning.ignored_texts {
+        effects.push(("ignored texts", None));
+    }
+    if cleaning.baseline {
+        effects.push(("earlier drafts", None));
+    }
+    effects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CleanOptions;
+
+    #[test]
+    fn test_explain() {
+        let clean_options = CleanOptions {
+            normalize_numbers: true,
+            ..CleanOptions::default()
+        };
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .clean_options(clean_options)
+            .build();
+        db.set_assignment_prompt("Why is the sky blue?");
+        db.add_trusted_text(
+            "book",
+            "Why is the sky blue? Air scatters about 10 times more blue light than red",
+        );
+        db.add_untrusted_text(
+            "essay",
+            "So why is the sky blue? Air scatters about 5 times more blue light",
+        );
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        let explanation = results[0].explain(&db);
+        assert_eq!(explanation.metric, Metric::Equal);
+        assert_eq!(explanation.score.score, results[0].score());
+
+        // The prompt ngrams are left out, so the span starts within it
+        assert_eq!(explanation.top_spans.len(), 1);
+        let span = &explanation.top_spans[0];
+        assert_eq!(span.span1.0, 3);
+        assert_eq!(
+            span.text2.as_deref(),
+            Some("sky blue air scatters about <NUM> times more blue light")
+        );
+
+        let names: Vec<&str> = explanation
+            .normalizations
+            .iter()
+            .map(|effect| effect.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["numbers", "assignment prompt"]);
+        let numbers = &explanation.normalizations[0];
+        assert_eq!(
+            (numbers.changes_owner1, numbers.changes_owner2),
+            (Some(1), Some(1))
+        );
+        // "the sky blue" is right before the span and "sky blue" in it
+        let prompt = &explanation.normalizations[1];
+        assert_eq!(
+            (prompt.changes_owner1, prompt.changes_owner2),
+            (Some(3), Some(3))
+        );
+        assert_eq!(
+            explanation.configured_normalizations,
+            vec!["lowercase", "confusables", "numbers", "assignment prompt"]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::error::Result;
+use crate::plagiarism_database::Fragmentation;
+use crate::text_utils::FragmentHash;
+use std::future::Future;
+
+/// A corpus of source documents that is too large to store locally (e.g. an
+///     index of the web), queried for the few documents that may match a
+///     text. See `PlagiarismDatabase::check_against_external`.
+pub trait ExternalSource {
+    /// Sorted IDs of the documents containing any of the fingerprints (fragment
+    ///     hashes), which were made from a text with `fragmentation`
+    fn candidates(
+        &self,
+        fragmentation: &Fragmentation,
+        fingerprints: &[FragmentHash],
+    ) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// Full text of a candidate document
+    fn fetch(&self, document_id: &str) -> impl Future<Output = Result<String>> + Send;
+}
+
+#[cfg(feature = "fetch")]
+pub use remote::RemoteIndex;
+
+#[cfg(feature = "fetch")]
+mod remote {
+    use super::ExternalSource;
+    use crate::error::{Error, Result};
+    use crate::plagiarism_database::Fragmentation;
+    use crate::text_utils::FragmentHash;
+    use serde::{Deserialize, Serialize};
+    use std::io;
+
+    /// Body of a candidates request
+    #[derive(Serialize)]
+    struct CandidatesRequest<'a> {
+        fragmentation: &'a Fragmentation,
+        fingerprints: &'a [FragmentHash],
+    }
+
+    /// Body of a candidates response
+    #[derive(Deserialize)]
+    struct CandidatesResponse {
+        documents: Vec<String>,
+    }
+
+    /// An `ExternalSource` served over HTTP. `POST <endpoint>/candidates` with
+    ///     a JSON body `{"fragmentation": ..., "fingerprints": [...]}` answers
+    ///     `{"documents": ["<id>", ...]}`, and `GET <endpoint>/documents/<id>`
+    ///     answers the plain text of a document. Needs the `fetch` feature.
+    pub struct RemoteIndex {
+        endpoint: reqwest::Url,
+        client: reqwest::Client,
+    }
+
+    impl RemoteIndex {
+        /// A client of the index service at a base URL, e.g.
+        ///     `https://index.example.com/v1`
+        pub fn new(endpoint: &str) -> Result<RemoteIndex> {
+            let endpoint = reqwest::Url::parse(endpoint.trim_end_matches('/'))
+                .map_err(|err| remote_error(endpoint, err))?;
+            if endpoint.cannot_be_a_base() {
+                return Err(remote_error(endpoint.as_str(), "not a base URL"));
+            }
+            Ok(RemoteIndex {
+                endpoint,
+                client: reqwest::Client::new(),
+            })
+        }
+
+        /// URL of a path below the endpoint, with every segment escaped
+        fn url(&self, segments: &[&str]) -> reqwest::Url {
+            let mut url = self.endpoint.clone();
+            url.path_segments_mut()
+                .expect("The endpoint is a base URL")
+                .extend(segments);
+            url
+        }
+    }
+
+    fn remote_error<E: std::fmt::Display>(url: &str, err: E) -> Error {
+        Error::Io(io::Error::other(format!(
+            "Cannot query index {}: {}",
+            url, err
+        )))
+    }
+
+    impl ExternalSource for RemoteIndex {
+        async fn candidates(
+            &self,
+            fragmentation: &Fragmentation,
+            fingerprints: &[FragmentHash],
+        ) -> Result<Vec<String>> {
+            let url = self.url(&["candidates"]);
+            let request = CandidatesRequest {
+                fragmentation,
+                fingerprints,
+            };
+            let response: CandidatesResponse = self
+                .client
+                .post(url.clone())
+                .json(&request)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|err| remote_error(url.as_str(), err))?
+                .json()
+                .await
+                .map_err(|err| remote_error(url.as_str(), err))?;
+            let mut documents = response.documents;
+            documents.sort();
+            documents.dedup();
+            Ok(documents)
+        }
+
+        async fn fetch(&self, document_id: &str) -> Result<String> {
+            let url = self.url(&["documents", document_id]);
+            self.client
+                .get(url.clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|err| remote_error(url.as_str(), err))?
+                .text()
+                .await
+                .map_err(|err| remote_error(url.as_str(), err))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_remote_urls() {
+            let index = RemoteIndex::new("https://index.example.com/v1/").unwrap();
+            assert_eq!(
+                index.url(&["documents", "a/b c"]).as_str(),
+                "https://index.example.com/v1/documents/a%2Fb%20c"
+            );
+            assert!(RemoteIndex::new("not a url").is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::text_utils::hash_fragment;
+    use crate::{Metric, PlagiarismDatabase};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::task::{Context, Poll, Waker};
+
+    /// Documents in memory, remembering which ones were fetched
+    struct MockSource {
+        documents: HashMap<String, String>,
+        fetched: Mutex<Vec<String>>,
+    }
+
+    impl ExternalSource for MockSource {
+        async fn candidates(
+            &self,
+            _fragmentation: &Fragmentation,
+            fingerprints: &[FragmentHash],
+        ) -> Result<Vec<String>> {
+            let mut ids: Vec<String> = self
+                .documents
+                .iter()
+                .filter(|(_, text)| {
+                    let words: Vec<&str> = text.split(' ').collect();
+                    words
+                        .windows(3)
+                        .any(|ngram| fingerprints.contains(&hash_fragment(&ngram.join(" "))))
+                })
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.sort();
+            Ok(ids)
+        }
+
+        async fn fetch(&self, document_id: &str) -> Result<String> {
+            self.fetched.lock().unwrap().push(document_id.to_string());
+            self.documents
+                .get(document_id)
+                .cloned()
+                .ok_or_else(|| Error::UnknownOwner {
+                    owner_id: document_id.to_string(),
+                })
+        }
+    }
+
+    /// Runs a future that never has to wait
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("The future is not ready"),
+        }
+    }
+
+    #[test]
+    fn test_check_against_external() {
+        let documents: HashMap<String, String> = [
+            (
+                "wiki/rust",
+                "rust guarantees memory safety without a garbage collector",
+            ),
+            ("wiki/go", "go uses a garbage collector and goroutines"),
+            ("wiki/space", "the universe began with the big bang"),
+        ]
+        .iter()
+        .map(|(id, text)| (id.to_string(), text.to_string()))
+        .collect();
+        let source = MockSource {
+            documents,
+            fetched: Mutex::new(Vec::new()),
+        };
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_untrusted_text("essay", "they say rust guarantees memory safety too");
+        let results = block_on(db.check_against_external(&source)).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id1, "wiki/rust");
+        assert_eq!(results[0].matching_fragments.len(), 2);
+        // Documents without any of the fingerprints are never downloaded
+        assert_eq!(*source.fetched.lock().unwrap(), vec!["wiki/rust"]);
+    }
+}
@@ -0,0 +1,318 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Reads the plain text of a file, picking the extraction method from its extension.
+///     PDF and DOCX files need the `pdf` and `docx` features respectively,
+///     scanned images and OCR output (hOCR, ALTO) the `ocr` feature, and
+///     everything else is read as UTF-8 text (failing with InvalidData
+///     wrapping a FromUtf8Error if it isn't). With the `ocr` feature, PDFs
+///     without any text (i.e. scans) are OCR'd.
+pub fn extract_text(path: &Path) -> io::Result<String> {
+    extract_bytes(path, || fs::read(path))
+}
+
+/// Same as `extract_text`, with the contents of the file coming from `read`
+///     (which isn't called if the file type isn't supported)
+fn extract_bytes(path: &Path, read: impl FnOnce() -> io::Result<Vec<u8>>) -> io::Result<String> {
+    match extension(path).as_deref() {
+        #[cfg(feature = "ocr")]
+        Some("pdf") => {
+            let bytes = read()?;
+            match extract_pdf(path, || Ok(bytes.clone())) {
+                Ok(text) if !text.trim().is_empty() => Ok(text),
+                _ => crate::ocr::extract_ocr(path, "pdf", || Ok(bytes)),
+            }
+        }
+        #[cfg(not(feature = "ocr"))]
+        Some("pdf") => extract_pdf(path, read),
+        Some("docx") => extract_docx(path, read),
+        #[cfg(feature = "ocr")]
+        Some("xml") => crate::ocr::extract_ocr(path, "xml", read),
+        Some(ext) if OCR_EXTENSIONS.contains(&ext) => extract_ocr(path, ext, read),
+        _ => String::from_utf8(read()?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+}
+
+/// Checks if a path is a ZIP archive of texts by its extension
+pub fn is_zip(path: &Path) -> bool {
+    extension(path).as_deref() == Some("zip")
+}
+
+/// Files in a ZIP archive that are read as texts, everything else (e.g.
+///     images, unless they are OCR'd) is skipped
+#[cfg(feature = "zip")]
+const ZIP_TEXT_EXTENSIONS: [&str; 8] = [
+    "txt", "md", "markdown", "html", "htm", "xhtml", "pdf", "docx",
+];
+
+/// Files that are scanned images or OCR output, read with the `ocr` feature
+const OCR_EXTENSIONS: [&str; 7] = ["hocr", "alto", "png", "jpg", "jpeg", "tif", "tiff"];
+
+#[cfg(feature = "ocr")]
+fn extract_ocr(
+    path: &Path,
+    extension: &str,
+    read: impl FnOnce() -> io::Result<Vec<u8>>,
+) -> io::Result<String> {
+    crate::ocr::extract_ocr(path, extension, read)
+}
+
+#[cfg(not(feature = "ocr"))]
+fn extract_ocr(
+    path: &Path,
+    _extension: &str,
+    _read: impl FnOnce() -> io::Result<Vec<u8>>,
+) -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "{} is a scan or OCR output, but plagiarismbasic_lib was built without the `ocr` feature",
+            path.display()
+        ),
+    ))
+}
+
+/// One text read from a ZIP archive
+pub struct ZipText {
+    /// Top-level folder the file is in, or its file name if it isn't in a
+    ///     folder. Files of a nested archive are owned by the owner of the
+    ///     nested archive, without its ".zip" extension if it isn't in a folder.
+    pub owner_id: String,
+    /// Archive path joined with the path of the file inside it (and inside
+    ///     any nested archives)
+    pub path: PathBuf,
+    pub text: io::Result<String>,
+}
+
+/// Reads the supported files of a ZIP archive (e.g. an LMS export with a
+///     folder per student), including those in nested archives, sorted by
+///     path. Needs the `zip` feature. Only fails if the archive itself cannot
+///     be read, the text of every file can fail on its own.
+#[cfg(feature = "zip")]
+pub fn extract_zip(path: &Path) -> io::Result<Vec<ZipText>> {
+    let mut texts = Vec::new();
+    read_zip(fs::read(path)?, path, None, &mut texts)?;
+    texts.sort_by(|text1, text2| text1.path.cmp(&text2.path));
+    Ok(texts)
+}
+
+#[cfg(not(feature = "zip"))]
+pub fn extract_zip(path: &Path) -> io::Result<Vec<ZipText>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "{} is a ZIP file, but plagiarismbasic_lib was built without the `zip` feature",
+            path.display()
+        ),
+    ))
+}
+
+/// Adds the texts of an archive to `texts`, all owned by `owner_id` if given
+#[cfg(feature = "zip")]
+fn read_zip(
+    bytes: Vec<u8>,
+    path: &Path,
+    owner_id: Option<&str>,
+    texts: &mut Vec<ZipText>,
+) -> io::Result<()> {
+    use std::io::Read;
+
+    let invalid = |err: zip::result::ZipError| io::Error::new(io::ErrorKind::InvalidData, err);
+    let mut archive = zip::ZipArchive::new(io::Cursor::new(bytes)).map_err(invalid)?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).map_err(invalid)?;
+        // Skips directories, unsafe paths like "../x" and hidden or macOS
+        //     metadata files
+        let name = match file.enclosed_name() {
+            Some(name) if !file.is_dir() => name,
+            _ => continue,
+        };
+        if name.components().any(|component| {
+            let component = component.as_os_str().to_string_lossy();
+            component.starts_with('.') || component == "__MACOSX"
+        }) {
+            continue;
+        }
+        let nested = is_zip(&name);
+        let is_text = extension(&name).is_some_and(|ext| {
+            ZIP_TEXT_EXTENSIONS.contains(&ext.as_str())
+                || cfg!(feature = "ocr") && OCR_EXTENSIONS.contains(&ext.as_str())
+        });
+        if !nested && !is_text {
+            continue;
+        }
+        let entry_path = path.join(&name);
+        let entry_owner = match owner_id {
+            Some(owner_id) => owner_id.to_string(),
+            None => {
+                let in_folder = name.components().count() > 1;
+                let owner_id = if in_folder {
+                    name.components()
+                        .next()
+                        .map(|component| component.as_os_str())
+                } else if nested {
+                    name.file_stem()
+                } else {
+                    name.file_name()
+                };
+                owner_id.unwrap_or_default().to_string_lossy().into_owned()
+            }
+        };
+        let mut bytes = Vec::new();
+        let text = file.read_to_end(&mut bytes).and_then(|_| {
+            if nested {
+                read_zip(bytes, &entry_path, Some(&entry_owner), texts)?;
+                Ok(None)
+            } else {
+                extract_bytes(&entry_path, || Ok(bytes)).map(Some)
+            }
+        });
+        if let Some(text) = text.transpose() {
+            texts.push(ZipText {
+                owner_id: entry_owner,
+                path: entry_path,
+                text,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Guesses if a file's text is HTML or Markdown, from its extension or
+///     (for HTML) from the start of its contents
+pub fn is_markup(path: &Path, text: &str) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+    if let Some("html" | "htm" | "xhtml" | "md" | "markdown") = extension.as_deref() {
+        return true;
+    }
+    let start: String = text.trim_start().chars().take(15).collect();
+    let start = start.to_lowercase();
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+}
+
+#[cfg(feature = "pdf")]
+fn extract_pdf(path: &Path, read: impl FnOnce() -> io::Result<Vec<u8>>) -> io::Result<String> {
+    pdf_extract::extract_text_from_mem(&read()?).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Cannot extract text from PDF {}: {}", path.display(), err),
+        )
+    })
+}
+
+#[cfg(not(feature = "pdf"))]
+fn extract_pdf(path: &Path, _read: impl FnOnce() -> io::Result<Vec<u8>>) -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "{} is a PDF file, but plagiarismbasic_lib was built without the `pdf` feature",
+            path.display()
+        ),
+    ))
+}
+
+#[cfg(feature = "docx")]
+fn extract_docx(_path: &Path, read: impl FnOnce() -> io::Result<Vec<u8>>) -> io::Result<String> {
+    use std::io::Read;
+
+    let mut archive = zip::ZipArchive::new(io::Cursor::new(read()?))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let mut document = String::new();
+    archive
+        .by_name("word/document.xml")
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+        .read_to_string(&mut document)?;
+    docx_xml_to_text(&document)
+}
+
+#[cfg(not(feature = "docx"))]
+fn extract_docx(path: &Path, _read: impl FnOnce() -> io::Result<Vec<u8>>) -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "{} is a DOCX file, but plagiarismbasic_lib was built without the `docx` feature",
+            path.display()
+        ),
+    ))
+}
+
+/// Collects the text runs (<w:t>) of a DOCX document body, separating
+///     paragraphs by newlines and tabs/breaks by spaces
+#[cfg(feature = "docx")]
+fn docx_xml_to_text(xml: &str) -> io::Result<String> {
+    use quick_xml::escape::resolve_predefined_entity;
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    let invalid = |err: quick_xml::Error| io::Error::new(io::ErrorKind::InvalidData, err);
+    let mut reader = Reader::from_str(xml);
+    let mut text = String::new();
+    let mut in_text_run = false;
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(tag) if tag.local_name().as_ref() == "t" => in_text_run = true,
+            Event::End(tag) => match tag.local_name().as_ref() {
+                "t" => in_text_run = false,
+                "p" => text.push('\n'),
+                _ => {}
+            },
+            Event::Empty(tag) if matches!(tag.local_name().as_ref(), "tab" | "br") => {
+                text.push(' ')
+            }
+            Event::Text(run) if in_text_run => text.push_str(&run.xml10_content()),
+            Event::GeneralRef(entity) if in_text_run => {
+                if let Some(c) = entity.resolve_char_ref().map_err(invalid)? {
+                    text.push(c);
+                } else if let Some(resolved) = resolve_predefined_entity(&entity.xml10_content()) {
+                    text.push_str(resolved);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "docx")]
+    fn test_docx_xml_to_text() {
+        let xml = r#"<w:document xmlns:w="x"><w:body>
+            <w:p><w:r><w:t>Fish &amp; chips</w:t><w:tab/><w:t xml:space="preserve">are </w:t></w:r><w:r><w:t>great</w:t></w:r></w:p>
+            <w:p><w:r><w:t>Second &#65;</w:t></w:r></w:p>
+        </w:body></w:document>"#;
+        assert_eq!(
+            docx_xml_to_text(xml).unwrap(),
+            "Fish & chips are great\nSecond A\n"
+        );
+    }
+
+    #[test]
+    fn test_is_markup() {
+        assert!(is_markup(Path::new("notes.MD"), "plain"));
+        assert!(is_markup(Path::new("page.txt"), "  <!DOCTYPE html><html>"));
+        assert!(!is_markup(Path::new("essay.txt"), "a < b and <html> later"));
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "pdf", feature = "ocr")))]
+    fn test_pdf_requires_feature() {
+        let err = extract_text(Path::new("essay.PDF")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::error::Result;
+use crate::extract::is_markup;
+use crate::plagiarism_database::PlagiarismDatabase;
+use crate::text_utils::strip_markup;
+use reqwest::header::CONTENT_TYPE;
+use std::io;
+use std::path::Path;
+
+impl PlagiarismDatabase {
+    /// Downloads a web page, strips its HTML and adds it as a trusted text
+    ///     owned by its URL. Fails like `try_add_trusted_text` if the page
+    ///     has no words or the URL was already added.
+    pub async fn add_trusted_url(&mut self, url: &str) -> Result<()> {
+        let text = fetch_text(url).await?;
+        self.try_add_trusted_text(url, &text)
+    }
+
+    /// Adds every page of a list of reference URLs as a trusted text,
+    ///     stopping at the first page that cannot be downloaded or added
+    pub async fn add_trusted_urls<I, S>(&mut self, urls: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for url in urls {
+            self.add_trusted_url(url.as_ref()).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Downloads the text of a web page, removing its markup if it is HTML
+pub async fn fetch_text(url: &str) -> io::Result<String> {
+    let response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| io::Error::other(format!("Cannot download {}: {}", url, err)))?;
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let body = response
+        .text()
+        .await
+        .map_err(|err| io::Error::other(format!("Cannot read {}: {}", url, err)))?;
+    Ok(page_to_text(url, content_type.as_deref(), body))
+}
+
+/// Strips the markup of a downloaded page that is (or looks like) HTML or Markdown
+fn page_to_text(url: &str, content_type: Option<&str>, body: String) -> String {
+    let is_html = content_type.is_some_and(|value| value.contains("html"));
+    if is_html || is_markup(Path::new(url), &body) {
+        strip_markup(&body)
+    } else {
+        body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_to_text() {
+        let page = "<p>Some <b>bold</b> text</p>".to_string();
+        let text = page_to_text(
+            "https://example.com/a",
+            Some("text/html; charset=utf-8"),
+            page,
+        );
+        assert!(!text.contains('<'));
+        assert_eq!(
+            text.split_whitespace().collect::<Vec<_>>(),
+            ["Some", "bold", "text"]
+        );
+        let plain = "1 < 2 and <b>".to_string();
+        assert_eq!(
+            page_to_text(
+                "https://example.com/a.txt",
+                Some("text/plain"),
+                plain.clone()
+            ),
+            plain
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::extract::extract_text;
 use std::fs;
 use std::path::PathBuf;
 
@@ -17,10 +18,10 @@ pub fn get_file_contents_from_dir(dir: &str) -> Vec<(String, String)> {
             .to_string();
         file_id_contents.push((
             file_path_str.clone(),
-            fs::read_to_string(filepath).unwrap_or_else(|_| {
+            extract_text(&filepath).unwrap_or_else(|err| {
                 panic!(
-                    "{} cannot be read as an UTF-8 file! Please ensure it is in the UTF-8 format.",
-                    file_path_str
+                    "{} cannot be read as an UTF-8, PDF or DOCX file! ({})",
+                    file_path_str, err
                 )
             }),
         ))
@@ -33,13 +34,11 @@ pub fn get_file_contents_from_dir(dir: &str) -> Vec<(String, String)> {
 ///     Panics on error if it's a filename error
 ///     Silently replaces file contents by error string otherwise.
 pub fn get_file_contents_from_text(text: &str) -> Vec<(String, String)> {
-    let mut file_id_contents: Vec<(String, String)> = Vec::new();
-    file_id_contents.push(("untrusted".to_string(), text.to_string()));
-    file_id_contents
+    vec![("untrusted".to_string(), text.to_string())]
 }
 
 /// Returns a list of paths to files (not subdirectories) that are in a directory
-fn get_file_paths_from_dir(dir: &str) -> Vec<PathBuf> {
+pub fn get_file_paths_from_dir(dir: &str) -> Vec<PathBuf> {
     let mut paths: Vec<PathBuf> = Vec::new();
     for entry in fs::read_dir(dir).unwrap_or_else(|_| {
         panic!(
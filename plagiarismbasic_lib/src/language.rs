@@ -0,0 +1,61 @@
+use rust_stemmers::Algorithm;
+use whatlang::Lang;
+
+/// Confidence (0.0 to 1.0) below which a detected language is not used.
+///     Lower than whatlang's own reliability threshold, which rejects most
+///     texts of a few sentences.
+const MIN_CONFIDENCE: f64 = 0.3;
+
+/// Detects the language of a text and returns its ISO 639-3 code (e.g. "eng").
+///     None if the text is too short or too mixed to tell.
+pub fn detect_language(text: &str) -> Option<String> {
+    whatlang::detect(text)
+        .filter(|info| info.confidence() >= MIN_CONFIDENCE)
+        .map(|info| info.lang().code().to_string())
+}
+
+/// Snowball stemmer for an ISO 639-3 language code, if there is one
+pub fn stemmer_for(language: &str) -> Option<Algorithm> {
+    let algorithm = match Lang::from_code(language)? {
+        Lang::Ara => Algorithm::Arabic,
+        Lang::Dan => Algorithm::Danish,
+        Lang::Nld => Algorithm::Dutch,
+        Lang::Eng => Algorithm::English,
+        Lang::Fin => Algorithm::Finnish,
+        Lang::Fra => Algorithm::French,
+        Lang::Deu => Algorithm::German,
+        Lang::Ell => Algorithm::Greek,
+        Lang::Hun => Algorithm::Hungarian,
+        Lang::Ita => Algorithm::Italian,
+        Lang::Nob => Algorithm::Norwegian,
+        Lang::Por => Algorithm::Portuguese,
+        Lang::Ron => Algorithm::Romanian,
+        Lang::Rus => Algorithm::Russian,
+        Lang::Spa => Algorithm::Spanish,
+        Lang::Swe => Algorithm::Swedish,
+        Lang::Tam => Algorithm::Tamil,
+        Lang::Tur => Algorithm::Turkish,
+        _ => return None,
+    };
+    Some(algorithm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(
+            detect_language("the committee approved the new budget for the city library"),
+            Some("eng".to_string())
+        );
+        assert_eq!(
+            detect_language("Der schnelle braune Fuchs springt über den faulen Hund und rennt weg"),
+            Some("deu".to_string())
+        );
+        assert_eq!(detect_language("ok"), None);
+        assert_eq!(stemmer_for("fra"), Some(Algorithm::French));
+        assert_eq!(stemmer_for("jpn"), None);
+    }
+}
@@ -1,21 +1,151 @@
+mod checkpoint;
+mod cjk;
+mod clusters;
+mod code_mode;
+mod confidence;
+mod config;
+pub mod corpus;
+mod cost;
+mod embeddings;
+mod error;
+mod explain;
+mod external;
+mod extract;
+#[cfg(feature = "fetch")]
+mod fetch;
 mod file_utils;
+mod language;
+mod lsh;
+mod manifest;
+mod notify;
+#[cfg(feature = "ocr")]
+pub mod ocr;
 mod plagiarism_database;
+mod pseudonyms;
+#[cfg(feature = "python")]
+mod python;
+mod result_output_annotated;
+mod result_output_csv;
+mod result_output_diff;
 mod result_output_html;
+mod result_output_json;
+mod result_output_junit;
 mod result_printer;
+pub mod results;
+mod sections;
+#[cfg(feature = "serve")]
+mod server;
+mod session;
+mod similarity;
+pub mod storage;
 mod string_compare;
+mod synonyms;
 mod text_utils;
+mod translation;
+mod tuning;
+#[cfg(feature = "wasm")]
+mod wasm;
 
-use file_utils::get_file_contents_from_dir;
-use plagiarism_database::{PlagiarismDatabase, PlagiarismResult};
+pub use checkpoint::Checkpoint;
+pub use cjk::CjkSegmenter;
+pub use clusters::cluster_results;
+pub use code_mode::{CodeMode, IDENTIFIER_TOKEN, NUMBER_TOKEN, STRING_TOKEN};
+pub use confidence::{Confidence, ConfidenceRule, ConfidenceRules};
+pub use config::{CleaningConfig, Config, ExclusionConfig, OutputConfig, OutputFormat};
+pub use cost::{CheckCost, CostEstimate};
+pub use embeddings::WordVectors;
+pub use error::{Error, Result};
+pub use explain::{ExplainedSpan, Explanation, NormalizationEffect, ScoreBreakdown};
+pub use external::ExternalSource;
+#[cfg(feature = "fetch")]
+pub use external::RemoteIndex;
+pub use extract::is_zip;
+#[cfg(feature = "fetch")]
+pub use fetch::fetch_text;
+use file_utils::{get_file_contents_from_dir, get_file_paths_from_dir};
+pub use manifest::{read_manifest, ManifestEntry, ManifestSource};
+#[cfg(feature = "fetch")]
+pub use notify::WebhookNotifier;
+pub use notify::{Notifier, StdoutNotifier};
+pub use plagiarism_database::{
+    compare_texts, dedup_results, in_namespace, sort_results, Alignment, AllowedPairPolicy,
+    ArchivedText, BudgetedCheck, ByteRange, CheckOptions, DatabaseStats, DuplicateOwnerPolicy,
+    DuplicateResult, FragmentLocation, Fragmentation, GroupComparisons, MetricResult, NgramCount,
+    OwnerStats, OwnerSummary, PipelineConfig, PlagiarismDatabase, PlagiarismDatabaseBuilder,
+    PlagiarismResult, RankedSource, ResultSort, SimilarityMatrix, SourceAttribution, SpanContext,
+    TextOwnerID, COMMON_NGRAM_COUNT, DUPLICATE_THRESHOLD,
+};
+pub use pseudonyms::{OwnerMapping, PseudonymConfig, PseudonymScheme};
+pub use result_output_annotated::{annotate_text, AnnotationFormat};
+pub use sections::{SectionMatch, SectionSplitter};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serve")]
+pub use server::{router, serve};
+pub use session::CheckSession;
+pub use similarity::SimilarityMetric;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+pub use string_compare::{levenshtein_batch, AlignedWord, EditOp, LevenshteinPattern};
+pub use synonyms::SynonymNormalizer;
+pub use text_utils::{
+    clean_text, clean_text_with_options, extract_clean_word_ngrams, extract_clean_word_skipgrams,
+    strip_markup, tokenize_text, CleanOptions, CleanedText, DefaultTokenizer, Normalization,
+    Tokenizer,
+};
+pub use translation::{DictionaryTranslator, Translator};
+pub use tuning::TuneSuggestion;
 
 /// Indicates which metric is being used for plagiarism comparison
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Metric {
     /// Check for equality between strings
     Equal,
     /// Check that Levenshtein distance between strings is lower than
     /// a given bound
     Lev,
+    /// Check that the Hamming distance between the SimHashes of the strings
+    /// is at most a given number of bits (out of 64)
+    SimHash,
+    /// Check that the cosine similarity of the averaged word vectors of the
+    /// strings is at least 100 minus a given number of percent
+    Embedding,
+    /// Find the longest common subsequence of words of two whole texts and
+    /// report its spans of at least n words, with at most a given number
+    /// of unmatched words between two matched ones
+    Lcs,
+    /// Compare fragments with the `SimilarityMetric` given to the builder
+    Custom,
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    /// Parses the metric names used on the CLI and in config files
+    fn from_str(name: &str) -> std::result::Result<Metric, String> {
+        match name {
+            "equal" => Ok(Metric::Equal),
+            "lev" => Ok(Metric::Lev),
+            "simhash" => Ok(Metric::SimHash),
+            "embedding" => Ok(Metric::Embedding),
+            "lcs" => Ok(Metric::Lcs),
+            _ => Err(format!("Unknown metric {}", name)),
+        }
+    }
+}
+
+/// How texts are split into the fragments that are compared
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FragmentMode {
+    /// Every run of n consecutive words
+    #[default]
+    Ngram,
+    /// Every sentence of at least n words
+    Sentence,
 }
 
 /// Setting fields parsed by CLI frontend
@@ -23,21 +153,111 @@ pub struct AppSettings {
     pub n: usize,
     pub s: usize,
     pub metric: Metric,
+    /// Metrics also run on every pair the metric reports
+    pub extra_metrics: Vec<Metric>,
     pub utext: String,
+    /// Directory of untrusted files, each checked as a separate submission,
+    ///     or a ZIP archive of them (see `PlagiarismDatabase::add_untrusted_zip`)
+    pub udir: Option<String>,
     pub tdir: Option<String>,
     pub idir: Option<String>,
-    // pub output_cli: bool,
-    // pub output_html: bool,
-    // pub open_html_after: bool,
+    /// File with the assignment prompt (see
+    ///     `PlagiarismDatabase::set_assignment_prompt`)
+    pub prompt: Option<String>,
+    /// Fold accented characters to their base letters before comparison
+    pub strip_diacritics: bool,
+    /// Leave text inside quotation marks out of plagiarism checks
+    pub exclude_quotes: bool,
+    /// Leave citation markers like "(Smith, 2020)" out of plagiarism checks
+    pub exclude_citations: bool,
+    /// Regexes whose matches are dropped from all texts before tokenization
+    pub exclude_patterns: Vec<String>,
+    /// Compare words case-sensitively
+    pub preserve_case: bool,
+    /// Keep digits in words
+    pub keep_numbers: bool,
+    /// Replace numbers, dates and capitalized names with placeholder words
+    pub normalize_numbers: bool,
+    pub normalize_dates: bool,
+    pub normalize_entities: bool,
+    /// Drop invisible characters and fold look-alike letters
+    pub fold_confusables: bool,
+    /// Keep punctuation inside words, e.g. apostrophes
+    pub keep_punctuation: bool,
+    /// Treat hyphenated words as two words
+    pub split_hyphens: bool,
+    /// Words left out of all texts
+    pub stopwords: Vec<String>,
+    /// Remove HTML/Markdown syntax from trusted files that look like markup
+    pub strip_markup: bool,
+    /// Compare texts as source code of this language (see `CodeMode::for_language`)
+    pub code_language: Option<String>,
+    /// Compare ngrams or whole sentences
+    pub fragment_mode: FragmentMode,
+    /// Number of threads to split the comparisons over
+    pub threads: usize,
+    /// Minimum number of matching fragments for an owner pair to be reported
+    pub min_matches: usize,
+    /// Minimum density of the matches in either text for a pair to be reported
+    pub min_density: f32,
+    /// Weight the matches by how rare their fragments are in the corpus
+    pub idf_weighting: bool,
+    /// Minimum weighted coverage of either text for a pair to be reported
+    pub min_weighted_score: f32,
+    /// Compare ngrams of all these sizes in one run (empty means just n)
+    pub ngram_sizes: Vec<usize>,
+    /// Maximum number of words skipped inside an ngram (k-skip-n-grams)
+    pub skip: usize,
+    /// Only make an ngram at every stride-th word (1 makes all of them)
+    pub stride: usize,
+    /// Only make ngrams within one sentence
+    pub within_sentences: bool,
+    /// GloVe or fastText word vector file for the embedding metric
+    pub word_vectors: Option<String>,
+    /// File of comma-separated synonym sets, one per line
+    pub synonyms: Option<String>,
+    /// File of "word, translation" pairs to the pivot language, one per line
+    pub translations: Option<String>,
+    /// Word list splitting texts detected to be in Chinese or Japanese
+    pub cjk_dictionary: Option<String>,
+    /// Fingerprint files written by `save_fingerprints`, loaded as trusted sources
+    pub fingerprints: Vec<String>,
+    /// Detect the language of every text and only compare texts in the same language
+    pub detect_language: bool,
+    /// Stem words with the stemmer of the detected language
+    pub stem: bool,
+    /// Add the word alignments of matches to results of non-equal metrics
+    pub alignments: bool,
+    /// Add snippets of every match with this many words around it to results
+    pub snippet_words: Option<usize>,
+    /// Compare texts longer than this many words in chunks of that many words
+    pub chunk_words: Option<usize>,
+    /// Only compare the candidates of a cheap first pass with the metric
+    pub pipeline: Option<PipelineConfig>,
+    /// How results are labeled High, Medium or Low confidence
+    pub confidence: ConfidenceRules,
+    /// Rename the untrusted owners for a blind review
+    pub pseudonyms: Option<PseudonymConfig>,
+    /// Split texts into sections and count the matches in each
+    pub sections: Option<SectionSplitter>,
+    /// Checkpoint file to write the progress of the check to
+    pub checkpoint: Option<String>,
+    /// Checkpoint of an interrupted run to resume
+    pub resume: Option<String>,
+    /// Seconds between two checkpoints
+    pub checkpoint_interval: u64,
+    /// Manifest of texts to add (see `read_manifest`), "-" to read it from stdin
+    pub manifest: Option<String>,
+    /// Exit with a warning (1) if the highest score is above this
+    pub warn_above: Option<f32>,
+    /// Exit with a failure (2) if the highest score is above this
+    pub fail_above: Option<f32>,
 }
 
-/// Reads settings from CLI input.
 /// Reads all the relevant source files based on settings
-/// Loads all sources into DB
-/// Runs the plagiarism algorithm with settings from CLI
-/// Prints results on CLI
-/// Renders results as HTML and opens it automatically using xdg-open if possible
-pub fn run_plagiarism_checks(appsettings: AppSettings) -> Vec<PlagiarismResult> {
+/// Loads all sources into a DB configured with the settings,
+///     ready to run checks on
+pub fn build_database(appsettings: &AppSettings) -> PlagiarismDatabase {
     // Read all file contents in both specified directories
     // Fail with panic if any file is not UTF8, or any other error
     let untrusted_contents = file_utils::get_file_contents_from_text(&appsettings.utext);
@@ -45,49 +265,251 @@ pub fn run_plagiarism_checks(appsettings: AppSettings) -> Vec<PlagiarismResult>
     // Try to add ignore-text if specified. This is required early for optimization.
     let mut ignored_texts: Vec<String> = Vec::new();
     if let Some(idir) = &appsettings.idir {
-        let ignore_contents = get_file_contents_from_dir(&idir);
+        let ignore_contents = get_file_contents_from_dir(idir);
         for (_, val) in ignore_contents {
             ignored_texts.push(val);
         }
     }
 
     // Add text to the DB
-    let mut db = PlagiarismDatabase::new(
-        appsettings.n,
-        appsettings.s,
-        appsettings.metric,
-        ignored_texts,
-    );
+    let mut builder = PlagiarismDatabase::builder(appsettings.n, appsettings.s, appsettings.metric)
+        .ignored_texts(ignored_texts)
+        .extra_metrics(appsettings.extra_metrics.clone())
+        .strip_diacritics(appsettings.strip_diacritics)
+        .exclude_quotes(appsettings.exclude_quotes)
+        .exclude_citations(appsettings.exclude_citations)
+        .exclude_patterns(appsettings.exclude_patterns.clone())
+        .preserve_case(appsettings.preserve_case)
+        .keep_numbers(appsettings.keep_numbers)
+        .normalize_numbers(appsettings.normalize_numbers)
+        .normalize_dates(appsettings.normalize_dates)
+        .normalize_entities(appsettings.normalize_entities)
+        .fold_confusables(appsettings.fold_confusables)
+        .keep_punctuation(appsettings.keep_punctuation)
+        .split_hyphens(appsettings.split_hyphens)
+        .stopwords(appsettings.stopwords.clone())
+        .detect_markup(appsettings.strip_markup)
+        .fragment_mode(appsettings.fragment_mode)
+        .threads(appsettings.threads)
+        .min_matches(appsettings.min_matches)
+        .min_density(appsettings.min_density)
+        .idf_weighting(appsettings.idf_weighting)
+        .min_weighted_score(appsettings.min_weighted_score)
+        .ngram_sizes(appsettings.ngram_sizes.clone())
+        .skip(appsettings.skip)
+        .stride(appsettings.stride)
+        .within_sentences(appsettings.within_sentences)
+        .alignments(appsettings.alignments)
+        .confidence_rules(appsettings.confidence.clone())
+        .detect_language(appsettings.detect_language)
+        .stem(appsettings.stem);
+    if let Some(pipeline) = &appsettings.pipeline {
+        builder = builder.pipeline(pipeline.clone());
+    }
+    if let Some(splitter) = &appsettings.sections {
+        builder = builder.sections(splitter.clone());
+    }
+    if let Some(context_words) = appsettings.snippet_words {
+        builder = builder.snippets(context_words);
+    }
+    if let Some(words) = appsettings.chunk_words {
+        builder = builder.chunk_words(words);
+    }
+    if let Some(path) = &appsettings.word_vectors {
+        let word_vectors = WordVectors::from_path(path)
+            .unwrap_or_else(|err| panic!("Could not read word vectors from {}: {}", path, err));
+        builder = builder.word_vectors(word_vectors);
+    }
+    if let Some(path) = &appsettings.synonyms {
+        let synonyms = SynonymNormalizer::from_path(path)
+            .unwrap_or_else(|err| panic!("Could not read synonyms from {}: {}", path, err));
+        builder = builder.synonyms(synonyms);
+    }
+    if let Some(path) = &appsettings.translations {
+        let translator = DictionaryTranslator::from_path(path)
+            .unwrap_or_else(|err| panic!("Could not read translations from {}: {}", path, err));
+        builder = builder.translator(translator);
+    }
+    if let Some(path) = &appsettings.cjk_dictionary {
+        let segmenter = CjkSegmenter::from_path(path)
+            .unwrap_or_else(|err| panic!("Could not read the CJK dictionary {}: {}", path, err));
+        builder = builder.cjk_dictionary(segmenter);
+    }
+    if let Some(language) = &appsettings.code_language {
+        let code_mode = CodeMode::for_language(language)
+            .unwrap_or_else(|| panic!("No code mode is available for language {}", language));
+        builder = builder.code_mode(code_mode);
+    }
+    let mut db = builder.build();
+    if let Some(prompt) = &appsettings.prompt {
+        let text = extract::extract_text(Path::new(prompt))
+            .unwrap_or_else(|err| panic!("Cannot read prompt {}! ({})", prompt, err));
+        db.set_assignment_prompt(&text);
+    }
 
-    for (id, val) in untrusted_contents {
-        db.add_untrusted_text(&id, &val);
+    if !appsettings.utext.is_empty() {
+        db.add_untrusted_texts_par(untrusted_contents);
+    }
+    if let Some(udir) = &appsettings.udir {
+        if is_zip(Path::new(udir)) {
+            db.add_untrusted_zip(udir)
+        } else {
+            db.add_untrusted_files_par(&get_file_paths_from_dir(udir))
+        }
+        .unwrap_or_else(|err| panic!("Cannot read an untrusted text! ({})", err));
+    }
+
+    if let Some(manifest) = &appsettings.manifest {
+        let entries = if manifest == "-" {
+            read_manifest(io::stdin().lock())
+        } else {
+            File::open(manifest)
+                .map_err(Error::from)
+                .and_then(|file| read_manifest(BufReader::new(file)))
+        }
+        .unwrap_or_else(|err| panic!("Cannot read manifest {}! ({})", manifest, err));
+        db.add_manifest(&entries)
+            .unwrap_or_else(|err| panic!("Cannot add the texts of the manifest! ({})", err));
     }
 
     // Try to add trusted text if specified
     if let Some(tdir) = &appsettings.tdir {
-        let trusted_contents = get_file_contents_from_dir(&tdir);
-        for (id, val) in trusted_contents {
-            db.add_trusted_text(&id, &val);
+        for path in get_file_paths_from_dir(tdir) {
+            db.add_trusted_file(&path).unwrap_or_else(|err| {
+                panic!(
+                    "{} cannot be read as a trusted text! ({})",
+                    path.display(),
+                    err
+                )
+            });
         }
     }
+    for path in &appsettings.fingerprints {
+        db.load_fingerprints(path)
+            .unwrap_or_else(|err| panic!("Cannot read fingerprints {}! ({})", path, err));
+    }
+    if let Some(pseudonyms) = &appsettings.pseudonyms {
+        db.pseudonymize_owners(pseudonyms.scheme, &pseudonyms.key)
+            .write(&pseudonyms.mapping)
+            .unwrap_or_else(|err| {
+                panic!(
+                    "Cannot write pseudonym mapping {}! ({})",
+                    pseudonyms.mapping, err
+                )
+            });
+    }
+    db
+}
 
+/// Builds the DB from the settings and runs the plagiarism algorithm
+///     against the trusted texts
+pub fn run_plagiarism_checks(appsettings: AppSettings) -> Vec<PlagiarismResult> {
+    let db = build_database(&appsettings);
     // Run both inter-source plagiarism and external-source-based plagiarism checks
     // let mut ut_result: Vec<PlagiarismResult> = db.check_untrusted_plagiarism();
-    let t_result: Vec<PlagiarismResult> = db.check_trusted_plagiarism();
-    t_result
-    // // Print them separately on the CLI
-    // if appsettings.output_cli {
-    //     result_printer::print_results_ut(&mut ut_result);
-    //     result_printer::print_results_t(&mut t_result);
-    // }
-
-    // if appsettings.output_html {
-    //     // Pass them together to the HTML output module
-    //     ut_result.append(&mut t_result);
-    //     result_output_html::output_results(
-    //         &mut ut_result,
-    //         db.get_all_cleantext(),
-    //         appsettings.open_html_after,
-    //     );
-    // }
+    run_trusted_check(&db, &appsettings)
+}
+
+/// Checks the untrusted texts of a DB against its trusted texts, writing
+///     checkpoints and resuming from one if the settings ask for it
+pub fn run_trusted_check(
+    db: &PlagiarismDatabase,
+    appsettings: &AppSettings,
+) -> Vec<PlagiarismResult> {
+    let path = match appsettings
+        .checkpoint
+        .as_ref()
+        .or(appsettings.resume.as_ref())
+    {
+        Some(path) => path,
+        None => return db.check_trusted_plagiarism(),
+    };
+    let resume = appsettings.resume.as_ref().map(|resume| {
+        Checkpoint::from_path(resume)
+            .unwrap_or_else(|err| panic!("Cannot read checkpoint {}! ({})", resume, err))
+    });
+    let interval = Duration::from_secs(appsettings.checkpoint_interval);
+    db.check_with_checkpoint(path, interval, resume)
+        .unwrap_or_else(|err| panic!("Cannot check with checkpoint {}! ({})", path, err))
+}
+
+/// Prints results of a trusted check on the CLI, most significant first
+pub fn print_results(results: &mut [PlagiarismResult]) {
+    result_printer::print_results_t(results);
+}
+
+/// Renders results as HTML into the output directory, and opens the report
+///     automatically using xdg-open if asked to
+pub fn write_html_report<P: AsRef<Path>>(
+    results: &mut [PlagiarismResult],
+    db: &PlagiarismDatabase,
+    output_dir: P,
+    open_after: bool,
+) {
+    result_output_html::output_results(
+        results,
+        db.get_all_cleantext(),
+        output_dir.as_ref(),
+        open_after,
+    );
+}
+
+/// Renders results as a HTML page, with its assets expected in a relative
+///     `assets/` directory
+pub fn render_html_report(results: &mut [PlagiarismResult], db: &PlagiarismDatabase) -> String {
+    result_output_html::render_results(results, db.get_all_cleantext())
+}
+
+/// Writes every untrusted text of the settings (from `-u` or `--utext`) into
+///     the output directory with its matches marked, see `annotate_text`
+pub fn write_annotated_texts<P: AsRef<Path>>(
+    results: &[PlagiarismResult],
+    db: &PlagiarismDatabase,
+    appsettings: &AppSettings,
+    output_dir: P,
+    format: AnnotationFormat,
+) -> Result<()> {
+    let mut texts = file_utils::get_file_contents_from_text(&appsettings.utext);
+    texts.retain(|(_, text)| !text.is_empty());
+    if let Some(udir) = &appsettings.udir {
+        texts.extend(db.read_untrusted_texts(udir)?);
+    }
+    result_output_annotated::output_texts(results, &texts, output_dir.as_ref(), format)?;
+    Ok(())
+}
+
+/// Writes results to a JSON file, most significant first
+pub fn write_json_report<P: AsRef<Path>>(
+    results: &mut [PlagiarismResult],
+    output_path: P,
+) -> io::Result<()> {
+    result_output_json::output_results(results, output_path.as_ref())
+}
+
+/// Writes results to a JUnit XML file for CI dashboards, with every owner
+///     pair as a failed test case
+pub fn write_junit_report<P: AsRef<Path>>(
+    results: &mut [PlagiarismResult],
+    output_path: P,
+) -> io::Result<()> {
+    result_output_junit::output_results(results, output_path.as_ref())
+}
+
+/// Renders results as a JUnit XML document
+pub fn render_junit_report(results: &mut [PlagiarismResult]) -> String {
+    result_output_junit::render_results(results)
+}
+
+/// Writes a similarity matrix to a CSV file, with a header row and one row
+///     per owner
+pub fn write_similarity_csv<P: AsRef<Path>>(
+    matrix: &SimilarityMatrix,
+    output_path: P,
+) -> io::Result<()> {
+    result_output_csv::output_matrix(matrix, output_path.as_ref())
+}
+
+/// Renders a similarity matrix as CSV
+pub fn render_similarity_csv(matrix: &SimilarityMatrix) -> String {
+    result_output_csv::render_matrix(matrix)
 }
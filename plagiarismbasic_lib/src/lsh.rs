@@ -0,0 +1,104 @@
+use crate::text_utils::FragmentHash;
+use std::collections::{BTreeSet, HashMap};
+
+/// Computes a MinHash signature of the given length for a set of fragment
+///     hashes. Each hash is remixed with a different seed per signature slot
+///     to simulate independent hash functions.
+pub fn minhash_signature<'a, I>(fragments: I, num_hashes: usize) -> Vec<u64>
+where
+    I: IntoIterator<Item = &'a FragmentHash>,
+{
+    let mut signature = vec![u64::MAX; num_hashes];
+    for &base in fragments {
+        for (i, slot) in signature.iter_mut().enumerate() {
+            let value = splitmix64(base ^ splitmix64(i as u64));
+            if value < *slot {
+                *slot = value;
+            }
+        }
+    }
+    signature
+}
+
+/// Splits each signature into `bands` bands of `rows` values and returns every
+///     pair of signature indices (lower index first) that agree on at least one band,
+///     in sorted order
+pub fn candidate_pairs(
+    signatures: &[Vec<u64>],
+    bands: usize,
+    rows: usize,
+) -> BTreeSet<(usize, usize)> {
+    let mut candidates: BTreeSet<(usize, usize)> = BTreeSet::new();
+    for band in 0..bands {
+        // Signatures with the same values in this band land in the same bucket
+        let mut buckets: HashMap<&[u64], Vec<usize>> = HashMap::new();
+        for (idx, signature) in signatures.iter().enumerate() {
+            let band_values = &signature[band * rows..(band + 1) * rows];
+            buckets.entry(band_values).or_default().push(idx);
+        }
+        for members in buckets.values() {
+            for (i, &first) in members.iter().enumerate() {
+                for &second in members.iter().skip(i + 1) {
+                    candidates.insert((first, second));
+                }
+            }
+        }
+    }
+    candidates
+}
+
+/// Finalizer of the SplitMix64 generator, used as a cheap 64-bit mixing function
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_utils::hash_fragment;
+
+    fn fragment_set(range: std::ops::Range<usize>) -> Vec<FragmentHash> {
+        range
+            .map(|i| hash_fragment(&format!("fragment {}", i)))
+            .collect()
+    }
+
+    /// Estimates the Jaccard similarity of two sets from their MinHash signatures
+    fn estimate_similarity(sig1: &[u64], sig2: &[u64]) -> f32 {
+        let equal = sig1.iter().zip(sig2).filter(|(a, b)| a == b).count();
+        equal as f32 / sig1.len() as f32
+    }
+
+    #[test]
+    fn test_minhash_similarity() {
+        let a = fragment_set(0..100);
+        let b = fragment_set(0..100);
+        let c = fragment_set(50..150);
+        let d = fragment_set(1000..1100);
+        let sig_a = minhash_signature(&a, 128);
+        assert_eq!(sig_a, minhash_signature(&b, 128));
+        // True Jaccard similarity of a and c is 50 / 150
+        let estimate = estimate_similarity(&sig_a, &minhash_signature(&c, 128));
+        assert!(estimate > 0.2 && estimate < 0.5);
+        assert!(estimate_similarity(&sig_a, &minhash_signature(&d, 128)) < 0.1);
+    }
+
+    #[test]
+    fn test_candidate_pairs() {
+        let signatures: Vec<Vec<u64>> = [
+            fragment_set(0..100),
+            fragment_set(1000..1100),
+            fragment_set(0..98),
+        ]
+        .iter()
+        .map(|set| minhash_signature(set, 32))
+        .collect();
+        let candidates = candidate_pairs(&signatures, 8, 4);
+        assert!(candidates.contains(&(0, 2)));
+        assert!(!candidates.contains(&(0, 1)));
+        assert!(!candidates.contains(&(1, 2)));
+    }
+}
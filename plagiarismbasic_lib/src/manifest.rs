@@ -0,0 +1,151 @@
+use crate::error::{Error, Result};
+use crate::plagiarism_database::TextOwnerID;
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+
+/// Where the text of a manifest entry comes from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestSource {
+    /// A file read like `add_trusted_file` (plain text, or PDF/DOCX with the
+    ///     matching feature enabled)
+    Path(String),
+    /// The text itself
+    Text(String),
+}
+
+/// One text of a manifest, see `read_manifest`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub owner_id: TextOwnerID,
+    /// Added as a potential source instead of a text to check
+    #[serde(default)]
+    pub trusted: bool,
+    #[serde(flatten)]
+    pub source: ManifestSource,
+}
+
+/// Reads a manifest describing a whole corpus, so that other tools can
+///     drive the checker without writing its texts into folders first
+///     (see `PlagiarismDatabase::add_manifest`). Three formats are read:
+///     - a JSON array of objects like
+///       `{"owner_id": "alice", "trusted": false, "path": "essays/alice.txt"}`,
+///       with `"text": "..."` instead of `path` for an inline text;
+///     - JSON Lines with one such object per line;
+///     - TSV lines of `owner_id`, `trusted` or `untrusted`, and a path, or
+///       `text:` followed by an inline text with `\t`, `\n` and `\\`
+///       escaped. Empty lines, lines starting with '#' and a header line
+///       starting with `owner_id` are skipped.
+pub fn read_manifest<R: BufRead>(mut reader: R) -> Result<Vec<ManifestEntry>> {
+    let mut manifest = String::new();
+    reader.read_to_string(&mut manifest)?;
+    match manifest.trim_start().chars().next() {
+        Some('[') => Ok(serde_json::from_str(&manifest)?),
+        Some('{') => manifest
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect(),
+        _ => manifest
+            .lines()
+            .enumerate()
+            .filter(|(idx, line)| {
+                !(line.trim().is_empty()
+                    || line.starts_with('#')
+                    || (*idx == 0 && line.starts_with("owner_id\t")))
+            })
+            .map(|(idx, line)| parse_tsv_line(idx + 1, line))
+            .collect(),
+    }
+}
+
+/// Reads one `owner_id<TAB>trusted<TAB>path-or-text:...` line
+fn parse_tsv_line(line_number: usize, line: &str) -> Result<ManifestEntry> {
+    let invalid = |message: &str| Error::InvalidManifest {
+        line: line_number,
+        message: message.to_string(),
+    };
+    let mut columns = line.splitn(3, '\t');
+    let (owner_id, trust, source) = match (columns.next(), columns.next(), columns.next()) {
+        (Some(owner_id), Some(trust), Some(source)) if !owner_id.is_empty() => {
+            (owner_id, trust, source)
+        }
+        _ => return Err(invalid("expected owner_id, trust flag and path or text")),
+    };
+    let trusted = match trust.trim().to_lowercase().as_str() {
+        "trusted" | "true" | "1" => true,
+        "untrusted" | "false" | "0" => false,
+        _ => return Err(invalid("the trust flag must be trusted or untrusted")),
+    };
+    let source = match source.strip_prefix("text:") {
+        Some(text) => ManifestSource::Text(unescape(text)),
+        None => ManifestSource::Path(source.trim_end_matches('\r').to_string()),
+    };
+    Ok(ManifestEntry {
+        owner_id: owner_id.to_string(),
+        trusted,
+        source,
+    })
+}
+
+/// Replaces the `\t`, `\n`, `\r` and `\\` escapes of an inline TSV text
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.trim_end_matches('\r').chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+    use std::fs;
+
+    #[test]
+    fn test_read_manifest() {
+        let path = std::env::temp_dir().join("plagiarismbasic_test_manifest_book.txt");
+        fs::write(&path, "the mitochondria is the powerhouse of the cell").unwrap();
+        let tsv = format!(
+            "owner_id\ttrusted\tsource\nbook\ttrusted\t{}\n# comment\nessay\tuntrusted\ttext:we know\\nthe powerhouse of the cell\n",
+            path.display()
+        );
+        let entries = read_manifest(tsv.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[1].source,
+            ManifestSource::Text("we know\nthe powerhouse of the cell".to_string())
+        );
+        let json = serde_json::to_string(&entries).unwrap();
+        assert!(json.contains("\"path\":"));
+        assert_eq!(read_manifest(json.as_bytes()).unwrap(), entries);
+        let lines = "{\"owner_id\": \"essay\", \"text\": \"hello\"}\n";
+        assert!(!read_manifest(lines.as_bytes()).unwrap()[0].trusted);
+        assert!(matches!(
+            read_manifest("a\tmaybe\tb.txt".as_bytes()),
+            Err(Error::InvalidManifest { line: 1, .. })
+        ));
+
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_manifest(&entries).unwrap();
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            (results[0].owner_id1.as_str(), results[0].owner_id2.as_str()),
+            ("book", "essay")
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
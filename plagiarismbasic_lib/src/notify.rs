@@ -0,0 +1,162 @@
+use crate::error::Result;
+use crate::plagiarism_database::PlagiarismResult;
+use crate::results::Severity;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// Alerted about every result whose severity reaches the thresholds given to
+///     `notifier` on the builder, as soon as a check finds it, so that a
+///     service embedding the checker doesn't need to poll result lists.
+///     Closures taking the result and its severity implement it too.
+pub trait Notifier: Send + Sync {
+    /// Called from the thread that compared the owner pair, so results of
+    ///     different pairs may arrive at the same time and in any order. A
+    ///     failed notification doesn't stop the check.
+    fn notify(&self, result: &PlagiarismResult, severity: Severity) -> Result<()>;
+}
+
+impl<F: Fn(&PlagiarismResult, Severity) -> Result<()> + Send + Sync> Notifier for F {
+    fn notify(&self, result: &PlagiarismResult, severity: Severity) -> Result<()> {
+        self(result, severity)
+    }
+}
+
+/// Prints every result as a JSON line after its severity, like
+///     `[fail] {"owner_id1": ...}`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutNotifier;
+
+impl Notifier for StdoutNotifier {
+    fn notify(&self, result: &PlagiarismResult, severity: Severity) -> Result<()> {
+        let line = format!("[{}] {}\n", label(severity), serde_json::to_string(result)?);
+        // One write per line, so that lines of several threads don't interleave
+        io::stdout().lock().write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// POSTs every result as JSON to a URL, with its severity in the
+///     `X-Plagiarism-Severity` header. Blocks the comparing thread until the
+///     hook answers, and must not be called from an async runtime (run checks
+///     with `spawn_blocking` there).
+#[cfg(feature = "fetch")]
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    url: String,
+    client: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "fetch")]
+impl WebhookNotifier {
+    pub fn new(url: &str) -> WebhookNotifier {
+        WebhookNotifier {
+            url: url.to_string(),
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+}
+
+#[cfg(feature = "fetch")]
+impl Notifier for WebhookNotifier {
+    fn notify(&self, result: &PlagiarismResult, severity: Severity) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .header("X-Plagiarism-Severity", label(severity))
+            .json(result)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| io::Error::other(format!("Cannot notify {}: {}", self.url, err)))?;
+        Ok(())
+    }
+}
+
+/// Lowercase name of a severity
+fn label(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Pass => "pass",
+        Severity::Warn => "warn",
+        Severity::Fail => "fail",
+    }
+}
+
+/// The notifier of a database with its thresholds, shared by its clones and
+///     threads
+#[derive(Clone)]
+pub(crate) struct Notifications {
+    pub(crate) notifier: Arc<dyn Notifier>,
+    pub(crate) warn_above: Option<f32>,
+    pub(crate) fail_above: Option<f32>,
+}
+
+impl Notifications {
+    /// Passes on the results that are at least `Warn`
+    pub(crate) fn send(&self, results: &[PlagiarismResult]) {
+        for result in results {
+            let severity = Severity::of(
+                std::slice::from_ref(result),
+                self.warn_above,
+                self.fail_above,
+            );
+            if severity == Severity::Pass {
+                continue;
+            }
+            if let Err(err) = self.notifier.notify(result, severity) {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %err, "Notification failed");
+                #[cfg(not(feature = "tracing"))]
+                let _ = err;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Notifications {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Notifications")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_notifier() {
+        let notified: Arc<Mutex<Vec<(String, Severity)>>> = Arc::default();
+        let log = Arc::clone(&notified);
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .threads(2)
+            .notifier(
+                move |result: &PlagiarismResult, severity: Severity| {
+                    log.lock()
+                        .unwrap()
+                        .push((result.owner_id2.clone(), severity));
+                    Ok(())
+                },
+                Some(0.5),
+                Some(0.9),
+            )
+            .build();
+        db.add_trusted_text("source", "the mitochondria is the powerhouse of the cell");
+        // 3 of 6 ngrams match (0.5), 3 of 4 (0.75) and 4 of 4 (1.0)
+        db.add_untrusted_text("half", "we know the powerhouse of the cell well");
+        db.add_untrusted_text("most", "the powerhouse of the cell well");
+        db.add_untrusted_text("all", "is the powerhouse of the cell");
+        db.add_untrusted_text("other", "a text about something else entirely the cell");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 3);
+        // A score equal to the warning threshold is not notified
+        let mut notified = notified.lock().unwrap().clone();
+        notified.sort();
+        assert_eq!(
+            notified,
+            vec![
+                ("all".to_string(), Severity::Fail),
+                ("most".to_string(), Severity::Warn)
+            ]
+        );
+    }
+}
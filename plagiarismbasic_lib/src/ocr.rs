@@ -0,0 +1,312 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Reads the text of a scanned image, a scanned PDF or OCR output (hOCR or
+///     ALTO XML), see `extract::extract_text`. The text is cleaned of common
+///     OCR noise with `normalize_ocr_noise`. `xml` files that aren't ALTO
+///     are read as they are.
+pub(crate) fn extract_ocr(
+    path: &Path,
+    extension: &str,
+    read: impl FnOnce() -> io::Result<Vec<u8>>,
+) -> io::Result<String> {
+    let text = match extension {
+        "hocr" => hocr_to_text(&utf8(read()?)?)?,
+        "alto" | "xml" => {
+            let xml = utf8(read()?)?;
+            if !xml.contains("<alto") {
+                return Ok(xml);
+            }
+            alto_to_text(&xml)?
+        }
+        "pdf" => ocr_pdf(path, &read()?)?,
+        _ => with_temp_file(&read()?, extension, ocr_image)?,
+    };
+    Ok(normalize_ocr_noise(&text))
+}
+
+fn utf8(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Recognizes the text of an image with the `tesseract` command, which must
+///     be installed
+pub fn ocr_image(path: &Path) -> io::Result<String> {
+    run(
+        "tesseract",
+        Command::new("tesseract").arg(path).arg("stdout"),
+    )
+}
+
+/// Recognizes the text of every page of a scanned PDF, rendered with the
+///     `pdftoppm` command (from poppler) for `tesseract`
+pub fn ocr_pdf(path: &Path, bytes: &[u8]) -> io::Result<String> {
+    with_temp_file(bytes, "pdf", |pdf| {
+        let prefix = pdf.with_extension("page");
+        run(
+            "pdftoppm",
+            Command::new("pdftoppm")
+                .args(["-r", "300", "-png"])
+                .arg(pdf)
+                .arg(&prefix),
+        )?;
+        // pdftoppm pads the page numbers, so the pages sort by name
+        let prefix_name = prefix.file_name().unwrap_or_default().to_string_lossy();
+        let dir = prefix.parent().unwrap_or(Path::new("."));
+        let mut pages: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|page| {
+                page.file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(&*prefix_name))
+            })
+            .collect();
+        pages.sort();
+        let texts: io::Result<Vec<String>> = pages.iter().map(|page| ocr_image(page)).collect();
+        for page in &pages {
+            let _ = fs::remove_file(page);
+        }
+        Ok(texts?.join("\n\n"))
+    })
+    .map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("Cannot OCR PDF {}: {}", path.display(), err),
+        )
+    })
+}
+
+/// Runs a command, returning its standard output
+fn run(name: &str, command: &mut Command) -> io::Result<String> {
+    let output = command.output().map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("Cannot run {} (is it installed?): {}", name, err),
+        )
+    })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    utf8(output.stdout)
+}
+
+/// Calls f with a temporary file holding the bytes, since the OCR commands
+///     only read files (and the bytes may come from a ZIP archive)
+fn with_temp_file<T>(
+    bytes: &[u8],
+    extension: &str,
+    f: impl FnOnce(&Path) -> io::Result<T>,
+) -> io::Result<T> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "plagiarismbasic_ocr_{}_{}.{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed),
+        extension
+    ));
+    fs::write(&path, bytes)?;
+    let result = f(&path);
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// Collects the words of an hOCR document (HTML with `ocrx_word` spans),
+///     separating lines by newlines and paragraphs by blank lines
+pub fn hocr_to_text(xml: &str) -> io::Result<String> {
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    let invalid = |err: quick_xml::Error| io::Error::new(io::ErrorKind::InvalidData, err);
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().check_end_names = false;
+    let mut text = String::new();
+    // Classes of the open elements, to know when a word, line or paragraph ends
+    let mut classes: Vec<String> = Vec::new();
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(tag) => {
+                let class = tag
+                    .try_get_attribute("class")
+                    .ok()
+                    .flatten()
+                    .map(|class| class.value.into_owned())
+                    .unwrap_or_default();
+                if class.split_whitespace().any(|class| class == "ocrx_word")
+                    && !text.is_empty()
+                    && !text.ends_with(char::is_whitespace)
+                {
+                    text.push(' ');
+                }
+                classes.push(class);
+            }
+            Event::End(_) => match classes.pop().as_deref() {
+                Some("ocr_line" | "ocrx_line") => text.push('\n'),
+                Some("ocr_par") => text.push('\n'),
+                _ => {}
+            },
+            Event::Text(run) if in_word(&classes) => text.push_str(&run.xml10_content()),
+            Event::GeneralRef(entity) if in_word(&classes) => {
+                push_entity(&mut text, &entity).map_err(invalid)?
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+/// Checks if any of the open hOCR elements is a word
+fn in_word(classes: &[String]) -> bool {
+    classes
+        .iter()
+        .any(|class| class.split_whitespace().any(|class| class == "ocrx_word"))
+}
+
+/// Collects the words (`String` elements) of an ALTO XML document,
+///     separating lines by newlines and text blocks by blank lines. Words
+///     hyphenated across lines are joined.
+pub fn alto_to_text(xml: &str) -> io::Result<String> {
+    use quick_xml::events::{BytesStart, Event};
+    use quick_xml::Reader;
+
+    let invalid = |err: quick_xml::Error| io::Error::new(io::ErrorKind::InvalidData, err);
+    let content = |tag: &BytesStart| -> io::Result<Option<String>> {
+        match tag.try_get_attribute("CONTENT") {
+            Ok(Some(content)) => Ok(Some(
+                content
+                    .normalized_value(quick_xml::XmlVersion::Implicit1_0)
+                    .map_err(invalid)?
+                    .into_owned(),
+            )),
+            _ => Ok(None),
+        }
+    };
+    let mut reader = Reader::from_str(xml);
+    let mut text = String::new();
+    let mut hyphenated = false;
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(tag) | Event::Empty(tag) => match tag.local_name().as_ref() {
+                "String" => {
+                    if let Some(word) = content(&tag)? {
+                        if !hyphenated && !text.is_empty() && !text.ends_with('\n') {
+                            text.push(' ');
+                        }
+                        text.push_str(&word);
+                        hyphenated = false;
+                    }
+                }
+                "HYP" => hyphenated = true,
+                _ => {}
+            },
+            Event::End(tag) => match tag.local_name().as_ref() {
+                "TextLine" if !hyphenated => text.push('\n'),
+                "TextBlock" => text.push('\n'),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+fn push_entity(
+    text: &mut String,
+    entity: &quick_xml::events::BytesRef,
+) -> Result<(), quick_xml::Error> {
+    if let Some(c) = entity.resolve_char_ref()? {
+        text.push(c);
+    } else if let Some(resolved) =
+        quick_xml::escape::resolve_predefined_entity(&entity.xml10_content())
+    {
+        text.push_str(resolved);
+    }
+    Ok(())
+}
+
+/// Characters OCR confuses with letters, and the letters they are folded to
+///     when they are inside a word
+const CONFUSED_WITH_LETTERS: [(char, char); 4] = [('0', 'o'), ('1', 'l'), ('|', 'l'), ('5', 's')];
+
+/// Typographic ligatures and the letters they stand for
+const LIGATURES: [(char, &str); 7] = [
+    ('\u{FB00}', "ff"),
+    ('\u{FB01}', "fi"),
+    ('\u{FB02}', "fl"),
+    ('\u{FB03}', "ffi"),
+    ('\u{FB04}', "ffl"),
+    ('\u{FB05}', "st"),
+    ('\u{FB06}', "st"),
+];
+
+lazy_static! {
+    /// A word hyphenated at the end of a line
+    static ref LINE_HYPHEN: Regex = Regex::new(r"(\p{L})-[ \t]*\r?\n\s*(\p{L})").unwrap();
+}
+
+/// Removes common OCR noise before tokenization: words hyphenated across
+///     lines are joined, soft hyphens are dropped, ligatures are spelled out,
+///     curly quotes are straightened and digits or bars between two letters
+///     (like "w0rd" or "he|p") are folded to the letters they look like
+pub fn normalize_ocr_noise(text: &str) -> String {
+    let joined = LINE_HYPHEN.replace_all(text, "$1$2");
+    let chars: Vec<char> = joined.chars().filter(|&c| c != '\u{AD}').collect();
+    let mut normalized = String::with_capacity(joined.len());
+    for (idx, &c) in chars.iter().enumerate() {
+        if let Some(&(_, letters)) = LIGATURES.iter().find(|&&(ligature, _)| ligature == c) {
+            normalized.push_str(letters);
+            continue;
+        }
+        let between_letters = idx > 0
+            && chars[idx - 1].is_alphabetic()
+            && chars.get(idx + 1).is_some_and(|next| next.is_alphabetic());
+        let folded = CONFUSED_WITH_LETTERS
+            .iter()
+            .find(|&&(confused, _)| confused == c)
+            .filter(|_| between_letters)
+            .map(|&(_, letter)| letter);
+        normalized.push(match c {
+            '\u{2018}' | '\u{2019}' => '\'',
+            '\u{201C}' | '\u{201D}' => '"',
+            _ => folded.unwrap_or(c),
+        });
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ocr_formats() {
+        let hocr = r#"<html><body><div class="ocr_page">
+            <p class="ocr_par"><span class="ocr_line"><span class="ocrx_word">Fish</span>
+            <span class="ocrx_word">&amp;</span> <span class="ocrx_word"><strong>chips</strong></span></span>
+            <span class="ocr_line"><span class="ocrx_word">second</span></span></p>
+        </div></body></html>"#;
+        assert_eq!(hocr_to_text(hocr).unwrap(), "Fish & chips\nsecond\n\n");
+
+        let alto = r#"<alto><Layout><Page><PrintSpace><TextBlock>
+            <TextLine><String CONTENT="The"/><SP/><String CONTENT="pro"/><HYP CONTENT="-"/></TextLine>
+            <TextLine><String CONTENT="cess"/><SP/><String CONTENT="&quot;works&quot;"/></TextLine>
+        </TextBlock></PrintSpace></Page></Layout></alto>"#;
+        assert_eq!(alto_to_text(alto).unwrap(), "The process \"works\"\n\n");
+
+        assert_eq!(
+            normalize_ocr_noise(
+                "The w0rd \u{FB01}sh he|p exam-\nple\u{AD}s \u{201C}2019\u{201D} a 10"
+            ),
+            "The word fish help examples \"2019\" a 10"
+        );
+    }
+}
@@ -1,16 +1,54 @@
-use crate::string_compare::is_plagiarised;
-use crate::text_utils::{clean_text, extract_clean_word_ngrams};
-use crate::Metric;
-use serde::Serialize;
-use std::collections::{HashMap, HashSet};
-use std::iter::FromIterator;
+use crate::cjk::{is_cjk_language, CjkSegmenter};
+use crate::code_mode::CodeMode;
+use crate::confidence::{Confidence, ConfidenceRules};
+use crate::embeddings::{check_embedding, WordVectors};
+use crate::error::{Error, Result};
+use crate::external::ExternalSource;
+use crate::extract::{extract_text, extract_zip, is_markup, is_zip, ZipText};
+use crate::file_utils::get_file_paths_from_dir;
+use crate::language::{detect_language, stemmer_for};
+use crate::lsh::{candidate_pairs, minhash_signature};
+use crate::manifest::{ManifestEntry, ManifestSource};
+use crate::notify::{Notifications, Notifier};
+use crate::pseudonyms::{make_pseudonyms, OwnerMapping, PseudonymScheme};
+use crate::sections::{section_matches, split_sections, SectionMatch, SectionSplitter};
+use crate::similarity::{CustomMetric, SimilarityMetric};
+use crate::storage::archive::ArchiveStore;
+use crate::string_compare::{
+    align_words, check_simhash, is_plagiarised, lcs_spans, longest_common_subsequence, simhash,
+    AlignedWord, EditOp, LevenshteinPattern,
+};
+use crate::synonyms::SynonymNormalizer;
+use crate::text_utils::{
+    hash_fragment, hash_word, hash_word_hashes, rolling_ngram_hashes, skipgram_indices,
+    strip_markup, CleanOptions, CleanedText, DefaultTokenizer, FragmentBuildHasher, FragmentHash,
+    FragmentMap, FragmentSet, SharedTokenizer, Tokenizer, HASH_VERSION,
+};
+use crate::translation::{SharedTranslator, Translator};
+use crate::{FragmentMode, Metric};
+use regex::Regex;
+use rust_stemmers::Stemmer;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{btree_map, BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::string::FromUtf8Error;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
 pub type TextOwnerID = String;
 /// (start index (inclusive), end index (exclusive))
 pub type FragmentLocation = (usize, usize);
+/// (start byte (inclusive), end byte (exclusive)) in the original text
+pub type ByteRange = (usize, usize);
 
-/// Report for plagiarism between two owners
-#[derive(Serialize, Debug)]
+/// Report for plagiarism between two owners. The default result has no
+///     owners, matches or scores, for building results field by field.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct PlagiarismResult {
     pub owner_id1: TextOwnerID,
     pub owner_id2: TextOwnerID,
@@ -19,291 +57,6167 @@ pub struct PlagiarismResult {
     /// Each element is the locations of one of the matching texts,
     ///     corresponding to each element of matching_fragments
     pub matching_fragments_locations: Vec<(Vec<FragmentLocation>, Vec<FragmentLocation>)>,
+    /// The locations as byte ranges of the original (uncleaned) texts, for
+    ///     highlighting. Empty for texts loaded from an index without them.
+    #[serde(default)]
+    pub char_locations: Vec<(Vec<ByteRange>, Vec<ByteRange>)>,
     pub trusted_owner1: bool,  // Is the first owner a trusted source?
+    pub trusted_owner2: bool,  // Is the second owner a trusted source?
     pub equal_fragments: bool, // Can we ignore one element of the tuple?
+    /// Ngram size (or minimum sentence length) the fragments were made with
+    pub n: usize,
+    /// Words between the starts of consecutive ngrams (1 for all of them)
+    #[serde(default = "default_stride")]
+    pub stride: usize,
+    /// Metadata attached to the texts of the two owners (e.g. student names)
+    #[serde(default)]
+    pub metadata1: HashMap<String, String>,
+    #[serde(default)]
+    pub metadata2: HashMap<String, String>,
+    /// Fraction of each owner's fragments of this size that match (0.0 to 1.0),
+    ///     so that most of a short essay matching a small part of a long book
+    ///     stands out. For the LCS metric, the fraction of words in the spans.
+    #[serde(default)]
+    pub coverage_owner1: f32,
+    #[serde(default)]
+    pub coverage_owner2: f32,
+    /// Word alignment of every span of overlapping matching fragments, so
+    ///     that near-matches show which words changed. Only made for non-equal
+    ///     metrics when enabled with `alignments` on the builder.
+    #[serde(default)]
+    pub alignments: Vec<Alignment>,
+    /// The owners are allowed to share text (see `allow_pair`), so the
+    ///     matches are no violation
+    #[serde(default)]
+    pub allowed: bool,
+    /// How likely the matches are real plagiarism, see `ConfidenceRules`
+    #[serde(default)]
+    pub confidence: Confidence,
+    /// Fraction of each owner's matched words that are in a run of
+    ///     overlapping or adjacent matches longer than `n` words (0.0 to 1.0).
+    ///     Copied paragraphs are dense, while common phrases scattered over
+    ///     two texts on the same topic are not.
+    #[serde(default)]
+    pub density_owner1: f32,
+    #[serde(default)]
+    pub density_owner2: f32,
+    /// Matches of the extra metrics (see `extra_metrics` on the builder)
+    ///     between the same texts, one element per extra metric
+    #[serde(default)]
+    pub metric_results: Vec<MetricResult>,
+    /// Matches in every section of each owner's text, if the database splits
+    ///     texts into sections (see `sections` on the builder)
+    #[serde(default)]
+    pub sections_owner1: Vec<SectionMatch>,
+    #[serde(default)]
+    pub sections_owner2: Vec<SectionMatch>,
+    /// Like the coverage, but with every fragment weighted by its inverse
+    ///     document frequency in the corpus, so that matching phrases most
+    ///     texts use count less than rare ones. Only computed with
+    ///     `idf_weighting` on the builder (0.0 otherwise, and for LCS).
+    #[serde(default)]
+    pub weighted_coverage_owner1: f32,
+    #[serde(default)]
+    pub weighted_coverage_owner2: f32,
+    /// The words of every matching fragment (at its first location) in both
+    ///     texts with some words around them, so that reports can show
+    ///     previews without the texts. Only made with `snippets` on the
+    ///     builder, empty for fingerprints.
+    #[serde(default)]
+    pub snippets: Vec<(SpanContext, SpanContext)>,
+    /// Fraction of the words of each owner's text covered by ngrams of the
+    ///     assignment prompt (see `set_assignment_prompt`), 0.0 without one
+    #[serde(default)]
+    pub prompt_coverage_owner1: f32,
+    #[serde(default)]
+    pub prompt_coverage_owner2: f32,
+    /// Chunks of each owner's text the matches are in, if it was split into
+    ///     chunks for comparisons (see `chunk_words` on the builder and
+    ///     `PlagiarismDatabase::chunks`)
+    #[serde(default)]
+    pub chunks_owner1: Vec<usize>,
+    #[serde(default)]
+    pub chunks_owner2: Vec<usize>,
 }
 
-/// A single user's "submission" or text string, broken into fragments
-#[derive(Debug)]
-struct TextEntry {
-    owner: TextOwnerID,
-    /// Cleaned text (word-by-word) for usage in printing
-    clean_text_words: Vec<String>,
-    /// Unique string fragments in the text
-    fragments: HashSet<String>,
-    /// Mapping between fragment strings and where in the text they are located
-    fragment_locations: HashMap<String, Vec<FragmentLocation>>,
-}
-
-/// Stores the corpus of trusted and untrusted strings
-#[derive(Debug)]
-pub struct PlagiarismDatabase {
-    // Constant value for ngram size
-    n: usize,
-    // Constant value for metric cutoff value
-    s: usize,
-    // Metric to use
-    metric: Metric,
-    /// Mapping owner ID to the processed text entry for that owner
-    trusted_texts: HashMap<TextOwnerID, TextEntry>,
-    /// Mapping owner ID to the processed text entry for that owner
-    untrusted_texts: HashMap<TextOwnerID, TextEntry>,
-    /// Mapping owner ID to the text contents to ignore
-    ignored_texts: HashSet<String>,
+/// What one of the extra metrics of a database matched between the owners
+///     and ngram size of the result holding it, so that e.g. strict matches
+///     can be compared with fuzzy ones from a single check
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MetricResult {
+    pub metric: Metric,
+    /// Locations of the matching fragments, like
+    ///     `PlagiarismResult::matching_fragments_locations`
+    pub matching_fragments_locations: Vec<(Vec<FragmentLocation>, Vec<FragmentLocation>)>,
+    /// Fraction of each owner's fragments that this metric matched
+    pub coverage_owner1: f32,
+    pub coverage_owner2: f32,
 }
 
-impl PlagiarismDatabase {
-    /// Initializes the plagiarism sensitivity and similarity metric values
-    ///     and the actual metric type to be used in computing plagiarism
-    ///     scores
-    pub fn new(
-        n: usize,
-        s: usize,
-        metric: Metric,
-        ignored_texts: Vec<String>,
-    ) -> PlagiarismDatabase {
-        PlagiarismDatabase {
-            n,
-            s,
-            metric,
-            trusted_texts: HashMap::new(),
-            untrusted_texts: HashMap::new(),
-            ignored_texts: PlagiarismDatabase::construct_ignored_texts(&ignored_texts, n),
+impl MetricResult {
+    /// Sub-result of a metric from its result, with no matches for `None`
+    fn new(metric: Metric, result: Option<PlagiarismResult>) -> MetricResult {
+        match result {
+            Some(result) => MetricResult {
+                metric,
+                matching_fragments_locations: result.matching_fragments_locations,
+                coverage_owner1: result.coverage_owner1,
+                coverage_owner2: result.coverage_owner2,
+            },
+            None => MetricResult {
+                metric,
+                matching_fragments_locations: Vec::new(),
+                coverage_owner1: 0.0,
+                coverage_owner2: 0.0,
+            },
         }
     }
 
-    /// Creates a hashset of strings to ignore at the start
-    ///     Doesn't take an owner ID as we just want to collate the
-    ///     strings together to avoid scaling badly with the number of
-    ///     ignored texts as well
-    fn construct_ignored_texts(texts: &Vec<String>, n: usize) -> HashSet<String> {
-        let mut ignored_text_set: HashSet<String> = HashSet::new();
-        for text in texts {
-            let clean_text_words = clean_text(text);
-            let (fragments, _) = PlagiarismDatabase::get_textfragments(&clean_text_words, n);
-            ignored_text_set.extend(fragments)
+    /// Swaps the two sides of the sub-result
+    fn swap(&mut self) {
+        std::mem::swap(&mut self.coverage_owner1, &mut self.coverage_owner2);
+        for (locs1, locs2) in &mut self.matching_fragments_locations {
+            std::mem::swap(locs1, locs2);
         }
-        ignored_text_set
     }
+}
 
-    /// Gets only the ID -> clean text mapping for all texts
-    pub fn get_all_cleantext(&self) -> HashMap<TextOwnerID, Vec<String>> {
-        let trusted = self
-            .trusted_texts
-            .iter()
-            .map(|(k, v)| (k.clone(), v.clean_text_words.clone()));
-        let untrusted = self
-            .untrusted_texts
-            .iter()
-            .map(|(k, v)| (k.clone(), v.clean_text_words.clone()));
-        trusted.chain(untrusted).collect()
+/// Words of two spans of text aligned with each other
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Alignment {
+    /// Word locations of the span in the texts of the two owners
+    pub span1: FragmentLocation,
+    pub span2: FragmentLocation,
+    /// Aligned words, with indexes into the cleaned words of each text
+    pub words: Vec<AlignedWord>,
+}
+
+impl Alignment {
+    /// Swaps the two sides of the alignment
+    fn swap(&mut self) {
+        std::mem::swap(&mut self.span1, &mut self.span2);
+        for aligned in &mut self.words {
+            std::mem::swap(&mut aligned.word1, &mut aligned.word2);
+            aligned.op = match aligned.op {
+                EditOp::Insert => EditOp::Delete,
+                EditOp::Delete => EditOp::Insert,
+                op => op,
+            };
+        }
     }
+}
 
-    /// Adds a text string as potential plagiarism source material
-    pub fn add_trusted_text(&mut self, owner_id: &str, text: &str) {
-        let clean_text_words = clean_text(text);
-        let (mut fragments, fragment_locations) =
-            PlagiarismDatabase::get_textfragments(&clean_text_words, self.n);
-        // Remove strings that match the ignored list (equality test directly)
-        fragments = fragments
-            .difference(&self.ignored_texts)
-            .map(String::from)
-            .collect();
-        self.trusted_texts.insert(
-            owner_id.to_string(),
-            TextEntry {
-                owner: owner_id.to_string(),
-                clean_text_words,
-                fragments,
-                fragment_locations,
-            },
-        );
+impl PlagiarismResult {
+    /// Puts the owners in canonical order: a trusted owner always comes first
+    ///     before an untrusted one, otherwise the owner IDs are sorted.
+    ///     Fragments and locations are swapped along with the owners.
+    pub fn canonicalize(&mut self) {
+        let trusted_first = self.trusted_owner1 && !self.trusted_owner2;
+        if !self.trusted_owner1 && self.trusted_owner2
+            || !trusted_first && self.owner_id1 > self.owner_id2
+        {
+            self.swap_owners();
+        }
     }
 
-    /// Adds a text string as a potential plagiarized string
-    pub fn add_untrusted_text(&mut self, owner_id: &str, text: &str) {
-        let clean_text_words = clean_text(text);
-        let (mut fragments, fragment_locations) =
-            PlagiarismDatabase::get_textfragments(&clean_text_words, self.n);
-        // Remove strings that match the ignored list (equality test directly)
-        fragments = fragments
-            .difference(&self.ignored_texts)
-            .map(String::from)
-            .collect();
-        self.untrusted_texts.insert(
-            owner_id.to_string(),
-            TextEntry {
-                owner: owner_id.to_string(),
-                clean_text_words,
-                fragments,
-                fragment_locations,
-            },
+    /// Swaps the two sides of the result
+    fn swap_owners(&mut self) {
+        std::mem::swap(&mut self.owner_id1, &mut self.owner_id2);
+        std::mem::swap(&mut self.trusted_owner1, &mut self.trusted_owner2);
+        std::mem::swap(&mut self.metadata1, &mut self.metadata2);
+        std::mem::swap(&mut self.coverage_owner1, &mut self.coverage_owner2);
+        std::mem::swap(
+            &mut self.weighted_coverage_owner1,
+            &mut self.weighted_coverage_owner2,
+        );
+        std::mem::swap(
+            &mut self.prompt_coverage_owner1,
+            &mut self.prompt_coverage_owner2,
         );
+        std::mem::swap(&mut self.chunks_owner1, &mut self.chunks_owner2);
+        for (f1, f2) in &mut self.matching_fragments {
+            std::mem::swap(f1, f2);
+        }
+        for (locs1, locs2) in &mut self.matching_fragments_locations {
+            std::mem::swap(locs1, locs2);
+        }
+        for (ranges1, ranges2) in &mut self.char_locations {
+            std::mem::swap(ranges1, ranges2);
+        }
+        for alignment in &mut self.alignments {
+            alignment.swap();
+        }
+        for (snippet1, snippet2) in &mut self.snippets {
+            std::mem::swap(snippet1, snippet2);
+        }
+        for metric_result in &mut self.metric_results {
+            metric_result.swap();
+        }
+        std::mem::swap(&mut self.sections_owner1, &mut self.sections_owner2);
     }
 
-    // /// Check for plagiarism by comparing metric against cutoff
-    // ///     for all untrusted textfragments currently in database
-    // pub fn check_untrusted_plagiarism(&self) -> Vec<PlagiarismResult> {
-    //     let mut results: Vec<PlagiarismResult> = Vec::new();
-    //     // .skip() in second loop to avoid checking same combinations twice
-    //     for (sourceidx, source) in self.untrusted_texts.values().enumerate() {
-    //         for against in self.untrusted_texts.values().skip(sourceidx + 1) {
-    //             if let Some(result) = self.run_metrics(source, against, false) {
-    //                 results.push(result);
-    //             }
-    //         }
-    //     }
-    //     results
-    // }
-
-    /// Check for plagiarism by comparing metric against cutoff
-    ///     for textfragments in database against trusted fragments
-    pub fn check_trusted_plagiarism(&self) -> Vec<PlagiarismResult> {
-        let mut results: Vec<PlagiarismResult> = Vec::new();
-        for source in self.trusted_texts.values() {
-            for against in self.untrusted_texts.values() {
-                if let Some(result) = self.run_metrics(source, against, true) {
-                    results.push(result);
+    /// Adds the fragments of another result for the same owner pair (in either
+    ///     order) and ngram size, skipping fragment pairs this result already contains.
+    ///     Keeps the larger coverage of each owner, also for the sub-results
+    ///     of every extra metric. Fails without changing this result if the
+    ///     owners or ngram sizes differ.
+    pub fn merge(&mut self, mut other: PlagiarismResult) -> Result<()> {
+        let reversed = other.owner_id1 == self.owner_id2 && other.owner_id2 == self.owner_id1;
+        if reversed {
+            other.swap_owners();
+        }
+        if other.owner_id1 != self.owner_id1 || other.owner_id2 != self.owner_id2 {
+            return Err(Error::IncompatibleFragments(format!(
+                "Cannot merge results for {}/{} into results for {}/{}",
+                other.owner_id1, other.owner_id2, self.owner_id1, self.owner_id2
+            )));
+        }
+        if other.n != self.n {
+            return Err(Error::IncompatibleFragments(format!(
+                "Cannot merge results for n = {} into results for n = {}",
+                other.n, self.n
+            )));
+        }
+        let mut known: HashMap<(String, String), usize> = self
+            .matching_fragments
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(idx, fragments)| (fragments, idx))
+            .collect();
+        let mut other_char_locations = other.char_locations.into_iter();
+        let mut other_snippets = other.snippets.into_iter();
+        let others = other
+            .matching_fragments
+            .into_iter()
+            .zip(other.matching_fragments_locations);
+        for (fragments, (locs1, locs2)) in others {
+            let (ranges1, ranges2) = other_char_locations.next().unwrap_or_default();
+            let snippet = other_snippets.next();
+            match known.get(&fragments) {
+                Some(&idx) => {
+                    let (known1, known2) = &mut self.matching_fragments_locations[idx];
+                    extend_unique(known1, locs1);
+                    extend_unique(known2, locs2);
+                    if let Some((known1, known2)) = self.char_locations.get_mut(idx) {
+                        extend_unique(known1, ranges1);
+                        extend_unique(known2, ranges2);
+                    }
+                }
+                None => {
+                    known.insert(fragments.clone(), self.matching_fragments.len());
+                    self.matching_fragments.push(fragments);
+                    self.matching_fragments_locations.push((locs1, locs2));
+                    self.char_locations.push((ranges1, ranges2));
+                    // Only if the snippets of this result still line up with its fragments
+                    if let Some(snippet) = snippet {
+                        if self.snippets.len() + 1 == self.matching_fragments.len() {
+                            self.snippets.push(snippet);
+                        }
+                    }
                 }
             }
         }
-        results
+        self.equal_fragments &= other.equal_fragments;
+        extend_unique(&mut self.alignments, other.alignments);
+        extend_unique(&mut self.chunks_owner1, other.chunks_owner1);
+        extend_unique(&mut self.chunks_owner2, other.chunks_owner2);
+        self.coverage_owner1 = self.coverage_owner1.max(other.coverage_owner1);
+        self.coverage_owner2 = self.coverage_owner2.max(other.coverage_owner2);
+        self.weighted_coverage_owner1 = self
+            .weighted_coverage_owner1
+            .max(other.weighted_coverage_owner1);
+        self.weighted_coverage_owner2 = self
+            .weighted_coverage_owner2
+            .max(other.weighted_coverage_owner2);
+        self.prompt_coverage_owner1 = self
+            .prompt_coverage_owner1
+            .max(other.prompt_coverage_owner1);
+        self.prompt_coverage_owner2 = self
+            .prompt_coverage_owner2
+            .max(other.prompt_coverage_owner2);
+        self.confidence = self.confidence.max(other.confidence);
+        self.density_owner1 = self.density_owner1.max(other.density_owner1);
+        self.density_owner2 = self.density_owner2.max(other.density_owner2);
+        for other_result in other.metric_results {
+            let known = self
+                .metric_results
+                .iter_mut()
+                .find(|metric_result| metric_result.metric == other_result.metric);
+            match known {
+                Some(known) => {
+                    extend_unique(
+                        &mut known.matching_fragments_locations,
+                        other_result.matching_fragments_locations,
+                    );
+                    known.coverage_owner1 = known.coverage_owner1.max(other_result.coverage_owner1);
+                    known.coverage_owner2 = known.coverage_owner2.max(other_result.coverage_owner2);
+                }
+                None => self.metric_results.push(other_result),
+            }
+        }
+        if self.sections_owner1.is_empty() && self.sections_owner2.is_empty() {
+            self.sections_owner1 = other.sections_owner1;
+            self.sections_owner2 = other.sections_owner2;
+        }
+        let section_ranges = |sections: &mut Vec<SectionMatch>| -> Vec<(String, FragmentLocation)> {
+            std::mem::take(sections)
+                .into_iter()
+                .map(|section| (section.title, section.words))
+                .collect()
+        };
+        let sections1 = section_ranges(&mut self.sections_owner1);
+        let sections2 = section_ranges(&mut self.sections_owner2);
+        self.count_sections(sections1, sections2);
+        Ok(())
     }
 
-    /// Helper function to actually run the plagiarism check against sources
-    fn run_metrics(
-        &self,
-        source: &TextEntry,
-        against: &TextEntry,
-        is_trusted_owner1: bool,
-    ) -> Option<PlagiarismResult> {
-        // Run metrics against both sources to get all matching strings
-        let matching_fragments = match self.metric {
-            Metric::Equal => self.check_plagiarism_equal(source, against),
-            _ => self.check_plagiarism_other(source, self.metric, against),
-        };
-        // No plagiarism between these two sources
-        if matching_fragments.is_empty() {
-            return None;
-        }
-        // Get the locations of each matching fragment from each source text
-        let matching_fragments_locations = matching_fragments
+    /// Counts the matches in every section of the two texts, given as titles
+    ///     and word ranges
+    fn count_sections(
+        &mut self,
+        sections1: Vec<(String, FragmentLocation)>,
+        sections2: Vec<(String, FragmentLocation)>,
+    ) {
+        let locations = &self.matching_fragments_locations;
+        let locations1: Vec<&[FragmentLocation]> = locations
             .iter()
-            .map(|(f1, f2)| {
-                if is_trusted_owner1 {
-                    self.fragments_to_locations_trusted(f1, &source.owner, f2, &against.owner)
-                } else {
-                    self.fragments_to_locations(f1, &source.owner, f2, &against.owner)
-                }
-            })
+            .map(|(locs1, _)| locs1.as_slice())
             .collect();
-        // Construct result
-        let result = PlagiarismResult {
-            owner_id1: source.owner.clone(),
-            owner_id2: against.owner.clone(),
-            matching_fragments_locations,
-            matching_fragments,
-            trusted_owner1: is_trusted_owner1,
-            equal_fragments: self.metric == Metric::Equal,
+        let locations2: Vec<&[FragmentLocation]> = locations
+            .iter()
+            .map(|(_, locs2)| locs2.as_slice())
+            .collect();
+        self.sections_owner1 = section_matches(sections1.into_iter(), &locations1);
+        self.sections_owner2 = section_matches(sections2.into_iter(), &locations2);
+    }
+
+    /// Moves the word locations of owner 1 by `offset` words, from a chunk
+    ///     of its text to the whole text
+    fn shift_owner1(&mut self, offset: usize) {
+        let shift = |location: &mut FragmentLocation| {
+            location.0 += offset;
+            location.1 += offset;
         };
-        Some(result)
+        for (locs1, _) in &mut self.matching_fragments_locations {
+            locs1.iter_mut().for_each(shift);
+        }
+        for metric_result in &mut self.metric_results {
+            for (locs1, _) in &mut metric_result.matching_fragments_locations {
+                locs1.iter_mut().for_each(shift);
+            }
+        }
+        for alignment in &mut self.alignments {
+            shift(&mut alignment.span1);
+            for word in alignment
+                .words
+                .iter_mut()
+                .filter_map(|word| word.word1.as_mut())
+            {
+                *word += offset;
+            }
+        }
     }
 
-    /// Takes in a separated tuple of matching fragments and their owner IDs.
-    ///     Returns vectors representing where they can be found in their respective texts
-    fn fragments_to_locations(
-        &self,
-        f1: &str,
-        owner1: &str,
-        f2: &str,
-        owner2: &str,
-    ) -> (Vec<FragmentLocation>, Vec<FragmentLocation>) {
-        let f1_locations: Vec<FragmentLocation> =
-            self.untrusted_texts[owner1].fragment_locations[f1].clone();
-        let f2_locations: Vec<FragmentLocation> =
-            self.untrusted_texts[owner2].fragment_locations[f2].clone();
-        (f1_locations, f2_locations)
-    }
-    /// Takes in a separated tuple of matching fragments and their owner IDs.
-    ///     Returns vectors representing where they can be found in their respective texts
-    ///     This checks the trusted_texts map for the first owner
-    fn fragments_to_locations_trusted(
-        &self,
-        f1: &str,
-        owner1: &str,
-        f2: &str,
-        owner2: &str,
-    ) -> (Vec<FragmentLocation>, Vec<FragmentLocation>) {
-        let f1_locations: Vec<FragmentLocation> =
-            self.trusted_texts[owner1].fragment_locations[f1].clone();
-        let f2_locations: Vec<FragmentLocation> =
-            self.untrusted_texts[owner2].fragment_locations[f2].clone();
-        (f1_locations, f2_locations)
+    /// Computes `density_owner1` and `density_owner2` from the locations
+    fn compute_density(&mut self) {
+        let locations = &self.matching_fragments_locations;
+        let locations1 = locations
+            .iter()
+            .flat_map(|(locs1, _)| locs1.iter().copied());
+        let locations2 = locations
+            .iter()
+            .flat_map(|(_, locs2)| locs2.iter().copied());
+        self.density_owner1 = density(locations1.collect(), self.n);
+        self.density_owner2 = density(locations2.collect(), self.n);
     }
 
-    /// Splits a text string into separate ngram TextFragments
-    ///     Also creates the map of fragments -> locations at the same time before
-    ///     vector location information is lost
-    fn get_textfragments(
-        words: &Vec<String>,
-        n: usize,
-    ) -> (HashSet<String>, HashMap<String, Vec<FragmentLocation>>) {
-        let ngrams = extract_clean_word_ngrams(words, n);
-        let mut fragment_locations: HashMap<String, Vec<FragmentLocation>> = HashMap::new();
-        // Insert all ngrams into hashmap of ngram locations
-        // Handle both the case with no key (new vec) and existing key (push)
-        for (start_location, ngram) in ngrams.iter().enumerate() {
-            if fragment_locations.contains_key(ngram) {
-                fragment_locations
-                    .get_mut(ngram)
-                    .unwrap_or_else(|| {
-                        panic!("Cannot find ngram {} in fragment locations even though we checked it earlier - fatal error", ngram);
-                    })
-                    .push((start_location, start_location + n - 1));
-            } else {
-                let mut loc_vec: Vec<FragmentLocation> = Vec::new();
-                loc_vec.push((start_location, start_location + n - 1));
-                fragment_locations.insert(ngram.to_string(), loc_vec);
+    /// The larger coverage of the two owners (0.0 to 1.0), see `CheckOptions`
+    pub fn score(&self) -> f32 {
+        self.coverage_owner1.max(self.coverage_owner2)
+    }
+
+    /// The larger weighted coverage of the two owners (0.0 to 1.0), see
+    ///     `weighted_coverage_owner1`
+    pub fn weighted_score(&self) -> f32 {
+        self.weighted_coverage_owner1
+            .max(self.weighted_coverage_owner2)
+    }
+
+    /// Checks if the untrusted owners of this result are all in a namespace,
+    ///     see `in_namespace`
+    pub fn in_namespace(&self, namespace: &str) -> bool {
+        (self.trusted_owner1 || in_namespace(&self.owner_id1, namespace))
+            && (self.trusted_owner2 || in_namespace(&self.owner_id2, namespace))
+    }
+}
+
+/// Compares two texts without managing a database: builds one with the
+///     settings of `options` (e.g. `PlagiarismDatabase::builder(3, 0,
+///     Metric::Equal)`), adds the texts as the untrusted owners "a" and "b"
+///     and checks them. Both are untrusted, so swapping the texts only swaps
+///     the matches of the owners. With several ngram sizes, the result of the
+///     size with the highest score is returned, and without matches a result
+///     with none and a score of 0.0.
+pub fn compare_texts(a: &str, b: &str, options: PlagiarismDatabaseBuilder) -> PlagiarismResult {
+    let mut db = options.build();
+    db.add_untrusted_text("a", a);
+    db.add_untrusted_text("b", b);
+    db.check_untrusted_plagiarism()
+        .into_iter()
+        .max_by(|x, y| x.score().total_cmp(&y.score()))
+        .unwrap_or_else(|| PlagiarismResult {
+            owner_id1: "a".to_string(),
+            owner_id2: "b".to_string(),
+            equal_fragments: db.metric == Metric::Equal,
+            n: db.n,
+            stride: db.stride,
+            ..PlagiarismResult::default()
+        })
+}
+
+/// Checks if an owner ID is in a namespace, the part of a namespaced ID like
+///     `course/assignment/student` before a '/': `cs101/hw1/alice` is in
+///     `cs101` and `cs101/hw1`, but not in `cs101/hw`. Every ID is in the
+///     empty namespace.
+pub fn in_namespace(owner_id: &str, namespace: &str) -> bool {
+    let namespace = namespace.trim_end_matches('/');
+    namespace.is_empty()
+        || owner_id
+            .strip_prefix(namespace)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Appends the new values that aren't in known yet
+fn extend_unique<T: PartialEq>(known: &mut Vec<T>, new: Vec<T>) {
+    for value in new {
+        if !known.contains(&value) {
+            known.push(value);
+        }
+    }
+}
+
+/// Canonicalizes the owner order of all results and merges results for the
+///     same owner pair and ngram size, so that every pair appears once (per
+///     size) with unique fragments. Keeps the order in which pairs first appear.
+pub fn dedup_results(results: Vec<PlagiarismResult>) -> Vec<PlagiarismResult> {
+    let mut merged: Vec<PlagiarismResult> = Vec::new();
+    let mut pair_indices: HashMap<(TextOwnerID, TextOwnerID, bool, bool, usize), usize> =
+        HashMap::new();
+    for mut result in results {
+        result.canonicalize();
+        let key = (
+            result.owner_id1.clone(),
+            result.owner_id2.clone(),
+            result.trusted_owner1,
+            result.trusted_owner2,
+            result.n,
+        );
+        match pair_indices.get(&key) {
+            Some(&idx) => merged[idx]
+                .merge(result)
+                .expect("Results are grouped by owners and ngram size"),
+            None => {
+                pair_indices.insert(key, merged.len());
+                // Merging into an empty result also removes duplicate fragments
+                let mut unique = PlagiarismResult {
+                    owner_id1: result.owner_id1.clone(),
+                    owner_id2: result.owner_id2.clone(),
+                    trusted_owner1: result.trusted_owner1,
+                    trusted_owner2: result.trusted_owner2,
+                    equal_fragments: result.equal_fragments,
+                    n: result.n,
+                    stride: result.stride,
+                    metadata1: result.metadata1.clone(),
+                    metadata2: result.metadata2.clone(),
+                    allowed: result.allowed,
+                    ..PlagiarismResult::default()
+                };
+                unique
+                    .merge(result)
+                    .expect("Results are grouped by owners and ngram size");
+                merged.push(unique);
             }
         }
-        (HashSet::from_iter(ngrams), fragment_locations)
     }
+    merged
+}
 
-    /// Checks plagiarism by equality of fragments, uses fast set intersection
-    /// Returns a tuple of all matches (second tuple element is identical to first)
-    fn check_plagiarism_equal(
-        &self,
-        source: &TextEntry,
-        against: &TextEntry,
-    ) -> Vec<(String, String)> {
-        let intersect: Vec<&String> = source.fragments.intersection(&against.fragments).collect();
-        if !intersect.is_empty() {
-            // Plagiarism!
-            intersect
-                .iter()
-                .map(|val| ((*val).to_string(), (*val).to_string()))
-                .collect()
-        } else {
-            Vec::new()
+/// Sorts results most significant (most matching fragments) first. Ties are
+///     broken by the owner IDs and the ngram size, so the order is the same
+///     in every run and outputs can be diffed.
+pub fn sort_results(results: &mut [PlagiarismResult]) {
+    results.sort_by(|a, b| {
+        b.matching_fragments
+            .len()
+            .cmp(&a.matching_fragments.len())
+            .then_with(|| tie_break(a, b))
+    });
+}
+
+/// Order of results that are equally significant
+fn tie_break(a: &PlagiarismResult, b: &PlagiarismResult) -> Ordering {
+    a.owner_id1
+        .cmp(&b.owner_id1)
+        .then_with(|| a.owner_id2.cmp(&b.owner_id2))
+        .then_with(|| a.trusted_owner1.cmp(&b.trusted_owner1))
+        .then_with(|| a.trusted_owner2.cmp(&b.trusted_owner2))
+        .then_with(|| a.n.cmp(&b.n))
+}
+
+/// What makes a result more significant than another, see `CheckOptions`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ResultSort {
+    /// Most matching fragments first, like `sort_results`
+    #[default]
+    Matches,
+    /// Highest `PlagiarismResult::score` first
+    Score,
+}
+
+/// Limits on the results of a check, so that checks of huge corpora can
+///     return only the most significant results without keeping all of them
+///     in memory. See `check_trusted_plagiarism_with`.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct CheckOptions {
+    /// Only keep this many of the most significant results
+    pub max_results: Option<usize>,
+    /// Only keep results with at least this score (0.0 to 1.0)
+    pub min_score: f32,
+    /// How the results are sorted, most significant first
+    pub sort: ResultSort,
+}
+
+impl CheckOptions {
+    /// Sorts results most significant first
+    pub fn sort_results(&self, results: &mut [PlagiarismResult]) {
+        match self.sort {
+            ResultSort::Matches => sort_results(results),
+            ResultSort::Score => results.sort_by(|a, b| {
+                b.score()
+                    .total_cmp(&a.score())
+                    .then_with(|| tie_break(a, b))
+            }),
         }
     }
 
-    /// Checks plagiarism by non-equal metric (string-by-string)
-    /// Returns a tuple of all matches (second tuple element is identical to first)
-    fn check_plagiarism_other(
+    /// Filters and sorts results, keeping at most `max_results` of them (and
+    ///     never more than twice as many in memory) while they come in
+    pub fn collect<I: IntoIterator<Item = PlagiarismResult>>(
         &self,
-        source: &TextEntry,
-        metric: Metric,
-        against: &TextEntry,
-    ) -> Vec<(String, String)> {
-        let mut results: Vec<(String, String)> = Vec::new();
-        for source_frag in source.fragments.iter() {
-            for against_frag in against.fragments.iter() {
-                if is_plagiarised(source_frag, against_frag, metric, self.s) {
-                    results.push((source_frag.to_string(), against_frag.to_string()));
+        results: I,
+    ) -> Vec<PlagiarismResult> {
+        let mut kept: Vec<PlagiarismResult> = Vec::new();
+        for result in results {
+            if result.score() < self.min_score {
+                continue;
+            }
+            kept.push(result);
+            if let Some(max_results) = self.max_results {
+                if kept.len() >= 2 * max_results.max(1) {
+                    self.sort_results(&mut kept);
+                    kept.truncate(max_results);
                 }
             }
         }
-        results
+        self.sort_results(&mut kept);
+        if let Some(max_results) = self.max_results {
+            kept.truncate(max_results);
+        }
+        kept
+    }
+}
+
+/// All entries of a text map, sorted by owner ID, so that checks compare
+///     owners in the same order in every run
+fn sorted_entries(texts: &HashMap<TextOwnerID, TextEntry>) -> Vec<&TextEntry> {
+    let mut entries: Vec<&TextEntry> = texts.values().collect();
+    entries.sort_by(|a, b| a.owner.cmp(&b.owner));
+    entries
+}
+
+/// Owner ID without the version suffix `#<version>` of
+///     `DuplicateOwnerPolicy::NewVersion`
+fn unversioned_owner(owner_id: &str) -> &str {
+    match owner_id.rsplit_once('#') {
+        Some((owner, version))
+            if !version.is_empty() && version.bytes().all(|byte| byte.is_ascii_digit()) =>
+        {
+            owner
+        }
+        _ => owner_id,
+    }
+}
+
+/// Fraction of the words covered by some (inclusive) ranges that are in a
+///     union of overlapping or adjacent ranges longer than `n` words
+fn density(mut locations: Vec<FragmentLocation>, n: usize) -> f32 {
+    locations.sort_unstable();
+    let mut runs: Vec<FragmentLocation> = Vec::new();
+    for (start, end) in locations {
+        match runs.last_mut() {
+            Some(run) if start <= run.1 + 1 => run.1 = run.1.max(end),
+            _ => runs.push((start, end)),
+        }
+    }
+    let lengths = runs.iter().map(|(start, end)| end - start + 1);
+    let matched: usize = lengths.clone().sum();
+    let clustered: usize = lengths.filter(|&length| length > n).sum();
+    if matched == 0 {
+        0.0
+    } else {
+        clustered as f32 / matched as f32
+    }
+}
+
+/// Attributes every word matching trusted sources to one of them: the source
+///     with the most words not attributed yet gets all of its remaining
+///     words, until none are left (ties go to the lower owner ID)
+fn attribute_sources(mut sources: HashMap<&str, HashSet<usize>>) -> Vec<SourceAttribution> {
+    let total: usize = sources
+        .values()
+        .flatten()
+        .collect::<HashSet<&usize>>()
+        .len();
+    let mut attribution: Vec<SourceAttribution> = Vec::new();
+    while let Some((&source, _)) = sources
+        .iter()
+        .filter(|(_, words)| !words.is_empty())
+        .max_by(|a, b| a.1.len().cmp(&b.1.len()).then_with(|| b.0.cmp(a.0)))
+    {
+        let words = sources.remove(source).unwrap_or_default();
+        for other in sources.values_mut() {
+            other.retain(|word| !words.contains(word));
+        }
+        attribution.push(SourceAttribution {
+            source: source.to_string(),
+            words: words.len(),
+            percent: words.len() as f32 / total as f32 * 100.0,
+        });
+    }
+    attribution
+}
+
+/// Turns an error from reading a file into `Error::InvalidUtf8` if the file
+///     isn't UTF-8 text, or else into an IO error mentioning the path
+fn read_error(path: &Path, err: io::Error) -> Error {
+    if err
+        .get_ref()
+        .is_some_and(|inner| inner.is::<FromUtf8Error>())
+    {
+        Error::InvalidUtf8 {
+            path: path.to_path_buf(),
+        }
+    } else {
+        Error::Io(io::Error::new(
+            err.kind(),
+            format!("{}: {}", path.display(), err),
+        ))
+    }
+}
+
+/// Fraction of shared ngrams above which two texts are reported as duplicates
+pub const DUPLICATE_THRESHOLD: f32 = 0.95;
+
+/// Report for two owners that submitted (almost) the same text
+#[derive(Serialize, Debug)]
+pub struct DuplicateResult {
+    pub owner_id1: TextOwnerID,
+    pub owner_id2: TextOwnerID,
+    /// Number of unique fragments found in both texts
+    pub shared_fragments: usize,
+    /// Shared fragments divided by the fragment count of the larger text (0.0 to 1.0)
+    pub similarity: f32,
+}
+
+/// A trusted source ranked by how much of an untrusted text it matches
+#[derive(Serialize, Debug)]
+pub struct RankedSource {
+    pub owner_id: TextOwnerID,
+    /// Fraction of the untrusted text's words covered by matches with this
+    ///     source (0.0 to 1.0)
+    pub score: f32,
+    /// Number of matching fragments over all ngram sizes
+    pub matching_fragments: usize,
+}
+
+/// Results of `PlagiarismDatabase::check_with_budget`
+#[derive(Serialize, Debug)]
+pub struct BudgetedCheck {
+    /// Results of the owner pairs compared within the budget, most
+    ///     significant first
+    pub results: Vec<PlagiarismResult>,
+    /// All owner pairs were compared, so no results are missing
+    pub complete: bool,
+    /// Number of owner pairs compared
+    pub compared: usize,
+    /// Number of owner pairs a full check compares
+    pub total: usize,
+}
+
+/// Memoized metric outcomes for (fragment 1, fragment 2, metric, cutoff),
+///     shared between all owner pairs compared in a single check run
+pub(crate) type ComparisonCache =
+    HashMap<(FragmentHash, FragmentHash, Metric, usize), bool, FragmentBuildHasher>;
+
+/// Locations of every unique fragment of a text, by the hash of the fragment
+type FragmentLocations = FragmentMap<Vec<FragmentLocation>>;
+
+/// Words inside the first location of a skip-gram that are not part of it,
+///     by fragment hash. Fragments of consecutive words have no entry.
+type SkippedWords = FragmentMap<Vec<usize>>;
+
+/// Pairwise similarity of all untrusted texts, e.g. for a heatmap
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SimilarityMatrix {
+    /// Sorted owner IDs, the labels of both the rows and the columns
+    pub owners: Vec<TextOwnerID>,
+    /// `scores[i][j]` is the fraction of the fragments of owner i that match
+    ///     owner j (its coverage, 0.0 to 1.0), so the matrix isn't symmetric.
+    ///     Texts fully cover themselves.
+    pub scores: Vec<Vec<f32>>,
+}
+
+/// Number of most common ngrams listed in `DatabaseStats`
+pub const COMMON_NGRAM_COUNT: usize = 10;
+
+/// Size and repetitiveness of one text, see `PlagiarismDatabase::stats`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct OwnerStats {
+    pub owner_id: TextOwnerID,
+    pub trusted: bool,
+    /// Number of cleaned words (0 for fingerprints)
+    pub words: usize,
+    /// Number of distinct ngrams
+    pub unique_ngrams: usize,
+    /// Fraction of ngram occurrences that repeat an earlier one of the same
+    ///     text (0.0 to 1.0)
+    pub duplicate_ngram_rate: f32,
+    /// Number of invisible or look-alike characters that were normalized,
+    ///     which are often inserted to evade detection
+    pub suspicious_chars: usize,
+    /// Fraction of the words that are just the assignment prompt (0.0 to
+    ///     1.0), see `PlagiarismDatabase::set_assignment_prompt`
+    pub prompt_coverage: f32,
+}
+
+/// An ngram and the number of owners whose texts contain it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NgramCount {
+    pub ngram: String,
+    pub owners: usize,
+}
+
+/// Statistics of all texts of a database, e.g. to tune ngram sizes or find
+///     boilerplate that should be ignored
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DatabaseStats {
+    /// Trusted owners first, each sorted by owner ID
+    pub owners: Vec<OwnerStats>,
+    /// Ngrams found in the most texts (at least two), most common first
+    pub common_ngrams: Vec<NgramCount>,
+}
+
+/// Aggregate of all plagiarism results involving one untrusted owner
+#[derive(Serialize, Debug)]
+pub struct OwnerSummary {
+    pub owner_id: TextOwnerID,
+    /// Total number of matching fragments over all results for this owner
+    pub matched_fragments: usize,
+    /// Percentage of the owner's words covered by at least one match (0.0 to 100.0)
+    pub matched_percent: f32,
+    /// Owners this text matched with and the number of matching fragments,
+    ///     sorted by decreasing overlap
+    pub counterparts: Vec<(TextOwnerID, usize)>,
+    /// Which trusted sources the words matching them come from, sorted by
+    ///     decreasing share. Every word is attributed to only one source, so
+    ///     the shares add up to 100.
+    pub attribution: Vec<SourceAttribution>,
+}
+
+/// Share of the words of an untrusted text matching trusted sources that
+///     comes from one source, see `OwnerSummary::attribution`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SourceAttribution {
+    pub source: TextOwnerID,
+    /// Words of the untrusted text attributed to the source
+    pub words: usize,
+    /// Percentage of all words of the text matching trusted sources (0.0
+    ///     to 100.0)
+    pub percent: f32,
+}
+
+/// What cleaning a text dropped or replaced, from
+///     `PlagiarismDatabase::text_cleaning`
+pub(crate) struct TextCleaning<'a> {
+    pub(crate) words: &'a [String],
+    pub(crate) word_byte_ranges: &'a [ByteRange],
+    pub(crate) stripped: &'a [ByteRange],
+    pub(crate) excluded: &'a [(usize, usize)],
+    pub(crate) prompt_words: &'a [usize],
+    /// The cleaning options of the text's language
+    pub(crate) options: Cow<'a, CleanOptions>,
+    pub(crate) stemmed: bool,
+    pub(crate) code_mode: bool,
+    pub(crate) translated: bool,
+    /// The database ignores the fragments of some texts
+    pub(crate) ignored_texts: bool,
+    /// Fragments of the owner's earlier drafts are removed from the text
+    pub(crate) baseline: bool,
+}
+
+/// A single user's "submission" or text string, broken into fragments
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TextEntry {
+    owner: TextOwnerID,
+    /// Cleaned text (word-by-word) for usage in printing
+    clean_text_words: Vec<String>,
+    /// Byte range of each cleaned word in the original text
+    #[serde(default)]
+    word_byte_ranges: Vec<ByteRange>,
+    /// ISO 639-3 code of the detected language, if detection is enabled
+    #[serde(default)]
+    language: Option<String>,
+    /// Arbitrary information about the text, echoed in its results
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    /// Byte ranges of the original text dropped by exclusion patterns
+    #[serde(default)]
+    stripped: Vec<ByteRange>,
+    /// Word ranges (both inclusive) left out of all fragments
+    #[serde(default)]
+    excluded: Vec<(usize, usize)>,
+    /// Where each unique fragment of the text is located, by fragment hash.
+    ///     Fragment texts aren't stored, but rebuilt from the words when needed.
+    fragment_locations: FragmentLocations,
+    /// Words skipped by skip-gram fragments
+    #[serde(default)]
+    skipped_words: SkippedWords,
+    /// Loaded from a fingerprint file, so only the fragment hashes and
+    ///     locations are known but none of the words
+    #[serde(default)]
+    fingerprint: bool,
+    /// Label of the group the text belongs to (e.g. a class section or a
+    ///     student with several drafts)
+    #[serde(default)]
+    group: Option<String>,
+    /// Number of invisible or look-alike characters dropped or replaced
+    ///     while cleaning, see `CleanOptions::fold_confusables`
+    #[serde(default)]
+    suspicious_chars: usize,
+    /// Title and word range (both inclusive) of every section, if the
+    ///     database splits texts into sections
+    #[serde(default)]
+    sections: Vec<(String, FragmentLocation)>,
+    /// Sorted indices of the words covered by ngrams of the assignment prompt
+    #[serde(default)]
+    prompt_words: Vec<usize>,
+    /// Parts of a long text that are compared on their own, see
+    ///     `chunk_words` on the builder. Not saved with the text.
+    #[serde(skip)]
+    chunks: Vec<TextEntry>,
+    /// Index of the first word of a chunk in its whole text
+    #[serde(skip)]
+    chunk_offset: usize,
+    /// The same unique fragments, bucketed by their length in characters
+    #[serde(skip)]
+    fragments_by_length: BTreeMap<usize, Vec<FragmentHash>>,
+}
+
+/// Trusted texts written by `save_trusted_index`, along with the ngram size
+///     and fragment mode they were fragmented with
+#[derive(Serialize, Deserialize)]
+struct TrustedIndex {
+    n: usize,
+    /// Missing in indexes written before several sizes could be compared
+    #[serde(default)]
+    ngram_sizes: Vec<usize>,
+    #[serde(default)]
+    fragment_mode: FragmentMode,
+    #[serde(default)]
+    skip: usize,
+    #[serde(default = "default_stride")]
+    stride: usize,
+    #[serde(default)]
+    within_sentences: bool,
+    /// Missing in indexes written before fragments were hashed word by word
+    #[serde(default = "legacy_hash_version")]
+    hash_version: u32,
+    trusted_texts: Vec<TextEntry>,
+}
+
+/// Fragment hash version of files that don't record one
+fn legacy_hash_version() -> u32 {
+    1
+}
+
+/// Stride of files and results that don't record one
+fn default_stride() -> usize {
+    1
+}
+
+/// Fragments of a text written by `save_fingerprints`, without any of its words
+#[derive(Serialize, Deserialize)]
+struct Fingerprint {
+    owner: TextOwnerID,
+    #[serde(default)]
+    language: Option<String>,
+    fragment_locations: FragmentLocations,
+    #[serde(default)]
+    skipped_words: SkippedWords,
+}
+
+/// Fingerprints written by `save_fingerprints`, along with the settings
+///     they were fragmented with
+#[derive(Serialize, Deserialize)]
+struct FingerprintSet {
+    n: usize,
+    ngram_sizes: Vec<usize>,
+    fragment_mode: FragmentMode,
+    skip: usize,
+    #[serde(default = "default_stride")]
+    stride: usize,
+    #[serde(default)]
+    within_sentences: bool,
+    #[serde(default = "legacy_hash_version")]
+    hash_version: u32,
+    fingerprints: Vec<Fingerprint>,
+}
+
+/// Clean words of a location in a text with some words around it, see
+///     `PlagiarismDatabase::get_context`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct SpanContext {
+    pub before: String,
+    pub text: String,
+    pub after: String,
+}
+
+/// How a database splits texts into fragments. Fragments of two databases
+///     can only be compared if they were made the same way.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Fragmentation {
+    pub n: usize,
+    pub ngram_sizes: Vec<usize>,
+    pub fragment_mode: FragmentMode,
+    pub skip: usize,
+    #[serde(default = "default_stride")]
+    pub stride: usize,
+    /// Ngrams never span the end of a sentence
+    #[serde(default)]
+    pub within_sentences: bool,
+    /// Version of the fragment hashes, see `text_utils::HASH_VERSION`
+    #[serde(default = "legacy_hash_version")]
+    pub hash_version: u32,
+}
+
+/// A cleaned and fragmented trusted text, as kept by an `ArchiveStore`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(transparent)]
+pub struct ArchivedText {
+    entry: TextEntry,
+}
+
+impl ArchivedText {
+    pub fn owner(&self) -> &str {
+        &self.entry.owner
+    }
+
+    /// Hashes of all unique fragments of the text
+    pub fn fragments(&self) -> impl Iterator<Item = FragmentHash> + '_ {
+        self.entry.fragment_locations.keys().copied()
+    }
+}
+
+impl TextEntry {
+    /// Byte range of the original text covered by a word location, if known
+    fn byte_range(&self, location: FragmentLocation) -> Option<ByteRange> {
+        let start = self.word_byte_ranges.get(location.0)?.0;
+        let end = self.word_byte_ranges.get(location.1)?.1;
+        Some((start, end))
+    }
+
+    /// Clean words at a location with up to context_words words before and
+    ///     after it, see `PlagiarismDatabase::get_context`
+    fn context(&self, location: FragmentLocation, context_words: usize) -> Option<SpanContext> {
+        let words = &self.clean_text_words;
+        let (start, end) = location;
+        if start > end || end >= words.len() {
+            return None;
+        }
+        let before = start.saturating_sub(context_words);
+        let after = (end + context_words).min(words.len() - 1);
+        Some(SpanContext {
+            before: words[before..start].join(" "),
+            text: words[start..=end].join(" "),
+            after: words[end + 1..=after].join(" "),
+        })
+    }
+
+    /// Byte ranges of all the given word locations that are known
+    fn byte_ranges(&self, locations: &[FragmentLocation]) -> Vec<ByteRange> {
+        locations
+            .iter()
+            .filter_map(|&location| self.byte_range(location))
+            .collect()
+    }
+
+    /// Where a fragment first appears in the text, if it does at all
+    fn first_location(&self, fragment: FragmentHash) -> Option<FragmentLocation> {
+        self.fragment_locations.get(&fragment)?.first().copied()
+    }
+
+    /// Rebuilds the text of a fragment from the words at its first location.
+    ///     Fragments of fingerprints are shown as their hash instead.
+    fn fragment_text(&self, fragment: FragmentHash) -> String {
+        if self.fingerprint {
+            return format!("#{:016x}", fragment);
+        }
+        let (start, end) = match self.first_location(fragment) {
+            Some(location) => location,
+            None => return String::new(),
+        };
+        let skipped = self.skipped_words.get(&fragment);
+        (start..=end)
+            .filter(|&idx| {
+                !self
+                    .excluded
+                    .iter()
+                    .any(|&(ex_start, ex_end)| ex_start <= idx && idx <= ex_end)
+                    && !skipped.is_some_and(|skipped| skipped.contains(&idx))
+            })
+            .map(|idx| self.clean_text_words[idx].as_str())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// Rebuilds the texts of all fragments
+    fn fragment_texts(&self) -> FragmentMap<String> {
+        self.fragment_locations
+            .keys()
+            .map(|&fragment| (fragment, self.fragment_text(fragment)))
+            .collect()
+    }
+
+    /// Removes the given fragments, e.g. because they are ignored
+    fn remove_fragments(&mut self, fragments: &FragmentSet) {
+        self.fragment_locations
+            .retain(|fragment, _| !fragments.contains(fragment));
+        self.skipped_words
+            .retain(|fragment, _| !fragments.contains(fragment));
+        self.bucket_fragments();
+        for chunk in &mut self.chunks {
+            chunk.remove_fragments(fragments);
+        }
+    }
+
+    /// Removes the fragments of an assignment prompt, keeping the words they
+    ///     covered to tell how much of the text is just the prompt
+    fn remove_prompt(&mut self, prompt: &FragmentSet) {
+        if prompt.is_empty() {
+            return;
+        }
+        for (fragment, locations) in &self.fragment_locations {
+            if prompt.contains(fragment) {
+                for &(start, end) in locations {
+                    self.prompt_words.extend(start..=end);
+                }
+            }
+        }
+        self.prompt_words.sort_unstable();
+        self.prompt_words.dedup();
+        self.remove_fragments(prompt);
+    }
+
+    /// Fraction of the words covered by ngrams of the assignment prompt
+    fn prompt_coverage(&self) -> f32 {
+        if self.clean_text_words.is_empty() {
+            return 0.0;
+        }
+        self.prompt_words.len() as f32 / self.clean_text_words.len() as f32
+    }
+
+    /// Rebuilds the length buckets after the fragment set has changed
+    fn bucket_fragments(&mut self) {
+        let mut fragments_by_length: BTreeMap<usize, Vec<FragmentHash>> = BTreeMap::new();
+        for (fragment, text) in self.fragment_texts() {
+            fragments_by_length
+                .entry(text.chars().count())
+                .or_default()
+                .push(fragment);
+        }
+        self.fragments_by_length = fragments_by_length;
+    }
+}
+
+/// Stores the corpus of trusted and untrusted strings
+#[derive(Debug)]
+pub struct PlagiarismDatabase {
+    // Constant value for ngram size
+    n: usize,
+    // Constant value for metric cutoff value
+    s: usize,
+    // Metric to use
+    metric: Metric,
+    /// Metrics also run on every pair the metric reports, see `MetricResult`
+    extra_metrics: Vec<Metric>,
+    /// How raw text is normalized and split into words
+    clean_options: CleanOptions,
+    /// Strip HTML/Markdown from files that look like markup when reading them
+    detect_markup: bool,
+    /// Tokenize texts as source code instead of prose
+    code_mode: Option<CodeMode>,
+    /// Compare ngrams of n words, or whole sentences of at least n words
+    fragment_mode: FragmentMode,
+    /// Sorted sizes of the ngrams made of every text, in ngram mode
+    ngram_sizes: Vec<usize>,
+    /// Maximum number of words skipped inside an ngram (k of k-skip-n-grams)
+    skip: usize,
+    /// Only ngrams starting at every stride-th word are made
+    stride: usize,
+    /// Ngrams never span the end of a sentence
+    within_sentences: bool,
+    /// Number of threads the owner pairs of a check are split over
+    threads: usize,
+    /// Minimum number of matching fragments for an owner pair to be reported
+    min_matches: usize,
+    /// Minimum density of either owner for an owner pair to be reported
+    min_density: f32,
+    /// Weight fragments by their inverse document frequency in the corpus
+    idf_weighting: bool,
+    /// Minimum weighted coverage of either owner for an owner pair to be reported
+    min_weighted_score: f32,
+    /// Number of texts containing every fragment, counted when first needed
+    ///     and reset whenever texts or their fragments change
+    document_frequencies: OnceLock<FragmentMap<usize>>,
+    /// Detect the language of every text added
+    detect_language: bool,
+    /// Stem the words of texts in a detected language
+    stem: bool,
+    /// Extra stopwords for texts in a detected language, by language code
+    language_stopwords: HashMap<String, HashSet<String>>,
+    /// Also compare texts detected to be in different languages
+    cross_language: bool,
+    /// Mapping owner ID to the processed text entry for that owner
+    trusted_texts: HashMap<TextOwnerID, TextEntry>,
+    /// Mapping owner ID to the processed text entry for that owner
+    untrusted_texts: HashMap<TextOwnerID, TextEntry>,
+    /// Check that fragments with equal hashes really have the same text
+    check_collisions: bool,
+    /// Word vectors for the embedding metric
+    word_vectors: Option<WordVectors>,
+    /// Hashes of the fragments to ignore
+    ignored_texts: FragmentSet,
+    /// Hashes of the fragments of the earlier drafts of every owner, removed
+    ///     from the owner's own texts
+    baseline_fragments: HashMap<TextOwnerID, FragmentSet>,
+    /// Fragments of the assignment prompt, excluded from all matching
+    prompt_fragments: FragmentSet,
+    /// What happens when a text is added for an owner that already has one
+    duplicate_owners: DuplicateOwnerPolicy,
+    /// Which pairs of texts with a group are compared
+    group_comparisons: GroupComparisons,
+    /// Owner pairs allowed to share text, with the smaller owner ID first
+    allowed_pairs: HashSet<(TextOwnerID, TextOwnerID)>,
+    /// What happens to the matches of allowed pairs
+    allowed_pair_policy: AllowedPairPolicy,
+    /// How results are labeled High, Medium or Low confidence
+    confidence_rules: ConfidenceRules,
+    /// Align the words of matches for non-equal metrics
+    alignments: bool,
+    /// Words of context around the snippets of matches, if they are made
+    snippet_words: Option<usize>,
+    /// Texts longer than this many words are compared in chunks
+    chunk_words: Option<usize>,
+    /// Only compare the candidates of a cheap first pass with the metric
+    pipeline: Option<PipelineConfig>,
+    /// Finds the start of every section of a text
+    section_splitter: Option<Regex>,
+    /// Comparison of `Metric::Custom`
+    custom_metric: Option<CustomMetric>,
+    /// Splits texts into words
+    tokenizer: SharedTokenizer,
+    /// Translates the words of texts to a pivot language
+    translator: Option<SharedTranslator>,
+    /// Alerted about results as they are found
+    notifications: Option<Notifications>,
+    /// Segments texts detected to be in Chinese or Japanese
+    cjk_segmenter: Arc<CjkSegmenter>,
+}
+
+/// A cheap first pass over all owner pairs, whose candidates are then the
+///     only pairs compared with the (expensive) configured metric
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PipelineConfig {
+    /// Metric of the first pass, `Equal` or `SimHash`
+    pub prefilter: Metric,
+    /// Cutoff of the prefilter metric (bits for SimHash)
+    #[serde(default)]
+    pub cutoff: usize,
+    /// Minimum number of fragments the prefilter must match for a pair to
+    ///     be a candidate
+    #[serde(default = "default_candidate_matches")]
+    pub min_matches: usize,
+}
+
+fn default_candidate_matches() -> usize {
+    1
+}
+
+impl Default for PipelineConfig {
+    fn default() -> PipelineConfig {
+        PipelineConfig {
+            prefilter: Metric::Equal,
+            cutoff: 0,
+            min_matches: default_candidate_matches(),
+        }
+    }
+}
+
+/// Which pairs of texts are compared, by their groups (see `set_group`).
+///     Texts without a group are always compared.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum GroupComparisons {
+    /// Compare all pairs
+    #[default]
+    All,
+    /// Only compare texts of different groups, e.g. to skip the drafts of
+    ///     the same student
+    AcrossGroups,
+    /// Only compare texts of the same group, e.g. within one section
+    WithinGroups,
+}
+
+/// What happens to the matches of owner pairs allowed to share text, e.g.
+///     declared group-work partners
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum AllowedPairPolicy {
+    /// Report the matches with `allowed` set in their results
+    #[default]
+    Flag,
+    /// Don't compare allowed pairs at all
+    Suppress,
+}
+
+/// What happens when a text is added for an owner ID that already has a
+///     text of the same kind (trusted or untrusted)
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum DuplicateOwnerPolicy {
+    /// The new text replaces the old one
+    #[default]
+    Overwrite,
+    /// The new text is rejected with `Error::DuplicateOwner`
+    Error,
+    /// The new text is added with the next free version ID `<owner>#<version>`,
+    ///     starting from `#2` (e.g. for a resubmission), and compared like any
+    ///     other text
+    NewVersion,
+}
+
+/// Sets up a PlagiarismDatabase with non-default ignored texts and cleaning
+///     behaviour. Created with `PlagiarismDatabase::builder`.
+#[derive(Debug)]
+pub struct PlagiarismDatabaseBuilder {
+    n: usize,
+    s: usize,
+    metric: Metric,
+    extra_metrics: Vec<Metric>,
+    ignored_texts: Vec<String>,
+    clean_options: CleanOptions,
+    detect_markup: bool,
+    code_mode: Option<CodeMode>,
+    fragment_mode: FragmentMode,
+    ngram_sizes: Vec<usize>,
+    skip: usize,
+    stride: usize,
+    within_sentences: bool,
+    threads: usize,
+    min_matches: usize,
+    min_density: f32,
+    idf_weighting: bool,
+    min_weighted_score: f32,
+    detect_language: bool,
+    stem: bool,
+    language_stopwords: HashMap<String, HashSet<String>>,
+    cross_language: bool,
+    check_collisions: bool,
+    word_vectors: Option<WordVectors>,
+    duplicate_owners: DuplicateOwnerPolicy,
+    group_comparisons: GroupComparisons,
+    allowed_pair_policy: AllowedPairPolicy,
+    confidence_rules: ConfidenceRules,
+    alignments: bool,
+    snippet_words: Option<usize>,
+    chunk_words: Option<usize>,
+    pipeline: Option<PipelineConfig>,
+    section_splitter: Option<Regex>,
+    custom_metric: Option<CustomMetric>,
+    tokenizer: SharedTokenizer,
+    translator: Option<SharedTranslator>,
+    notifications: Option<Notifications>,
+    cjk_segmenter: Arc<CjkSegmenter>,
+    /// First invalid exclusion pattern, reported by `try_build`
+    pattern_error: Option<Error>,
+}
+
+impl PlagiarismDatabaseBuilder {
+    /// Texts whose fragments are removed from all texts added later
+    pub fn ignored_texts(mut self, ignored_texts: Vec<String>) -> PlagiarismDatabaseBuilder {
+        self.ignored_texts = ignored_texts;
+        self
+    }
+
+    /// Replaces all cleaning options at once
+    pub fn clean_options(mut self, clean_options: CleanOptions) -> PlagiarismDatabaseBuilder {
+        self.clean_options = clean_options;
+        self
+    }
+
+    /// Fold accented characters to their base letters
+    pub fn strip_diacritics(mut self, strip_diacritics: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.strip_diacritics = strip_diacritics;
+        self
+    }
+
+    /// Leave text inside double quotes out of all fragments
+    pub fn exclude_quotes(mut self, exclude_quotes: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.exclude_quotes = exclude_quotes;
+        self
+    }
+
+    /// Drops the regions of every text matching any of these regular
+    ///     expressions before it is split into words (e.g. `(?ms)^References$.*`
+    ///     for everything from a "References" line onward). An invalid
+    ///     pattern fails the build.
+    pub fn exclude_patterns(mut self, patterns: Vec<String>) -> PlagiarismDatabaseBuilder {
+        let mut compiled = Vec::with_capacity(patterns.len());
+        for pattern in patterns {
+            match Regex::new(&pattern) {
+                Ok(regex) => compiled.push(regex),
+                Err(err) => {
+                    self.pattern_error.get_or_insert(Error::InvalidPattern {
+                        pattern,
+                        message: err.to_string(),
+                    });
+                }
+            }
+        }
+        self.clean_options.exclude_patterns = compiled;
+        self
+    }
+
+    /// The user-defined comparison run for `Metric::Custom`, as the metric
+    ///     of the database or one of its extra metrics
+    pub fn custom_metric<M: SimilarityMetric + 'static>(
+        mut self,
+        metric: M,
+    ) -> PlagiarismDatabaseBuilder {
+        self.custom_metric = Some(CustomMetric(Arc::new(metric)));
+        self
+    }
+
+    /// Splits every text into sections (e.g. the chapters of a thesis), so
+    ///     that results list the matches in each section of both texts in
+    ///     `sections_owner1` and `sections_owner2`. An invalid pattern fails
+    ///     the build.
+    pub fn sections(mut self, splitter: SectionSplitter) -> PlagiarismDatabaseBuilder {
+        match splitter.regex() {
+            Ok(regex) => self.section_splitter = Some(regex),
+            Err(err) => {
+                if let SectionSplitter::Pattern(pattern) = splitter {
+                    self.pattern_error.get_or_insert(Error::InvalidPattern {
+                        pattern,
+                        message: err.to_string(),
+                    });
+                }
+            }
+        }
+        self
+    }
+
+    /// Leave citation markers out of all fragments
+    pub fn exclude_citations(mut self, exclude_citations: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.exclude_citations = exclude_citations;
+        self
+    }
+
+    /// Compare words case-sensitively
+    pub fn preserve_case(mut self, preserve_case: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.preserve_case = preserve_case;
+        self
+    }
+
+    /// Keep or remove digits in words
+    pub fn keep_numbers(mut self, keep_numbers: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.keep_numbers = keep_numbers;
+        self
+    }
+
+    /// Replace numbers with `<NUM>`
+    pub fn normalize_numbers(mut self, normalize_numbers: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.normalize_numbers = normalize_numbers;
+        self
+    }
+
+    /// Replace dates with `<DATE>`
+    pub fn normalize_dates(mut self, normalize_dates: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.normalize_dates = normalize_dates;
+        self
+    }
+
+    /// Replace runs of capitalized words that don't start a sentence with `<ENT>`
+    pub fn normalize_entities(mut self, normalize_entities: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.normalize_entities = normalize_entities;
+        self
+    }
+
+    /// Drop invisible characters and fold look-alike letters (on by default)
+    pub fn fold_confusables(mut self, fold_confusables: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.fold_confusables = fold_confusables;
+        self
+    }
+
+    /// Keep or remove punctuation inside words
+    pub fn keep_punctuation(mut self, keep_punctuation: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.keep_punctuation = keep_punctuation;
+        self
+    }
+
+    /// Treat hyphenated words as two words (true) or one word (false)
+    pub fn split_hyphens(mut self, split_hyphens: bool) -> PlagiarismDatabaseBuilder {
+        self.clean_options.split_hyphens = split_hyphens;
+        self
+    }
+
+    /// Leave these words out of all texts (compared after cleaning)
+    pub fn stopwords(mut self, stopwords: Vec<String>) -> PlagiarismDatabaseBuilder {
+        self.clean_options.stopwords = stopwords.into_iter().collect();
+        self
+    }
+
+    /// Remove HTML tags and Markdown syntax from files added with
+    ///     `add_*_file` if their extension or contents show they are markup
+    pub fn detect_markup(mut self, detect_markup: bool) -> PlagiarismDatabaseBuilder {
+        self.detect_markup = detect_markup;
+        self
+    }
+
+    /// Tokenize all texts as source code with the given language settings.
+    ///     The cleaning options are not used in this mode.
+    pub fn code_mode(mut self, code_mode: CodeMode) -> PlagiarismDatabaseBuilder {
+        self.tokenizer = SharedTokenizer(Arc::new(code_mode.clone()));
+        self.code_mode = Some(code_mode);
+        self
+    }
+
+    /// Splits all texts into words with this tokenizer instead of the default
+    ///     one (`tokenize_text` with the cleaning options). Overrides code mode.
+    pub fn tokenizer<T: Tokenizer + 'static>(mut self, tokenizer: T) -> PlagiarismDatabaseBuilder {
+        self.tokenizer = SharedTokenizer(Arc::new(tokenizer));
+        self
+    }
+
+    /// Translates the words of every text to a pivot language before
+    ///     fragments are built, so that translated copies of a source still
+    ///     match it. Also compares texts detected to be in different
+    ///     languages (see `cross_language`). With `stem`, the translated words
+    ///     are stemmed in the pivot language.
+    pub fn translator<T: Translator + 'static>(
+        mut self,
+        translator: T,
+    ) -> PlagiarismDatabaseBuilder {
+        self.translator = Some(SharedTranslator(Arc::new(translator)));
+        self.cross_language = true;
+        self
+    }
+
+    /// Passes every result whose severity with these thresholds (see
+    ///     `Severity::of`) is at least `Warn` to the notifier as soon as a
+    ///     check finds it, e.g. a `WebhookNotifier` alerting instructors. Set
+    ///     only `fail_above` to be notified of failing results only.
+    pub fn notifier<N: Notifier + 'static>(
+        mut self,
+        notifier: N,
+        warn_above: Option<f32>,
+        fail_above: Option<f32>,
+    ) -> PlagiarismDatabaseBuilder {
+        self.notifications = Some(Notifications {
+            notifier: Arc::new(notifier),
+            warn_above,
+            fail_above,
+        });
+        self
+    }
+
+    /// Compare ngrams (the default) or whole sentences. In sentence mode,
+    ///     n is the minimum number of words of a sentence.
+    pub fn fragment_mode(mut self, fragment_mode: FragmentMode) -> PlagiarismDatabaseBuilder {
+        self.fragment_mode = fragment_mode;
+        self
+    }
+
+    /// Split the comparisons of each check over this many threads (at least 1)
+    pub fn threads(mut self, threads: usize) -> PlagiarismDatabaseBuilder {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Only report owner pairs that share at least this many matching
+    ///     fragments (default 1), so a single common phrase isn't reported
+    pub fn min_matches(mut self, min_matches: usize) -> PlagiarismDatabaseBuilder {
+        self.min_matches = min_matches.max(1);
+        self
+    }
+
+    /// Only report owner pairs whose matches are at least this dense in one
+    ///     of the texts (see `PlagiarismResult::density_owner1`, default 0), so
+    ///     a few common phrases scattered over two texts aren't reported
+    pub fn min_density(mut self, min_density: f32) -> PlagiarismDatabaseBuilder {
+        self.min_density = min_density;
+        self
+    }
+
+    /// Weight every matching fragment by how rare it is in the corpus of
+    ///     trusted and untrusted texts (its smoothed inverse document
+    ///     frequency), filling `PlagiarismResult::weighted_coverage_owner1`
+    ///     and `weighted_coverage_owner2`
+    pub fn idf_weighting(mut self, idf_weighting: bool) -> PlagiarismDatabaseBuilder {
+        self.idf_weighting = idf_weighting;
+        self
+    }
+
+    /// Only report owner pairs with at least this weighted score (see
+    ///     `PlagiarismResult::weighted_score`, default 0), so that pairs only
+    ///     sharing stock phrases aren't reported. Turns on `idf_weighting`
+    ///     if above 0.
+    pub fn min_weighted_score(mut self, min_weighted_score: f32) -> PlagiarismDatabaseBuilder {
+        self.min_weighted_score = min_weighted_score;
+        self
+    }
+
+    /// Detect the language of every text. Texts detected to be in different
+    ///     languages are not compared (see `cross_language`).
+    pub fn detect_language(mut self, detect_language: bool) -> PlagiarismDatabaseBuilder {
+        self.detect_language = detect_language;
+        self
+    }
+
+    /// Splits the texts detected to be in Chinese or Japanese (see
+    ///     `detect_language`) into the words of this dictionary, see
+    ///     `CjkSegmenter`. Without one, they are split into character bigrams.
+    pub fn cjk_dictionary(mut self, segmenter: CjkSegmenter) -> PlagiarismDatabaseBuilder {
+        self.cjk_segmenter = Arc::new(segmenter);
+        self
+    }
+
+    /// Stem the words of every text with the stemmer of its detected language
+    ///     (needs `detect_language`)
+    pub fn stem(mut self, stem: bool) -> PlagiarismDatabaseBuilder {
+        self.stem = stem;
+        self
+    }
+
+    /// Also leave these words out of texts detected to be in the given language
+    ///     (ISO 639-3 code, e.g. "eng"), on top of the general stopwords
+    pub fn language_stopwords(
+        mut self,
+        language: &str,
+        stopwords: Vec<String>,
+    ) -> PlagiarismDatabaseBuilder {
+        self.language_stopwords
+            .entry(language.to_string())
+            .or_default()
+            .extend(stopwords);
+        self
+    }
+
+    /// Compare texts detected to be in different languages anyway
+    pub fn cross_language(mut self, cross_language: bool) -> PlagiarismDatabaseBuilder {
+        self.cross_language = cross_language;
+        self
+    }
+
+    /// Fragments are only stored as 64-bit hashes of their text. With this
+    ///     set (the default), the texts of fragments with equal hashes are
+    ///     also compared, so that a hash collision can never be reported as
+    ///     a match. Turning it off only saves comparing the texts. Fingerprints
+    ///     have no texts to compare.
+    pub fn check_collisions(mut self, check_collisions: bool) -> PlagiarismDatabaseBuilder {
+        self.check_collisions = check_collisions;
+        self
+    }
+
+    /// Compares ngrams of each of these sizes in one run instead of only
+    ///     ngrams of n words. The texts are only cleaned once, and every
+    ///     result is tagged with the size that found it. Empty means just n.
+    pub fn ngram_sizes(mut self, ngram_sizes: Vec<usize>) -> PlagiarismDatabaseBuilder {
+        self.ngram_sizes = ngram_sizes;
+        self
+    }
+
+    /// Also makes ngrams that skip up to `skip` words in between (k-skip-n-grams),
+    ///     so that words inserted into a copied passage still leave matching
+    ///     fragments with the equal metric. Has no effect in sentence mode.
+    pub fn skip(mut self, skip: usize) -> PlagiarismDatabaseBuilder {
+        self.skip = skip;
+        self
+    }
+
+    /// Only makes the ngrams starting at every `stride`-th word (1 by default
+    ///     makes all of them), which trades recall for speed and memory on very
+    ///     large corpora: a passage copied at positions that differ by a
+    ///     multiple of the stride still matches, other copies may not.
+    ///     Has no effect in sentence mode.
+    pub fn stride(mut self, stride: usize) -> PlagiarismDatabaseBuilder {
+        self.stride = stride.max(1);
+        self
+    }
+
+    /// Only makes ngrams within one sentence, so that no fragment like
+    ///     "dog the quick" spans the end of a sentence. Sentences shorter than
+    ///     n words have no ngrams. Has no effect in sentence mode and on text
+    ///     that isn't split into sentences (e.g. in code mode).
+    pub fn within_sentences(mut self, within_sentences: bool) -> PlagiarismDatabaseBuilder {
+        self.within_sentences = within_sentences;
+        self
+    }
+
+    /// Maps synonyms to the same word before fragments are built, so that
+    ///     paraphrases using synonyms still match with the equal metric
+    pub fn synonyms(mut self, synonyms: SynonymNormalizer) -> PlagiarismDatabaseBuilder {
+        self.clean_options.synonyms = Some(Arc::new(synonyms));
+        self
+    }
+
+    /// Word vectors to average per fragment for the embedding metric,
+    ///     which requires them
+    pub fn word_vectors(mut self, word_vectors: WordVectors) -> PlagiarismDatabaseBuilder {
+        self.word_vectors = Some(word_vectors);
+        self
+    }
+
+    /// What to do when a text is added for an owner that already has one
+    pub fn duplicate_owners(
+        mut self,
+        duplicate_owners: DuplicateOwnerPolicy,
+    ) -> PlagiarismDatabaseBuilder {
+        self.duplicate_owners = duplicate_owners;
+        self
+    }
+
+    /// Which pairs of texts with a group are compared
+    pub fn group_comparisons(
+        mut self,
+        group_comparisons: GroupComparisons,
+    ) -> PlagiarismDatabaseBuilder {
+        self.group_comparisons = group_comparisons;
+        self
+    }
+
+    /// What happens to the matches of owner pairs allowed to share text
+    ///     (see `allow_pair`)
+    pub fn allowed_pairs(mut self, policy: AllowedPairPolicy) -> PlagiarismDatabaseBuilder {
+        self.allowed_pair_policy = policy;
+        self
+    }
+
+    /// How results are labeled High, Medium or Low confidence
+    pub fn confidence_rules(mut self, rules: ConfidenceRules) -> PlagiarismDatabaseBuilder {
+        self.confidence_rules = rules;
+        self
+    }
+
+    /// Add word alignments of the matches to results of non-equal metrics
+    pub fn alignments(mut self, alignments: bool) -> PlagiarismDatabaseBuilder {
+        self.alignments = alignments;
+        self
+    }
+
+    /// Add a snippet of every matching fragment in both texts, with up to
+    ///     `context_words` words before and after it, to the results (see
+    ///     `PlagiarismResult::snippets`)
+    pub fn snippets(mut self, context_words: usize) -> PlagiarismDatabaseBuilder {
+        self.snippet_words = Some(context_words);
+        self
+    }
+
+    /// Splits texts longer than `words` words into chunks of that many words
+    ///     that are compared on their own, so that the threads of a check
+    ///     share the work of a few very long sources (e.g. books) evenly.
+    ///     Every owner pair still has one result per ngram size, with the
+    ///     word locations in the whole text and the chunks it matched in
+    ///     `PlagiarismResult::chunks_owner1`. `min_matches`, `min_density`
+    ///     and `min_weighted_score` are checked once the chunks of a pair are
+    ///     merged, so matches spread over several chunks count together. Only ngrams are
+    ///     chunked (not sentences or the LCS metric), and texts loaded from
+    ///     indexes, fingerprints or archives aren't.
+    pub fn chunk_words(mut self, words: usize) -> PlagiarismDatabaseBuilder {
+        self.chunk_words = Some(words.max(1));
+        self
+    }
+
+    /// Also run these metrics, with the same cutoff, on every owner pair the
+    ///     metric reports, and add what they match as `metric_results`
+    pub fn extra_metrics(mut self, extra_metrics: Vec<Metric>) -> PlagiarismDatabaseBuilder {
+        self.extra_metrics = extra_metrics;
+        self
+    }
+
+    /// Only compare the owner pairs found by a cheap first pass with the metric
+    pub fn pipeline(mut self, pipeline: PipelineConfig) -> PlagiarismDatabaseBuilder {
+        self.pipeline = Some(pipeline);
+        self
+    }
+
+    /// Creates the database like `try_build`, panicking on invalid settings
+    pub fn build(self) -> PlagiarismDatabase {
+        self.try_build().unwrap_or_else(|err| panic!("{}!", err))
+    }
+
+    /// Creates the database, cleaning and fragmenting the ignored texts.
+    ///     Fails on an invalid exclusion pattern, if the embedding metric
+    ///     (or extra metric) was chosen without word vectors, or if the pipeline prefilter
+    ///     isn't a cheap metric.
+    pub fn try_build(self) -> Result<PlagiarismDatabase> {
+        if let Some(err) = self.pattern_error {
+            return Err(err);
+        }
+        let uses_embedding =
+            self.metric == Metric::Embedding || self.extra_metrics.contains(&Metric::Embedding);
+        if uses_embedding && self.word_vectors.is_none() {
+            return Err(Error::MissingWordVectors);
+        }
+        let uses_custom =
+            self.metric == Metric::Custom || self.extra_metrics.contains(&Metric::Custom);
+        if uses_custom && self.custom_metric.is_none() {
+            return Err(Error::MissingCustomMetric);
+        }
+        if let Some(pipeline) = &self.pipeline {
+            if !matches!(pipeline.prefilter, Metric::Equal | Metric::SimHash) {
+                return Err(Error::InvalidPrefilter {
+                    metric: pipeline.prefilter,
+                });
+            }
+        }
+        let mut ngram_sizes = if self.ngram_sizes.is_empty() {
+            vec![self.n]
+        } else {
+            self.ngram_sizes
+        };
+        ngram_sizes.sort_unstable();
+        ngram_sizes.dedup();
+        let mut db = PlagiarismDatabase {
+            n: self.n,
+            s: self.s,
+            metric: self.metric,
+            extra_metrics: self.extra_metrics,
+            clean_options: self.clean_options,
+            detect_markup: self.detect_markup,
+            code_mode: self.code_mode,
+            fragment_mode: self.fragment_mode,
+            ngram_sizes,
+            skip: self.skip,
+            stride: self.stride,
+            within_sentences: self.within_sentences,
+            threads: self.threads,
+            min_matches: self.min_matches,
+            min_density: self.min_density,
+            idf_weighting: self.idf_weighting || self.min_weighted_score > 0.0,
+            min_weighted_score: self.min_weighted_score,
+            document_frequencies: OnceLock::new(),
+            detect_language: self.detect_language,
+            stem: self.stem,
+            language_stopwords: self.language_stopwords,
+            cross_language: self.cross_language,
+            check_collisions: self.check_collisions,
+            word_vectors: self.word_vectors,
+            trusted_texts: HashMap::new(),
+            untrusted_texts: HashMap::new(),
+            ignored_texts: FragmentSet::default(),
+            baseline_fragments: HashMap::new(),
+            prompt_fragments: FragmentSet::default(),
+            duplicate_owners: self.duplicate_owners,
+            group_comparisons: self.group_comparisons,
+            allowed_pairs: HashSet::new(),
+            allowed_pair_policy: self.allowed_pair_policy,
+            confidence_rules: self.confidence_rules,
+            alignments: self.alignments,
+            snippet_words: self.snippet_words,
+            chunk_words: self.chunk_words,
+            pipeline: self.pipeline,
+            section_splitter: self.section_splitter,
+            custom_metric: self.custom_metric,
+            tokenizer: self.tokenizer,
+            translator: self.translator,
+            notifications: self.notifications,
+            cjk_segmenter: self.cjk_segmenter,
+        };
+        db.ignored_texts = db.construct_ignored_texts(&self.ignored_texts)?;
+        Ok(db)
+    }
+}
+
+impl PlagiarismDatabase {
+    /// Starts building a database with the given sensitivity, similarity and metric.
+    ///     All other settings start at their defaults.
+    pub fn builder(n: usize, s: usize, metric: Metric) -> PlagiarismDatabaseBuilder {
+        PlagiarismDatabaseBuilder {
+            n,
+            s,
+            metric,
+            extra_metrics: Vec::new(),
+            ignored_texts: Vec::new(),
+            clean_options: CleanOptions::default(),
+            detect_markup: false,
+            code_mode: None,
+            fragment_mode: FragmentMode::Ngram,
+            ngram_sizes: Vec::new(),
+            skip: 0,
+            stride: 1,
+            within_sentences: false,
+            threads: 1,
+            min_matches: 1,
+            min_density: 0.0,
+            idf_weighting: false,
+            min_weighted_score: 0.0,
+            detect_language: false,
+            stem: false,
+            language_stopwords: HashMap::new(),
+            cross_language: false,
+            check_collisions: true,
+            word_vectors: None,
+            duplicate_owners: DuplicateOwnerPolicy::default(),
+            group_comparisons: GroupComparisons::default(),
+            allowed_pair_policy: AllowedPairPolicy::default(),
+            confidence_rules: ConfidenceRules::default(),
+            alignments: false,
+            snippet_words: None,
+            chunk_words: None,
+            pipeline: None,
+            section_splitter: None,
+            custom_metric: None,
+            tokenizer: SharedTokenizer(Arc::new(DefaultTokenizer)),
+            translator: None,
+            notifications: None,
+            cjk_segmenter: Arc::new(CjkSegmenter::default()),
+            pattern_error: None,
+        }
+    }
+
+    /// Initializes the plagiarism sensitivity and similarity metric values
+    ///     and the actual metric type to be used in computing plagiarism
+    ///     scores
+    pub fn new(
+        n: usize,
+        s: usize,
+        metric: Metric,
+        ignored_texts: Vec<String>,
+    ) -> PlagiarismDatabase {
+        PlagiarismDatabase::with_clean_options(n, s, metric, ignored_texts, CleanOptions::default())
+    }
+
+    /// Same as `new`, but with explicit options for how texts are cleaned
+    ///     before being split into fragments
+    pub fn with_clean_options(
+        n: usize,
+        s: usize,
+        metric: Metric,
+        ignored_texts: Vec<String>,
+        clean_options: CleanOptions,
+    ) -> PlagiarismDatabase {
+        PlagiarismDatabase::builder(n, s, metric)
+            .ignored_texts(ignored_texts)
+            .clean_options(clean_options)
+            .build()
+    }
+
+    /// Creates a hashset of strings to ignore at the start
+    ///     Doesn't take an owner ID as we just want to collate the
+    ///     strings together to avoid scaling badly with the number of
+    ///     ignored texts as well
+    fn construct_ignored_texts(&self, texts: &[String]) -> Result<FragmentSet> {
+        let mut ignored_text_set = FragmentSet::default();
+        for text in texts {
+            let language = self.detect_text_language(text);
+            let cleaned = self.tokenize("an ignored text", text, language.as_deref())?;
+            ignored_text_set.extend(self.get_textfragments(&cleaned).0.into_keys())
+        }
+        Ok(ignored_text_set)
+    }
+
+    /// Detects the language of a text if language detection is enabled
+    fn detect_text_language(&self, text: &str) -> Option<String> {
+        if self.detect_language && self.code_mode.is_none() {
+            detect_language(text)
+        } else {
+            None
+        }
+    }
+
+    /// Splits a text into words with the tokenizer (code tokens in code
+    ///     mode), passing it the stopwords and stemmer of the text's language.
+    ///     Fails if the translator doesn't keep the number of words of the
+    ///     text of the owner (or the shared text) it is for.
+    fn tokenize(&self, owner_id: &str, text: &str, language: Option<&str>) -> Result<CleanedText> {
+        let tokenizer = &self.tokenizer.0;
+        let translator = match &self.translator {
+            Some(translator) => &translator.0,
+            None => return Ok(tokenizer.tokenize(text, &self.language_options(language))),
+        };
+        // Words are stemmed after translating them, in the pivot language
+        let mut options = self.language_options(language).into_owned();
+        options.stemmer = None;
+        let mut cleaned = tokenizer.tokenize(text, &options);
+        let translated = translator.translate(&cleaned.words, language);
+        if translated.len() != cleaned.words.len() {
+            return Err(Error::TranslatorMismatch {
+                owner_id: owner_id.to_string(),
+                expected: cleaned.words.len(),
+                got: translated.len(),
+            });
+        }
+        let stemmer = stemmer_for(translator.pivot_language())
+            .filter(|_| self.stem)
+            .map(Stemmer::create);
+        cleaned.words = match stemmer {
+            Some(stemmer) => translated
+                .iter()
+                .map(|word| stemmer.stem(word).into_owned())
+                .collect(),
+            None => translated,
+        };
+        Ok(cleaned)
+    }
+
+    /// The cleaning options for a text in a language, with its stopwords and
+    ///     stemmer (if enabled)
+    fn language_options(&self, language: Option<&str>) -> Cow<'_, CleanOptions> {
+        let language = match language {
+            Some(language) => language,
+            None => return Cow::Borrowed(&self.clean_options),
+        };
+        let mut options = self.clean_options.clone();
+        if let Some(stopwords) = self.language_stopwords.get(language) {
+            options.stopwords.extend(stopwords.iter().cloned());
+        }
+        if self.stem {
+            options.stemmer = stemmer_for(language);
+        }
+        if is_cjk_language(language) {
+            options.cjk_segmenter = Some(Arc::clone(&self.cjk_segmenter));
+        }
+        Cow::Owned(options)
+    }
+
+    /// Gets the clean words of a trusted or untrusted text
+    pub fn get_clean_words(&self, owner_id: &str, trusted: bool) -> &[String] {
+        let texts = if trusted {
+            &self.trusted_texts
+        } else {
+            &self.untrusted_texts
+        };
+        texts
+            .get(owner_id)
+            .map_or(&[], |entry| entry.clean_text_words.as_slice())
+    }
+
+    /// Gets the sorted IDs of all trusted or untrusted texts
+    pub fn get_owners(&self, trusted: bool) -> Vec<&TextOwnerID> {
+        let texts = if trusted {
+            &self.trusted_texts
+        } else {
+            &self.untrusted_texts
+        };
+        let mut owners: Vec<&TextOwnerID> = texts.keys().collect();
+        owners.sort();
+        owners
+    }
+
+    /// Gets the sorted IDs of all trusted or untrusted texts in a namespace,
+    ///     see `in_namespace`
+    pub fn get_owners_in_namespace(&self, namespace: &str, trusted: bool) -> Vec<&TextOwnerID> {
+        let mut owners = self.get_owners(trusted);
+        owners.retain(|owner| in_namespace(owner, namespace));
+        owners
+    }
+
+    /// Gets the number of invisible or look-alike characters that were
+    ///     normalized in a trusted or untrusted text, see `OwnerStats`
+    pub fn get_suspicious_chars(&self, owner_id: &str, trusted: bool) -> usize {
+        let texts = if trusted {
+            &self.trusted_texts
+        } else {
+            &self.untrusted_texts
+        };
+        texts
+            .get(owner_id)
+            .map_or(0, |entry| entry.suspicious_chars)
+    }
+
+    /// Gets the byte ranges of a trusted or untrusted text that were dropped
+    ///     by exclusion patterns
+    pub fn get_stripped_ranges(&self, owner_id: &str, trusted: bool) -> &[ByteRange] {
+        let texts = if trusted {
+            &self.trusted_texts
+        } else {
+            &self.untrusted_texts
+        };
+        texts
+            .get(owner_id)
+            .map_or(&[], |entry| entry.stripped.as_slice())
+    }
+
+    /// Gets the metadata of a trusted or untrusted text
+    pub fn get_metadata(&self, owner_id: &str, trusted: bool) -> Option<&HashMap<String, String>> {
+        let texts = if trusted {
+            &self.trusted_texts
+        } else {
+            &self.untrusted_texts
+        };
+        texts.get(owner_id).map(|entry| &entry.metadata)
+    }
+
+    /// Puts a trusted or untrusted text in a group, for `group_comparisons`
+    pub fn set_group(&mut self, owner_id: &str, trusted: bool, group: &str) -> Result<()> {
+        let texts = if trusted {
+            &mut self.trusted_texts
+        } else {
+            &mut self.untrusted_texts
+        };
+        let entry = texts.get_mut(owner_id).ok_or_else(|| Error::UnknownOwner {
+            owner_id: owner_id.to_string(),
+        })?;
+        entry.group = Some(group.to_string());
+        Ok(())
+    }
+
+    /// Allows two owners to share text (e.g. declared group-work partners),
+    ///     so that their matches are flagged or suppressed, see
+    ///     `PlagiarismDatabaseBuilder::allowed_pairs`. The owners don't need
+    ///     to have texts yet.
+    pub fn allow_pair(&mut self, owner_id1: &str, owner_id2: &str) {
+        let pair = if owner_id1 <= owner_id2 {
+            (owner_id1.to_string(), owner_id2.to_string())
+        } else {
+            (owner_id2.to_string(), owner_id1.to_string())
+        };
+        self.allowed_pairs.insert(pair);
+    }
+
+    /// Allows every pair of the owners to share text, see `allow_pair`
+    pub fn allow_group(&mut self, owner_ids: &[&str]) {
+        for (idx, owner_id1) in owner_ids.iter().enumerate() {
+            for owner_id2 in &owner_ids[idx + 1..] {
+                self.allow_pair(owner_id1, owner_id2);
+            }
+        }
+    }
+
+    /// Whether two owners are allowed to share text
+    pub fn is_allowed_pair(&self, owner_id1: &str, owner_id2: &str) -> bool {
+        let (first, second) = if owner_id1 <= owner_id2 {
+            (owner_id1, owner_id2)
+        } else {
+            (owner_id2, owner_id1)
+        };
+        self.allowed_pairs
+            .contains(&(first.to_string(), second.to_string()))
+    }
+
+    /// Renames every untrusted owner to a pseudonym, so that all results and
+    ///     reports can be reviewed blind. Their metadata is dropped, since it
+    ///     usually names the student too. Trusted sources keep their IDs, and
+    ///     texts added later are not renamed. Returns the mapping back to the
+    ///     real owner IDs.
+    pub fn pseudonymize_owners(&mut self, scheme: PseudonymScheme, key: &str) -> OwnerMapping {
+        let owners: Vec<&str> = self.untrusted_texts.keys().map(String::as_str).collect();
+        let pseudonyms = make_pseudonyms(scheme, key, &owners);
+        let rename = |owner: &TextOwnerID| {
+            pseudonyms
+                .get(owner)
+                .cloned()
+                .unwrap_or_else(|| owner.clone())
+        };
+        self.untrusted_texts = self
+            .untrusted_texts
+            .drain()
+            .map(|(owner, mut entry)| {
+                entry.owner = rename(&owner);
+                entry.metadata.clear();
+                (entry.owner.clone(), entry)
+            })
+            .collect();
+        let allowed_pairs: Vec<(TextOwnerID, TextOwnerID)> = self.allowed_pairs.drain().collect();
+        for (owner_id1, owner_id2) in allowed_pairs {
+            self.allow_pair(&rename(&owner_id1), &rename(&owner_id2));
+        }
+        OwnerMapping {
+            scheme,
+            owners: pseudonyms
+                .iter()
+                .map(|(owner, pseudonym)| (pseudonym.clone(), owner.clone()))
+                .collect(),
+        }
+    }
+
+    /// Gets the group of a trusted or untrusted text
+    pub fn get_group(&self, owner_id: &str, trusted: bool) -> Option<&str> {
+        let texts = if trusted {
+            &self.trusted_texts
+        } else {
+            &self.untrusted_texts
+        };
+        texts.get(owner_id)?.group.as_deref()
+    }
+
+    /// Gets the detected language (ISO 639-3 code) of a text
+    pub fn get_language(&self, owner_id: &str) -> Option<&str> {
+        self.trusted_texts
+            .get(owner_id)
+            .or_else(|| self.untrusted_texts.get(owner_id))
+            .and_then(|entry| entry.language.as_deref())
+    }
+
+    /// Gets the clean words at a location (e.g. of a matching fragment) in
+    ///     the text of an owner, joined by spaces. None for unknown owners,
+    ///     fingerprints and locations past the end of the text.
+    pub fn get_span_text(&self, owner_id: &str, location: FragmentLocation) -> Option<String> {
+        self.get_context(owner_id, location, 0)
+            .map(|context| context.text)
+    }
+
+    /// How the text of an owner was cleaned, see `PlagiarismResult::explain`
+    pub(crate) fn text_cleaning(&self, owner_id: &str, trusted: bool) -> Option<TextCleaning<'_>> {
+        let texts = if trusted {
+            &self.trusted_texts
+        } else {
+            &self.untrusted_texts
+        };
+        let entry = texts.get(owner_id)?;
+        let options = self.language_options(entry.language.as_deref());
+        Some(TextCleaning {
+            words: &entry.clean_text_words,
+            word_byte_ranges: &entry.word_byte_ranges,
+            stripped: &entry.stripped,
+            excluded: &entry.excluded,
+            prompt_words: &entry.prompt_words,
+            // With a translator, words are stemmed in the pivot language
+            stemmed: options.stemmer.is_some() || (self.translator.is_some() && self.stem),
+            options,
+            code_mode: self.code_mode.is_some(),
+            translated: self.translator.is_some(),
+            ignored_texts: !self.ignored_texts.is_empty(),
+            baseline: self
+                .baseline_fragments
+                .get(owner_id)
+                .is_some_and(|fragments| !fragments.is_empty()),
+        })
+    }
+
+    /// Same as `get_span_text`, along with up to context_words clean words
+    ///     before and after the location, for reports
+    pub fn get_context(
+        &self,
+        owner_id: &str,
+        location: FragmentLocation,
+        context_words: usize,
+    ) -> Option<SpanContext> {
+        self.trusted_texts
+            .get(owner_id)
+            .or_else(|| self.untrusted_texts.get(owner_id))?
+            .context(location, context_words)
+    }
+
+    /// Gets only the ID -> clean text mapping for all texts
+    pub fn get_all_cleantext(&self) -> HashMap<TextOwnerID, Vec<String>> {
+        let trusted = self
+            .trusted_texts
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clean_text_words.clone()));
+        let untrusted = self
+            .untrusted_texts
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clean_text_words.clone()));
+        trusted.chain(untrusted).collect()
+    }
+
+    /// Adds a text string as potential plagiarism source material.
+    ///     Panics if the owner already has a text and duplicates are an error,
+    ///     or if the translator doesn't keep the number of words.
+    pub fn add_trusted_text(&mut self, owner_id: &str, text: &str) {
+        self.make_text_entry(owner_id, text)
+            .and_then(|entry| self.insert_entries(vec![entry], true, false))
+            .unwrap_or_else(|err| panic!("{}!", err));
+    }
+
+    /// Adds a text string as a potential plagiarized string. Panics like
+    ///     `add_trusted_text`.
+    pub fn add_untrusted_text(&mut self, owner_id: &str, text: &str) {
+        self.add_untrusted_text_with_meta(owner_id, text, HashMap::new());
+    }
+
+    /// Adds a text string as potential plagiarism, with metadata (e.g. the
+    ///     student name or submission time) that is copied into its results
+    pub fn add_untrusted_text_with_meta(
+        &mut self,
+        owner_id: &str,
+        text: &str,
+        metadata: HashMap<String, String>,
+    ) {
+        self.try_add_text_with_meta(owner_id, text, metadata, false)
+            .unwrap_or_else(|err| panic!("{}!", err));
+    }
+
+    /// Adds a trusted text like `add_trusted_text`, but fails instead of
+    ///     panicking, and rejects texts without any words
+    pub fn try_add_trusted_text(&mut self, owner_id: &str, text: &str) -> Result<()> {
+        let entry = self.make_text_entry(owner_id, text)?;
+        self.insert_entries(vec![entry], true, true)
+    }
+
+    /// Adds an untrusted text like `add_untrusted_text`, but fails instead of
+    ///     panicking, and rejects texts without any words
+    pub fn try_add_untrusted_text(&mut self, owner_id: &str, text: &str) -> Result<()> {
+        self.try_add_untrusted_text_with_meta(owner_id, text, HashMap::new())
+    }
+
+    /// Adds an untrusted text with metadata like `add_untrusted_text_with_meta`,
+    ///     but fails instead of panicking, and rejects texts without any words
+    pub fn try_add_untrusted_text_with_meta(
+        &mut self,
+        owner_id: &str,
+        text: &str,
+        metadata: HashMap<String, String>,
+    ) -> Result<()> {
+        self.try_add_text_with_meta(owner_id, text, metadata, true)
+    }
+
+    /// Adds an untrusted text with metadata, rejecting it without words if
+    ///     `reject_empty`
+    fn try_add_text_with_meta(
+        &mut self,
+        owner_id: &str,
+        text: &str,
+        metadata: HashMap<String, String>,
+        reject_empty: bool,
+    ) -> Result<()> {
+        let mut entry = self.make_text_entry(owner_id, text)?;
+        entry.metadata = metadata;
+        self.insert_entries(vec![entry], false, reject_empty)
+    }
+
+    /// Reads a file (plain text, or PDF/DOCX with the matching feature enabled)
+    ///     and adds it as potential plagiarism source material, owned by its
+    ///     file name. Files without any words are added and match nothing, so
+    ///     that one empty submission doesn't stop a whole folder.
+    pub fn add_trusted_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let (owner_id, text) = self.read_owned_file(path.as_ref())?;
+        let entry = self.make_text_entry(&owner_id, &text)?;
+        self.insert_entries(vec![entry], true, false)
+    }
+
+    /// Reads a file (plain text, or PDF/DOCX with the matching feature enabled)
+    ///     and adds it as a potential plagiarized text, owned by its file name.
+    ///     Files without any words are added, like in `add_trusted_file`.
+    pub fn add_untrusted_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let (owner_id, text) = self.read_owned_file(path.as_ref())?;
+        let entry = self.make_text_entry(&owner_id, &text)?;
+        self.insert_entries(vec![entry], false, false)
+    }
+
+    /// Adds many trusted texts at once, cleaning and fragmenting them on the
+    ///     configured number of threads. Panics like `add_trusted_text`.
+    pub fn add_trusted_texts_par(&mut self, texts: Vec<(TextOwnerID, String)>) {
+        self.add_texts_par(texts, true, false)
+            .unwrap_or_else(|err| panic!("{}!", err));
+    }
+
+    /// Adds many untrusted texts at once, cleaning and fragmenting them on the
+    ///     configured number of threads. Panics like `add_untrusted_text`.
+    pub fn add_untrusted_texts_par(&mut self, texts: Vec<(TextOwnerID, String)>) {
+        self.add_texts_par(texts, false, false)
+            .unwrap_or_else(|err| panic!("{}!", err));
+    }
+
+    /// Adds trusted texts like `add_trusted_texts_par`, but fails instead of
+    ///     panicking, and rejects texts without any words. Nothing is added
+    ///     if any text fails.
+    pub fn try_add_trusted_texts_par(&mut self, texts: Vec<(TextOwnerID, String)>) -> Result<()> {
+        self.add_texts_par(texts, true, true)
+    }
+
+    /// Adds untrusted texts like `add_untrusted_texts_par`, but fails like
+    ///     `try_add_trusted_texts_par`
+    pub fn try_add_untrusted_texts_par(&mut self, texts: Vec<(TextOwnerID, String)>) -> Result<()> {
+        self.add_texts_par(texts, false, true)
+    }
+
+    /// Adds many texts on the configured number of threads, rejecting texts
+    ///     without words if `reject_empty`
+    fn add_texts_par(
+        &mut self,
+        texts: Vec<(TextOwnerID, String)>,
+        trusted: bool,
+        reject_empty: bool,
+    ) -> Result<()> {
+        let entries = self.make_text_entries(texts)?;
+        self.insert_entries(entries, trusted, reject_empty)
+    }
+
+    /// Reads files like `add_untrusted_file` and adds them on the configured
+    ///     number of threads. Fails on the first file that cannot be read or
+    ///     added, before any text is added.
+    pub fn add_untrusted_files_par<P: AsRef<Path>>(&mut self, paths: &[P]) -> Result<()> {
+        let texts = paths
+            .iter()
+            .map(|path| self.read_owned_file(path.as_ref()))
+            .collect::<Result<Vec<(TextOwnerID, String)>>>()?;
+        let entries = self.make_text_entries(texts)?;
+        self.insert_entries(entries, false, false)
+    }
+
+    /// Adds all texts of a manifest (see `read_manifest`) with their owner
+    ///     IDs, reading the files of entries with a path. Fails like
+    ///     `add_untrusted_files_par` on files that cannot be read, before
+    ///     any text is added.
+    pub fn add_manifest(&mut self, entries: &[ManifestEntry]) -> Result<()> {
+        let mut trusted: Vec<(TextOwnerID, String)> = Vec::new();
+        let mut untrusted: Vec<(TextOwnerID, String)> = Vec::new();
+        for entry in entries {
+            let text = match &entry.source {
+                ManifestSource::Path(path) => self.read_text_file(Path::new(path))?,
+                ManifestSource::Text(text) => text.clone(),
+            };
+            if entry.trusted {
+                trusted.push((entry.owner_id.clone(), text));
+            } else {
+                untrusted.push((entry.owner_id.clone(), text));
+            }
+        }
+        let trusted = self.make_text_entries(trusted)?;
+        let untrusted = self.make_text_entries(untrusted)?;
+        self.insert_entries(trusted, true, false)?;
+        self.insert_entries(untrusted, false, false)
+    }
+
+    /// Reads the text files, PDFs and DOCX files of a ZIP archive (e.g. an LMS
+    ///     export with a folder per student), including nested archives, and
+    ///     adds the files of every owner, joined, as one potential plagiarized
+    ///     text. The owner of a file is the top-level folder it is in, or else
+    ///     its file name (without ".zip" for a nested archive). Needs the `zip`
+    ///     feature. Fails like `add_untrusted_files_par`, before any text is added.
+    pub fn add_untrusted_zip<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let texts = self.read_zip_texts(path.as_ref())?;
+        let entries = self.make_text_entries(texts)?;
+        self.insert_entries(entries, false, false)
+    }
+
+    /// Reads the texts of a file, a folder or a ZIP archive exactly like
+    ///     `add_untrusted_file`, `add_untrusted_files_par` and
+    ///     `add_untrusted_zip` would add them, without adding them. The byte
+    ///     ranges of results refer to these texts, e.g. for `annotate_text`.
+    pub fn read_untrusted_texts<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<Vec<(TextOwnerID, String)>> {
+        let path = path.as_ref();
+        if is_zip(path) {
+            self.read_zip_texts(path)
+        } else if path.is_dir() {
+            let dir = path.to_string_lossy();
+            get_file_paths_from_dir(&dir)
+                .iter()
+                .map(|path| self.read_owned_file(path))
+                .collect()
+        } else {
+            Ok(vec![self.read_owned_file(path)?])
+        }
+    }
+
+    /// Reads the texts of a ZIP archive, joined by owner, see `add_untrusted_zip`
+    fn read_zip_texts(&self, path: &Path) -> Result<Vec<(TextOwnerID, String)>> {
+        let zip_texts = extract_zip(path).map_err(|err| read_error(path, err))?;
+        let mut joined: BTreeMap<TextOwnerID, String> = BTreeMap::new();
+        for ZipText {
+            owner_id,
+            path,
+            text,
+        } in zip_texts
+        {
+            let mut text = text.map_err(|err| read_error(&path, err))?;
+            if self.detect_markup && is_markup(&path, &text) {
+                text = strip_markup(&text);
+            }
+            let owner_text = joined.entry(owner_id).or_default();
+            if !owner_text.is_empty() {
+                owner_text.push_str("\n\n");
+            }
+            owner_text.push_str(&text);
+        }
+        Ok(joined.into_iter().collect())
+    }
+
+    /// Adds text entries after checking all of them against the duplicate
+    ///     owner policy (and for words, if `reject_empty`), so that either all
+    ///     or none of them are added
+    fn insert_entries(
+        &mut self,
+        mut entries: Vec<TextEntry>,
+        trusted: bool,
+        reject_empty: bool,
+    ) -> Result<()> {
+        let texts = if trusted {
+            &self.trusted_texts
+        } else {
+            &self.untrusted_texts
+        };
+        let mut new_owners: HashSet<TextOwnerID> = HashSet::new();
+        for entry in entries.iter_mut() {
+            if reject_empty && entry.clean_text_words.is_empty() {
+                return Err(Error::EmptyText {
+                    owner_id: entry.owner.clone(),
+                });
+            }
+            let taken =
+                |owner: &TextOwnerID| texts.contains_key(owner) || new_owners.contains(owner);
+            if taken(&entry.owner) {
+                match self.duplicate_owners {
+                    DuplicateOwnerPolicy::Overwrite => {}
+                    DuplicateOwnerPolicy::Error => {
+                        return Err(Error::DuplicateOwner {
+                            owner_id: entry.owner.clone(),
+                        })
+                    }
+                    DuplicateOwnerPolicy::NewVersion => {
+                        entry.owner = (2..)
+                            .map(|version| format!("{}#{}", entry.owner, version))
+                            .find(|owner| !taken(owner))
+                            .expect("There is always a free version");
+                    }
+                }
+            }
+            new_owners.insert(entry.owner.clone());
+            entry.remove_prompt(&self.prompt_fragments);
+            if let Some(fragments) = self.baseline_fragments.get(unversioned_owner(&entry.owner)) {
+                entry.remove_fragments(fragments);
+            }
+        }
+        let texts = if trusted {
+            &mut self.trusted_texts
+        } else {
+            &mut self.untrusted_texts
+        };
+        for entry in entries {
+            texts.insert(entry.owner.clone(), entry);
+            self.document_frequencies = OnceLock::new();
+        }
+        Ok(())
+    }
+
+    /// Cleans and fragments texts split over the configured number of threads,
+    ///     keeping their order. Fails like `make_text_entry` on the first text
+    ///     that fails.
+    fn make_text_entries(&self, texts: Vec<(TextOwnerID, String)>) -> Result<Vec<TextEntry>> {
+        // Don't spawn anything for a single thread (threads aren't available on wasm32)
+        if self.threads == 1 {
+            return texts
+                .iter()
+                .map(|(owner_id, text)| self.make_text_entry(owner_id, text))
+                .collect();
+        }
+        let chunk_size = texts.len().div_ceil(self.threads).max(1);
+        thread::scope(|scope| {
+            let handles: Vec<_> = texts
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|(owner_id, text)| self.make_text_entry(owner_id, text))
+                            .collect::<Vec<Result<TextEntry>>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("A text ingestion thread panicked!"))
+                .collect()
+        })
+    }
+
+    /// Extracts the text of a file along with the owner ID (file name) to use for it
+    fn read_owned_file(&self, path: &Path) -> Result<(TextOwnerID, String)> {
+        let owner_id = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} does not have a valid file name", path.display()),
+                )
+            })?
+            .to_string();
+        Ok((owner_id, self.read_text_file(path)?))
+    }
+
+    /// Extracts the text of a file, without its markup if it looks like markup
+    fn read_text_file(&self, path: &Path) -> Result<String> {
+        let text = extract_text(path).map_err(|err| read_error(path, err))?;
+        if self.detect_markup && is_markup(path, &text) {
+            return Ok(strip_markup(&text));
+        }
+        Ok(text)
+    }
+
+    /// Cleans and fragments a text with the database settings, failing if
+    ///     the translator doesn't keep the number of words
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(owner = owner_id, bytes = text.len()))
+    )]
+    fn make_text_entry(&self, owner_id: &str, text: &str) -> Result<TextEntry> {
+        let language = self.detect_text_language(text);
+        let cleaned = self.tokenize(owner_id, text, language.as_deref())?;
+        let (mut fragment_locations, mut skipped_words) = self.get_textfragments(&cleaned);
+        let sections = self
+            .section_splitter
+            .as_ref()
+            .map(|splitter| split_sections(text, splitter, &cleaned.byte_ranges))
+            .unwrap_or_default();
+        // Remove fragments that match the ignored list (equality test directly)
+        fragment_locations.retain(|fragment, _| !self.ignored_texts.contains(fragment));
+        skipped_words.retain(|fragment, _| !self.ignored_texts.contains(fragment));
+        let chunks = self.make_chunks(&cleaned, &language);
+        let mut entry = TextEntry {
+            owner: owner_id.to_string(),
+            clean_text_words: cleaned.words,
+            word_byte_ranges: cleaned.byte_ranges,
+            language,
+            metadata: HashMap::new(),
+            stripped: cleaned.stripped,
+            excluded: cleaned.excluded,
+            fragment_locations,
+            skipped_words,
+            fingerprint: false,
+            group: None,
+            suspicious_chars: cleaned.suspicious_chars,
+            sections,
+            prompt_words: Vec::new(),
+            chunks,
+            chunk_offset: 0,
+            fragments_by_length: BTreeMap::new(),
+        };
+        entry.bucket_fragments();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            words = entry.clean_text_words.len(),
+            fragments = entry.fragment_locations.len(),
+            language = entry.language.as_deref(),
+            "Made text entry"
+        );
+        Ok(entry)
+    }
+
+    /// Splits a long text into chunks of `chunk_words` words for
+    ///     comparisons, each with the fragments starting in it. Chunks also
+    ///     have the words of the longest fragment after them, so that every
+    ///     fragment is whole in the chunk it starts in.
+    fn make_chunks(&self, cleaned: &CleanedText, language: &Option<String>) -> Vec<TextEntry> {
+        let chunk_words = match self.chunk_words {
+            Some(words)
+                if self.fragment_mode == FragmentMode::Ngram && self.metric != Metric::Lcs =>
+            {
+                words
+            }
+            _ => return Vec::new(),
+        };
+        // Chunks start on the stride, so they have the same ngrams as the text
+        let step = chunk_words.div_ceil(self.stride) * self.stride;
+        if cleaned.words.len() <= step {
+            return Vec::new();
+        }
+        let longest = self
+            .ngram_sizes
+            .iter()
+            .map(|&n| n + self.skip * n.saturating_sub(1))
+            .max()
+            .unwrap_or(0);
+        (0..cleaned.words.len())
+            .step_by(step)
+            .map(|start| {
+                let end = (start + step + longest.saturating_sub(1)).min(cleaned.words.len());
+                let chunk = CleanedText {
+                    words: cleaned.words[start..end].to_vec(),
+                    excluded: cleaned
+                        .excluded
+                        .iter()
+                        .filter(|&&(ex_start, ex_end)| ex_start < end && start <= ex_end)
+                        .map(|&(ex_start, ex_end)| {
+                            (ex_start.max(start) - start, ex_end.min(end - 1) - start)
+                        })
+                        .collect(),
+                    sentence_starts: cleaned
+                        .sentence_starts
+                        .iter()
+                        .filter(|&&idx| start <= idx && idx < end)
+                        .map(|&idx| idx - start)
+                        .collect(),
+                    byte_ranges: cleaned
+                        .byte_ranges
+                        .get(start..end)
+                        .map(<[ByteRange]>::to_vec)
+                        .unwrap_or_default(),
+                    stripped: Vec::new(),
+                    suspicious_chars: 0,
+                };
+                let (mut fragment_locations, mut skipped_words) = self.get_textfragments(&chunk);
+                // Fragments starting after the chunk belong to the next one
+                fragment_locations.retain(|fragment, locations| {
+                    locations.retain(|&(location_start, _)| location_start < step);
+                    !locations.is_empty() && !self.ignored_texts.contains(fragment)
+                });
+                skipped_words.retain(|fragment, _| fragment_locations.contains_key(fragment));
+                let mut entry = TextEntry {
+                    owner: TextOwnerID::new(),
+                    clean_text_words: chunk.words,
+                    word_byte_ranges: chunk.byte_ranges,
+                    language: language.clone(),
+                    metadata: HashMap::new(),
+                    stripped: Vec::new(),
+                    excluded: chunk.excluded,
+                    fragment_locations,
+                    skipped_words,
+                    fingerprint: false,
+                    group: None,
+                    suspicious_chars: 0,
+                    sections: Vec::new(),
+                    prompt_words: Vec::new(),
+                    chunks: Vec::new(),
+                    chunk_offset: start,
+                    fragments_by_length: BTreeMap::new(),
+                };
+                entry.bucket_fragments();
+                entry
+            })
+            .collect()
+    }
+
+    /// Adds shared text (e.g. an assignment prompt or template) whose ngrams
+    ///     are excluded from all matching, including for texts added before
+    ///     it. Panics if the translator doesn't keep the number of words.
+    pub fn add_boilerplate_text(&mut self, text: &str) {
+        self.try_add_boilerplate_text(text)
+            .unwrap_or_else(|err| panic!("{}!", err));
+    }
+
+    /// Adds boilerplate like `add_boilerplate_text`, but fails instead of
+    ///     panicking
+    pub fn try_add_boilerplate_text(&mut self, text: &str) -> Result<()> {
+        let language = self.detect_text_language(text);
+        let cleaned = self.tokenize("the boilerplate", text, language.as_deref())?;
+        let fragments: FragmentSet = self.get_textfragments(&cleaned).0.into_keys().collect();
+        for entry in self
+            .trusted_texts
+            .values_mut()
+            .chain(self.untrusted_texts.values_mut())
+        {
+            entry.remove_fragments(&fragments);
+        }
+        self.ignored_texts.extend(fragments);
+        self.document_frequencies = OnceLock::new();
+        Ok(())
+    }
+
+    /// Sets the assignment prompt the submissions answer. Like boilerplate,
+    ///     its ngrams are excluded from all matching (also for texts added
+    ///     before it), and every result has the fraction of both texts that
+    ///     is just the prompt, a quality signal of its own (see
+    ///     `prompt_coverage`). Another prompt adds its ngrams to the first.
+    ///     Panics like `add_boilerplate_text`.
+    pub fn set_assignment_prompt(&mut self, text: &str) {
+        self.try_set_assignment_prompt(text)
+            .unwrap_or_else(|err| panic!("{}!", err));
+    }
+
+    /// Sets the assignment prompt like `set_assignment_prompt`, but fails
+    ///     instead of panicking
+    pub fn try_set_assignment_prompt(&mut self, text: &str) -> Result<()> {
+        let language = self.detect_text_language(text);
+        let cleaned = self.tokenize("the assignment prompt", text, language.as_deref())?;
+        let fragments: FragmentSet = self.get_textfragments(&cleaned).0.into_keys().collect();
+        for entry in self
+            .trusted_texts
+            .values_mut()
+            .chain(self.untrusted_texts.values_mut())
+        {
+            entry.remove_prompt(&fragments);
+        }
+        self.prompt_fragments.extend(fragments);
+        self.document_frequencies = OnceLock::new();
+        Ok(())
+    }
+
+    /// Word ranges (both inclusive) of the chunks an owner's text (untrusted,
+    ///     else trusted) is compared in, see `chunk_words` on the builder.
+    ///     Empty if it isn't chunked.
+    pub fn chunks(&self, owner_id: &str) -> Vec<FragmentLocation> {
+        self.untrusted_texts
+            .get(owner_id)
+            .or_else(|| self.trusted_texts.get(owner_id))
+            .map_or_else(Vec::new, |entry| {
+                entry
+                    .chunks
+                    .iter()
+                    .map(|chunk| {
+                        let end = chunk.chunk_offset + chunk.clean_text_words.len() - 1;
+                        (chunk.chunk_offset, end)
+                    })
+                    .collect()
+            })
+    }
+
+    /// Fraction of the words of an owner's text (untrusted, else trusted)
+    ///     covered by ngrams of the assignment prompt, 0.0 to 1.0
+    pub fn prompt_coverage(&self, owner_id: &str) -> Option<f32> {
+        self.untrusted_texts
+            .get(owner_id)
+            .or_else(|| self.trusted_texts.get(owner_id))
+            .map(TextEntry::prompt_coverage)
+    }
+
+    /// Adds an earlier draft of an owner as its baseline: the draft's ngrams
+    ///     are removed from the owner's own texts (including its versions
+    ///     `<owner>#<version>`, and texts added before the draft), so that a
+    ///     resubmission isn't flagged for matching its previous draft in the
+    ///     trusted corpus. Passages the draft already shared with other
+    ///     texts are not reported for the owner either. Other owners are
+    ///     still compared with all of the owner's text. Panics like
+    ///     `add_boilerplate_text`.
+    pub fn add_baseline_text(&mut self, owner_id: &str, text: &str) {
+        self.try_add_baseline_text(owner_id, text)
+            .unwrap_or_else(|err| panic!("{}!", err));
+    }
+
+    /// Adds a baseline draft like `add_baseline_text`, but fails instead of
+    ///     panicking
+    pub fn try_add_baseline_text(&mut self, owner_id: &str, text: &str) -> Result<()> {
+        let language = self.detect_text_language(text);
+        let cleaned = self.tokenize(owner_id, text, language.as_deref())?;
+        let fragments: FragmentSet = self.get_textfragments(&cleaned).0.into_keys().collect();
+        let owner_id = unversioned_owner(owner_id);
+        for entry in self
+            .trusted_texts
+            .values_mut()
+            .chain(self.untrusted_texts.values_mut())
+            .filter(|entry| unversioned_owner(&entry.owner) == owner_id)
+        {
+            entry.remove_fragments(&fragments);
+        }
+        self.baseline_fragments
+            .entry(owner_id.to_string())
+            .or_default()
+            .extend(fragments);
+        self.document_frequencies = OnceLock::new();
+        Ok(())
+    }
+
+    /// Adds all texts of another database, e.g. a shard of a large trusted
+    ///     corpus that was built on another thread or machine. Both databases
+    ///     must fragment texts the same way. Owners in both databases are
+    ///     resolved with the duplicate owner policy of this database: with
+    ///     `Overwrite` the text of the other database wins, with `NewVersion`
+    ///     it gets the next free version ID and with `Error` nothing is merged.
+    ///     Boilerplate and allowed pairs of either database apply to all texts.
+    pub fn merge(&mut self, other: PlagiarismDatabase) -> Result<()> {
+        self.check_fragmentation("Merged database was", &other.fragmentation())?;
+        if self.duplicate_owners == DuplicateOwnerPolicy::Error {
+            let mut collisions = sorted_entries(&other.trusted_texts)
+                .into_iter()
+                .filter(|entry| self.trusted_texts.contains_key(&entry.owner))
+                .chain(
+                    sorted_entries(&other.untrusted_texts)
+                        .into_iter()
+                        .filter(|entry| self.untrusted_texts.contains_key(&entry.owner)),
+                );
+            if let Some(entry) = collisions.next() {
+                return Err(Error::DuplicateOwner {
+                    owner_id: entry.owner.clone(),
+                });
+            }
+        }
+        let into_sorted = |texts: HashMap<TextOwnerID, TextEntry>| {
+            let mut entries: Vec<TextEntry> = texts.into_values().collect();
+            entries.sort_by(|a, b| a.owner.cmp(&b.owner));
+            entries
+        };
+        let mut trusted = into_sorted(other.trusted_texts);
+        let mut untrusted = into_sorted(other.untrusted_texts);
+        for entry in trusted.iter_mut().chain(untrusted.iter_mut()) {
+            entry.remove_fragments(&self.ignored_texts);
+        }
+        for entry in self
+            .trusted_texts
+            .values_mut()
+            .chain(self.untrusted_texts.values_mut())
+        {
+            entry.remove_fragments(&other.ignored_texts);
+            entry.remove_prompt(&other.prompt_fragments);
+            if let Some(fragments) = other
+                .baseline_fragments
+                .get(unversioned_owner(&entry.owner))
+            {
+                entry.remove_fragments(fragments);
+            }
+        }
+        self.document_frequencies = OnceLock::new();
+        self.ignored_texts.extend(other.ignored_texts);
+        self.prompt_fragments.extend(other.prompt_fragments);
+        for (owner_id, fragments) in other.baseline_fragments {
+            self.baseline_fragments
+                .entry(owner_id)
+                .or_default()
+                .extend(fragments);
+        }
+        self.allowed_pairs.extend(other.allowed_pairs);
+        self.insert_entries(trusted, true, false)?;
+        self.insert_entries(untrusted, false, false)
+    }
+
+    /// Check for plagiarism by comparing metric against cutoff
+    ///     for all untrusted textfragments currently in database
+    pub fn check_untrusted_plagiarism(&self) -> Vec<PlagiarismResult> {
+        self.check_untrusted_plagiarism_iter().collect()
+    }
+
+    /// Same as `check_untrusted_plagiarism`, but yields the results one owner
+    ///     pair at a time as they are found instead of keeping them all in
+    ///     memory. The first owner of every result has the lower ID.
+    pub fn check_untrusted_plagiarism_iter(&self) -> impl Iterator<Item = PlagiarismResult> + '_ {
+        self.check_untrusted_plagiarism_in_namespace("")
+    }
+
+    /// Same as `check_untrusted_plagiarism_iter`, comparing only the untrusted
+    ///     texts of a namespace with each other, see `in_namespace`
+    pub fn check_untrusted_plagiarism_in_namespace<'a>(
+        &'a self,
+        namespace: &str,
+    ) -> impl Iterator<Item = PlagiarismResult> + 'a {
+        let mut untrusted = sorted_entries(&self.untrusted_texts);
+        untrusted.retain(|entry| in_namespace(&entry.owner, namespace));
+        let count = untrusted.len();
+        let mut cache = ComparisonCache::default();
+        // Start the second loop after the first index to avoid checking same combinations twice
+        (0..count)
+            .flat_map(move |sourceidx| (sourceidx + 1..count).map(move |idx| (sourceidx, idx)))
+            .flat_map(move |(sourceidx, idx)| {
+                self.run_metrics(
+                    untrusted[sourceidx],
+                    untrusted[idx],
+                    (false, false),
+                    &mut cache,
+                )
+            })
+    }
+
+    /// Finds pairs of untrusted texts that are wholesale copies of each other,
+    ///     i.e. more than `DUPLICATE_THRESHOLD` of their ngrams are identical.
+    ///     Sorted by decreasing similarity.
+    pub fn check_exact_duplicates(&self) -> Vec<DuplicateResult> {
+        self.check_duplicates_with_threshold(DUPLICATE_THRESHOLD)
+    }
+
+    /// Same as `check_exact_duplicates`, with a custom similarity threshold
+    pub fn check_duplicates_with_threshold(&self, threshold: f32) -> Vec<DuplicateResult> {
+        let mut results: Vec<DuplicateResult> = Vec::new();
+        let untrusted = sorted_entries(&self.untrusted_texts);
+        // .skip() in second loop to avoid checking same combinations twice
+        for (sourceidx, source) in untrusted.iter().enumerate() {
+            for against in untrusted.iter().skip(sourceidx + 1) {
+                let (source_frags, against_frags) =
+                    (&source.fragment_locations, &against.fragment_locations);
+                let largest = source_frags.len().max(against_frags.len());
+                if largest == 0 {
+                    continue;
+                }
+                let shared_fragments = source_frags
+                    .keys()
+                    .filter(|fragment| against_frags.contains_key(fragment))
+                    .count();
+                let similarity = shared_fragments as f32 / largest as f32;
+                if similarity > threshold {
+                    results.push(DuplicateResult {
+                        owner_id1: source.owner.clone(),
+                        owner_id2: against.owner.clone(),
+                        shared_fragments,
+                        similarity,
+                    });
+                }
+            }
+        }
+        // Stable sort, so equally similar pairs stay in owner order
+        results.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        results
+    }
+
+    /// Check for plagiarism by comparing metric against cutoff
+    ///     for textfragments in database against trusted fragments
+    pub fn check_trusted_plagiarism(&self) -> Vec<PlagiarismResult> {
+        self.check_trusted_plagiarism_in_namespace("")
+    }
+
+    /// Same as `check_trusted_plagiarism`, but only returns the results
+    ///     allowed by the options, so that even huge corpora only keep the
+    ///     most significant results in memory
+    pub fn check_trusted_plagiarism_with(&self, options: &CheckOptions) -> Vec<PlagiarismResult> {
+        let untrusted = sorted_entries(&self.untrusted_texts);
+        let trusted = sorted_entries(&self.trusted_texts);
+        // One trusted text at a time, so only its results are in memory at once
+        options.collect(trusted.into_iter().flat_map(|source| {
+            let pairs: Vec<(&TextEntry, &TextEntry)> =
+                untrusted.iter().map(|&against| (source, against)).collect();
+            self.run_metrics_on_pairs(&pairs, (true, false))
+        }))
+    }
+
+    /// Same as `check_untrusted_plagiarism`, but only returns the results
+    ///     allowed by the options, see `check_trusted_plagiarism_with`
+    pub fn check_untrusted_plagiarism_with(&self, options: &CheckOptions) -> Vec<PlagiarismResult> {
+        options.collect(self.check_untrusted_plagiarism_iter())
+    }
+
+    /// Same as `check_trusted_plagiarism`, for the untrusted texts of a
+    ///     namespace (e.g. one assignment of a course) only, see `in_namespace`
+    pub fn check_trusted_plagiarism_in_namespace(&self, namespace: &str) -> Vec<PlagiarismResult> {
+        let mut untrusted = sorted_entries(&self.untrusted_texts);
+        untrusted.retain(|entry| in_namespace(&entry.owner, namespace));
+        let pairs: Vec<(&TextEntry, &TextEntry)> = sorted_entries(&self.trusted_texts)
+            .into_iter()
+            .flat_map(|source| untrusted.iter().map(move |&against| (source, against)))
+            .collect();
+        self.run_metrics_on_pairs(&pairs, (true, false))
+    }
+
+    /// Number of threads checks are split over
+    pub(crate) fn thread_count(&self) -> usize {
+        self.threads
+    }
+
+    /// Sorted (trusted owner, untrusted owner) pairs compared by
+    ///     `check_trusted_plagiarism`
+    pub(crate) fn trusted_owner_pairs(&self) -> Vec<(TextOwnerID, TextOwnerID)> {
+        let mut pairs: Vec<(TextOwnerID, TextOwnerID)> = self
+            .get_owners(true)
+            .into_iter()
+            .flat_map(|source| {
+                self.get_owners(false)
+                    .into_iter()
+                    .map(move |against| (source.clone(), against.clone()))
+            })
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    /// Metric and similarity threshold of the database
+    pub(crate) fn metric_settings(&self) -> (Metric, usize) {
+        (self.metric, self.s)
+    }
+
+    /// Compares (trusted owner, untrusted owner) pairs on the configured
+    ///     number of threads, skipping owners without a text
+    pub(crate) fn check_trusted_pairs(
+        &self,
+        pairs: &[(TextOwnerID, TextOwnerID)],
+    ) -> Vec<PlagiarismResult> {
+        self.check_owner_pairs(pairs, (true, false), true)
+    }
+
+    /// Compares owner pairs on the configured number of threads, with the
+    ///     first and second owner of every pair trusted or not, skipping
+    ///     owners without a text. The results are passed to the notifier only
+    ///     if `notify` is set.
+    pub(crate) fn check_owner_pairs(
+        &self,
+        pairs: &[(TextOwnerID, TextOwnerID)],
+        trusted: (bool, bool),
+        notify: bool,
+    ) -> Vec<PlagiarismResult> {
+        let texts = |trusted| {
+            if trusted {
+                &self.trusted_texts
+            } else {
+                &self.untrusted_texts
+            }
+        };
+        let entries: Vec<(&TextEntry, &TextEntry)> = pairs
+            .iter()
+            .filter_map(|(owner1, owner2)| {
+                Some((texts(trusted.0).get(owner1)?, texts(trusted.1).get(owner2)?))
+            })
+            .collect();
+        self.compare_pairs(&entries, trusted, notify)
+    }
+
+    /// Compares one trusted text with one untrusted text
+    pub(crate) fn check_trusted_pair(
+        &self,
+        trusted_owner: &str,
+        untrusted_owner: &str,
+        cache: &mut ComparisonCache,
+    ) -> Vec<PlagiarismResult> {
+        match (
+            self.trusted_texts.get(trusted_owner),
+            self.untrusted_texts.get(untrusted_owner),
+        ) {
+            (Some(source), Some(against)) => {
+                self.run_metrics(source, against, (true, false), cache)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// (owner, clean words, fragments) of every trusted or untrusted text,
+    ///     sorted by owner
+    pub(crate) fn text_sizes(&self, trusted: bool) -> Vec<(&TextOwnerID, usize, usize)> {
+        let texts = if trusted {
+            &self.trusted_texts
+        } else {
+            &self.untrusted_texts
+        };
+        sorted_entries(texts)
+            .into_iter()
+            .map(|entry| {
+                (
+                    &entry.owner,
+                    entry.clean_text_words.len(),
+                    entry.fragment_locations.len(),
+                )
+            })
+            .collect()
+    }
+
+    /// Compares two texts on the calling thread, without notifying about the
+    ///     results. Empty if one of them was never added.
+    pub(crate) fn compare_owners(
+        &self,
+        (owner1, trusted1): (&str, bool),
+        (owner2, trusted2): (&str, bool),
+    ) -> Vec<PlagiarismResult> {
+        let texts = |trusted| {
+            if trusted {
+                &self.trusted_texts
+            } else {
+                &self.untrusted_texts
+            }
+        };
+        match (texts(trusted1).get(owner1), texts(trusted2).get(owner2)) {
+            (Some(source), Some(against)) => {
+                let mut cache = ComparisonCache::default();
+                let trusted = (trusted1, trusted2);
+                let results = self.compare_whole(source, against, trusted, &mut cache);
+                self.finish_results(source, against, results, false)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Ranks the trusted sources by the fraction of an untrusted text they
+    ///     match and returns the k best, to find where a text likely came
+    ///     from. Sources without any matches are left out, ties are sorted by
+    ///     owner ID. Empty if there is no untrusted text with the ID.
+    pub fn rank_sources(&self, owner_id: &str, k: usize) -> Vec<RankedSource> {
+        let against = match self.untrusted_texts.get(owner_id) {
+            Some(against) => against,
+            None => return Vec::new(),
+        };
+        let total_words = against.clean_text_words.len().max(1);
+        let mut cache = ComparisonCache::default();
+        let mut ranked: Vec<RankedSource> = self
+            .trusted_texts
+            .values()
+            .filter_map(|source| {
+                // Rankings repeat results of a check, so they aren't notified
+                let results = self.compare_whole(source, against, (true, false), &mut cache);
+                let results = self.finish_results(source, against, results, false);
+                let covered: HashSet<usize> = results
+                    .iter()
+                    .flat_map(|result| &result.matching_fragments_locations)
+                    .flat_map(|(_, locs)| locs)
+                    .flat_map(|&(start, end)| start..=end)
+                    .collect();
+                if covered.is_empty() {
+                    return None;
+                }
+                Some(RankedSource {
+                    owner_id: source.owner.clone(),
+                    score: covered.len() as f32 / total_words as f32,
+                    matching_fragments: results
+                        .iter()
+                        .map(|result| result.matching_fragments.len())
+                        .sum(),
+                })
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.owner_id.cmp(&b.owner_id))
+        });
+        ranked.truncate(k);
+        ranked
+    }
+
+    /// Checks the trusted texts against each other, to find duplicate or
+    ///     overlapping documents in a reference corpus before relying on it.
+    ///     Both owners of every result are trusted.
+    pub fn check_trusted_corpus_overlap(&self) -> Vec<PlagiarismResult> {
+        let trusted = sorted_entries(&self.trusted_texts);
+        // Sorted, so that the first owner of each pair is the lower ID
+        let pairs: Vec<(&TextEntry, &TextEntry)> = trusted
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, &source)| {
+                trusted[idx + 1..]
+                    .iter()
+                    .map(move |&against| (source, against))
+            })
+            .collect();
+        self.run_metrics_on_pairs(&pairs, (true, true))
+    }
+
+    /// Checks only the given (newly added) untrusted texts: against all trusted
+    ///     texts, and against all other untrusted texts. Pairs between two
+    ///     already checked texts are not compared again, so a corpus that grows
+    ///     over time doesn't need a full check after every submission.
+    ///     Results are canonicalized like with `dedup_results`. Fails if one
+    ///     of the texts was never added.
+    pub fn check_new_untrusted(&self, owner_ids: &[TextOwnerID]) -> Result<Vec<PlagiarismResult>> {
+        let mut new_owners: HashSet<&str> = HashSet::new();
+        let new_texts: Vec<&TextEntry> = owner_ids
+            .iter()
+            .filter(|owner| new_owners.insert(owner.as_str()))
+            .map(|owner| {
+                self.untrusted_texts
+                    .get(owner)
+                    .ok_or_else(|| Error::UnknownOwner {
+                        owner_id: owner.clone(),
+                    })
+            })
+            .collect::<Result<_>>()?;
+        let trusted_pairs: Vec<(&TextEntry, &TextEntry)> = sorted_entries(&self.trusted_texts)
+            .into_iter()
+            .flat_map(|source| new_texts.iter().map(move |&against| (source, against)))
+            .collect();
+        let mut untrusted_pairs: Vec<(&TextEntry, &TextEntry)> = Vec::new();
+        for (newidx, &source) in new_texts.iter().enumerate() {
+            // Old texts are compared with every new text, new texts only with
+            //     the new texts after them to avoid checking pairs twice
+            let later_new = new_texts[newidx + 1..].iter().copied();
+            let old = sorted_entries(&self.untrusted_texts)
+                .into_iter()
+                .filter(|entry| !new_owners.contains(entry.owner.as_str()));
+            untrusted_pairs.extend(later_new.chain(old).map(|against| (source, against)));
+        }
+        let mut results = self.run_metrics_on_pairs(&trusted_pairs, (true, false));
+        results.extend(self.run_metrics_on_pairs(&untrusted_pairs, (false, false)));
+        Ok(dedup_results(results))
+    }
+
+    /// Writes the fragments of all trusted texts to a JSON index file, so that
+    ///     a large trusted corpus only has to be read and cleaned once
+    pub fn save_trusted_index<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let trusted_texts = sorted_entries(&self.trusted_texts);
+        let index = serde_json::json!({
+            "n": self.n,
+            "ngram_sizes": self.ngram_sizes,
+            "fragment_mode": self.fragment_mode,
+            "skip": self.skip,
+            "stride": self.stride,
+            "within_sentences": self.within_sentences,
+            "hash_version": HASH_VERSION,
+            "trusted_texts": trusted_texts,
+        });
+        serde_json::to_writer(BufWriter::new(File::create(path)?), &index)?;
+        Ok(())
+    }
+
+    /// Adds all trusted texts of an index written by `save_trusted_index`,
+    ///     following the duplicate owner policy like the texts added directly.
+    ///     The index must have been built with the same ngram sizes (and
+    ///     should use the same cleaning options) as this database.
+    pub fn load_trusted_index<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let mut index: TrustedIndex = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if index.ngram_sizes.is_empty() {
+            index.ngram_sizes = vec![index.n];
+        }
+        self.check_fragmentation(
+            "Index was",
+            &Fragmentation {
+                n: index.n,
+                ngram_sizes: index.ngram_sizes,
+                fragment_mode: index.fragment_mode,
+                skip: index.skip,
+                stride: index.stride,
+                within_sentences: index.within_sentences,
+                hash_version: index.hash_version,
+            },
+        )?;
+        let mut entries = index.trusted_texts;
+        for entry in &mut entries {
+            entry.remove_fragments(&self.ignored_texts);
+        }
+        self.insert_entries(entries, true, false)
+    }
+
+    /// Writes only the fragment hashes (and word locations) of all trusted or
+    ///     untrusted texts to a JSON file, without any of their words, so that
+    ///     institutions can pool their submissions as sources without sharing
+    ///     student text. Owner IDs are written as they are.
+    pub fn save_fingerprints<P: AsRef<Path>>(&self, path: P, trusted: bool) -> Result<()> {
+        let texts = if trusted {
+            &self.trusted_texts
+        } else {
+            &self.untrusted_texts
+        };
+        let fingerprints = sorted_entries(texts)
+            .into_iter()
+            .map(|entry| Fingerprint {
+                owner: entry.owner.clone(),
+                language: entry.language.clone(),
+                fragment_locations: entry.fragment_locations.clone(),
+                skipped_words: entry.skipped_words.clone(),
+            })
+            .collect();
+        let set = FingerprintSet {
+            n: self.n,
+            ngram_sizes: self.ngram_sizes.clone(),
+            fragment_mode: self.fragment_mode,
+            skip: self.skip,
+            stride: self.stride,
+            within_sentences: self.within_sentences,
+            hash_version: HASH_VERSION,
+            fingerprints,
+        };
+        serde_json::to_writer(BufWriter::new(File::create(path)?), &set)?;
+        Ok(())
+    }
+
+    /// Adds the fingerprints of a file written by `save_fingerprints` as
+    ///     trusted texts, following the duplicate owner policy like
+    ///     `load_trusted_index`. They must have been made with the same ngram sizes
+    ///     (and should use the same cleaning options) as this database. Only the
+    ///     equal metric can match them, and their side of every match shows
+    ///     fragment hashes instead of text.
+    pub fn load_fingerprints<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let set: FingerprintSet = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        self.check_fragmentation(
+            "Fingerprints were",
+            &Fragmentation {
+                n: set.n,
+                ngram_sizes: set.ngram_sizes,
+                fragment_mode: set.fragment_mode,
+                skip: set.skip,
+                stride: set.stride,
+                within_sentences: set.within_sentences,
+                hash_version: set.hash_version,
+            },
+        )?;
+        let mut entries: Vec<TextEntry> = Vec::new();
+        for fingerprint in set.fingerprints {
+            let mut entry = TextEntry {
+                owner: fingerprint.owner,
+                clean_text_words: Vec::new(),
+                word_byte_ranges: Vec::new(),
+                language: fingerprint.language,
+                metadata: HashMap::new(),
+                stripped: Vec::new(),
+                excluded: Vec::new(),
+                fragment_locations: fingerprint.fragment_locations,
+                skipped_words: fingerprint.skipped_words,
+                fingerprint: true,
+                group: None,
+                suspicious_chars: 0,
+                sections: Vec::new(),
+                prompt_words: Vec::new(),
+                chunks: Vec::new(),
+                chunk_offset: 0,
+                fragments_by_length: BTreeMap::new(),
+            };
+            entry.remove_fragments(&self.ignored_texts);
+            entries.push(entry);
+        }
+        self.insert_entries(entries, true, false)
+    }
+
+    /// Checks that fragments loaded from a file (described by `what`, e.g.
+    ///     "Index was") were made the same way as the fragments of this database
+    pub(crate) fn check_fragmentation(
+        &self,
+        what: &str,
+        fragmentation: &Fragmentation,
+    ) -> Result<()> {
+        let Fragmentation {
+            n,
+            ngram_sizes,
+            fragment_mode,
+            skip,
+            stride,
+            within_sentences,
+            hash_version,
+        } = fragmentation;
+        let (n, fragment_mode, skip, stride) = (*n, *fragment_mode, *skip, *stride);
+        let mismatch = |message: String| -> Result<()> {
+            Err(Error::IncompatibleFragments(format!(
+                "{} {}",
+                what, message
+            )))
+        };
+        if *ngram_sizes != self.ngram_sizes {
+            return mismatch(format!(
+                "built with ngram sizes {:?}, but the database uses {:?}",
+                ngram_sizes, self.ngram_sizes
+            ));
+        }
+        if n != self.n {
+            return mismatch(format!(
+                "built with n = {}, but the database uses n = {}",
+                n, self.n
+            ));
+        }
+        if fragment_mode != self.fragment_mode {
+            return mismatch(format!(
+                "built with {:?} fragments, but the database uses {:?}",
+                fragment_mode, self.fragment_mode
+            ));
+        }
+        if skip != self.skip {
+            return mismatch(format!(
+                "built skipping up to {} words, but the database skips up to {}",
+                skip, self.skip
+            ));
+        }
+        if stride != self.stride {
+            return mismatch(format!(
+                "built with a stride of {} words, but the database uses {}",
+                stride, self.stride
+            ));
+        }
+        if *within_sentences != self.within_sentences {
+            return mismatch(format!(
+                "built with ngrams {} sentences, but the database makes them {} sentences",
+                if *within_sentences {
+                    "within"
+                } else {
+                    "across"
+                },
+                if self.within_sentences {
+                    "within"
+                } else {
+                    "across"
+                }
+            ));
+        }
+        if *hash_version != HASH_VERSION {
+            return mismatch(format!(
+                "hashed with version {} of the fragment hash, but the database uses version {}",
+                hash_version, HASH_VERSION
+            ));
+        }
+        Ok(())
+    }
+
+    /// How this database splits texts into fragments
+    pub fn fragmentation(&self) -> Fragmentation {
+        Fragmentation {
+            n: self.n,
+            ngram_sizes: self.ngram_sizes.clone(),
+            fragment_mode: self.fragment_mode,
+            skip: self.skip,
+            stride: self.stride,
+            within_sentences: self.within_sentences,
+            hash_version: HASH_VERSION,
+        }
+    }
+
+    /// Stores all trusted texts in an archive, which must fragment texts
+    ///     the same way as this database
+    pub fn archive_trusted_texts<A: ArchiveStore + ?Sized>(&self, archive: &mut A) -> Result<()> {
+        self.check_archive(archive)?;
+        let texts = sorted_entries(&self.trusted_texts)
+            .into_iter()
+            .map(|entry| ArchivedText {
+                entry: entry.clone(),
+            })
+            .collect();
+        archive.store(texts)
+    }
+
+    /// Checks the untrusted texts against the texts of an archive like
+    ///     `check_trusted_plagiarism`, without loading the whole archive: only
+    ///     archived texts sharing a fragment with an untrusted text are loaded
+    ///     (once) and compared with it. Fuzzy metrics therefore only find
+    ///     matches in archived texts that also share an exact fragment.
+    pub fn check_against_archive<A: ArchiveStore + ?Sized>(
+        &self,
+        archive: &A,
+    ) -> Result<Vec<PlagiarismResult>> {
+        self.check_archive(archive)?;
+        let mut loaded: HashMap<TextOwnerID, TextEntry> = HashMap::new();
+        let mut candidate_pairs: Vec<(TextOwnerID, &TextEntry)> = Vec::new();
+        for against in sorted_entries(&self.untrusted_texts) {
+            let fragments: Vec<FragmentHash> = against.fragment_locations.keys().copied().collect();
+            for owner in archive.candidates(&fragments)? {
+                if !loaded.contains_key(&owner) {
+                    let mut entry = archive.load(&owner)?.entry;
+                    entry.remove_fragments(&self.ignored_texts);
+                    loaded.insert(owner.clone(), entry);
+                }
+                candidate_pairs.push((owner, against));
+            }
+        }
+        // Same order as `check_trusted_plagiarism`, trusted owner first
+        candidate_pairs.sort_by(|(owner1, against1), (owner2, against2)| {
+            (owner1, &against1.owner).cmp(&(owner2, &against2.owner))
+        });
+        let pairs: Vec<(&TextEntry, &TextEntry)> = candidate_pairs
+            .iter()
+            .filter_map(|(owner, against)| Some((loaded.get(owner)?, *against)))
+            .collect();
+        Ok(self.run_metrics_on_pairs(&pairs, (true, false)))
+    }
+
+    /// Checks the untrusted texts against the documents of an external source
+    ///     like `check_trusted_plagiarism`: the source is asked for the
+    ///     documents sharing a fragment with every untrusted text, and only
+    ///     those are downloaded (once), cleaned like trusted texts and compared
+    ///     with it. Like with `check_against_archive`, fuzzy metrics only find
+    ///     matches in documents that also share an exact fragment.
+    pub async fn check_against_external<S: ExternalSource>(
+        &self,
+        source: &S,
+    ) -> Result<Vec<PlagiarismResult>> {
+        let fragmentation = self.fragmentation();
+        let mut loaded: HashMap<TextOwnerID, TextEntry> = HashMap::new();
+        let mut candidate_pairs: Vec<(TextOwnerID, &TextEntry)> = Vec::new();
+        for against in sorted_entries(&self.untrusted_texts) {
+            let mut fingerprints: Vec<FragmentHash> =
+                against.fragment_locations.keys().copied().collect();
+            fingerprints.sort_unstable();
+            for owner in source.candidates(&fragmentation, &fingerprints).await? {
+                if !loaded.contains_key(&owner) {
+                    let text = source.fetch(&owner).await?;
+                    loaded.insert(owner.clone(), self.make_text_entry(&owner, &text)?);
+                }
+                candidate_pairs.push((owner, against));
+            }
+        }
+        // Same order as `check_trusted_plagiarism`, trusted owner first
+        candidate_pairs.sort_by(|(owner1, against1), (owner2, against2)| {
+            (owner1, &against1.owner).cmp(&(owner2, &against2.owner))
+        });
+        let pairs: Vec<(&TextEntry, &TextEntry)> = candidate_pairs
+            .iter()
+            .filter_map(|(owner, against)| Some((loaded.get(owner)?, *against)))
+            .collect();
+        Ok(self.run_metrics_on_pairs(&pairs, (true, false)))
+    }
+
+    /// Checks that an archive fragments texts like this database
+    fn check_archive<A: ArchiveStore + ?Sized>(&self, archive: &A) -> Result<()> {
+        self.check_fragmentation("Archive was", archive.fragmentation())
+    }
+
+    /// Checks for plagiarism like `check_trusted_plagiarism`, but also between
+    ///     untrusted texts, and only runs the metric on owner pairs that are
+    ///     likely to be similar. Candidates are found by computing a MinHash
+    ///     signature of `bands * rows` values for every text and pairing up
+    ///     texts that agree on all rows of at least one band.
+    ///     More rows per band only keeps more similar pairs, more bands
+    ///     find more candidates. Results are canonicalized like with `dedup_results`.
+    pub fn check_with_lsh(&self, bands: usize, rows: usize) -> Vec<PlagiarismResult> {
+        // Trusted texts first, so that a trusted text always has the lower index
+        let entries: Vec<(&TextEntry, bool)> = sorted_entries(&self.trusted_texts)
+            .into_iter()
+            .map(|entry| (entry, true))
+            .chain(
+                sorted_entries(&self.untrusted_texts)
+                    .into_iter()
+                    .map(|entry| (entry, false)),
+            )
+            .filter(|(entry, _)| !entry.fragment_locations.is_empty())
+            .collect();
+        let signatures: Vec<Vec<u64>> = entries
+            .iter()
+            .map(|(entry, _)| minhash_signature(entry.fragment_locations.keys(), bands * rows))
+            .collect();
+
+        let mut results: Vec<PlagiarismResult> = Vec::new();
+        let mut cache = ComparisonCache::default();
+        for (first, second) in candidate_pairs(&signatures, bands, rows) {
+            let (source, source_trusted) = entries[first];
+            let (against, against_trusted) = entries[second];
+            // Trusted texts are never compared against each other
+            if against_trusted {
+                continue;
+            }
+            let trusted_owners = (source_trusted, false);
+            results.extend(self.run_metrics(source, against, trusted_owners, &mut cache));
+        }
+        dedup_results(results)
+    }
+
+    /// Checks for plagiarism like `check_with_lsh` (trusted against untrusted
+    ///     and between untrusted texts) until the time budget is used up, for
+    ///     interactive use where a good answer now beats a complete one later.
+    ///     Owner pairs sharing the most identical fragments are compared
+    ///     first, then all others in owner order. Only pairs that are
+    ///     started before the budget runs out are compared, so a check can
+    ///     take a bit longer than the budget. Results are canonicalized like
+    ///     with `dedup_results` and sorted like with `sort_results`.
+    pub fn check_with_budget(&self, budget: Duration) -> BudgetedCheck {
+        let started = Instant::now();
+        // Trusted texts first, so that a trusted text always has the lower index
+        let entries: Vec<(&TextEntry, bool)> = sorted_entries(&self.trusted_texts)
+            .into_iter()
+            .map(|entry| (entry, true))
+            .chain(
+                sorted_entries(&self.untrusted_texts)
+                    .into_iter()
+                    .map(|entry| (entry, false)),
+            )
+            .collect();
+        let trusted_count = self.trusted_texts.len();
+        let pairs: Vec<(usize, usize)> = (0..entries.len())
+            .flat_map(|first| {
+                // Trusted texts are never compared against each other
+                ((first + 1).max(trusted_count)..entries.len()).map(move |second| (first, second))
+            })
+            .collect();
+        // Inverted index of the fragments, to count the fragments every pair shares
+        let mut index: FragmentMap<Vec<usize>> = FragmentMap::default();
+        for (idx, (entry, _)) in entries.iter().enumerate() {
+            for &fragment in entry.fragment_locations.keys() {
+                index.entry(fragment).or_default().push(idx);
+            }
+        }
+        let mut shared: HashMap<(usize, usize), usize> = HashMap::new();
+        for owners in index.values() {
+            for (pos, &first) in owners.iter().enumerate() {
+                for &second in owners[pos + 1..]
+                    .iter()
+                    .filter(|&&second| second >= trusted_count)
+                {
+                    *shared.entry((first, second)).or_default() += 1;
+                }
+            }
+        }
+        let mut order: Vec<(usize, usize)> = pairs;
+        // Stable sort, so pairs sharing as many fragments stay in owner order
+        order.sort_by_key(|pair| std::cmp::Reverse(shared.get(pair).copied().unwrap_or(0)));
+
+        let total = order.len();
+        let mut compared = 0;
+        let mut results: Vec<PlagiarismResult> = Vec::new();
+        let mut cache = ComparisonCache::default();
+        for (first, second) in order {
+            if started.elapsed() >= budget {
+                break;
+            }
+            let (source, source_trusted) = entries[first];
+            let (against, _) = entries[second];
+            results.extend(self.run_metrics(source, against, (source_trusted, false), &mut cache));
+            compared += 1;
+        }
+        let mut results = dedup_results(results);
+        sort_results(&mut results);
+        BudgetedCheck {
+            results,
+            complete: compared == total,
+            compared,
+            total,
+        }
+    }
+
+    /// Compares every pair of untrusted texts (on the configured number of
+    ///     threads) and collects their coverage of each other in a matrix.
+    ///     With several ngram sizes, the largest coverage counts.
+    pub fn similarity_matrix(&self) -> SimilarityMatrix {
+        let untrusted = sorted_entries(&self.untrusted_texts);
+        let pairs: Vec<(&TextEntry, &TextEntry)> = untrusted
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, &source)| {
+                untrusted[idx + 1..]
+                    .iter()
+                    .map(move |&against| (source, against))
+            })
+            .collect();
+        let owners: Vec<TextOwnerID> = untrusted.iter().map(|entry| entry.owner.clone()).collect();
+        let position: HashMap<&str, usize> = owners
+            .iter()
+            .enumerate()
+            .map(|(idx, owner)| (owner.as_str(), idx))
+            .collect();
+        let mut scores = vec![vec![0.0f32; owners.len()]; owners.len()];
+        for (idx, row) in scores.iter_mut().enumerate() {
+            row[idx] = 1.0;
+        }
+        for result in self.run_metrics_on_pairs(&pairs, (false, false)) {
+            if let (Some(&idx1), Some(&idx2)) = (
+                position.get(result.owner_id1.as_str()),
+                position.get(result.owner_id2.as_str()),
+            ) {
+                scores[idx1][idx2] = scores[idx1][idx2].max(result.coverage_owner1);
+                scores[idx2][idx1] = scores[idx2][idx1].max(result.coverage_owner2);
+            }
+        }
+        SimilarityMatrix { owners, scores }
+    }
+
+    /// Counts the words and ngrams of every text, and the ngrams shared by
+    ///     the most texts over the whole corpus
+    pub fn stats(&self) -> DatabaseStats {
+        let mut owners = Vec::new();
+        for (texts, trusted) in [(&self.trusted_texts, true), (&self.untrusted_texts, false)] {
+            for entry in sorted_entries(texts) {
+                let unique_ngrams = entry.fragment_locations.len();
+                let occurrences: usize = entry.fragment_locations.values().map(Vec::len).sum();
+                let duplicate_ngram_rate = if occurrences == 0 {
+                    0.0
+                } else {
+                    (occurrences - unique_ngrams) as f32 / occurrences as f32
+                };
+                owners.push(OwnerStats {
+                    owner_id: entry.owner.clone(),
+                    trusted,
+                    words: entry.clean_text_words.len(),
+                    unique_ngrams,
+                    duplicate_ngram_rate,
+                    suspicious_chars: entry.suspicious_chars,
+                    prompt_coverage: entry.prompt_coverage(),
+                });
+            }
+        }
+        DatabaseStats {
+            owners,
+            common_ngrams: self.shared_ngrams(COMMON_NGRAM_COUNT),
+        }
+    }
+
+    /// Up to `limit` ngrams found in the texts of the most owners (trusted or
+    ///     not, at least two), most owners first. Ngrams shared by nearly all
+    ///     texts are usually phrases of the assignment prompt, worth ignoring.
+    pub fn shared_ngrams(&self, limit: usize) -> Vec<NgramCount> {
+        // Fragment -> (owner count, entry to rebuild its text from)
+        let mut ngram_owners: FragmentMap<(usize, &TextEntry)> = FragmentMap::default();
+        for texts in [&self.trusted_texts, &self.untrusted_texts] {
+            for entry in sorted_entries(texts) {
+                for &fragment in entry.fragment_locations.keys() {
+                    ngram_owners.entry(fragment).or_insert((0, entry)).0 += 1;
+                }
+            }
+        }
+        let mut common_ngrams: Vec<NgramCount> = ngram_owners
+            .into_iter()
+            .filter(|(_, (count, _))| *count > 1)
+            .map(|(fragment, (count, entry))| NgramCount {
+                ngram: entry.fragment_text(fragment),
+                owners: count,
+            })
+            .collect();
+        common_ngrams.sort_by(|a, b| b.owners.cmp(&a.owners).then_with(|| a.ngram.cmp(&b.ngram)));
+        common_ngrams.truncate(limit);
+        common_ngrams
+    }
+
+    /// Aggregates results into one summary per untrusted owner that appears in them
+    ///     (trusted owners are only counted as counterparts),
+    ///     sorted by decreasing percentage of text matched
+    pub fn summarize_by_owner(&self, results: &[PlagiarismResult]) -> Vec<OwnerSummary> {
+        // Owner -> (matched word indices, counterpart -> matching fragment
+        //     count, trusted counterpart -> matched word indices)
+        type OwnerMatches<'a> = (
+            HashSet<usize>,
+            HashMap<&'a str, usize>,
+            HashMap<&'a str, HashSet<usize>>,
+        );
+        let mut per_owner: HashMap<&str, OwnerMatches> = HashMap::new();
+        for result in results {
+            let mut sides = Vec::new();
+            if !result.trusted_owner2 {
+                sides.push((&result.owner_id2, &result.owner_id1, false));
+            }
+            if !result.trusted_owner1 {
+                sides.push((&result.owner_id1, &result.owner_id2, true));
+            }
+            for (owner, counterpart, is_owner1) in sides {
+                let counterpart_trusted = if is_owner1 {
+                    result.trusted_owner2
+                } else {
+                    result.trusted_owner1
+                };
+                let (words, counterparts, sources) = per_owner.entry(owner).or_default();
+                *counterparts.entry(counterpart).or_insert(0) += result.matching_fragments.len();
+                for (locs1, locs2) in &result.matching_fragments_locations {
+                    let locs = if is_owner1 { locs1 } else { locs2 };
+                    for &(start, end) in locs {
+                        words.extend(start..=end);
+                        if counterpart_trusted {
+                            sources.entry(counterpart).or_default().extend(start..=end);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut summaries: Vec<OwnerSummary> = per_owner
+            .into_iter()
+            .map(|(owner, (words, counterparts, sources))| {
+                let total_words = self
+                    .untrusted_texts
+                    .get(owner)
+                    .map_or(0, |entry| entry.clean_text_words.len());
+                let mut counterparts: Vec<(TextOwnerID, usize)> = counterparts
+                    .into_iter()
+                    .map(|(id, count)| (id.to_string(), count))
+                    .collect();
+                counterparts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                OwnerSummary {
+                    owner_id: owner.to_string(),
+                    matched_fragments: counterparts.iter().map(|(_, count)| count).sum(),
+                    matched_percent: if total_words == 0 {
+                        0.0
+                    } else {
+                        words.len() as f32 / total_words as f32 * 100.0
+                    },
+                    counterparts,
+                    attribution: attribute_sources(sources),
+                }
+            })
+            .collect();
+        summaries.sort_by(|a, b| {
+            b.matched_percent
+                .total_cmp(&a.matched_percent)
+                .then_with(|| a.owner_id.cmp(&b.owner_id))
+        });
+        summaries
+    }
+
+    /// Runs the metrics on every (source, against) pair, split over the
+    ///     configured number of threads that each keep their own cache.
+    ///     trusted_owners tells if the sources and the againsts are trusted.
+    ///     Every chunk of a chunked source is compared on its own, like a
+    ///     pair of its own.
+    fn run_metrics_on_pairs(
+        &self,
+        pairs: &[(&TextEntry, &TextEntry)],
+        trusted_owners: (bool, bool),
+    ) -> Vec<PlagiarismResult> {
+        self.compare_pairs(pairs, trusted_owners, true)
+    }
+
+    /// Same as `run_metrics_on_pairs`, passing the results to the notifier
+    ///     only if `notify` is set
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "info", skip_all, fields(pairs = pairs.len(), threads = self.threads))
+    )]
+    fn compare_pairs(
+        &self,
+        pairs: &[(&TextEntry, &TextEntry)],
+        trusted_owners: (bool, bool),
+        notify: bool,
+    ) -> Vec<PlagiarismResult> {
+        // Don't spawn anything for a single thread (threads aren't available on wasm32)
+        if self.threads == 1 {
+            let mut cache = ComparisonCache::default();
+            return pairs
+                .iter()
+                .flat_map(|(source, against)| {
+                    let results = self.compare_whole(source, against, trusted_owners, &mut cache);
+                    self.finish_results(source, against, results, notify)
+                })
+                .collect();
+        }
+        // (pair index, chunk of its source) of every comparison
+        let units: Vec<(usize, Option<usize>)> = pairs
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, (source, _))| {
+                let parts: Vec<Option<usize>> = if source.chunks.is_empty() {
+                    vec![None]
+                } else {
+                    (0..source.chunks.len()).map(Some).collect()
+                };
+                parts.into_iter().map(move |part| (idx, part))
+            })
+            .collect();
+        let chunk_size = units.len().div_ceil(self.threads).max(1);
+        #[cfg(feature = "tracing")]
+        let check_span = tracing::Span::current();
+        // Results of every unit, finished unless they are of a chunk
+        let outputs: Vec<(usize, bool, Vec<PlagiarismResult>)> = thread::scope(|scope| {
+            let handles: Vec<_> = units
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    #[cfg(feature = "tracing")]
+                    let check_span = check_span.clone();
+                    scope.spawn(move || {
+                        // The owner pair spans of every thread belong to the check
+                        #[cfg(feature = "tracing")]
+                        let _entered = check_span.enter();
+                        let mut cache = ComparisonCache::default();
+                        chunk
+                            .iter()
+                            .map(|&(idx, part)| {
+                                let (source, against) = pairs[idx];
+                                let results = self.compare_part(
+                                    source,
+                                    part,
+                                    against,
+                                    trusted_owners,
+                                    &mut cache,
+                                );
+                                match part {
+                                    Some(_) => (idx, false, results),
+                                    None => {
+                                        let results =
+                                            self.finish_results(source, against, results, notify);
+                                        (idx, true, results)
+                                    }
+                                }
+                            })
+                            .collect::<Vec<(usize, bool, Vec<PlagiarismResult>)>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("A plagiarism check thread panicked!"))
+                .collect()
+        });
+        // The chunks of a pair are consecutive, merge them once all are done
+        let mut results = Vec::new();
+        let mut pending: Vec<PlagiarismResult> = Vec::new();
+        let mut outputs = outputs.into_iter().peekable();
+        while let Some((idx, finished, unit_results)) = outputs.next() {
+            if finished {
+                results.extend(unit_results);
+                continue;
+            }
+            pending.extend(unit_results);
+            if outputs.peek().is_none_or(|(next, _, _)| *next != idx) {
+                let (source, against) = pairs[idx];
+                let merged = std::mem::take(&mut pending);
+                results.extend(self.finish_results(source, against, merged, notify));
+            }
+        }
+        results
+    }
+
+    /// Helper function to actually run the plagiarism check against sources.
+    ///     Returns one result per ngram size with enough matches.
+    fn run_metrics(
+        &self,
+        source: &TextEntry,
+        against: &TextEntry,
+        trusted_owners: (bool, bool),
+        cache: &mut ComparisonCache,
+    ) -> Vec<PlagiarismResult> {
+        let results = self.compare_whole(source, against, trusted_owners, cache);
+        self.finish_results(source, against, results, true)
+    }
+
+    /// Results of all chunks of a source (or the whole source), before
+    ///     `finish_results`
+    fn compare_whole(
+        &self,
+        source: &TextEntry,
+        against: &TextEntry,
+        trusted_owners: (bool, bool),
+        cache: &mut ComparisonCache,
+    ) -> Vec<PlagiarismResult> {
+        if source.chunks.is_empty() {
+            self.compare_part(source, None, against, trusted_owners, cache)
+        } else {
+            (0..source.chunks.len())
+                .flat_map(|part| {
+                    self.compare_part(source, Some(part), against, trusted_owners, cache)
+                })
+                .collect()
+        }
+    }
+
+    /// Runs the metrics on a source (or its chunk with that index) and
+    ///     another text, unless the settings skip the pair. The results of a
+    ///     chunk point at the words of the whole source.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(source = %source.owner, against = %against.owner, chunk = ?part))
+    )]
+    fn compare_part(
+        &self,
+        source: &TextEntry,
+        part: Option<usize>,
+        against: &TextEntry,
+        trusted_owners: (bool, bool),
+        cache: &mut ComparisonCache,
+    ) -> Vec<PlagiarismResult> {
+        if let (Some(group1), Some(group2)) = (&source.group, &against.group) {
+            let skipped = match self.group_comparisons {
+                GroupComparisons::All => false,
+                GroupComparisons::AcrossGroups => group1 == group2,
+                GroupComparisons::WithinGroups => group1 != group2,
+            };
+            if skipped {
+                return Vec::new();
+            }
+        }
+        // Matches between texts in different languages are meaningless
+        if !self.cross_language {
+            if let (Some(lang1), Some(lang2)) = (&source.language, &against.language) {
+                if lang1 != lang2 {
+                    return Vec::new();
+                }
+            }
+        }
+        // Fingerprints have no text to compare but their hashes
+        if (source.fingerprint || against.fingerprint) && self.metric != Metric::Equal {
+            return Vec::new();
+        }
+        if let Some(pipeline) = &self.pipeline {
+            if !self.is_candidate(pipeline, source, against) {
+                return Vec::new();
+            }
+        }
+        let allowed = self.is_allowed_pair(&source.owner, &against.owner);
+        if allowed && self.allowed_pair_policy == AllowedPairPolicy::Suppress {
+            return Vec::new();
+        }
+        let entry = part.map_or(source, |part| &source.chunks[part]);
+        // The matches of all chunks of a pair count together, see `finish_results`
+        let min_matches = if part.is_some() { 1 } else { self.min_matches };
+        let mut results = self.run_metric(
+            self.metric,
+            entry,
+            against,
+            (trusted_owners, min_matches),
+            cache,
+        );
+        if !results.is_empty() {
+            self.run_extra_metrics(
+                &mut results,
+                entry,
+                against,
+                (trusted_owners, min_matches),
+                cache,
+            );
+        }
+        if let Some(part) = part {
+            for result in &mut results {
+                // Chunks don't follow renamed owners or new metadata
+                result.owner_id1 = source.owner.clone();
+                result.metadata1 = source.metadata.clone();
+                result.shift_owner1(entry.chunk_offset);
+                result.chunks_owner1 = vec![part];
+            }
+        }
+        results
+    }
+
+    /// Merges the results of the chunks of a source by ngram size, then
+    ///     adds what needs the whole texts (density, sections, confidence),
+    ///     drops the results the filters don't allow and passes the rest to
+    ///     the notifier if `notify` is set
+    fn finish_results(
+        &self,
+        source: &TextEntry,
+        against: &TextEntry,
+        mut results: Vec<PlagiarismResult>,
+        notify: bool,
+    ) -> Vec<PlagiarismResult> {
+        if !source.chunks.is_empty() {
+            results = self.merge_chunk_results(source, against, results);
+            results.retain(|result| result.matching_fragments.len() >= self.min_matches);
+        }
+        let allowed = self.is_allowed_pair(&source.owner, &against.owner);
+        for result in &mut results {
+            result.allowed = allowed;
+            result.compute_density();
+            result.count_sections(source.sections.clone(), against.sections.clone());
+            result.confidence = self.confidence_rules.classify(result);
+        }
+        results
+            .retain(|result| result.density_owner1.max(result.density_owner2) >= self.min_density);
+        if self.idf_weighting {
+            results.retain(|result| result.weighted_score() >= self.min_weighted_score);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(results = results.len(), "Compared owner pair");
+        if let (true, Some(notifications)) = (notify, &self.notifications) {
+            notifications.send(&results);
+        }
+        results
+    }
+
+    /// One result per ngram size from the results of all chunks of a
+    ///     source, with the coverages of the whole texts
+    fn merge_chunk_results(
+        &self,
+        source: &TextEntry,
+        against: &TextEntry,
+        results: Vec<PlagiarismResult>,
+    ) -> Vec<PlagiarismResult> {
+        let mut by_size: BTreeMap<usize, PlagiarismResult> = BTreeMap::new();
+        for result in results {
+            match by_size.entry(result.n) {
+                btree_map::Entry::Vacant(slot) => {
+                    slot.insert(result);
+                }
+                btree_map::Entry::Occupied(mut slot) => slot
+                    .get_mut()
+                    .merge(result)
+                    .expect("Results are grouped by ngram size"),
+            }
+        }
+        by_size
+            .into_values()
+            .map(|mut result| {
+                let matched1: FragmentSet = result
+                    .matching_fragments
+                    .iter()
+                    .map(|(fragment, _)| hash_fragment(fragment))
+                    .collect();
+                // Fingerprints only match equal fragments, whose texts they don't have
+                let matched2: FragmentSet = if against.fingerprint {
+                    matched1.clone()
+                } else {
+                    result
+                        .matching_fragments
+                        .iter()
+                        .map(|(_, fragment)| hash_fragment(fragment))
+                        .collect()
+                };
+                result.coverage_owner1 =
+                    matched1.len() as f32 / self.fragment_count(source, result.n) as f32;
+                result.coverage_owner2 =
+                    matched2.len() as f32 / self.fragment_count(against, result.n) as f32;
+                result.weighted_coverage_owner1 =
+                    self.weighted_coverage(source, result.n, &matched1);
+                result.weighted_coverage_owner2 =
+                    self.weighted_coverage(against, result.n, &matched2);
+                result.prompt_coverage_owner1 = source.prompt_coverage();
+                result
+            })
+            .collect()
+    }
+
+    /// Runs the extra metrics on two texts, adding what each of them matched
+    ///     to the results of the metric of the same ngram size
+    fn run_extra_metrics(
+        &self,
+        results: &mut [PlagiarismResult],
+        source: &TextEntry,
+        against: &TextEntry,
+        filter: ((bool, bool), usize),
+        cache: &mut ComparisonCache,
+    ) {
+        for &metric in &self.extra_metrics {
+            let mut extra_results =
+                if (source.fingerprint || against.fingerprint) && metric != Metric::Equal {
+                    Vec::new()
+                } else {
+                    self.run_metric(metric, source, against, filter, cache)
+                };
+            for result in results.iter_mut() {
+                let extra_result = extra_results
+                    .iter()
+                    .position(|extra_result| extra_result.n == result.n)
+                    .map(|idx| extra_results.swap_remove(idx));
+                result
+                    .metric_results
+                    .push(MetricResult::new(metric, extra_result));
+            }
+        }
+    }
+
+    /// Runs a metric on two texts, one result per ngram size with at least
+    ///     the minimum number of matches given with the trusted owners
+    fn run_metric(
+        &self,
+        metric: Metric,
+        source: &TextEntry,
+        against: &TextEntry,
+        (trusted_owners, min_matches): ((bool, bool), usize),
+        cache: &mut ComparisonCache,
+    ) -> Vec<PlagiarismResult> {
+        if metric == Metric::Lcs {
+            return self
+                .check_plagiarism_lcs(source, against, trusted_owners)
+                .into_iter()
+                .collect();
+        }
+        // Run metrics against both sources to get all matching fragments
+        let matches = match metric {
+            Metric::Equal => self.check_plagiarism_equal(source, against),
+            Metric::SimHash => self.check_plagiarism_simhash(source, against, self.s),
+            Metric::Embedding => self.check_plagiarism_embedding(source, against),
+            _ => self.check_plagiarism_other(source, metric, against, cache),
+        };
+        // Split the matches by ngram size, only fragments of the same size can match
+        let mut matches_by_size: BTreeMap<usize, Vec<(FragmentHash, FragmentHash)>> =
+            BTreeMap::new();
+        for (f1, f2) in matches {
+            let size = self.fragment_size(source, f1);
+            if size == self.fragment_size(against, f2) {
+                matches_by_size.entry(size).or_default().push((f1, f2));
+            }
+        }
+        matches_by_size
+            .into_iter()
+            // Too few matches to report, also after dropping hash collisions
+            .filter(|(_, matches)| matches.len() >= min_matches)
+            .filter_map(|(n, matches)| {
+                self.matches_to_result(metric, source, against, trusted_owners, n, matches)
+                    .filter(|result| result.matching_fragments.len() >= min_matches)
+            })
+            .collect()
+    }
+
+    /// Number of words of an ngram (without skipped words), used to tell the
+    ///     ngram sizes apart. All sentences count as n words.
+    fn fragment_size(&self, entry: &TextEntry, fragment: FragmentHash) -> usize {
+        match self.fragment_mode {
+            FragmentMode::Ngram => entry.first_location(fragment).map_or(0, |(start, end)| {
+                let skipped = entry.skipped_words.get(&fragment).map_or(0, Vec::len);
+                end - start + 1 - skipped
+            }),
+            FragmentMode::Sentence => self.n,
+        }
+    }
+
+    /// Number of unique fragments of a text with the given ngram size
+    fn fragment_count(&self, entry: &TextEntry, n: usize) -> usize {
+        entry
+            .fragment_locations
+            .keys()
+            .filter(|&&fragment| self.fragment_size(entry, fragment) == n)
+            .count()
+    }
+
+    /// Smoothed inverse document frequency of a fragment in the corpus,
+    ///     ln((1 + texts) / (1 + texts containing it)) + 1
+    fn idf(&self, fragment: FragmentHash) -> f32 {
+        let frequencies = self.document_frequencies.get_or_init(|| {
+            let mut frequencies: FragmentMap<usize> = FragmentMap::default();
+            for entry in self
+                .trusted_texts
+                .values()
+                .chain(self.untrusted_texts.values())
+            {
+                for &fragment in entry.fragment_locations.keys() {
+                    *frequencies.entry(fragment).or_default() += 1;
+                }
+            }
+            frequencies
+        });
+        let texts = self.trusted_texts.len() + self.untrusted_texts.len();
+        let containing = frequencies.get(&fragment).copied().unwrap_or(0);
+        ((1 + texts) as f32 / (1 + containing) as f32).ln() + 1.0
+    }
+
+    /// Fraction of the inverse document frequencies of a text's fragments of
+    ///     the given ngram size that belongs to its matched fragments
+    fn weighted_coverage(&self, entry: &TextEntry, n: usize, matched: &FragmentSet) -> f32 {
+        if !self.idf_weighting {
+            return 0.0;
+        }
+        let (mut matched_weight, mut total_weight) = (0.0, 0.0);
+        for &fragment in entry.fragment_locations.keys() {
+            if self.fragment_size(entry, fragment) == n {
+                let weight = self.idf(fragment);
+                total_weight += weight;
+                if matched.contains(&fragment) {
+                    matched_weight += weight;
+                }
+            }
+        }
+        if total_weight == 0.0 {
+            0.0
+        } else {
+            matched_weight / total_weight
+        }
+    }
+
+    /// Builds the result for the matches of one ngram size between two texts
+    fn matches_to_result(
+        &self,
+        metric: Metric,
+        source: &TextEntry,
+        against: &TextEntry,
+        trusted_owners: (bool, bool),
+        n: usize,
+        matches: Vec<(FragmentHash, FragmentHash)>,
+    ) -> Option<PlagiarismResult> {
+        // Matches of fragments that aren't in their text can't be located, skip them
+        let mut matches: Vec<(
+            FragmentHash,
+            FragmentHash,
+            &[FragmentLocation],
+            &[FragmentLocation],
+        )> = matches
+            .into_iter()
+            .filter_map(|(f1, f2)| {
+                let locations1 = source.fragment_locations.get(&f1)?;
+                let locations2 = against.fragment_locations.get(&f2)?;
+                Some((f1, f2, locations1.as_slice(), locations2.as_slice()))
+            })
+            .collect();
+        // No plagiarism between these two sources
+        if matches.is_empty() {
+            return None;
+        }
+        // Fragments come out of hash maps, so order them by where they first
+        //     appear in the texts instead
+        matches.sort_by_key(|&(_, _, locations1, locations2)| {
+            (locations1.first().copied(), locations2.first().copied())
+        });
+        // Get the text and the locations of each matching fragment from each source text
+        let mut matching_fragments: Vec<(String, String)> = Vec::new();
+        let mut matching_fragments_locations: Vec<(Vec<FragmentLocation>, Vec<FragmentLocation>)> =
+            Vec::new();
+        let (mut matched1, mut matched2) = (FragmentSet::default(), FragmentSet::default());
+        for (f1, f2, locations1, locations2) in matches {
+            let texts = (source.fragment_text(f1), against.fragment_text(f2));
+            let has_text = !source.fingerprint && !against.fingerprint;
+            if self.check_collisions && metric == Metric::Equal && has_text && texts.0 != texts.1 {
+                continue;
+            }
+            matched1.insert(f1);
+            matched2.insert(f2);
+            matching_fragments.push(texts);
+            matching_fragments_locations.push((locations1.to_vec(), locations2.to_vec()));
+        }
+        if matching_fragments.is_empty() {
+            return None;
+        }
+        let char_locations = matching_fragments_locations
+            .iter()
+            .map(|(locs1, locs2)| (source.byte_ranges(locs1), against.byte_ranges(locs2)))
+            .collect();
+        // Construct result
+        let result = PlagiarismResult {
+            owner_id1: source.owner.clone(),
+            owner_id2: against.owner.clone(),
+            char_locations,
+            matching_fragments,
+            trusted_owner1: trusted_owners.0,
+            trusted_owner2: trusted_owners.1,
+            equal_fragments: metric == Metric::Equal,
+            n,
+            stride: self.stride,
+            metadata1: source.metadata.clone(),
+            metadata2: against.metadata.clone(),
+            coverage_owner1: matched1.len() as f32 / self.fragment_count(source, n) as f32,
+            coverage_owner2: matched2.len() as f32 / self.fragment_count(against, n) as f32,
+            weighted_coverage_owner1: self.weighted_coverage(source, n, &matched1),
+            weighted_coverage_owner2: self.weighted_coverage(against, n, &matched2),
+            prompt_coverage_owner1: source.prompt_coverage(),
+            prompt_coverage_owner2: against.prompt_coverage(),
+            alignments: self.align_matches(metric, source, against, &matching_fragments_locations),
+            snippets: self.snippets(source, against, &matching_fragments_locations),
+            matching_fragments_locations,
+            ..PlagiarismResult::default()
+        };
+        Some(result)
+    }
+
+    /// Splits a cleaned text into fragments according to the fragment mode,
+    ///     with the ngrams of every size in one map
+    fn get_textfragments(&self, cleaned: &CleanedText) -> (FragmentLocations, SkippedWords) {
+        match self.fragment_mode {
+            FragmentMode::Ngram => {
+                let mut fragment_locations = FragmentLocations::default();
+                let mut skipped_words = SkippedWords::default();
+                for &n in &self.ngram_sizes {
+                    let (locations, skipped) = PlagiarismDatabase::get_ngram_fragments(
+                        cleaned,
+                        n,
+                        self.skip,
+                        self.stride,
+                        self.within_sentences,
+                    );
+                    fragment_locations.extend(locations);
+                    skipped_words.extend(skipped);
+                }
+                (fragment_locations, skipped_words)
+            }
+            FragmentMode::Sentence => (
+                PlagiarismDatabase::get_sentence_fragments(cleaned, self.n),
+                SkippedWords::default(),
+            ),
+        }
+    }
+
+    /// Splits a text into one fragment per sentence of at least `min_words` words
+    ///     (not counting excluded words, which are left out of the fragment).
+    ///     Locations span the whole sentence.
+    fn get_sentence_fragments(cleaned: &CleanedText, min_words: usize) -> FragmentLocations {
+        let mut fragment_locations = FragmentLocations::default();
+        for (start, end) in cleaned.sentences() {
+            let words: Vec<&str> = (start..=end)
+                .filter(|&idx| !cleaned.is_excluded(idx, idx))
+                .map(|idx| cleaned.words[idx].as_str())
+                .collect();
+            if words.is_empty() || words.len() < min_words {
+                continue;
+            }
+            fragment_locations
+                .entry(hash_fragment(&words.join(" ")))
+                .or_default()
+                .push((start, end));
+        }
+        fragment_locations
+    }
+
+    /// Splits a text string into separate ngram TextFragments
+    ///     Also creates the map of fragments -> locations at the same time before
+    ///     vector location information is lost
+    ///     Ngrams touching an excluded word range are left out entirely.
+    ///     Skip-grams are located by the span from their first to their last word.
+    ///     Only the fragment hashes are computed, from the hashes of the words.
+    ///     With within_sentences, ngrams spanning a sentence start are left out.
+    fn get_ngram_fragments(
+        cleaned: &CleanedText,
+        n: usize,
+        skip: usize,
+        stride: usize,
+        within_sentences: bool,
+    ) -> (FragmentLocations, SkippedWords) {
+        let word_hashes: Vec<u64> = cleaned.words.iter().map(|word| hash_word(word)).collect();
+        let is_left_out = |(start, end): FragmentLocation| {
+            cleaned.is_excluded(start, end)
+                || within_sentences && cleaned.crosses_sentence(start, end)
+        };
+        let mut fragment_locations = FragmentLocations::default();
+        let mut skipped_words = SkippedWords::default();
+        if skip == 0 {
+            let hashes = rolling_ngram_hashes(&word_hashes, n);
+            for (start, fragment) in hashes.into_iter().enumerate().step_by(stride) {
+                let location = (start, start + n - 1);
+                if is_left_out(location) {
+                    continue;
+                }
+                fragment_locations
+                    .entry(fragment)
+                    .or_default()
+                    .push(location);
+            }
+            return (fragment_locations, skipped_words);
+        }
+        // Insert all skip-grams into hashmap of ngram locations
+        for indices in skipgram_indices(word_hashes.len(), n, skip) {
+            if indices[0] % stride != 0 {
+                continue;
+            }
+            let location = (indices[0], indices[indices.len() - 1]);
+            if is_left_out(location) {
+                continue;
+            }
+            let fragment = hash_word_hashes(indices.iter().map(|&idx| word_hashes[idx]));
+            let locations = fragment_locations.entry(fragment).or_default();
+            if locations.contains(&location) {
+                continue;
+            }
+            if locations.is_empty() && indices.len() < location.1 - location.0 + 1 {
+                let skipped = (location.0..=location.1)
+                    .filter(|idx| !indices.contains(idx))
+                    .collect();
+                skipped_words.insert(fragment, skipped);
+            }
+            locations.push(location);
+        }
+        (fragment_locations, skipped_words)
+    }
+
+    /// Checks plagiarism by equality of fragment hashes
+    /// Returns a tuple of all matches (second tuple element is identical to first)
+    fn check_plagiarism_equal(
+        &self,
+        source: &TextEntry,
+        against: &TextEntry,
+    ) -> Vec<(FragmentHash, FragmentHash)> {
+        source
+            .fragment_locations
+            .keys()
+            .filter(|fragment| against.fragment_locations.contains_key(fragment))
+            .map(|&fragment| (fragment, fragment))
+            .collect()
+    }
+
+    /// Checks if the prefilter of a pipeline finds enough matches between two
+    ///     texts for them to be compared with the configured metric
+    fn is_candidate(
+        &self,
+        pipeline: &PipelineConfig,
+        source: &TextEntry,
+        against: &TextEntry,
+    ) -> bool {
+        let matches = match pipeline.prefilter {
+            Metric::SimHash => self
+                .check_plagiarism_simhash(source, against, pipeline.cutoff)
+                .len(),
+            _ => self.check_plagiarism_equal(source, against).len(),
+        };
+        matches >= pipeline.min_matches
+    }
+
+    /// Checks plagiarism by the Hamming distance between fragment SimHashes
+    /// Returns a tuple of all matches
+    fn check_plagiarism_simhash(
+        &self,
+        source: &TextEntry,
+        against: &TextEntry,
+        cutoff: usize,
+    ) -> Vec<(FragmentHash, FragmentHash)> {
+        // Hash every fragment once, so comparing two fragments is a single XOR
+        let hash_all = |entry: &TextEntry| -> Vec<(u64, FragmentHash)> {
+            entry
+                .fragment_texts()
+                .into_iter()
+                .map(|(fragment, text)| (simhash(&text), fragment))
+                .collect()
+        };
+        let against_hashes = hash_all(against);
+        let mut results: Vec<(FragmentHash, FragmentHash)> = Vec::new();
+        for (source_hash, source_frag) in hash_all(source) {
+            for &(against_hash, against_frag) in &against_hashes {
+                if check_simhash(source_hash, against_hash, cutoff) {
+                    results.push((source_frag, against_frag));
+                }
+            }
+        }
+        results
+    }
+
+    /// Checks plagiarism by the cosine similarity of the averaged word vectors
+    ///     of the fragments. Fragments without any known word never match.
+    /// Returns a tuple of all matches
+    fn check_plagiarism_embedding(
+        &self,
+        source: &TextEntry,
+        against: &TextEntry,
+    ) -> Vec<(FragmentHash, FragmentHash)> {
+        let word_vectors = self
+            .word_vectors
+            .as_ref()
+            .expect("The embedding metric needs word vectors!");
+        let average_all = |entry: &TextEntry| -> Vec<(Vec<f32>, FragmentHash)> {
+            entry
+                .fragment_texts()
+                .into_iter()
+                .filter_map(|(fragment, text)| {
+                    word_vectors
+                        .average(text.split(' '))
+                        .map(|vector| (vector, fragment))
+                })
+                .collect()
+        };
+        let against_vectors = average_all(against);
+        let mut results: Vec<(FragmentHash, FragmentHash)> = Vec::new();
+        for (source_vector, source_frag) in average_all(source) {
+            for (against_vector, against_frag) in &against_vectors {
+                if check_embedding(&source_vector, against_vector, self.s) {
+                    results.push((source_frag, *against_frag));
+                }
+            }
+        }
+        results
+    }
+
+    /// Checks plagiarism by the longest common subsequence of the words of the
+    ///     two texts (leaving out excluded words). Every span of it with at
+    ///     least n matched words and at most s unmatched words between two
+    ///     matches is one matching fragment, located from its first to its
+    ///     last matched word. Ignored texts don't apply to these spans.
+    fn check_plagiarism_lcs(
+        &self,
+        source: &TextEntry,
+        against: &TextEntry,
+        trusted_owners: (bool, bool),
+    ) -> Option<PlagiarismResult> {
+        let included = |entry: &TextEntry| -> Vec<usize> {
+            (0..entry.clean_text_words.len())
+                .filter(|&idx| {
+                    !entry
+                        .excluded
+                        .iter()
+                        .any(|&(start, end)| start <= idx && idx <= end)
+                })
+                .collect()
+        };
+        let (indices1, indices2) = (included(source), included(against));
+        let words1: Vec<&str> = indices1
+            .iter()
+            .map(|&idx| source.clean_text_words[idx].as_str())
+            .collect();
+        let words2: Vec<&str> = indices2
+            .iter()
+            .map(|&idx| against.clean_text_words[idx].as_str())
+            .collect();
+        let pairs = longest_common_subsequence(&words1, &words2);
+        let spans = lcs_spans(&pairs, self.s, self.n);
+        if spans.is_empty() || spans.len() < self.min_matches {
+            return None;
+        }
+
+        let mut matching_fragments: Vec<(String, String)> = Vec::new();
+        let mut matching_fragments_locations: Vec<(Vec<FragmentLocation>, Vec<FragmentLocation>)> =
+            Vec::new();
+        let (mut covered1, mut covered2) = (0, 0);
+        for span in spans {
+            let (first1, first2) = span[0];
+            let (last1, last2) = span[span.len() - 1];
+            covered1 += last1 - first1 + 1;
+            covered2 += last2 - first2 + 1;
+            matching_fragments.push((
+                words1[first1..=last1].join(" "),
+                words2[first2..=last2].join(" "),
+            ));
+            matching_fragments_locations.push((
+                vec![(indices1[first1], indices1[last1])],
+                vec![(indices2[first2], indices2[last2])],
+            ));
+        }
+        let char_locations = matching_fragments_locations
+            .iter()
+            .map(|(locs1, locs2)| (source.byte_ranges(locs1), against.byte_ranges(locs2)))
+            .collect();
+        Some(PlagiarismResult {
+            owner_id1: source.owner.clone(),
+            owner_id2: against.owner.clone(),
+            matching_fragments,
+            char_locations,
+            trusted_owner1: trusted_owners.0,
+            trusted_owner2: trusted_owners.1,
+            equal_fragments: false,
+            n: self.n,
+            stride: 1,
+            metadata1: source.metadata.clone(),
+            metadata2: against.metadata.clone(),
+            coverage_owner1: covered1 as f32 / words1.len() as f32,
+            coverage_owner2: covered2 as f32 / words2.len() as f32,
+            alignments: self.align_matches(
+                Metric::Lcs,
+                source,
+                against,
+                &matching_fragments_locations,
+            ),
+            snippets: self.snippets(source, against, &matching_fragments_locations),
+            prompt_coverage_owner1: source.prompt_coverage(),
+            prompt_coverage_owner2: against.prompt_coverage(),
+            matching_fragments_locations,
+            ..PlagiarismResult::default()
+        })
+    }
+
+    /// Snippets of every matching fragment at its first location in both
+    ///     texts, if enabled with `snippets` on the builder
+    fn snippets(
+        &self,
+        source: &TextEntry,
+        against: &TextEntry,
+        locations: &[(Vec<FragmentLocation>, Vec<FragmentLocation>)],
+    ) -> Vec<(SpanContext, SpanContext)> {
+        let context_words = match self.snippet_words {
+            Some(context_words) if !source.fingerprint && !against.fingerprint => context_words,
+            _ => return Vec::new(),
+        };
+        let snippet = |entry: &TextEntry, locations: &[FragmentLocation]| {
+            locations
+                .first()
+                .and_then(|&location| entry.context(location, context_words))
+                .unwrap_or_default()
+        };
+        locations
+            .iter()
+            .map(|(locs1, locs2)| (snippet(source, locs1), snippet(against, locs2)))
+            .collect()
+    }
+
+    /// Merges the first locations of matching fragments into spans where
+    ///     they overlap (or touch) in both texts, and aligns the words of
+    ///     every span, if alignments are enabled for a non-equal metric
+    fn align_matches(
+        &self,
+        metric: Metric,
+        source: &TextEntry,
+        against: &TextEntry,
+        locations: &[(Vec<FragmentLocation>, Vec<FragmentLocation>)],
+    ) -> Vec<Alignment> {
+        if !self.alignments || metric == Metric::Equal || source.fingerprint || against.fingerprint
+        {
+            return Vec::new();
+        }
+        let mut pairs: Vec<(FragmentLocation, FragmentLocation)> = locations
+            .iter()
+            .filter_map(|(locs1, locs2)| Some((*locs1.first()?, *locs2.first()?)))
+            .collect();
+        pairs.sort_unstable();
+        let mut spans: Vec<(FragmentLocation, FragmentLocation)> = Vec::new();
+        for (loc1, loc2) in pairs {
+            match spans.last_mut() {
+                Some((span1, span2))
+                    if loc1.0 <= span1.1 + 1 && loc2.0 <= span2.1 + 1 && span2.0 <= loc2.1 + 1 =>
+                {
+                    span1.1 = span1.1.max(loc1.1);
+                    span2.0 = span2.0.min(loc2.0);
+                    span2.1 = span2.1.max(loc2.1);
+                }
+                _ => spans.push((loc1, loc2)),
+            }
+        }
+        spans
+            .into_iter()
+            .map(|(span1, span2)| {
+                let words1 = source
+                    .clean_text_words
+                    .get(span1.0..=span1.1)
+                    .unwrap_or(&[]);
+                let words2 = against
+                    .clean_text_words
+                    .get(span2.0..=span2.1)
+                    .unwrap_or(&[]);
+                let words = align_words(words1, words2)
+                    .into_iter()
+                    .map(|aligned| AlignedWord {
+                        op: aligned.op,
+                        word1: aligned.word1.map(|idx| idx + span1.0),
+                        word2: aligned.word2.map(|idx| idx + span2.0),
+                    })
+                    .collect();
+                Alignment {
+                    span1,
+                    span2,
+                    words,
+                }
+            })
+            .collect()
+    }
+
+    /// Checks plagiarism by non-equal metric (string-by-string)
+    /// Returns a tuple of all matches
+    /// Fragment pairs already scored earlier in the run are looked up in the cache
+    /// For Levenshtein, only fragments whose lengths differ by at most `s` are
+    ///     compared, since the distance is at least the difference in length,
+    ///     and every source fragment is preprocessed once for all targets
+    fn check_plagiarism_other(
+        &self,
+        source: &TextEntry,
+        metric: Metric,
+        against: &TextEntry,
+        cache: &mut ComparisonCache,
+    ) -> Vec<(FragmentHash, FragmentHash)> {
+        let source_texts = source.fragment_texts();
+        let against_texts = against.fragment_texts();
+        let mut results: Vec<(FragmentHash, FragmentHash)> = Vec::new();
+        #[cfg(feature = "tracing")]
+        let (cached_before, mut compared) = (cache.len(), 0);
+        for (&length, source_frags) in &source.fragments_by_length {
+            let candidate_lengths = match (metric, &self.custom_metric) {
+                (Metric::Lev, _) => length.saturating_sub(self.s)..=length.saturating_add(self.s),
+                (Metric::Custom, Some(custom)) => custom.0.candidate_lengths(length, self.s),
+                _ => 0..=usize::MAX,
+            };
+            let patterns: Vec<Option<LevenshteinPattern>> = source_frags
+                .iter()
+                .map(|source_frag| {
+                    (metric == Metric::Lev)
+                        .then(|| LevenshteinPattern::new(&source_texts[source_frag]))
+                })
+                .collect();
+            for against_frags in against
+                .fragments_by_length
+                .range(candidate_lengths)
+                .map(|(_, frags)| frags)
+            {
+                #[cfg(feature = "tracing")]
+                {
+                    compared += source_frags.len() * against_frags.len();
+                }
+                for (&source_frag, pattern) in source_frags.iter().zip(&patterns) {
+                    for &against_frag in against_frags {
+                        let is_match = *cache
+                            .entry((source_frag, against_frag, metric, self.s))
+                            .or_insert_with(|| {
+                                let (text1, text2) =
+                                    (&source_texts[&source_frag], &against_texts[&against_frag]);
+                                match (metric, &self.custom_metric, pattern) {
+                                    (Metric::Custom, Some(custom), _) => {
+                                        custom.0.is_match(text1, text2, self.s)
+                                    }
+                                    (_, _, Some(pattern)) => {
+                                        pattern.distance_within(text2, self.s).is_some()
+                                    }
+                                    _ => is_plagiarised(text1, text2, metric, self.s),
+                                }
+                            });
+                        if is_match {
+                            results.push((source_frag, against_frag));
+                        }
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            ?metric,
+            fragments_compared = compared,
+            cache_hits = compared - (cache.len() - cached_before),
+            matches = results.len(),
+            "Compared fragments"
+        );
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_duplicates() {
+        let mut db = PlagiarismDatabase::new(2, 0, Metric::Equal, Vec::new());
+        db.add_untrusted_text("a", "the quick brown fox jumps over the lazy dog");
+        db.add_untrusted_text("b", "The quick brown fox. Jumps over the lazy dog!");
+        db.add_untrusted_text("c", "the quick brown fox sleeps under a warm rug");
+        let duplicates = db.check_exact_duplicates();
+        assert_eq!(duplicates.len(), 1);
+        let mut owners = vec![&duplicates[0].owner_id1, &duplicates[0].owner_id2];
+        owners.sort();
+        assert_eq!(owners, vec!["a", "b"]);
+        assert_eq!(duplicates[0].shared_fragments, 8);
+        assert!((duplicates[0].similarity - 1.0).abs() < f32::EPSILON);
+        // Lowering the threshold also catches the partial copy
+        assert_eq!(db.check_duplicates_with_threshold(0.3).len(), 3);
+    }
+
+    #[test]
+    fn test_boilerplate_text() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("t", "describe the water cycle in detail please");
+        db.add_untrusted_text("u1", "describe the water cycle it rains a lot");
+        assert_eq!(db.check_trusted_plagiarism().len(), 1);
+        // Texts added before and after the boilerplate are both affected
+        db.add_boilerplate_text("Describe the water cycle.");
+        db.add_untrusted_text("u2", "describe the water cycle and then stop");
+        assert!(db.check_trusted_plagiarism().is_empty());
+    }
+
+    #[test]
+    fn test_chunk_words() {
+        let book = "the water cycle never stops and rain falls on the hills every day \
+            while rivers carry the water back to the sea where it warms in the sun \
+            and rises again as clouds over the hills";
+        let essay = "we know that rain falls on the hills and that rivers carry the water \
+            back to the sea and rises again as clouds";
+        let check = |db: &mut PlagiarismDatabase| {
+            db.add_trusted_text("book", book);
+            db.add_untrusted_text("essay", essay);
+            db.check_trusted_plagiarism()
+        };
+        let whole = check(&mut PlagiarismDatabase::new(
+            3,
+            0,
+            Metric::Equal,
+            Vec::new(),
+        ));
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .chunk_words(10)
+            .threads(3)
+            .build();
+        let chunked = check(&mut db);
+        assert_eq!(
+            db.chunks("book"),
+            vec![(0, 11), (10, 21), (20, 31), (30, 35)]
+        );
+        assert_eq!(chunked.len(), 1);
+        assert_eq!(chunked[0].chunks_owner1, vec![0, 1, 2, 3]);
+        assert!((chunked[0].coverage_owner1 - whole[0].coverage_owner1).abs() < 1e-6);
+        assert!((chunked[0].coverage_owner2 - whole[0].coverage_owner2).abs() < 1e-6);
+        let sorted = |result: &PlagiarismResult| {
+            let mut matches: Vec<(String, Vec<FragmentLocation>)> = result
+                .matching_fragments
+                .iter()
+                .map(|(fragment, _)| fragment.clone())
+                .zip(
+                    result
+                        .matching_fragments_locations
+                        .iter()
+                        .map(|(locs1, _)| {
+                            let mut locs1 = locs1.clone();
+                            locs1.sort_unstable();
+                            locs1
+                        }),
+                )
+                .collect();
+            matches.sort();
+            matches
+        };
+        // Same matches, at the same words of the whole book
+        assert_eq!(sorted(&chunked[0]), sorted(&whole[0]));
+        assert_eq!(
+            chunked[0].char_locations.len(),
+            whole[0].char_locations.len()
+        );
+    }
+
+    #[test]
+    fn test_chunk_min_matches() {
+        let book = "the water cycle never stops and rain falls on the hills every day \
+            while rivers carry the water back to the sea where it warms in the sun \
+            and rises again as clouds over the hills";
+        let essay = "we know that rain falls on the hills and that rivers carry the water \
+            back to the sea and rises again as clouds";
+        let check = |chunk_words: Option<usize>| {
+            let mut builder = PlagiarismDatabase::builder(3, 0, Metric::Equal).min_matches(4);
+            if let Some(words) = chunk_words {
+                builder = builder.chunk_words(words);
+            }
+            let mut db = builder.build();
+            db.add_trusted_text("book", book);
+            db.add_untrusted_text("essay", essay);
+            db.check_trusted_plagiarism()
+        };
+        let whole = check(None);
+        // Chunks with fewer than 4 matches still count towards the pair
+        let chunked = check(Some(10));
+        assert_eq!(chunked.len(), 1);
+        assert_eq!(
+            chunked[0].matching_fragments.len(),
+            whole[0].matching_fragments.len()
+        );
+        assert!((chunked[0].coverage_owner1 - whole[0].coverage_owner1).abs() < 1e-6);
+        // Pairs with too few matches in all chunks together are still dropped
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .min_matches(20)
+            .chunk_words(10)
+            .build();
+        db.add_trusted_text("book", book);
+        db.add_untrusted_text("essay", essay);
+        assert!(db.check_trusted_plagiarism().is_empty());
+    }
+
+    #[test]
+    fn test_assignment_prompt() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("book", "describe the water cycle and how rain falls");
+        db.add_untrusted_text("u1", "describe the water cycle and how rain falls");
+        db.set_assignment_prompt("Describe the water cycle.");
+        db.add_untrusted_text("u2", "describe the water cycle in short");
+        assert_eq!(db.prompt_coverage("u1"), Some(0.5));
+        assert_eq!(db.prompt_coverage("u2"), Some(4.0 / 6.0));
+        assert_eq!(db.prompt_coverage("missing"), None);
+        // Texts added before and after the prompt only match beyond it
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "u1");
+        assert!(results[0]
+            .matching_fragments
+            .iter()
+            .all(|(fragment, _)| !fragment.starts_with("describe")));
+        assert_eq!(results[0].prompt_coverage_owner2, 0.5);
+        let stats = db.stats();
+        assert_eq!(stats.owners[0].prompt_coverage, 0.5);
+    }
+
+    #[test]
+    fn test_baseline_text() {
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .duplicate_owners(DuplicateOwnerPolicy::NewVersion)
+            .build();
+        let draft = "my first try at the water cycle essay";
+        db.add_trusted_text("alice-draft", draft);
+        db.add_trusted_text("book", "rain falls and the water cycle begins");
+        db.add_untrusted_text("alice", "my first try at the water cycle essay ended well");
+        assert_eq!(db.check_trusted_plagiarism().len(), 2);
+        db.add_baseline_text("alice", draft);
+        // The resubmission is added as a new version and gets the baseline too
+        db.add_untrusted_text("alice", "my first try at the water cycle essay again");
+        db.add_untrusted_text("bob", "my first try at the water cycle");
+        let results = db.check_trusted_plagiarism();
+        let pairs: Vec<(&str, &str)> = results
+            .iter()
+            .map(|result| (result.owner_id1.as_str(), result.owner_id2.as_str()))
+            .collect();
+        assert_eq!(pairs, vec![("alice-draft", "bob"), ("book", "bob")]);
+    }
+
+    #[test]
+    fn test_lev_matches_across_owners() {
+        let mut db = PlagiarismDatabase::new(3, 1, Metric::Lev, Vec::new());
+        db.add_trusted_text("t", "the cat sat");
+        db.add_untrusted_text("u1", "the cat sad");
+        db.add_untrusted_text("u2", "the cat sat");
+        db.add_untrusted_text("u3", "a dog barked");
+        let mut matched: Vec<TextOwnerID> = db
+            .check_trusted_plagiarism()
+            .into_iter()
+            .map(|result| result.owner_id2)
+            .collect();
+        matched.sort();
+        assert_eq!(matched, vec!["u1", "u2"]);
+    }
+
+    #[test]
+    fn test_check_with_lsh() {
+        let mut db = PlagiarismDatabase::new(2, 0, Metric::Equal, Vec::new());
+        let essay = "it was the best of times it was the worst of times it was the age of wisdom";
+        db.add_trusted_text("t", essay);
+        db.add_untrusted_text("u1", essay);
+        db.add_untrusted_text("u2", &format!("{} and so on", essay));
+        db.add_untrusted_text("u3", "completely unrelated words that share nothing at all");
+        let mut pairs: Vec<(TextOwnerID, TextOwnerID, bool)> = db
+            .check_with_lsh(16, 2)
+            .into_iter()
+            .map(|result| {
+                let mut owners = [result.owner_id1, result.owner_id2];
+                if !result.trusted_owner1 {
+                    owners.sort();
+                }
+                (owners[0].clone(), owners[1].clone(), result.trusted_owner1)
+            })
+            .collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec![
+                ("t".to_string(), "u1".to_string(), true),
+                ("t".to_string(), "u2".to_string(), true),
+                ("u1".to_string(), "u2".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_builder_clean_options() {
+        let mut db = PlagiarismDatabase::builder(2, 0, Metric::Equal)
+            .preserve_case(true)
+            .split_hyphens(false)
+            .build();
+        db.add_trusted_text("t", "A well-known Fact indeed");
+        db.add_untrusted_text("u1", "a well known fact indeed");
+        db.add_untrusted_text("u2", "so a wellknown Fact");
+        let mut matched: Vec<String> = db
+            .check_trusted_plagiarism()
+            .into_iter()
+            .flat_map(|result| result.matching_fragments)
+            .map(|(fragment, _)| fragment)
+            .collect();
+        matched.sort();
+        assert_eq!(matched, vec!["wellknown Fact"]);
+    }
+
+    #[test]
+    fn test_code_mode() {
+        let mut db = PlagiarismDatabase::builder(11, 0, Metric::Equal)
+            .code_mode(CodeMode::for_language("python").unwrap())
+            .build();
+        db.add_trusted_text(
+            "orig.py",
+            "def area(w, h):\n    return w * h  # width times height\n",
+        );
+        db.add_untrusted_text("copy.py", "def size(a, b):\n    return a * b\n");
+        db.add_untrusted_text("other.py", "def size(a, b):\n    return a + b\n");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "copy.py");
+    }
+
+    #[test]
+    fn test_add_texts_par() {
+        let texts: Vec<(TextOwnerID, String)> = (0..10)
+            .map(|i| {
+                (
+                    format!("student{}", i),
+                    format!("number {} copied the lazy dog", i),
+                )
+            })
+            .collect();
+        let mut serial = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        serial.add_trusted_text("source", "the quick brown fox jumps over the lazy dog");
+        for (owner, text) in &texts {
+            serial.add_untrusted_text(owner, text);
+        }
+        let mut parallel = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .threads(3)
+            .build();
+        parallel.add_trusted_texts_par(vec![(
+            "source".to_string(),
+            "the quick brown fox jumps over the lazy dog".to_string(),
+        )]);
+        parallel.add_untrusted_texts_par(texts);
+        assert_eq!(parallel.get_owners(false).len(), 10);
+        assert_eq!(
+            serde_json::to_string(&parallel.check_trusted_plagiarism()).unwrap(),
+            serde_json::to_string(&serial.check_trusted_plagiarism()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fingerprints() {
+        let mut pool = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        pool.add_untrusted_text("alice", "the quick brown fox jumps over the lazy dog");
+        let path = std::env::temp_dir().join("plagiarismbasic_test_fingerprints.json");
+        pool.save_fingerprints(&path, false).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("alice"));
+        assert!(!contents.contains("fox"));
+
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.load_fingerprints(&path).unwrap();
+        db.add_untrusted_text("bob", "a quick brown fox jumps high");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results[0].owner_id1, "alice");
+        assert_eq!(results[0].matching_fragments.len(), 2);
+        assert!(results[0].matching_fragments[0].0.starts_with('#'));
+        assert_eq!(results[0].matching_fragments[0].1, "quick brown fox");
+        let mut lev = PlagiarismDatabase::new(3, 2, Metric::Lev, Vec::new());
+        lev.load_fingerprints(&path).unwrap();
+        lev.add_untrusted_text("bob", "a quick brown fox jumps high");
+        assert!(lev.check_trusted_plagiarism().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_threads_and_trusted_index() {
+        let trusted = "the quick brown fox jumps over the lazy dog";
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .threads(4)
+            .build();
+        db.add_trusted_text("source", trusted);
+        for i in 0..6 {
+            let text = format!("student {} wrote that the lazy dog sleeps", i);
+            db.add_untrusted_text(&format!("student{}", i), &text);
+        }
+        assert_eq!(db.check_trusted_plagiarism().len(), 6);
+
+        let path = std::env::temp_dir().join("plagiarismbasic_test_index.json");
+        db.save_trusted_index(&path).unwrap();
+        let mut query = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .ignored_texts(vec!["the lazy dog".to_string()])
+            .build();
+        query.load_trusted_index(&path).unwrap();
+        query.add_untrusted_text("copy", "a quick brown fox jumps");
+        query.add_untrusted_text("ignored", "see the lazy dog");
+        let results = query.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "copy");
+        let mut wrong_n = PlagiarismDatabase::new(4, 0, Metric::Equal, Vec::new());
+        let err = wrong_n.load_trusted_index(&path).unwrap_err();
+        assert!(matches!(err, Error::IncompatibleFragments(_)));
+
+        // Loaded texts follow the duplicate owner policy
+        let mut strict = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .duplicate_owners(DuplicateOwnerPolicy::Error)
+            .build();
+        strict.add_trusted_text("source", "a text that was there first");
+        assert!(matches!(
+            strict.load_trusted_index(&path),
+            Err(Error::DuplicateOwner { owner_id }) if owner_id == "source"
+        ));
+        assert_eq!(strict.get_clean_words("source", true)[0], "a");
+        let mut versioned = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .duplicate_owners(DuplicateOwnerPolicy::NewVersion)
+            .build();
+        versioned.add_trusted_text("source", "a text that was there first");
+        versioned.load_trusted_index(&path).unwrap();
+        assert_eq!(versioned.get_owners(true), vec!["source", "source#2"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_check_new_untrusted() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("t", "the quick brown fox jumps");
+        db.add_untrusted_text("old1", "a quick brown fox naps");
+        db.add_untrusted_text("old2", "we all saw a quick brown fox");
+        db.add_untrusted_text("new", "they saw a lazy dog and the quick brown fox");
+        let mut pairs: Vec<(TextOwnerID, TextOwnerID, bool)> = db
+            .check_new_untrusted(&["new".to_string(), "new".to_string()])
+            .unwrap()
+            .into_iter()
+            .map(|result| (result.owner_id1, result.owner_id2, result.trusted_owner1))
+            .collect();
+        pairs.sort();
+        // old1 and old2 also match each other, but that pair isn't new
+        assert_eq!(
+            pairs,
+            vec![
+                ("new".to_string(), "old1".to_string(), false),
+                ("new".to_string(), "old2".to_string(), false),
+                ("t".to_string(), "new".to_string(), true),
+            ]
+        );
+        assert!(matches!(
+            db.check_new_untrusted(&["missing".to_string()]),
+            Err(Error::UnknownOwner { owner_id }) if owner_id == "missing"
+        ));
+    }
+
+    #[test]
+    fn test_fallible_ingestion() {
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .duplicate_owners(DuplicateOwnerPolicy::Error)
+            .build();
+        db.try_add_trusted_text("t", "the quick brown fox jumps")
+            .unwrap();
+        assert!(matches!(
+            db.try_add_trusted_text("t", "another text entirely"),
+            Err(Error::DuplicateOwner { .. })
+        ));
+        assert!(matches!(
+            db.try_add_untrusted_text("empty", "  ... !"),
+            Err(Error::EmptyText { .. })
+        ));
+        // The same owner may have a trusted and an untrusted text
+        db.try_add_untrusted_text("t", "a quick brown fox naps")
+            .unwrap();
+        assert!(db.get_owners(false).len() == 1);
+        // A batch with one duplicate owner adds none of its texts
+        let batch = vec![
+            ("new".to_string(), "a new trusted text".to_string()),
+            ("t".to_string(), "the quick brown fox again".to_string()),
+        ];
+        assert!(matches!(
+            db.try_add_trusted_texts_par(batch),
+            Err(Error::DuplicateOwner { .. })
+        ));
+        assert_eq!(db.get_owners(true), vec!["t"]);
+
+        // Empty files don't stop the other files of a folder
+        let path = std::env::temp_dir().join("plagiarismbasic_test_empty.txt");
+        std::fs::write(&path, "  ... !").unwrap();
+        db.add_untrusted_files_par(&[&path]).unwrap();
+        assert!(db
+            .get_clean_words("plagiarismbasic_test_empty.txt", false)
+            .is_empty());
+        std::fs::remove_file(&path).unwrap();
+
+        let path = std::env::temp_dir().join("plagiarismbasic_test_latin1.txt");
+        std::fs::write(&path, b"caf\xe9 au lait").unwrap();
+        assert!(matches!(
+            db.add_untrusted_file(&path),
+            Err(Error::InvalidUtf8 { .. })
+        ));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            PlagiarismDatabase::builder(3, 0, Metric::Equal)
+                .exclude_patterns(vec!["(unclosed".to_string()])
+                .try_build(),
+            Err(Error::InvalidPattern { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "zip")]
+    fn test_add_untrusted_zip() {
+        use std::io::{Cursor, Write};
+        use zip::write::{SimpleFileOptions, ZipWriter};
+
+        let zip = |files: &[(&str, &[u8])]| {
+            let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+            for (name, contents) in files {
+                writer
+                    .start_file(*name, SimpleFileOptions::default())
+                    .unwrap();
+                writer.write_all(contents).unwrap();
+            }
+            writer.finish().unwrap().into_inner()
+        };
+        let nested = zip(&[("draft/essay.txt", b"the powerhouse of the cell")]);
+        let export = zip(&[
+            ("alice/part1.txt", b"we know the powerhouse"),
+            ("alice/part2.md", b"of the *cell* well"),
+            ("alice/notes.bin", b"not text"),
+            ("__MACOSX/alice/._part1.txt", b"metadata"),
+            ("bob.txt", b"nothing in common"),
+            ("carol.zip", &nested),
+        ]);
+        let path = std::env::temp_dir().join("plagiarismbasic_test_export.zip");
+        std::fs::write(&path, export).unwrap();
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .detect_markup(true)
+            .build();
+        db.add_untrusted_zip(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(db.get_owners(false), vec!["alice", "bob.txt", "carol"]);
+        assert_eq!(
+            db.get_clean_words("alice", false).join(" "),
+            "we know the powerhouse of the cell well"
+        );
+        db.add_trusted_text("book", "the powerhouse of the cell");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_owner_versions_and_namespaces() {
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .duplicate_owners(DuplicateOwnerPolicy::NewVersion)
+            .build();
+        db.add_trusted_text("book", "the quick brown fox jumps");
+        db.add_untrusted_text("cs101/hw1/alice", "a quick brown fox naps");
+        db.add_untrusted_text("cs101/hw1/alice", "the quick brown fox jumps again");
+        db.add_untrusted_texts_par(vec![
+            ("cs101/hw1/alice".to_string(), "quick brown fox".to_string()),
+            (
+                "cs101/hw12/bob".to_string(),
+                "one quick brown fox".to_string(),
+            ),
+        ]);
+        assert_eq!(
+            db.get_owners_in_namespace("cs101/hw1/", false),
+            vec!["cs101/hw1/alice", "cs101/hw1/alice#2", "cs101/hw1/alice#3"]
+        );
+        assert!(in_namespace("cs101/hw12/bob", "cs101"));
+        assert!(!in_namespace("cs101/hw12/bob", "cs101/hw1"));
+
+        let trusted = db.check_trusted_plagiarism_in_namespace("cs101/hw1");
+        assert_eq!(trusted.len(), 3);
+        assert!(trusted
+            .iter()
+            .all(|result| result.in_namespace("cs101/hw1")));
+        let untrusted: Vec<PlagiarismResult> = db
+            .check_untrusted_plagiarism_in_namespace("cs101/hw1")
+            .collect();
+        assert_eq!(untrusted.len(), 3);
+        assert_eq!(db.check_untrusted_plagiarism().len(), 6);
+    }
+
+    #[test]
+    fn test_group_comparisons() {
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .group_comparisons(GroupComparisons::AcrossGroups)
+            .build();
+        db.add_untrusted_text("alice1", "the quick brown fox jumps");
+        db.add_untrusted_text("alice2", "the quick brown fox naps");
+        db.add_untrusted_text("bob", "a quick brown fox");
+        db.add_untrusted_text("carol", "my quick brown fox");
+        db.set_group("alice1", false, "alice").unwrap();
+        db.set_group("alice2", false, "alice").unwrap();
+        db.set_group("bob", false, "bob").unwrap();
+        assert!(db.set_group("dave", false, "dave").is_err());
+        assert_eq!(db.get_group("bob", false), Some("bob"));
+        let pairs = |results: Vec<PlagiarismResult>| -> Vec<(String, String)> {
+            results
+                .into_iter()
+                .map(|result| (result.owner_id1, result.owner_id2))
+                .collect()
+        };
+        // carol has no group, so she is compared with everyone
+        assert_eq!(pairs(db.check_untrusted_plagiarism()).len(), 5);
+
+        db.group_comparisons = GroupComparisons::WithinGroups;
+        assert_eq!(
+            pairs(db.check_untrusted_plagiarism()),
+            vec![
+                ("alice1".to_string(), "alice2".to_string()),
+                ("alice1".to_string(), "carol".to_string()),
+                ("alice2".to_string(), "carol".to_string()),
+                ("bob".to_string(), "carol".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alignments() {
+        let mut db = PlagiarismDatabase::builder(3, 3, Metric::Lev)
+            .alignments(true)
+            .build();
+        db.add_trusted_text("t", "so the big red dog ran home");
+        db.add_untrusted_text("u", "the big red cat ran home now");
+        let results = db.check_trusted_plagiarism();
+        let alignment = &results[0].alignments[0];
+        assert_eq!((alignment.span1, alignment.span2), ((1, 6), (0, 5)));
+        assert!(alignment.words.contains(&AlignedWord {
+            op: EditOp::Substitute,
+            word1: Some(4),
+            word2: Some(3),
+        }));
+        assert_eq!(
+            alignment
+                .words
+                .iter()
+                .filter(|aligned| aligned.op == EditOp::Keep)
+                .count(),
+            5
+        );
+
+        let mut equal = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .alignments(true)
+            .build();
+        equal.add_trusted_text("t", "so the big red dog ran home");
+        equal.add_untrusted_text("u", "the big red cat ran home now");
+        assert!(equal.check_trusted_plagiarism()[0].alignments.is_empty());
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let pipeline = PipelineConfig {
+            prefilter: Metric::Equal,
+            cutoff: 0,
+            min_matches: 2,
+        };
+        let mut db = PlagiarismDatabase::builder(3, 2, Metric::Lev)
+            .pipeline(pipeline.clone())
+            .build();
+        db.add_trusted_text("t", "the quick brown fox jumps over the lazy dog");
+        // Shares two trigrams exactly, so it is compared with Lev
+        db.add_untrusted_text("copy", "the quick brown fox jumps ovr the lazy dgo");
+        // Only close with Lev, no exact match
+        db.add_untrusted_text("close", "teh quick brwn fox jumsp");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "copy");
+        assert!(results[0].matching_fragments.len() > 2);
+
+        assert!(matches!(
+            PlagiarismDatabase::builder(3, 2, Metric::Lev)
+                .pipeline(PipelineConfig {
+                    prefilter: Metric::Lev,
+                    ..pipeline
+                })
+                .try_build(),
+            Err(Error::InvalidPrefilter { .. })
+        ));
+    }
+
+    #[test]
+    fn test_allowed_pairs() {
+        for policy in [AllowedPairPolicy::Flag, AllowedPairPolicy::Suppress] {
+            let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+                .allowed_pairs(policy)
+                .build();
+            for owner in ["alice", "bob", "carol"] {
+                db.add_untrusted_text(owner, "our team built the water filter");
+            }
+            db.allow_group(&["bob", "alice"]);
+            assert!(db.is_allowed_pair("bob", "alice"));
+            assert!(!db.is_allowed_pair("alice", "carol"));
+            let results = db.check_untrusted_plagiarism();
+            let allowed: Vec<(&str, &str, bool)> = results
+                .iter()
+                .map(|r| (r.owner_id1.as_str(), r.owner_id2.as_str(), r.allowed))
+                .collect();
+            match policy {
+                AllowedPairPolicy::Flag => assert_eq!(
+                    allowed,
+                    vec![
+                        ("alice", "bob", true),
+                        ("alice", "carol", false),
+                        ("bob", "carol", false)
+                    ]
+                ),
+                AllowedPairPolicy::Suppress => assert_eq!(
+                    allowed,
+                    vec![("alice", "carol", false), ("bob", "carol", false)]
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_stride() {
+        let mut db = PlagiarismDatabase::builder(2, 0, Metric::Equal)
+            .stride(2)
+            .build();
+        db.add_trusted_text("source", "one two three four five six");
+        db.add_untrusted_text("aligned", "zero what one two three four");
+        db.add_untrusted_text("shifted", "what one two three four");
+        assert_eq!(db.trusted_texts["source"].fragment_locations.len(), 3);
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "aligned");
+        assert_eq!(results[0].stride, 2);
+        assert_eq!(results[0].matching_fragments.len(), 2);
+        assert_eq!(db.fragmentation().stride, 2);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut db = PlagiarismDatabase::new(2, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("prompt", "write about the cell");
+        db.add_untrusted_text("a", "the cell the cell divides");
+        db.add_untrusted_text("b", "i write about the cell");
+        let stats = db.stats();
+        let owners: Vec<&str> = stats.owners.iter().map(|o| o.owner_id.as_str()).collect();
+        assert_eq!(owners, vec!["prompt", "a", "b"]);
+        assert_eq!(stats.owners[1].words, 5);
+        assert_eq!(stats.owners[1].unique_ngrams, 3);
+        assert_eq!(stats.owners[1].duplicate_ngram_rate, 0.25);
+        assert_eq!(stats.owners[0].duplicate_ngram_rate, 0.0);
+        assert_eq!(
+            stats.common_ngrams[0],
+            NgramCount {
+                ngram: "the cell".to_string(),
+                owners: 3
+            }
+        );
+        assert_eq!(stats.common_ngrams.len(), 3);
+        let top = db.shared_ngrams(2);
+        assert_eq!(top[1].ngram, "about the");
+        assert_eq!(top.len(), 2);
+    }
+
+    #[test]
+    fn test_deterministic_order() {
+        let build = || {
+            let mut db = PlagiarismDatabase::new(2, 0, Metric::Equal, Vec::new());
+            for owner in ["s1", "s2", "s3"] {
+                db.add_trusted_text(owner, "one two three four five six");
+            }
+            for owner in ["u3", "u1", "u2", "u4"] {
+                db.add_untrusted_text(owner, "four five one two three");
+            }
+            db
+        };
+        let results = build().check_trusted_plagiarism();
+        let pairs: Vec<(&str, &str)> = results
+            .iter()
+            .map(|result| (result.owner_id1.as_str(), result.owner_id2.as_str()))
+            .collect();
+        let mut sorted = pairs.clone();
+        sorted.sort();
+        assert_eq!(pairs, sorted);
+        let fragments: Vec<&str> = results[0]
+            .matching_fragments
+            .iter()
+            .map(|(fragment, _)| fragment.as_str())
+            .collect();
+        assert_eq!(fragments, vec!["one two", "two three", "four five"]);
+        for _ in 0..5 {
+            let again = build().check_trusted_plagiarism();
+            assert_eq!(
+                serde_json::to_string(&again).unwrap(),
+                serde_json::to_string(&results).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_dedup_and_merge() {
+        let result = |owner1: &str, owner2: &str, fragments: Vec<(&str, &str)>| {
+            let locations = (0..fragments.len())
+                .map(|idx| (vec![(idx, idx + 1)], vec![(idx + 10, idx + 11)]))
+                .collect();
+            PlagiarismResult {
+                owner_id1: owner1.to_string(),
+                owner_id2: owner2.to_string(),
+                matching_fragments: fragments
+                    .into_iter()
+                    .map(|(f1, f2)| (f1.to_string(), f2.to_string()))
+                    .collect(),
+                matching_fragments_locations: locations,
+                n: 2,
+                stride: 1,
+                coverage_owner1: 0.5,
+                coverage_owner2: 0.25,
+                ..PlagiarismResult::default()
+            }
+        };
+        let results = dedup_results(vec![
+            result("b", "a", vec![("x y", "x z"), ("p q", "p r")]),
+            result("a", "c", vec![("m n", "m n")]),
+            result("a", "b", vec![("x z", "x y"), ("s t", "s u")]),
+        ]);
+        assert_eq!(results.len(), 2);
+        assert_eq!((&*results[0].owner_id1, &*results[0].owner_id2), ("a", "b"));
+        assert_eq!(
+            results[0].matching_fragments,
+            vec![
+                ("x z".to_string(), "x y".to_string()),
+                ("p r".to_string(), "p q".to_string()),
+                ("s t".to_string(), "s u".to_string()),
+            ]
+        );
+        // Both sides of the swapped result moved along with the owners
+        assert_eq!(
+            results[0].matching_fragments_locations[0],
+            (vec![(10, 11), (0, 1)], vec![(0, 1), (10, 11)])
+        );
+        assert_eq!(
+            (results[0].coverage_owner1, results[0].coverage_owner2),
+            (0.5, 0.5)
+        );
+        assert_eq!(results[1].owner_id2, "c");
+
+        let mut merged = results[0].clone();
+        assert!(matches!(
+            merged.merge(results[1].clone()),
+            Err(Error::IncompatibleFragments(_))
+        ));
+        assert_eq!(merged, results[0]);
+    }
+
+    #[test]
+    fn test_min_matches() {
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .min_matches(2)
+            .build();
+        db.add_trusted_text("t", "at the end of the day we all went home early");
+        db.add_untrusted_text("common", "look at the end");
+        db.add_untrusted_text("copy", "so we all went home early");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "copy");
+    }
+
+    #[test]
+    fn test_merge() {
+        let shard = |owner: &str, text: &str| {
+            let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+                .duplicate_owners(DuplicateOwnerPolicy::NewVersion)
+                .build();
+            db.add_trusted_text(owner, text);
+            db
+        };
+        let mut db = shard("book", "the mitochondria is the powerhouse of the cell");
+        db.add_untrusted_text(
+            "essay",
+            "we know the powerhouse of the cell and the big bang",
+        );
+        let mut other = shard("book", "the universe began with the big bang long ago");
+        // Boilerplate of the other shard also applies to the texts of this one
+        other.add_boilerplate_text("the powerhouse of the cell");
+        db.merge(other).unwrap();
+        assert_eq!(db.get_owners(true), vec!["book", "book#2"]);
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id1, "book#2");
+        assert_eq!(
+            results[0].matching_fragments,
+            vec![("the big bang".to_string(), "the big bang".to_string())]
+        );
+
+        let wrong_n = PlagiarismDatabase::new(4, 0, Metric::Equal, Vec::new());
+        assert!(db.merge(wrong_n).is_err());
+        let mut strict = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .duplicate_owners(DuplicateOwnerPolicy::Error)
+            .build();
+        strict.add_trusted_text("book", "anything at all");
+        let collision = shard("book", "something else entirely");
+        assert!(matches!(
+            strict.merge(collision),
+            Err(Error::DuplicateOwner { .. })
+        ));
+    }
+
+    #[test]
+    fn test_get_context() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("t", "one two three four five six seven");
+        assert_eq!(db.get_span_text("t", (2, 4)).unwrap(), "three four five");
+        let context = db.get_context("t", (1, 2), 2).unwrap();
+        assert_eq!(context.before, "one");
+        assert_eq!(context.text, "two three");
+        assert_eq!(context.after, "four five");
+        assert_eq!(db.get_context("t", (5, 6), 3).unwrap().after, "");
+        assert!(db.get_span_text("t", (6, 7)).is_none());
+        assert!(db.get_span_text("missing", (0, 0)).is_none());
+    }
+
+    #[test]
+    fn test_snippets() {
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .snippets(2)
+            .build();
+        db.add_trusted_text("book", "the mitochondria is the powerhouse of the cell");
+        db.add_untrusted_text("essay", "we all know the powerhouse of everything");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results[0].matching_fragments.len(), 1);
+        let (snippet1, snippet2) = &results[0].snippets[0];
+        assert_eq!(snippet1.before, "mitochondria is");
+        assert_eq!(snippet1.text, "the powerhouse of");
+        assert_eq!(snippet1.after, "the cell");
+        assert_eq!(snippet2.before, "all know");
+        assert_eq!(snippet2.after, "everything");
+        let json = serde_json::to_string(&results[0]).unwrap();
+        assert!(json.contains("\"before\":\"all know\""));
+
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("book", "the mitochondria is the powerhouse of the cell");
+        db.add_untrusted_text("essay", "we all know the powerhouse of everything");
+        assert!(db.check_trusted_plagiarism()[0].snippets.is_empty());
+    }
+
+    #[test]
+    fn test_within_sentences() {
+        let text = "The dog barked. The quick fox ran";
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .within_sentences(true)
+            .build();
+        db.add_untrusted_text("a", text);
+        db.add_untrusted_text("b", "Wow. the dog barked the quick fox ran");
+        let results = db.check_untrusted_plagiarism();
+        assert_eq!(
+            results[0].matching_fragments,
+            vec![
+                ("the dog barked".to_string(), "the dog barked".to_string()),
+                ("the quick fox".to_string(), "the quick fox".to_string()),
+                ("quick fox ran".to_string(), "quick fox ran".to_string()),
+            ]
+        );
+        assert!(db.fragmentation().within_sentences);
+    }
+
+    #[test]
+    fn test_extra_metrics() {
+        let mut db = PlagiarismDatabase::builder(3, 1, Metric::Lev)
+            .extra_metrics(vec![Metric::Equal])
+            .build();
+        db.add_trusted_text("t", "the cat sat on the mat");
+        db.add_untrusted_text("u", "the cat sat on the hat");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].matching_fragments.len(), 4);
+        let equal = &results[0].metric_results;
+        assert_eq!(equal.len(), 1);
+        assert_eq!(equal[0].metric, Metric::Equal);
+        assert_eq!(equal[0].matching_fragments_locations.len(), 3);
+        assert_eq!(equal[0].coverage_owner2, 0.75);
+    }
+
+    #[test]
+    fn test_check_options() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("t", "at the end of the day we all went home early");
+        db.add_untrusted_text("a", "at the end of the day");
+        db.add_untrusted_text("b", "so at the end of many long weeks we all went home");
+        db.add_untrusted_text("c", "we all went there and back");
+        let all = db.check_trusted_plagiarism_with(&CheckOptions::default());
+        let owners: Vec<&str> = all.iter().map(|r| r.owner_id2.as_str()).collect();
+        assert_eq!(owners, vec!["a", "b", "c"]);
+
+        let options = CheckOptions {
+            max_results: Some(1),
+            sort: ResultSort::Score,
+            ..CheckOptions::default()
+        };
+        let top = db.check_trusted_plagiarism_with(&options);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].owner_id2, "a");
+        assert_eq!(top[0].score(), 1.0);
+
+        let options = CheckOptions {
+            min_score: 0.4,
+            ..CheckOptions::default()
+        };
+        let owners: Vec<String> = db
+            .check_trusted_plagiarism_with(&options)
+            .into_iter()
+            .map(|r| r.owner_id2)
+            .collect();
+        assert_eq!(owners, vec!["a", "b"]);
+        assert!(db
+            .check_untrusted_plagiarism_with(&CheckOptions {
+                max_results: Some(0),
+                ..CheckOptions::default()
+            })
+            .is_empty());
+    }
+
+    #[test]
+    fn test_min_density() {
+        let build = |min_density: f32| {
+            let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+                .min_density(min_density)
+                .build();
+            db.add_trusted_text(
+                "t",
+                "at the end of the day we all went home early to rest for a while",
+            );
+            db.add_untrusted_text("scattered", "at the end we said so we all went");
+            db.add_untrusted_text("copy", "so the day we all went home early they said");
+            db.check_trusted_plagiarism()
+        };
+        let results = build(0.0);
+        assert_eq!(results.len(), 2);
+        let copy = results.iter().find(|r| r.owner_id2 == "copy").unwrap();
+        assert_eq!(copy.density_owner2, 1.0);
+        let scattered = results.iter().find(|r| r.owner_id2 == "scattered").unwrap();
+        assert_eq!(scattered.density_owner2, 0.0);
+        let results = build(0.5);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "copy");
+    }
+
+    #[test]
+    fn test_check_with_budget() {
+        let mut db = PlagiarismDatabase::new(3, 1, Metric::Lev, Vec::new());
+        db.add_trusted_text(
+            "textbook",
+            "a hash table maps keys to buckets with a hash function",
+        );
+        // Two students who swapped essays, one who read the textbook
+        let essay = "collisions in a hash table are resolved by chaining the entries of a bucket";
+        db.add_untrusted_text("ann", essay);
+        db.add_untrusted_text("ben", essay);
+        db.add_untrusted_text("cat", "a hash table maps keys to values");
+        db.add_untrusted_text("dan", "nothing in common with anything");
+
+        let check = db.check_with_budget(Duration::from_secs(60));
+        assert!(check.complete);
+        // 4 trusted and 6 untrusted pairs
+        assert_eq!((check.compared, check.total), (10, 10));
+        let mut expected = db.check_trusted_plagiarism();
+        expected.extend(db.check_untrusted_plagiarism());
+        let mut expected = dedup_results(expected);
+        sort_results(&mut expected);
+        assert_eq!(check.results, expected);
+        // The most similar pair comes first, though it is untrusted
+        assert_eq!(
+            (&*check.results[0].owner_id1, &*check.results[0].owner_id2),
+            ("ann", "ben")
+        );
+
+        let check = db.check_with_budget(Duration::ZERO);
+        assert!(!check.complete);
+        assert_eq!(check.compared, 0);
+        assert!(check.results.is_empty());
+    }
+
+    #[test]
+    fn test_idf_weighting() {
+        let build = |min_weighted_score: f32| {
+            let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+                .idf_weighting(true)
+                .min_weighted_score(min_weighted_score)
+                .build();
+            db.add_untrusted_text("alice", "to sum up quantum flux hums");
+            db.add_untrusted_text("bob", "to sum up bananas are yellow");
+            db.add_untrusted_text("carol", "to sum up roads need bikes");
+            db.add_untrusted_text("dave", "so loud quantum flux hums here");
+            db.add_untrusted_text("erin", "to sum up cats sleep often");
+            db.check_untrusted_plagiarism()
+        };
+        let results = build(0.0);
+        let pair = |owner: &str| {
+            results
+                .iter()
+                .find(|r| {
+                    [&r.owner_id1, &r.owner_id2].contains(&&"alice".to_string())
+                        && [&r.owner_id1, &r.owner_id2].contains(&&owner.to_string())
+                })
+                .unwrap()
+        };
+        // Both share one of four trigrams with alice, but few texts say "quantum flux hums"
+        let (common, rare) = (pair("bob"), pair("dave"));
+        assert_eq!(common.score(), rare.score());
+        assert!(common.weighted_score() < common.score());
+        assert!(rare.weighted_score() > common.weighted_score());
+
+        let threshold = (common.weighted_score() + rare.weighted_score()) / 2.0;
+        let results = build(threshold);
+        assert_eq!(results.len(), 1);
+        assert!([&results[0].owner_id1, &results[0].owner_id2].contains(&&"dave".to_string()));
+    }
+
+    #[test]
+    fn test_language_detection() {
+        let english = "the committee approved the new budget for the city library last week";
+        let stemmed = "the committees approving the new budgets for the city libraries";
+        // Shares "new budget for" with the English text
+        let spanish = "Según el documento, el comité aprobó el nuevo presupuesto para la \
+            biblioteca de la ciudad, que incluye más libros para los niños: el new budget for";
+        let build = |cross_language: bool| {
+            let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+                .detect_language(true)
+                .stem(true)
+                .language_stopwords("eng", vec!["the".to_string()])
+                .cross_language(cross_language)
+                .build();
+            db.add_trusted_text("t", english);
+            db.add_untrusted_text("en", stemmed);
+            db.add_untrusted_text("es", spanish);
+            db
+        };
+        let db = build(false);
+        assert_eq!(db.get_language("en"), Some("eng"));
+        assert_eq!(db.get_language("es"), Some("spa"));
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "en");
+        assert!(results[0].matching_fragments.contains(&(
+            "committe approv new".to_string(),
+            "committe approv new".to_string()
+        )));
+        assert_eq!(build(true).check_trusted_plagiarism().len(), 2);
+    }
+
+    #[test]
+    fn test_trusted_corpus_overlap() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("wiki", "a fruit is the seed bearing structure of a plant");
+        db.add_trusted_text(
+            "book",
+            "the fruit is the seed bearing part of flowering plants",
+        );
+        db.add_trusted_text("notes", "vegetables are other edible parts of plants");
+        db.add_untrusted_text("u", "a fruit is the seed bearing structure");
+        let results = db.check_trusted_corpus_overlap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            (&*results[0].owner_id1, &*results[0].owner_id2),
+            ("book", "wiki")
+        );
+        assert!(results[0].trusted_owner1 && results[0].trusted_owner2);
+        let mut shared: Vec<&str> = results[0]
+            .matching_fragments
+            .iter()
+            .map(|(fragment, _)| fragment.as_str())
+            .collect();
+        shared.sort();
+        assert_eq!(
+            shared,
+            vec!["fruit is the", "is the seed", "the seed bearing"]
+        );
+    }
+
+    #[test]
+    fn test_simhash_matches() {
+        let mut db = PlagiarismDatabase::new(8, 10, Metric::SimHash, Vec::new());
+        db.add_trusted_text(
+            "source",
+            "the inheritance concept was invented in 1967 for simula",
+        );
+        db.add_untrusted_text(
+            "copy",
+            "the inheritance concept was invented in 1967 for simulas",
+        );
+        db.add_untrusted_text(
+            "other",
+            "a fruit is a generalization of apple orange mango and many others",
+        );
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "copy");
+        assert!(!results[0].equal_fragments);
+    }
+
+    #[test]
+    fn test_embedding_metric() {
+        let word_vectors = WordVectors::from_reader(
+            "big 1 0 0\nlarge 0.95 0.05 0\nhouse 0 1 0\nhome 0.05 0.95 0\nred 0 0 1\n".as_bytes(),
+        )
+        .unwrap();
+        let mut db = PlagiarismDatabase::builder(2, 5, Metric::Embedding)
+            .word_vectors(word_vectors)
+            .build();
+        db.add_trusted_text("t", "big house");
+        db.add_untrusted_text("paraphrase", "large home");
+        db.add_untrusted_text("other", "red house");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "paraphrase");
+        assert_eq!(
+            results[0].matching_fragments,
+            vec![("big house".to_string(), "large home".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_check_untrusted_plagiarism_iter() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_untrusted_text("c", "the quick brown fox jumps");
+        db.add_untrusted_text("a", "a quick brown fox sleeps");
+        db.add_untrusted_text("b", "nothing in common at all");
+        db.add_untrusted_text("d", "sleeps all day long");
+        let mut results = db.check_untrusted_plagiarism_iter();
+        let first = results.next().unwrap();
+        assert_eq!(
+            (first.owner_id1.as_str(), first.owner_id2.as_str()),
+            ("a", "c")
+        );
+        assert_eq!(first.matching_fragments.len(), 1);
+        assert!(results.next().is_none());
+        assert_eq!(db.check_untrusted_plagiarism().len(), 1);
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("t", "the quick brown fox jumps");
+        let metadata: HashMap<String, String> = vec![("student", "Ada"), ("course", "CS101")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        db.add_untrusted_text_with_meta("u", "a quick brown fox sleeps", metadata.clone());
+        let results = db.check_trusted_plagiarism();
+        assert!(results[0].metadata1.is_empty());
+        assert_eq!(results[0].metadata2, metadata);
+        assert_eq!(db.get_metadata("u", false), Some(&metadata));
+    }
+
+    #[test]
+    fn test_rank_sources() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("wiki", "the mitochondria is the powerhouse of the cell");
+        db.add_trusted_text("book", "cells need energy from the powerhouse of the cell");
+        db.add_trusted_text("blog", "the cell and the mitochondria");
+        db.add_trusted_text("news", "stocks went up today");
+        db.add_untrusted_text("u", "the mitochondria is the powerhouse of the cell indeed");
+        let ranked = db.rank_sources("u", 2);
+        let owners: Vec<&str> = ranked
+            .iter()
+            .map(|source| source.owner_id.as_str())
+            .collect();
+        assert_eq!(owners, vec!["wiki", "book"]);
+        assert!((ranked[0].score - 8.0 / 9.0).abs() < 1e-6);
+        assert_eq!(ranked[1].matching_fragments, 3);
+        assert_eq!(db.rank_sources("u", 10).len(), 2);
+        assert!(db.rank_sources("missing", 3).is_empty());
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .exclude_patterns(vec![r"(?ms)^References$.*".to_string()])
+            .build();
+        let essay = "My own words here.\nReferences\nSmith J. The Water Cycle. 2020.";
+        db.add_trusted_text("t", "Smith J. The Water Cycle. 2020.");
+        db.add_untrusted_text("u", essay);
+        assert!(db.check_trusted_plagiarism().is_empty());
+        assert_eq!(db.get_stripped_ranges("u", false), &[(19, essay.len())]);
+    }
+
+    #[test]
+    fn test_multiple_ngram_sizes() {
+        let mut db = PlagiarismDatabase::builder(2, 0, Metric::Equal)
+            .ngram_sizes(vec![4, 2])
+            .build();
+        db.add_trusted_text("t", "the quick brown fox jumps over the lazy dog");
+        db.add_untrusted_text("u", "a quick brown fox jumps and the lazy dog sleeps");
+        let results = db.check_trusted_plagiarism();
+        let mut sizes: Vec<(usize, usize)> = results
+            .iter()
+            .map(|result| (result.n, result.matching_fragments.len()))
+            .collect();
+        sizes.sort_unstable();
+        // "quick brown fox jumps" is the only 4-gram in both texts
+        assert_eq!(sizes, vec![(2, 5), (4, 1)]);
+        assert_eq!(
+            results
+                .iter()
+                .find(|result| result.n == 4)
+                .unwrap()
+                .matching_fragments[0]
+                .0,
+            "quick brown fox jumps"
+        );
+    }
+
+    #[test]
+    fn test_coverage() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text(
+            "book",
+            "it was the best of times it was the worst of times it was the age of wisdom",
+        );
+        db.add_untrusted_text("essay", "the best of times");
+        let results = db.check_trusted_plagiarism();
+        // 2 of the essay's 2 trigrams, 2 of the book's 12 unique trigrams
+        assert_eq!(results[0].coverage_owner2, 1.0);
+        assert_eq!(results[0].coverage_owner1, 2.0 / 12.0);
+    }
+
+    #[test]
+    fn test_lcs_metric() {
+        let mut db = PlagiarismDatabase::new(5, 1, Metric::Lcs, Vec::new());
+        db.add_trusted_text(
+            "source",
+            "The mitochondria is the powerhouse of the cell. Plants also have chloroplasts.",
+        );
+        db.add_untrusted_text(
+            "essay",
+            "Mitochondria are the powerhouse of every cell. I like trains. Plants have chloroplasts.",
+        );
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].matching_fragments,
+            vec![(
+                "mitochondria is the powerhouse of the cell".to_string(),
+                "mitochondria are the powerhouse of every cell".to_string()
+            )]
+        );
+        assert_eq!(
+            results[0].matching_fragments_locations[0],
+            (vec![(1, 7)], vec![(0, 6)])
+        );
+        assert!(PlagiarismDatabase::new(8, 1, Metric::Lcs, Vec::new())
+            .check_trusted_plagiarism()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_skipgrams() {
+        let source = "the mitochondria is the powerhouse of the cell";
+        let copy = "the mitochondria is truly the powerhouse of the cell";
+        let mut db = PlagiarismDatabase::new(4, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("source", source);
+        db.add_untrusted_text("copy", copy);
+        assert_eq!(db.check_trusted_plagiarism()[0].matching_fragments.len(), 2);
+
+        let mut db = PlagiarismDatabase::builder(4, 0, Metric::Equal)
+            .skip(1)
+            .build();
+        db.add_trusted_text("source", source);
+        db.add_untrusted_text("copy", copy);
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results[0].n, 4);
+        let (locations1, locations2) = results[0]
+            .matching_fragments
+            .iter()
+            .zip(&results[0].matching_fragments_locations)
+            .find(|((fragment, _), _)| fragment == "mitochondria is the powerhouse")
+            .map(|(_, locations)| locations.clone())
+            .unwrap();
+        assert_eq!(locations1, vec![(1, 4)]);
+        assert_eq!(locations2, vec![(1, 5)]);
+        assert!(results[0]
+            .matching_fragments
+            .iter()
+            .all(|(fragment, _)| !fragment.contains("truly")));
+    }
+
+    #[test]
+    fn test_hashed_fragments() {
+        // Collisions are checked unless turned off
+        for check_collisions in [None, Some(false)] {
+            let mut builder = PlagiarismDatabase::builder(3, 0, Metric::Equal);
+            if let Some(check_collisions) = check_collisions {
+                builder = builder.check_collisions(check_collisions);
+            }
+            let mut db = builder.build();
+            db.add_trusted_text("t", "The cat sat on the mat");
+            db.add_untrusted_text("u", "a dog barked");
+            let trusted_hash = hash_fragment("the cat sat");
+            assert_eq!(
+                db.trusted_texts["t"].fragment_text(trusted_hash),
+                "the cat sat"
+            );
+            // Fake a hash collision between "a dog barked" and "the cat sat"
+            let entry = db.untrusted_texts.get_mut("u").unwrap();
+            let locations = entry
+                .fragment_locations
+                .remove(&hash_fragment("a dog barked"))
+                .unwrap();
+            entry.fragment_locations.insert(trusted_hash, locations);
+            entry.bucket_fragments();
+            let results = db.check_trusted_plagiarism();
+            assert_eq!(results.is_empty(), check_collisions.is_none());
+        }
+    }
+
+    #[test]
+    fn test_sentence_mode() {
+        let mut db = PlagiarismDatabase::builder(3, 3, Metric::Lev)
+            .fragment_mode(FragmentMode::Sentence)
+            .build();
+        db.add_trusted_text(
+            "source",
+            "Dr. Jones found the cure in 1990. It worked. The rest is history.",
+        );
+        db.add_untrusted_text(
+            "student",
+            "Everyone knows this. Dr. Jones found a cure in 1990! It worked.",
+        );
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].matching_fragments,
+            vec![(
+                "dr jones found the cure in 1990".to_string(),
+                "dr jones found a cure in 1990".to_string()
+            )]
+        );
+        assert_eq!(
+            results[0].matching_fragments_locations,
+            vec![(vec![(0, 6)], vec![(3, 9)])]
+        );
+        // Byte ranges cover the original sentences, without the terminators
+        assert_eq!(
+            results[0].char_locations,
+            vec![(vec![(0, 32)], vec![(21, 51)])]
+        );
+    }
+
+    #[test]
+    fn test_source_attribution() {
+        let mut db = PlagiarismDatabase::new(2, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("t1", "alpha beta gamma delta");
+        db.add_trusted_text("t2", "gamma delta epsilon zeta");
+        db.add_untrusted_text("u", "alpha beta gamma delta epsilon zeta omega");
+        let summaries = db.summarize_by_owner(&db.check_trusted_plagiarism());
+        // Both sources match 4 words, the 2 they share count for t1 only
+        let attribution: Vec<(&str, usize)> = summaries[0]
+            .attribution
+            .iter()
+            .map(|share| (share.source.as_str(), share.words))
+            .collect();
+        assert_eq!(attribution, vec![("t1", 4), ("t2", 2)]);
+        let total: f32 = summaries[0]
+            .attribution
+            .iter()
+            .map(|share| share.percent)
+            .sum();
+        assert!((total - 100.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_summarize_by_owner() {
+        let mut db = PlagiarismDatabase::new(2, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("t1", "one two three four");
+        db.add_trusted_text("t2", "five six seven eight");
+        db.add_untrusted_text("u", "one two three five six nine ten eleven");
+        let results = db.check_trusted_plagiarism();
+        let summaries = db.summarize_by_owner(&results);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].owner_id, "u");
+        assert_eq!(summaries[0].matched_fragments, 3);
+        assert!((summaries[0].matched_percent - 62.5).abs() < 0.001);
+        assert_eq!(
+            summaries[0].counterparts,
+            vec![("t1".to_string(), 2), ("t2".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_compare_texts() {
+        let book = "the mitochondria is the powerhouse of the cell";
+        let essay = "is the powerhouse of the cell";
+        let options = || PlagiarismDatabase::builder(3, 0, Metric::Equal);
+        let result = compare_texts(book, essay, options());
+        assert_eq!(
+            (result.owner_id1.as_str(), result.owner_id2.as_str()),
+            ("a", "b")
+        );
+        assert_eq!(result.matching_fragments.len(), 4);
+        // 4 of the 6 ngrams of the book, all of the essay
+        assert_eq!(
+            (result.coverage_owner1, result.coverage_owner2),
+            (4.0 / 6.0, 1.0)
+        );
+        let reversed = compare_texts(essay, book, options());
+        assert_eq!(
+            (reversed.coverage_owner1, reversed.coverage_owner2),
+            (1.0, 4.0 / 6.0)
+        );
+        assert_eq!(reversed.matching_fragments.len(), 4);
+
+        let unrelated = compare_texts(book, "a text about something else", options());
+        assert!(unrelated.matching_fragments.is_empty());
+        assert_eq!((unrelated.n, unrelated.score()), (3, 0.0));
     }
 }
@@ -0,0 +1,173 @@
+use crate::error::Result;
+use crate::plagiarism_database::TextOwnerID;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// How the pseudonyms of `PlagiarismDatabase::pseudonymize_owners` look
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PseudonymScheme {
+    /// Codes like `S001`, numbered in keyed hash order (so not alphabetically)
+    #[default]
+    Sequential,
+    /// `anon-` and the start of the keyed hash of the owner ID, the same for
+    ///     an owner in every run with the same key
+    Hash,
+}
+
+/// `[pseudonyms]` table of a config file, or the `--pseudonymize` options:
+///     how untrusted owners are renamed and where the mapping is written
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PseudonymConfig {
+    pub scheme: PseudonymScheme,
+    /// Secret key of the hashes. Without it, the pseudonyms of known owner
+    ///     IDs cannot be recomputed.
+    pub key: String,
+    /// JSON file the mapping back to the real owner IDs is written to
+    pub mapping: String,
+}
+
+impl Default for PseudonymConfig {
+    fn default() -> PseudonymConfig {
+        PseudonymConfig {
+            scheme: PseudonymScheme::default(),
+            key: String::new(),
+            mapping: "pseudonyms.json".to_string(),
+        }
+    }
+}
+
+/// Real owner ID of every pseudonym, to unmask confirmed cases after a
+///     blind review. Keep it away from the reviewers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct OwnerMapping {
+    pub scheme: PseudonymScheme,
+    pub owners: BTreeMap<TextOwnerID, TextOwnerID>,
+}
+
+impl OwnerMapping {
+    /// Real owner ID of a pseudonym
+    pub fn unmask(&self, pseudonym: &str) -> Option<&str> {
+        self.owners.get(pseudonym).map(String::as_str)
+    }
+
+    /// Writes the mapping to a JSON file
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+
+    /// Reads a mapping written by `write`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<OwnerMapping> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+/// Hex HMAC-SHA256 of an owner ID
+fn keyed_hash(key: &str, owner_id: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(owner_id.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Pseudonym of every owner ID, unique among them
+pub(crate) fn make_pseudonyms(
+    scheme: PseudonymScheme,
+    key: &str,
+    owner_ids: &[&str],
+) -> BTreeMap<TextOwnerID, TextOwnerID> {
+    let mut hashed: Vec<(String, &str)> = owner_ids
+        .iter()
+        .map(|&owner| (keyed_hash(key, owner), owner))
+        .collect();
+    hashed.sort_unstable();
+    hashed.dedup();
+    match scheme {
+        PseudonymScheme::Sequential => {
+            let width = hashed.len().to_string().len().max(3);
+            hashed
+                .iter()
+                .enumerate()
+                .map(|(idx, (_, owner))| (owner.to_string(), format!("S{:0width$}", idx + 1)))
+                .collect()
+        }
+        PseudonymScheme::Hash => {
+            // The shortest prefix (of at least 8 digits) that is still unique
+            let mut len = 8;
+            while hashed
+                .windows(2)
+                .any(|pair| pair[0].0[..len] == pair[1].0[..len])
+            {
+                len += 1;
+            }
+            hashed
+                .iter()
+                .map(|(hash, owner)| (owner.to_string(), format!("anon-{}", &hash[..len])))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_pseudonymize_owners() {
+        // Submissions named after the students, who worked on it together
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text(
+            "course wiki",
+            "a linked list stores a pointer to the next node",
+        );
+        db.add_untrusted_text(
+            "jane.doe@uni.edu",
+            "each node of a linked list stores a pointer",
+        );
+        db.add_untrusted_text("Ono, Yoko", "it stores a pointer to the next node");
+        db.allow_pair("jane.doe@uni.edu", "Ono, Yoko");
+        let mapping = db.pseudonymize_owners(PseudonymScheme::Sequential, "secret");
+        let mut pseudonyms: Vec<&String> = mapping.owners.keys().collect();
+        pseudonyms.sort();
+        assert_eq!(pseudonyms, vec!["S001", "S002"]);
+
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 2);
+        let json = serde_json::to_string(&results).unwrap();
+        assert!(!json.contains("jane") && !json.contains("Yoko"));
+        let mut unmasked: Vec<&str> = results
+            .iter()
+            .map(|result| mapping.unmask(&result.owner_id2).unwrap())
+            .collect();
+        unmasked.sort();
+        assert_eq!(unmasked, vec!["Ono, Yoko", "jane.doe@uni.edu"]);
+        // Trusted sources keep their names, allowed pairs their pseudonyms
+        assert!(results
+            .iter()
+            .all(|result| result.owner_id1 == "course wiki"));
+        assert!(db.is_allowed_pair("S001", "S002"));
+
+        let hashed = make_pseudonyms(PseudonymScheme::Hash, "secret", &["alice", "bob"]);
+        assert_eq!(
+            hashed,
+            make_pseudonyms(PseudonymScheme::Hash, "secret", &["bob", "alice"])
+        );
+        assert_ne!(
+            hashed,
+            make_pseudonyms(PseudonymScheme::Hash, "other", &["alice", "bob"])
+        );
+        assert!(hashed["alice"].starts_with("anon-"));
+    }
+}
@@ -0,0 +1,177 @@
+use crate::plagiarism_database::{
+    sort_results, ByteRange, FragmentLocation, PlagiarismDatabase, PlagiarismResult,
+};
+use crate::{Error, Metric};
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use std::collections::HashMap;
+
+/// `Metric` as a Python enum
+#[pyclass(name = "Metric", eq, eq_int)]
+#[derive(Copy, Clone, PartialEq)]
+pub enum PyMetric {
+    Equal,
+    Lev,
+    SimHash,
+    Lcs,
+}
+
+impl From<PyMetric> for Metric {
+    fn from(metric: PyMetric) -> Metric {
+        match metric {
+            PyMetric::Equal => Metric::Equal,
+            PyMetric::Lev => Metric::Lev,
+            PyMetric::SimHash => Metric::SimHash,
+            PyMetric::Lcs => Metric::Lcs,
+        }
+    }
+}
+
+/// I/O errors become OSError, everything else ValueError
+impl From<Error> for PyErr {
+    fn from(err: Error) -> PyErr {
+        match err {
+            Error::Io(err) => err.into(),
+            err @ Error::InvalidUtf8 { .. } => PyOSError::new_err(err.to_string()),
+            err => PyValueError::new_err(err.to_string()),
+        }
+    }
+}
+
+/// `PlagiarismResult` as a read-only Python class
+#[pyclass(name = "PlagiarismResult", get_all, frozen)]
+pub struct PyPlagiarismResult {
+    owner_id1: String,
+    owner_id2: String,
+    matching_fragments: Vec<(String, String)>,
+    matching_fragments_locations: Vec<(Vec<FragmentLocation>, Vec<FragmentLocation>)>,
+    char_locations: Vec<(Vec<ByteRange>, Vec<ByteRange>)>,
+    trusted_owner1: bool,
+    trusted_owner2: bool,
+    equal_fragments: bool,
+    n: usize,
+    metadata1: HashMap<String, String>,
+    metadata2: HashMap<String, String>,
+    coverage_owner1: f32,
+    coverage_owner2: f32,
+}
+
+impl From<PlagiarismResult> for PyPlagiarismResult {
+    fn from(result: PlagiarismResult) -> PyPlagiarismResult {
+        PyPlagiarismResult {
+            owner_id1: result.owner_id1,
+            owner_id2: result.owner_id2,
+            matching_fragments: result.matching_fragments,
+            matching_fragments_locations: result.matching_fragments_locations,
+            char_locations: result.char_locations,
+            trusted_owner1: result.trusted_owner1,
+            trusted_owner2: result.trusted_owner2,
+            equal_fragments: result.equal_fragments,
+            n: result.n,
+            metadata1: result.metadata1,
+            metadata2: result.metadata2,
+            coverage_owner1: result.coverage_owner1,
+            coverage_owner2: result.coverage_owner2,
+        }
+    }
+}
+
+#[pymethods]
+impl PyPlagiarismResult {
+    fn __repr__(&self) -> String {
+        format!(
+            "PlagiarismResult({:?}, {:?}, {} matching fragments)",
+            self.owner_id1,
+            self.owner_id2,
+            self.matching_fragments.len()
+        )
+    }
+}
+
+/// `PlagiarismDatabase` as a Python class
+#[pyclass(name = "PlagiarismDatabase")]
+pub struct PyPlagiarismDatabase {
+    db: PlagiarismDatabase,
+}
+
+#[pymethods]
+impl PyPlagiarismDatabase {
+    #[new]
+    #[pyo3(signature = (n, s, metric, ignored_texts = Vec::new()))]
+    fn new(n: usize, s: usize, metric: PyMetric, ignored_texts: Vec<String>) -> Self {
+        PyPlagiarismDatabase {
+            db: PlagiarismDatabase::new(n, s, metric.into(), ignored_texts),
+        }
+    }
+
+    /// Raises ValueError if the text has no words
+    fn add_trusted_text(&mut self, owner_id: &str, text: &str) -> PyResult<()> {
+        Ok(self.db.try_add_trusted_text(owner_id, text)?)
+    }
+
+    /// Raises ValueError if the text has no words
+    #[pyo3(signature = (owner_id, text, metadata = HashMap::new()))]
+    fn add_untrusted_text(
+        &mut self,
+        owner_id: &str,
+        text: &str,
+        metadata: HashMap<String, String>,
+    ) -> PyResult<()> {
+        Ok(self
+            .db
+            .try_add_untrusted_text_with_meta(owner_id, text, metadata)?)
+    }
+
+    /// Raises OSError if the file cannot be read
+    fn add_trusted_file(&mut self, path: &str) -> PyResult<()> {
+        Ok(self.db.add_trusted_file(path)?)
+    }
+
+    /// Raises OSError if the file cannot be read
+    fn add_untrusted_file(&mut self, path: &str) -> PyResult<()> {
+        Ok(self.db.add_untrusted_file(path)?)
+    }
+
+    fn add_boilerplate_text(&mut self, text: &str) -> PyResult<()> {
+        Ok(self.db.try_add_boilerplate_text(text)?)
+    }
+
+    fn set_assignment_prompt(&mut self, text: &str) -> PyResult<()> {
+        Ok(self.db.try_set_assignment_prompt(text)?)
+    }
+
+    /// Checks the untrusted texts against the trusted ones, most matches first
+    fn check_trusted_plagiarism(&self) -> Vec<PyPlagiarismResult> {
+        let mut results: Vec<PlagiarismResult> = self.db.check_trusted_plagiarism();
+        sort_results(&mut results);
+        results.into_iter().map(PyPlagiarismResult::from).collect()
+    }
+}
+
+#[pymodule]
+fn plagiarismbasic_lib(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyMetric>()?;
+    module.add_class::<PyPlagiarismResult>()?;
+    module.add_class::<PyPlagiarismDatabase>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_trusted_plagiarism() {
+        let mut db = PyPlagiarismDatabase::new(3, 0, PyMetric::Equal, Vec::new());
+        db.add_trusted_text("source", "the quick brown fox jumps")
+            .unwrap();
+        db.add_untrusted_text("student", "a quick brown fox appears", HashMap::new())
+            .unwrap();
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].__repr__(),
+            "PlagiarismResult(\"source\", \"student\", 1 matching fragments)"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::plagiarism_database::{ByteRange, PlagiarismResult, TextOwnerID};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How `annotate_text` marks the matches in a text
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AnnotationFormat {
+    /// The plain text with `[[match:owner:score]] ... [[/match]]` around
+    ///     every matching passage. Passages matching several owners list all
+    ///     of them, like `[[match:book:0.42;web:0.10]]`.
+    Markers,
+    /// A HTML page with the text and a `<mark>` element around every
+    ///     matching passage, with the owners in `data-owners`
+    Html,
+}
+
+impl AnnotationFormat {
+    /// Extension of the files `output_texts` writes
+    fn extension(self) -> &'static str {
+        match self {
+            AnnotationFormat::Markers => "txt",
+            AnnotationFormat::Html => "html",
+        }
+    }
+}
+
+/// Marks the character ranges of a text that match in any of the results,
+///     e.g. to import the matches into another review tool. The text must be
+///     the one the owner's text was added with, since the ranges are byte
+///     ranges into it (see `PlagiarismResult::char_locations`). Every passage
+///     names the other owners it matches, with the score of their result.
+pub fn annotate_text(
+    text: &str,
+    owner_id: &str,
+    results: &[PlagiarismResult],
+    format: AnnotationFormat,
+) -> String {
+    let passages = matched_passages(text, owner_id, results);
+    let mut annotated = String::with_capacity(text.len());
+    let mut end = 0;
+    let push_text = |annotated: &mut String, part: &str| match format {
+        AnnotationFormat::Markers => annotated.push_str(part),
+        AnnotationFormat::Html => annotated.push_str(&escape(part)),
+    };
+    for ((start, passage_end), owners) in passages {
+        push_text(&mut annotated, &text[end..start]);
+        let scores: Vec<String> = owners
+            .iter()
+            .map(|(owner, score)| format!("{}:{:.2}", owner, score))
+            .collect();
+        match format {
+            AnnotationFormat::Markers => {
+                annotated.push_str(&format!("[[match:{}]]", scores.join(";")));
+                annotated.push_str(&text[start..passage_end]);
+                annotated.push_str("[[/match]]");
+            }
+            AnnotationFormat::Html => {
+                let names: Vec<&str> = owners.keys().map(String::as_str).collect();
+                annotated.push_str(&format!(
+                    "<mark data-owners=\"{}\" title=\"{}\">",
+                    escape(&names.join(" ")),
+                    escape(&scores.join(", "))
+                ));
+                annotated.push_str(&escape(&text[start..passage_end]));
+                annotated.push_str("</mark>");
+            }
+        }
+        end = passage_end;
+    }
+    push_text(&mut annotated, &text[end..]);
+    match format {
+        AnnotationFormat::Markers => annotated,
+        AnnotationFormat::Html => format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n<pre style=\"white-space: pre-wrap\">{}</pre>\n</body>\n</html>\n",
+            escape(owner_id),
+            annotated
+        ),
+    }
+}
+
+/// Writes every text annotated with `annotate_text` into the output
+///     directory, as `<owner>.txt` or `<owner>.html` (with any '/' of
+///     namespaced owner IDs replaced by '_')
+pub fn output_texts(
+    results: &[PlagiarismResult],
+    texts: &[(TextOwnerID, String)],
+    output_dir: &Path,
+    format: AnnotationFormat,
+) -> io::Result<()> {
+    fs::create_dir_all(output_dir)?;
+    for (owner_id, text) in texts {
+        let file_name = format!("{}.{}", owner_id.replace('/', "_"), format.extension());
+        fs::write(
+            output_dir.join(file_name),
+            annotate_text(text, owner_id, results, format),
+        )?;
+    }
+    Ok(())
+}
+
+/// Passages of a text matching other owners, split wherever the set of
+///     owners changes, with the best score of every owner they match
+fn matched_passages(
+    text: &str,
+    owner_id: &str,
+    results: &[PlagiarismResult],
+) -> Vec<(ByteRange, BTreeMap<TextOwnerID, f32>)> {
+    let mut ranges: Vec<(ByteRange, &str, f32)> = Vec::new();
+    for result in results {
+        let (other, own_side) = if result.owner_id1 == owner_id {
+            (&result.owner_id2, true)
+        } else if result.owner_id2 == owner_id {
+            (&result.owner_id1, false)
+        } else {
+            continue;
+        };
+        for (ranges1, ranges2) in &result.char_locations {
+            let own_ranges = if own_side { ranges1 } else { ranges2 };
+            ranges.extend(
+                own_ranges
+                    .iter()
+                    .filter(|&&(start, end)| {
+                        start < end && text.is_char_boundary(start) && text.is_char_boundary(end)
+                    })
+                    .map(|&range| (range, other.as_str(), result.score())),
+            );
+        }
+    }
+    let mut boundaries: Vec<usize> = ranges
+        .iter()
+        .flat_map(|&((start, end), _, _)| [start, end])
+        .filter(|&boundary| boundary <= text.len())
+        .collect();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+    let mut passages: Vec<(ByteRange, BTreeMap<TextOwnerID, f32>)> = Vec::new();
+    for segment in boundaries.windows(2) {
+        let (start, end) = (segment[0], segment[1]);
+        let mut owners: BTreeMap<TextOwnerID, f32> = BTreeMap::new();
+        for &((range_start, range_end), other, score) in &ranges {
+            if range_start <= start && end <= range_end {
+                let best = owners.entry(other.to_string()).or_insert(score);
+                *best = best.max(score);
+            }
+        }
+        if owners.is_empty() {
+            continue;
+        }
+        match passages.last_mut() {
+            // Adjacent segments matching the same owners are one passage
+            Some(((_, last_end), last_owners)) if *last_end == start && *last_owners == owners => {
+                *last_end = end
+            }
+            _ => passages.push(((start, end), owners)),
+        }
+    }
+    passages
+}
+
+/// Escapes text for HTML attributes and element content
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_annotate_text() {
+        let essay = "We know <that> the powerhouse of the cell, is key.";
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("book", "the mitochondria is the powerhouse of the cell");
+        db.add_untrusted_text("essay", essay);
+        let results = db.check_trusted_plagiarism();
+        let score = format!("{:.2}", results[0].score());
+
+        let markers = annotate_text(essay, "essay", &results, AnnotationFormat::Markers);
+        assert_eq!(
+            markers,
+            format!(
+                "We know <that> [[match:book:{}]]the powerhouse of the cell[[/match]], is key.",
+                score
+            )
+        );
+        let html = annotate_text(essay, "essay", &results, AnnotationFormat::Html);
+        assert!(html.contains(&format!(
+            "We know &lt;that&gt; <mark data-owners=\"book\" title=\"book:{}\">the powerhouse of the cell</mark>, is key.",
+            score
+        )));
+        assert_eq!(
+            annotate_text(essay, "other", &results, AnnotationFormat::Markers),
+            essay
+        );
+    }
+}
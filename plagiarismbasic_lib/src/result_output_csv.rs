@@ -0,0 +1,56 @@
+use crate::plagiarism_database::SimilarityMatrix;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Renders a similarity matrix as CSV: a header row with an empty first cell
+///     and the owner IDs, then one row per owner starting with its ID, with
+///     scores rounded to 4 decimals
+pub fn render_matrix(matrix: &SimilarityMatrix) -> String {
+    let mut csv = String::new();
+    let header: Vec<String> = matrix.owners.iter().map(|owner| escape(owner)).collect();
+    csv.push_str(&format!(",{}\n", header.join(",")));
+    for (owner, row) in matrix.owners.iter().zip(&matrix.scores) {
+        let scores: Vec<String> = row.iter().map(|score| format!("{:.4}", score)).collect();
+        csv.push_str(&format!("{},{}\n", escape(owner), scores.join(",")));
+    }
+    csv
+}
+
+/// Writes a similarity matrix to a CSV file, see `render_matrix`
+pub fn output_matrix(matrix: &SimilarityMatrix, output_path: &Path) -> io::Result<()> {
+    fs::write(output_path, render_matrix(matrix))
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_render_matrix() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_untrusted_text("a", "the quick brown fox jumps");
+        db.add_untrusted_text("b, c", "the quick brown fox");
+        db.add_untrusted_text("d", "nothing to see here");
+        let matrix = db.similarity_matrix();
+        assert_eq!(matrix.scores[0][1], 2.0 / 3.0);
+        assert_eq!(matrix.scores[1][0], 1.0);
+        assert_eq!(
+            render_matrix(&matrix),
+            ",a,\"b, c\",d\n\
+             a,1.0000,0.6667,0.0000\n\
+             \"b, c\",1.0000,1.0000,0.0000\n\
+             d,0.0000,0.0000,1.0000\n"
+        );
+    }
+}
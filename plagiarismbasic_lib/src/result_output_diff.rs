@@ -0,0 +1,155 @@
+use crate::plagiarism_database::{FragmentLocation, PlagiarismDatabase, PlagiarismResult};
+use std::collections::HashSet;
+
+/// Width of each text column, in characters
+const COLUMN_WIDTH: usize = 40;
+/// Printed between the two columns
+const COLUMN_SEPARATOR: &str = " | ";
+/// ANSI escape codes around matching words (bold red), and after them
+const ANSI_MATCH: &str = "\x1b[1;31m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+impl PlagiarismResult {
+    /// Renders the two texts side by side in plain text columns, with
+    ///     every run of matching words in [brackets]
+    pub fn render_diff(&self, db: &PlagiarismDatabase) -> String {
+        self.render_columns(db, false)
+    }
+
+    /// Same as `render_diff`, but highlights matching words with ANSI colors
+    ///     for terminals instead of brackets
+    pub fn render_diff_ansi(&self, db: &PlagiarismDatabase) -> String {
+        self.render_columns(db, true)
+    }
+
+    /// Indices of the cleaned words of each owner covered by the matches,
+    ///     e.g. to highlight them
+    pub fn matched_word_indices(&self) -> (HashSet<usize>, HashSet<usize>) {
+        let locations1: Vec<&FragmentLocation> = self
+            .matching_fragments_locations
+            .iter()
+            .flat_map(|(locs1, _)| locs1)
+            .collect();
+        let locations2: Vec<&FragmentLocation> = self
+            .matching_fragments_locations
+            .iter()
+            .flat_map(|(_, locs2)| locs2)
+            .collect();
+        (matched_words(&locations1), matched_words(&locations2))
+    }
+
+    fn render_columns(&self, db: &PlagiarismDatabase, ansi: bool) -> String {
+        let words1 = db.get_clean_words(&self.owner_id1, self.trusted_owner1);
+        let words2 = db.get_clean_words(&self.owner_id2, self.trusted_owner2);
+        let (matched1, matched2) = self.matched_word_indices();
+        let column1 = wrap_words(words1, &matched1, ansi);
+        let column2 = wrap_words(words2, &matched2, ansi);
+
+        let mut output = format!(
+            "{}{}{}\n{}\n",
+            pad(&self.owner_id1, self.owner_id1.chars().count()),
+            COLUMN_SEPARATOR,
+            self.owner_id2,
+            "-".repeat(COLUMN_WIDTH * 2 + COLUMN_SEPARATOR.len())
+        );
+        for idx in 0..column1.len().max(column2.len()) {
+            let (left, left_width) = column1.get(idx).map_or(("", 0), |(l, w)| (l.as_str(), *w));
+            let right = column2.get(idx).map_or("", |(line, _)| line.as_str());
+            output.push_str(&pad(left, left_width));
+            output.push_str(COLUMN_SEPARATOR);
+            output.push_str(right);
+            output.push('\n');
+        }
+        output
+    }
+}
+
+/// Word indices covered by the locations
+fn matched_words(locations: &[&FragmentLocation]) -> HashSet<usize> {
+    locations
+        .iter()
+        .flat_map(|&&(start, end)| start..=end)
+        .collect()
+}
+
+/// Pads a line with spaces to the column width, given its visible width
+fn pad(line: &str, width: usize) -> String {
+    format!("{}{}", line, " ".repeat(COLUMN_WIDTH.saturating_sub(width)))
+}
+
+/// Wraps words into lines of at most the column width (unless a word is longer),
+///     marking matched words. Returns each line with its visible width.
+fn wrap_words(words: &[String], matched: &HashSet<usize>, ansi: bool) -> Vec<(String, usize)> {
+    let mut lines: Vec<(String, usize)> = Vec::new();
+    let mut line = String::new();
+    let mut width = 0;
+    for (idx, word) in words.iter().enumerate() {
+        let is_matched = matched.contains(&idx);
+        let (token, token_width) = if !is_matched {
+            (word.clone(), word.chars().count())
+        } else if ansi {
+            (
+                format!("{}{}{}", ANSI_MATCH, word, ANSI_RESET),
+                word.chars().count(),
+            )
+        } else {
+            let opens = idx == 0 || !matched.contains(&(idx - 1));
+            let closes = !matched.contains(&(idx + 1));
+            let token = format!(
+                "{}{}{}",
+                if opens { "[" } else { "" },
+                word,
+                if closes { "]" } else { "" }
+            );
+            let token_width = token.chars().count();
+            (token, token_width)
+        };
+        if width > 0 && width + 1 + token_width > COLUMN_WIDTH {
+            lines.push((std::mem::take(&mut line), width));
+            width = 0;
+        }
+        if width > 0 {
+            line.push(' ');
+            width += 1;
+        }
+        line.push_str(&token);
+        width += token_width;
+    }
+    if width > 0 {
+        lines.push((line, width));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_render_diff() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text(
+            "source",
+            "The mitochondria is the powerhouse of the cell, as everyone learns in school.",
+        );
+        db.add_untrusted_text("essay", "In short the powerhouse of the cell is important");
+        let results = db.check_trusted_plagiarism();
+        let diff = results[0].render_diff(&db);
+        let lines: Vec<&str> = diff.lines().collect();
+        assert_eq!(
+            lines[0].trim_end(),
+            format!("source{}| essay", " ".repeat(35))
+        );
+        assert_eq!(
+            lines[2],
+            "the mitochondria is [the powerhouse of   | in short [the powerhouse of the cell] is"
+        );
+        assert_eq!(
+            lines[3],
+            "the cell] as everyone learns in school   | important"
+        );
+        let ansi = results[0].render_diff_ansi(&db);
+        assert!(ansi.contains("\x1b[1;31mpowerhouse\x1b[0m"));
+        assert!(!ansi.contains("[the"));
+    }
+}
@@ -1,20 +1,20 @@
-use crate::plagiarism_database::{PlagiarismResult, TextOwnerID};
+use crate::plagiarism_database::{sort_results, PlagiarismResult, TextOwnerID};
 use crate::text_utils::get_boldtext_segments_from_intervals;
-use fs_extra;
 use gcollections::ops::*;
 use handlebars::Handlebars;
 use interval::interval_set::*;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::fs;
 use std::fs::create_dir_all;
 use std::fs::File;
+use std::path::Path;
 use std::process::Command;
 
 // Used to find the templates directory
 const TEMPLATE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/templates/report.hbs");
-const ASSETS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/");
-const OUTPUT_DIR: &str = "./www/";
-const OUTPUT_FILE_PATH: &str = "./www/report.html";
+pub const ASSETS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/");
+const OUTPUT_FILE_NAME: &str = "report.html";
 
 // Send a set of these over for each text display
 #[derive(Serialize, PartialEq, Eq, Debug)]
@@ -38,19 +38,100 @@ struct HBPlagiarismResult {
     text2_plag_percent: usize,
 }
 
-/// Outputs results to html
+/// Outputs results to report.html (along with its assets) in the output directory
 pub fn output_results(
-    results: &mut Vec<PlagiarismResult>,
+    results: &mut [PlagiarismResult],
     texts: HashMap<TextOwnerID, Vec<String>>,
+    output_dir: &Path,
     open_html_after: bool,
 ) {
-    // We want the results by most significant first (most matches)
-    results.sort_by(|a, b| {
-        b.matching_fragments
-            .len()
-            .partial_cmp(&a.matching_fragments.len())
-            .unwrap()
+    let plag_results = make_hb_results(results, texts);
+
+    // Create output directory and files, copy asserts over
+    let mut source_template = File::open(TEMPLATE_PATH).unwrap_or_else(|_| {
+        panic!(
+            "Cannot open template path {}, check path for existence of .hbs file",
+            TEMPLATE_PATH
+        );
+    });
+    create_dir_all(output_dir).unwrap_or_else(|_| {
+        panic!(
+            "Cannot create {} folder, check for permissions errors",
+            output_dir.display()
+        )
     });
+    let output_file_path = output_dir.join(OUTPUT_FILE_NAME);
+    let mut output_file = File::create(&output_file_path).unwrap_or_else(|_| {
+        panic!(
+            "Cannot create output {} file, check for permissions errors",
+            output_file_path.display()
+        )
+    });
+    let mut copy_options = fs_extra::dir::CopyOptions::new();
+    copy_options.overwrite = true;
+    fs_extra::dir::copy(ASSETS_PATH, output_dir, &copy_options).unwrap_or_else(|_| {
+        panic!(
+            "Failed to copy assets from {} to {}",
+            ASSETS_PATH,
+            output_dir.display()
+        )
+    });
+
+    // Output report to html
+    let hbars = Handlebars::new();
+    hbars
+        .render_template_source_to_write(&mut source_template, &plag_results, &mut output_file)
+        .unwrap_or_else(|_| {
+            panic!(
+                "Cannot render to template file without errors. JSON object: {:?}",
+                &plag_results
+            );
+        });
+
+    // Open the report using the OS-preferred method if possible
+    if open_html_after && cfg!(target_os = "linux") {
+        Command::new("xdg-open")
+            .arg(&output_file_path)
+            .output()
+            .unwrap_or_else(|_| {
+                panic!(
+                    "Failed to execute xdg-open to open {}!",
+                    output_file_path.display()
+                )
+            });
+    }
+}
+
+/// Renders results as a HTML page. It refers to its assets in a relative
+///     `assets/` directory.
+pub fn render_results(
+    results: &mut [PlagiarismResult],
+    texts: HashMap<TextOwnerID, Vec<String>>,
+) -> String {
+    let plag_results = make_hb_results(results, texts);
+    let template = fs::read_to_string(TEMPLATE_PATH).unwrap_or_else(|_| {
+        panic!(
+            "Cannot open template path {}, check path for existence of .hbs file",
+            TEMPLATE_PATH
+        );
+    });
+    Handlebars::new()
+        .render_template(&template, &plag_results)
+        .unwrap_or_else(|_| {
+            panic!(
+                "Cannot render template without errors. JSON object: {:?}",
+                &plag_results
+            );
+        })
+}
+
+/// Computes the text to display for each result, with matches in bold
+fn make_hb_results(
+    results: &mut [PlagiarismResult],
+    texts: HashMap<TextOwnerID, Vec<String>>,
+) -> Vec<HBPlagiarismResult> {
+    // We want the results by most significant first (most matches)
+    sort_results(results);
 
     // Goal: display the clean text side by side for each plagiarism result
     //       while highlighting each matching ngram in the same colors
@@ -92,7 +173,7 @@ pub fn output_results(
             )
         });
         let t1_boldtext: Vec<TextMaybeBold> =
-            get_boldtext_segments_from_intervals(&t1_text, &text1_intervals);
+            get_boldtext_segments_from_intervals(t1_text, &text1_intervals);
 
         let t2_text = texts.get(&result.owner_id2).unwrap_or_else(|| {
             panic!(
@@ -101,7 +182,7 @@ pub fn output_results(
             )
         });
         let t2_boldtext: Vec<TextMaybeBold> =
-            get_boldtext_segments_from_intervals(&t2_text, &text2_intervals);
+            get_boldtext_segments_from_intervals(t2_text, &text2_intervals);
 
         // Add the result to an overall vector to be sent to Handlebars
         plag_results.push(HBPlagiarismResult {
@@ -115,51 +196,5 @@ pub fn output_results(
             text2_plag_percent: ((numwords2 as f32) / (t2_text.len() as f32) * 100.0) as usize,
         })
     }
-
-    // Create output directory and files, copy asserts over
-    let mut source_template = File::open(TEMPLATE_PATH).unwrap_or_else(|_| {
-        panic!(
-            "Cannot open template path {}, check path for existence of .hbs file",
-            TEMPLATE_PATH
-        );
-    });
-    create_dir_all(OUTPUT_DIR).unwrap_or_else(|_| {
-        panic!(
-            "Cannot create {} folder, check for permissions errors",
-            OUTPUT_DIR
-        )
-    });
-    let mut output_file = File::create(OUTPUT_FILE_PATH).unwrap_or_else(|_| {
-        panic!(
-            "Cannot create output {} file, check for permissions errors",
-            OUTPUT_FILE_PATH
-        )
-    });
-    let mut copy_options = fs_extra::dir::CopyOptions::new();
-    copy_options.overwrite = true;
-    fs_extra::dir::copy(ASSETS_PATH, OUTPUT_DIR, &copy_options).unwrap_or_else(|_| {
-        panic!(
-            "Failed to copy assets from {} to {}",
-            ASSETS_PATH, OUTPUT_DIR
-        )
-    });
-
-    // Output report to html
-    let hbars = Handlebars::new();
-    hbars
-        .render_template_source_to_write(&mut source_template, &plag_results, &mut output_file)
-        .unwrap_or_else(|_| {
-            panic!(
-                "Cannot render to template file without errors. JSON object: {:?}",
-                &plag_results
-            );
-        });
-
-    // Open the report using the OS-preferred method if possible
-    if open_html_after && cfg!(target_os = "linux") {
-        Command::new("xdg-open")
-            .args(&["./www/report.html"])
-            .output()
-            .expect("Failed to execute xdg-open to open ./www/report.html!");
-    }
+    plag_results
 }
@@ -0,0 +1,16 @@
+use crate::plagiarism_database::{sort_results, PlagiarismResult};
+use crate::results::SCHEMA_VERSION;
+use serde_json::json;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Writes results to a JSON file as an object with the format version and
+///     an array of plagiarism results, most significant (most matches) first
+pub fn output_results(results: &mut [PlagiarismResult], output_path: &Path) -> io::Result<()> {
+    sort_results(results);
+    let mut writer = BufWriter::new(File::create(output_path)?);
+    let file = json!({ "version": SCHEMA_VERSION, "results": results });
+    serde_json::to_writer_pretty(&mut writer, &file)?;
+    writer.flush()
+}
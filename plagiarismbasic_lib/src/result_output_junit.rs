@@ -0,0 +1,91 @@
+use crate::plagiarism_database::{sort_results, PlagiarismResult};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Renders results as a JUnit XML test suite for CI dashboards: every owner
+///     pair with a result is one failed test case, most significant first,
+///     listing its matching fragments in the failure message
+pub fn render_results(results: &mut [PlagiarismResult]) -> String {
+    sort_results(results);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites tests=\"{0}\" failures=\"{0}\">\n  <testsuite name=\"plagiarism\" tests=\"{0}\" failures=\"{0}\">\n",
+        results.len()
+    ));
+    for result in results.iter() {
+        let message = format!(
+            "{} matching fragments between {} and {}",
+            result.matching_fragments.len(),
+            result.owner_id1,
+            result.owner_id2
+        );
+        let details: Vec<String> = result
+            .matching_fragments
+            .iter()
+            .map(|(fragment1, fragment2)| {
+                if result.equal_fragments {
+                    fragment1.clone()
+                } else {
+                    format!("{} / {}", fragment1, fragment2)
+                }
+            })
+            .collect();
+        xml.push_str(&format!(
+            "    <testcase classname=\"{}\" name=\"{}\">\n      <failure type=\"plagiarism\" message=\"{}\">{}</failure>\n    </testcase>\n",
+            escape(&result.owner_id1),
+            escape(&result.owner_id2),
+            escape(&message),
+            escape(&details.join("\n"))
+        ));
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Writes results to a JUnit XML file, see `render_results`
+pub fn output_results(results: &mut [PlagiarismResult], output_path: &Path) -> io::Result<()> {
+    fs::write(output_path, render_results(results))
+}
+
+/// Escapes text for XML attributes and element content
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_render_junit() {
+        let mut db = PlagiarismDatabase::new(3, 1, Metric::Lev, Vec::new());
+        db.add_trusted_text("notes \"v2\"", "the cat sat on the mat today");
+        db.add_untrusted_text("a&b", "the cat sat on the hat today");
+        db.add_untrusted_text("c<d", "the cat sat");
+        db.add_untrusted_text("other", "nothing to see here at all");
+        let xml = render_results(&mut db.check_trusted_plagiarism());
+        assert!(xml.contains("<testsuite name=\"plagiarism\" tests=\"2\" failures=\"2\">"));
+        // Most matching fragments first, with escaped owners
+        let first = xml.find("name=\"a&amp;b\"").unwrap();
+        assert!(first < xml.find("name=\"c&lt;d\"").unwrap());
+        assert!(xml.contains("<testcase classname=\"notes &quot;v2&quot;\" name=\"a&amp;b\">"));
+        assert!(xml
+            .contains("message=\"5 matching fragments between notes &quot;v2&quot; and a&amp;b\""));
+        // Fragments of both texts, as the metric isn't equal
+        assert!(xml.contains("on the mat / on the hat\n"));
+        assert!(!xml.contains("other"));
+    }
+}
@@ -1,13 +1,10 @@
-use crate::plagiarism_database::PlagiarismResult;
+use crate::plagiarism_database::{sort_results, PlagiarismResult};
 
 /// Print all untrusted results
-pub fn print_results_ut(results: &mut Vec<PlagiarismResult>) {
-    results.sort_by(|a, b| {
-        b.matching_fragments
-            .len()
-            .partial_cmp(&a.matching_fragments.len())
-            .unwrap()
-    });
+// The untrusted-vs-untrusted output is not wired up in this fork yet
+#[allow(dead_code)]
+pub fn print_results_ut(results: &mut [PlagiarismResult]) {
+    sort_results(results);
     println!("\t===== BEGIN UNTRUSTED COMPARISON REPORT (Sorted by decreasing severity) ===== \n");
     for result in results {
         println!(
@@ -19,13 +16,8 @@ pub fn print_results_ut(results: &mut Vec<PlagiarismResult>) {
     println!("\n\t===== END UNTRUSTED COMPARISON REPORT ===== \n");
 }
 
-pub fn print_results_t(results: &mut Vec<PlagiarismResult>) {
-    results.sort_by(|a, b| {
-        b.matching_fragments
-            .len()
-            .partial_cmp(&a.matching_fragments.len())
-            .unwrap()
-    });
+pub fn print_results_t(results: &mut [PlagiarismResult]) {
+    sort_results(results);
     println!("\t**** BEGIN TRUSTED COMPARISON REPORT (Sorted by decreasing severity) **** \n");
     for result in results {
         println!(
@@ -38,6 +30,67 @@ pub fn print_results_t(results: &mut Vec<PlagiarismResult>) {
 }
 
 fn print_result(result: &PlagiarismResult) {
+    println!(
+        "Coverage: {:.1}% of {}, {:.1}% of {}",
+        result.coverage_owner1 * 100.0,
+        result.owner_id1,
+        result.coverage_owner2 * 100.0,
+        result.owner_id2
+    );
+    println!(
+        "Density: {:.1}% of {}, {:.1}% of {}",
+        result.density_owner1 * 100.0,
+        result.owner_id1,
+        result.density_owner2 * 100.0,
+        result.owner_id2
+    );
+    if result.weighted_score() > 0.0 {
+        println!(
+            "Weighted coverage: {:.1}% of {}, {:.1}% of {}",
+            result.weighted_coverage_owner1 * 100.0,
+            result.owner_id1,
+            result.weighted_coverage_owner2 * 100.0,
+            result.owner_id2
+        );
+    }
+    if result.prompt_coverage_owner1 > 0.0 || result.prompt_coverage_owner2 > 0.0 {
+        println!(
+            "Assignment prompt: {:.1}% of {}, {:.1}% of {}",
+            result.prompt_coverage_owner1 * 100.0,
+            result.owner_id1,
+            result.prompt_coverage_owner2 * 100.0,
+            result.owner_id2
+        );
+    }
+    println!("Confidence: {:?}", result.confidence);
+    for metric_result in &result.metric_results {
+        println!(
+            "{:?} metric: {} matches, {:.1}% of {}, {:.1}% of {}",
+            metric_result.metric,
+            metric_result.matching_fragments_locations.len(),
+            metric_result.coverage_owner1 * 100.0,
+            result.owner_id1,
+            metric_result.coverage_owner2 * 100.0,
+            result.owner_id2
+        );
+    }
+    for (owner, sections) in [
+        (&result.owner_id1, &result.sections_owner1),
+        (&result.owner_id2, &result.sections_owner2),
+    ] {
+        for section in sections.iter().filter(|section| section.matches > 0) {
+            println!(
+                "Section \"{}\" of {}: {} matches, {:.1}% of its words",
+                section.title,
+                owner,
+                section.matches,
+                section.coverage * 100.0
+            );
+        }
+    }
+    if result.allowed {
+        println!("Allowed to share text, not a violation");
+    }
     if result.equal_fragments {
         for matching_fragment in &result.matching_fragments {
             println!("Identical fragment detected: {}", matching_fragment.0)
@@ -0,0 +1,397 @@
+use crate::error::{Error, Result};
+use crate::plagiarism_database::PlagiarismResult;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Version of the JSON results format written by `write_json_report`, bumped
+///     whenever older versions could no longer read a file
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Contents of a JSON results file
+#[derive(Deserialize)]
+struct ResultsFile {
+    results: Vec<PlagiarismResult>,
+}
+
+/// Reads the results of a previous run written by `write_json_report`, e.g.
+///     to compare them with a new run. Reports written before the format was
+///     versioned (a bare array of results) are read too.
+pub fn read_json<P: AsRef<Path>>(path: P) -> Result<Vec<PlagiarismResult>> {
+    let value: Value = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    if value.is_array() {
+        return Ok(serde_json::from_value(value)?);
+    }
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > SCHEMA_VERSION {
+        return Err(Error::UnsupportedSchema { version });
+    }
+    let file: ResultsFile = serde_json::from_value(value)?;
+    Ok(file.results)
+}
+
+/// A match between two owners found in both runs, with different fragments
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChangedResult {
+    pub old: PlagiarismResult,
+    pub new: PlagiarismResult,
+}
+
+/// What changed between the results of two runs, see `diff`
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct ResultsDiff {
+    /// Owner pairs that only match in the new run
+    pub added: Vec<PlagiarismResult>,
+    /// Owner pairs that only matched in the old run
+    pub removed: Vec<PlagiarismResult>,
+    /// Owner pairs that match in both runs, but not with the same fragments
+    pub changed: Vec<ChangedResult>,
+}
+
+impl ResultsDiff {
+    /// Whether both runs found the same matches
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Owners (with their trust) and ngram size a result is compared by
+type ResultKey = (String, bool, String, bool, usize);
+
+/// Canonicalized results by owner pair and ngram size
+fn by_key(results: &[PlagiarismResult]) -> BTreeMap<ResultKey, PlagiarismResult> {
+    results
+        .iter()
+        .map(|result| {
+            let mut result = result.clone();
+            result.canonicalize();
+            let key = (
+                result.owner_id1.clone(),
+                result.trusted_owner1,
+                result.owner_id2.clone(),
+                result.trusted_owner2,
+                result.n,
+            );
+            (key, result)
+        })
+        .collect()
+}
+
+/// Compares the results of two runs (e.g. before and after adding texts)
+///     by owner pair and ngram size, so that only the matches that appeared,
+///     disappeared or changed their fragments need reviewing. All results
+///     are canonicalized and sorted by owners.
+pub fn diff(old: &[PlagiarismResult], new: &[PlagiarismResult]) -> ResultsDiff {
+    let mut old = by_key(old);
+    let mut diff = ResultsDiff::default();
+    for (key, new_result) in by_key(new) {
+        match old.remove(&key) {
+            None => diff.added.push(new_result),
+            Some(old_result) => {
+                let fragments = |result: &PlagiarismResult| {
+                    result
+                        .matching_fragments
+                        .iter()
+                        .cloned()
+                        .collect::<BTreeSet<(String, String)>>()
+                };
+                if fragments(&old_result) != fragments(&new_result) {
+                    diff.changed.push(ChangedResult {
+                        old: old_result,
+                        new: new_result,
+                    });
+                }
+            }
+        }
+    }
+    diff.removed = old.into_values().collect();
+    diff
+}
+
+/// How a reviewer triaged a reported owner pair
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    /// The match is plagiarism
+    Confirmed,
+    /// The match is harmless (e.g. a common phrase or allowed collaboration)
+    Dismissed,
+}
+
+/// The verdict of a reviewer on one result
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub owner_id1: String,
+    pub owner_id2: String,
+    pub n: usize,
+    pub verdict: Verdict,
+}
+
+impl Decision {
+    /// A verdict on the owner pair and ngram size of a result
+    pub fn new(result: &PlagiarismResult, verdict: Verdict) -> Decision {
+        Decision {
+            owner_id1: result.owner_id1.clone(),
+            owner_id2: result.owner_id2.clone(),
+            n: result.n,
+            verdict,
+        }
+    }
+}
+
+/// How severe the highest score of a check is, e.g. to gate submissions in
+///     a pipeline on the exit code of the CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Severity {
+    /// `Fail` if the highest score of the results (see
+    ///     `PlagiarismResult::score`) is above `fail_above`, `Warn` if it is
+    ///     above `warn_above`, `Pass` otherwise (and without results)
+    pub fn of(
+        results: &[PlagiarismResult],
+        warn_above: Option<f32>,
+        fail_above: Option<f32>,
+    ) -> Severity {
+        let highest = results.iter().map(PlagiarismResult::score).reduce(f32::max);
+        let above = |threshold: Option<f32>| match (highest, threshold) {
+            (Some(highest), Some(threshold)) => highest > threshold,
+            _ => false,
+        };
+        if above(fail_above) {
+            Severity::Fail
+        } else if above(warn_above) {
+            Severity::Warn
+        } else {
+            Severity::Pass
+        }
+    }
+
+    /// Process exit code: 0 to pass, 1 to warn and 2 to fail
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Severity::Pass => 0,
+            Severity::Warn => 1,
+            Severity::Fail => 2,
+        }
+    }
+}
+
+/// Writes the decisions of a review to a JSON file
+pub fn write_decisions<P: AsRef<Path>>(path: P, decisions: &[Decision]) -> Result<()> {
+    serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), decisions)?;
+    Ok(())
+}
+
+/// Reads decisions written by `write_decisions`
+pub fn read_decisions<P: AsRef<Path>>(path: P) -> Result<Vec<Decision>> {
+    Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+}
+
+/// Appends results to a JSON Lines file (one result per line, created if
+///     missing), e.g. to log the results of `watch` as they are found
+pub fn append_json_lines<P: AsRef<Path>>(path: P, results: &[PlagiarismResult]) -> Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = BufWriter::new(file);
+    for result in results {
+        serde_json::to_writer(&mut writer, result)?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads all results of a file written by `append_json_lines`
+pub fn read_json_lines<P: AsRef<Path>>(path: P) -> Result<Vec<PlagiarismResult>> {
+    let mut results = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            results.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{write_json_report, Metric, PlagiarismDatabase};
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn test_json_round_trip() {
+        // Every optional part of a result survives the round trip
+        let mut db = PlagiarismDatabase::builder(3, 2, Metric::Lev)
+            .alignments(true)
+            .snippets(1)
+            .extra_metrics(vec![Metric::Equal])
+            .build();
+        db.add_trusted_text(
+            "lecture notes",
+            "photosynthesis turns light water and carbon dioxide into sugar",
+        );
+        let metadata: HashMap<String, String> =
+            [("student".to_string(), "S-17".to_string())].into();
+        db.add_untrusted_text_with_meta(
+            "essay",
+            "so photosynthesis turns light, water and carbon dioxides into sugars",
+            metadata,
+        );
+        let mut results = db.check_trusted_plagiarism();
+        assert!(!results[0].alignments.is_empty());
+        assert!(!results[0].snippets.is_empty());
+        assert_eq!(results[0].metric_results.len(), 1);
+        assert_eq!(results[0].metadata2["student"], "S-17");
+        let path = std::env::temp_dir().join("plagiarismbasic_test_results.json");
+        write_json_report(&mut results, &path).unwrap();
+        assert_eq!(read_json(&path).unwrap(), results);
+
+        fs::write(&path, "{\"version\": 99, \"results\": []}").unwrap();
+        assert!(matches!(
+            read_json(&path),
+            Err(Error::UnsupportedSchema { version: 99 })
+        ));
+        fs::write(&path, "[]").unwrap();
+        assert!(read_json(&path).unwrap().is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_decisions() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text(
+            "textbook",
+            "an object at rest stays at rest unless a force acts on it",
+        );
+        db.add_untrusted_text("copier", "an object at rest stays at rest unless pushed");
+        db.add_untrusted_text("quoter", "as newton put it an object at rest");
+        // A reviewer confirms the copied law and dismisses the short quote
+        let decisions: Vec<Decision> = db
+            .check_trusted_plagiarism()
+            .iter()
+            .map(|result| match result.owner_id2.as_str() {
+                "copier" => Decision::new(result, Verdict::Confirmed),
+                _ => Decision::new(result, Verdict::Dismissed),
+            })
+            .collect();
+        assert_eq!(decisions.len(), 2);
+        let path = std::env::temp_dir().join("plagiarismbasic_test_decisions.json");
+        write_decisions(&path, &decisions).unwrap();
+        let json = fs::read_to_string(&path).unwrap();
+        assert!(json.contains("\"confirmed\"") && json.contains("\"dismissed\""));
+        assert_eq!(read_decisions(&path).unwrap(), decisions);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_severity() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("source", "the mitochondria is the powerhouse of the cell");
+        db.add_untrusted_text("a", "we know the powerhouse of the cell well");
+        let results = db.check_trusted_plagiarism();
+        // Both texts share 3 of their 6 trigrams
+        assert_eq!(results[0].score(), 0.5);
+        assert_eq!(Severity::of(&results, None, None), Severity::Pass);
+        // A score equal to a threshold is not above it
+        assert_eq!(Severity::of(&results, Some(0.5), None), Severity::Pass);
+        assert_eq!(Severity::of(&results, Some(0.49), None), Severity::Warn);
+        assert_eq!(Severity::of(&results, Some(0.4), Some(0.5)), Severity::Warn);
+        assert_eq!(
+            Severity::of(&results, Some(0.4), Some(0.49)),
+            Severity::Fail
+        );
+        // Failing takes precedence over warning
+        assert_eq!(
+            Severity::of(&results, Some(0.9), Some(0.49)),
+            Severity::Fail
+        );
+        assert_eq!(Severity::of(&[], Some(0.0), Some(0.0)), Severity::Pass);
+        assert_eq!(Severity::Fail.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text(
+            "handout",
+            "binary search halves the range on every step until the target is found",
+        );
+        let path = std::env::temp_dir().join("plagiarismbasic_test_results.jsonl");
+        let _ = fs::remove_file(&path);
+        // Like watch, log the results of each submission as it arrives
+        db.add_untrusted_text("first", "binary search halves the range on every step");
+        let mut logged = db.check_trusted_plagiarism();
+        append_json_lines(&path, &logged).unwrap();
+        db.add_untrusted_text(
+            "second",
+            "each step halves the range until the target is found",
+        );
+        let second: Vec<PlagiarismResult> = db
+            .check_trusted_plagiarism()
+            .into_iter()
+            .filter(|result| result.owner_id2 == "second")
+            .collect();
+        append_json_lines(&path, &second).unwrap();
+        logged.extend(second);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+        // Empty lines, e.g. added by hand, are skipped
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file).unwrap();
+        assert_eq!(read_json_lines(&path).unwrap(), logged);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_diff() {
+        let run = |texts: &[(&str, &str)]| {
+            let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+            db.add_trusted_text(
+                "recipe",
+                "mix the flour with water and salt then knead the dough for ten minutes",
+            );
+            for (owner, text) in texts {
+                db.add_untrusted_text(owner, text);
+            }
+            db.check_trusted_plagiarism()
+        };
+        let old = run(&[
+            ("kept", "first mix the flour with water and salt"),
+            ("edited", "then knead the dough"),
+            ("dropped", "knead the dough for ten minutes"),
+        ]);
+        assert!(diff(&old, &old).is_empty());
+
+        // Resubmissions: one unchanged, one copying more, one rewritten
+        let new = run(&[
+            ("kept", "first mix the flour with water and salt"),
+            ("edited", "then knead the dough for ten minutes"),
+            ("dropped", "bake it until golden"),
+            ("added", "mix the flour with water"),
+        ]);
+        let changes = diff(&old, &new);
+        let owners = |results: &[PlagiarismResult]| -> Vec<String> {
+            results.iter().map(|r| r.owner_id2.clone()).collect()
+        };
+        assert_eq!(owners(&changes.added), vec!["added"]);
+        assert_eq!(owners(&changes.removed), vec!["dropped"]);
+        assert_eq!(changes.changed.len(), 1);
+        let edited = &changes.changed[0];
+        assert_eq!(edited.new.owner_id2, "edited");
+        assert_eq!(
+            (
+                edited.old.matching_fragments.len(),
+                edited.new.matching_fragments.len()
+            ),
+            (2, 5)
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::plagiarism_database::FragmentLocation;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    /// Lines that look like headings: Markdown headings, "Chapter 3 ...",
+    ///     and short numbered titles like "2.1 Related Work"
+    static ref HEADING: Regex = Regex::new(
+        r"(?m)^[ \t]*(?:#{1,6}[ \t]+\S[^\n]*|(?i:chapter|section|part|appendix)[ \t]+\w[^\n]{0,80}|\d+(?:\.\d+)*\.?[ \t]+\p{Lu}[^.\n]{0,80})[ \t]*$"
+    )
+    .expect("Regex to find headings could not be compiled properly!");
+}
+
+/// How texts are split into sections, see `sections` on the builder
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SectionSplitter {
+    /// At lines that look like headings (Markdown `#` headings, "Chapter 3",
+    ///     numbered titles like "2.1 Related Work")
+    Headings,
+    /// At every match of a regular expression, e.g. `(?m)^Chapter \d+`
+    Pattern(String),
+}
+
+impl SectionSplitter {
+    /// The regex that finds the start of every section
+    pub(crate) fn regex(&self) -> Result<Regex, regex::Error> {
+        match self {
+            SectionSplitter::Headings => Ok(HEADING.clone()),
+            SectionSplitter::Pattern(pattern) => Regex::new(pattern),
+        }
+    }
+}
+
+/// The matches of a result within one section of one of its texts, so that
+///     long documents show which chapters overlap
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SectionMatch {
+    /// Heading the section starts with, empty for the words before the first one
+    pub title: String,
+    /// Word range of the section (both inclusive)
+    pub words: FragmentLocation,
+    /// Number of matching fragments with a location starting in the section
+    pub matches: usize,
+    /// Fraction of the words of the section inside a match (0.0 to 1.0)
+    pub coverage: f32,
+}
+
+/// Titles and word ranges (both inclusive) of the sections of a text, from
+///     the byte ranges of its words. Texts without any section start, or
+///     without byte ranges, have no sections.
+pub(crate) fn split_sections(
+    text: &str,
+    splitter: &Regex,
+    byte_ranges: &[(usize, usize)],
+) -> Vec<(String, FragmentLocation)> {
+    let mut starts: Vec<(String, usize)> = Vec::new();
+    for found in splitter.find_iter(text) {
+        let first_word = byte_ranges.partition_point(|&(start, _)| start < found.start());
+        if first_word == byte_ranges.len() {
+            break;
+        }
+        // The whole line the section starts on, without Markdown heading marks
+        let line = text[found.start()..].lines().next().unwrap_or_default();
+        let title = line.trim().trim_start_matches('#').trim();
+        match starts.last_mut() {
+            // A heading directly after another one (without words in between)
+            Some(last) if last.1 == first_word => last.0 = title.to_string(),
+            _ => starts.push((title.to_string(), first_word)),
+        }
+    }
+    if starts.is_empty() {
+        return Vec::new();
+    }
+    if starts[0].1 > 0 {
+        starts.insert(0, (String::new(), 0));
+    }
+    let ends = starts
+        .iter()
+        .skip(1)
+        .map(|&(_, start)| start - 1)
+        .chain(std::iter::once(byte_ranges.len() - 1));
+    starts
+        .iter()
+        .zip(ends)
+        .map(|((title, start), end)| (title.clone(), (*start, end)))
+        .collect()
+}
+
+/// Counts the matches of one owner of a result in every section of its text
+pub(crate) fn section_matches(
+    sections: impl Iterator<Item = (String, FragmentLocation)>,
+    locations: &[&[FragmentLocation]],
+) -> Vec<SectionMatch> {
+    let mut covered: Vec<FragmentLocation> = locations
+        .iter()
+        .flat_map(|locs| locs.iter().copied())
+        .collect();
+    covered.sort_unstable();
+    sections
+        .map(|(title, (start, end))| {
+            let in_section =
+                |&(loc_start, _): &FragmentLocation| start <= loc_start && loc_start <= end;
+            let matches = locations
+                .iter()
+                .filter(|locs| locs.iter().any(in_section))
+                .count();
+            // Words of the section covered by any match, counted once
+            let mut covered_words = 0;
+            let mut next_word = start;
+            for &(loc_start, loc_end) in &covered {
+                let from = loc_start.max(next_word);
+                let to = loc_end.min(end);
+                if from <= to {
+                    covered_words += to - from + 1;
+                    next_word = to + 1;
+                }
+            }
+            SectionMatch {
+                title,
+                words: (start, end),
+                matches,
+                coverage: covered_words as f32 / (end - start + 1) as f32,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_sections() {
+        let thesis = "Preface words here\n\n# Introduction\nthe mitochondria is the powerhouse of the cell\n\n2.1 Related Work\nnothing copied in this chapter at all";
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .sections(SectionSplitter::Headings)
+            .build();
+        db.add_trusted_text(
+            "book",
+            "as written the mitochondria is the powerhouse of the cell",
+        );
+        db.add_untrusted_text("thesis", thesis);
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].sections_owner1.is_empty());
+        let sections = &results[0].sections_owner2;
+        let titles: Vec<&str> = sections
+            .iter()
+            .map(|section| section.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["", "Introduction", "2.1 Related Work"]);
+        assert_eq!(sections[0].matches, 0);
+        assert_eq!(sections[1].matches, 6);
+        assert_eq!(sections[1].coverage, 8.0 / 9.0);
+        assert_eq!(sections[2].coverage, 0.0);
+
+        let splitter = SectionSplitter::Pattern("(?m)^Part".to_string())
+            .regex()
+            .unwrap();
+        let byte_ranges = [(0, 4), (5, 8), (9, 13)];
+        assert_eq!(
+            split_sections("Part one\nPart", &splitter, &byte_ranges),
+            vec![
+                ("Part one".to_string(), (0, 1)),
+                ("Part".to_string(), (2, 2))
+            ]
+        );
+    }
+}
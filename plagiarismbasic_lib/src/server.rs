@@ -0,0 +1,182 @@
+use crate::error::{Error, Result};
+use crate::plagiarism_database::{sort_results, PlagiarismDatabase, PlagiarismResult};
+use crate::render_html_report;
+use crate::result_output_html::ASSETS_PATH;
+use axum::extract::{Path, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::Router;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Database and the results of the latest check, shared by all requests
+struct ServerState {
+    db: PlagiarismDatabase,
+    results: Vec<PlagiarismResult>,
+}
+
+type SharedState = Arc<Mutex<ServerState>>;
+
+/// Builds the HTTP API around a database (already holding any preloaded texts):
+///     - `PUT /trusted/{owner}` and `PUT /untrusted/{owner}` add the plain text
+///       request body as a text of that owner, answering 409 if the database
+///       rejects duplicate owners and the owner has a text, and 422 if the
+///       text has no words
+///     - `POST /check` checks the untrusted texts against the trusted ones and
+///       returns the results as JSON
+///     - `GET /results.json` and `GET /results.html` return the latest results
+pub fn router(db: PlagiarismDatabase) -> Router {
+    let state = Arc::new(Mutex::new(ServerState {
+        db,
+        results: Vec::new(),
+    }));
+    Router::new()
+        .route("/trusted/{owner}", put(add_trusted))
+        .route("/untrusted/{owner}", put(add_untrusted))
+        .route("/check", post(check))
+        .route("/results.json", get(results_json))
+        .route("/results.html", get(results_html))
+        .route("/assets/{file}", get(asset))
+        .with_state(state)
+}
+
+/// Serves the HTTP API on the address, blocking until the server fails
+pub fn serve(db: PlagiarismDatabase, addr: SocketAddr) -> io::Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, router(db)).await
+    })
+}
+
+fn lock(state: &SharedState) -> MutexGuard<'_, ServerState> {
+    state
+        .lock()
+        .expect("A request panicked while holding the database lock!")
+}
+
+async fn add_trusted(
+    State(state): State<SharedState>,
+    Path(owner): Path<String>,
+    text: String,
+) -> Response {
+    added_response(lock(&state).db.try_add_trusted_text(&owner, &text))
+}
+
+async fn add_untrusted(
+    State(state): State<SharedState>,
+    Path(owner): Path<String>,
+    text: String,
+) -> Response {
+    added_response(lock(&state).db.try_add_untrusted_text(&owner, &text))
+}
+
+/// No content for an added text, else the error with its status
+fn added_response(added: Result<()>) -> Response {
+    match added {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(err @ Error::DuplicateOwner { .. }) => {
+            (StatusCode::CONFLICT, err.to_string()).into_response()
+        }
+        Err(err) => (StatusCode::UNPROCESSABLE_ENTITY, err.to_string()).into_response(),
+    }
+}
+
+async fn check(State(state): State<SharedState>) -> Response {
+    let mut state = lock(&state);
+    let mut results = state.db.check_trusted_plagiarism();
+    sort_results(&mut results);
+    state.results = results;
+    json_response(&state.results)
+}
+
+async fn results_json(State(state): State<SharedState>) -> Response {
+    json_response(&lock(&state).results)
+}
+
+async fn results_html(State(state): State<SharedState>) -> Html<String> {
+    let state = &mut *lock(&state);
+    Html(render_html_report(&mut state.results, &state.db))
+}
+
+/// Serves the stylesheets and scripts used by the HTML report
+async fn asset(Path(file): Path<String>) -> Response {
+    if file.contains("..") {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let content_type = match file.rsplit('.').next() {
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        _ => "application/octet-stream",
+    };
+    match fs::read(format!("{}{}", ASSETS_PATH, file)) {
+        Ok(contents) => ([(CONTENT_TYPE, content_type)], contents).into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+fn json_response(results: &[PlagiarismResult]) -> Response {
+    match serde_json::to_string(results) {
+        Ok(json) => ([(CONTENT_TYPE, "application/json")], json).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DuplicateOwnerPolicy, Metric};
+
+    #[test]
+    fn test_upload_and_check() {
+        let db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .duplicate_owners(DuplicateOwnerPolicy::Error)
+            .build();
+        let state = Arc::new(Mutex::new(ServerState {
+            db,
+            results: Vec::new(),
+        }));
+        let upload = |owner: &str, text: &str, trusted: bool| {
+            let path = Path(owner.to_string());
+            let state = State(state.clone());
+            let text = text.to_string();
+            block_on(async move {
+                if trusted {
+                    add_trusted(state, path, text).await
+                } else {
+                    add_untrusted(state, path, text).await
+                }
+            })
+            .status()
+        };
+        assert_eq!(
+            upload("source", "the quick brown fox jumps", true),
+            StatusCode::NO_CONTENT
+        );
+        upload("student", "a quick brown fox appears", false);
+        // Rejected texts leave the database usable
+        assert_eq!(
+            upload("student", "another text", false),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            upload("empty", "...", false),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        let response = block_on(check(State(state.clone())));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(lock(&state).results.len(), 1);
+        let html = block_on(results_html(State(state.clone())));
+        assert!(html.0.contains("student"));
+        let missing = block_on(asset(Path("../Cargo.toml".to_string())));
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+}
@@ -0,0 +1,137 @@
+use crate::plagiarism_database::{ComparisonCache, PlagiarismDatabase, PlagiarismResult};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// A trusted check running on background threads, started with
+///     `PlagiarismDatabase::start_check`. Results can be collected while it
+///     runs, and it can be cancelled, e.g. when a server request times out.
+pub struct CheckSession {
+    cancelled: Arc<AtomicBool>,
+    /// Owner pairs compared so far
+    compared: Arc<AtomicUsize>,
+    total: usize,
+    /// Results found since they were last taken
+    results: Arc<Mutex<Vec<PlagiarismResult>>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl PlagiarismDatabase {
+    /// Starts a check like `check_trusted_plagiarism` on the configured number
+    ///     of background threads and returns immediately
+    pub fn start_check(self: Arc<Self>) -> CheckSession {
+        let pairs = self.trusted_owner_pairs();
+        let total = pairs.len();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let compared = Arc::new(AtomicUsize::new(0));
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let chunk_size = total.div_ceil(self.thread_count()).max(1);
+        let handles = pairs
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let chunk = chunk.to_vec();
+                let db = Arc::clone(&self);
+                let cancelled = Arc::clone(&cancelled);
+                let compared = Arc::clone(&compared);
+                let results = Arc::clone(&results);
+                thread::spawn(move || {
+                    let mut cache = ComparisonCache::default();
+                    for (trusted_owner, untrusted_owner) in chunk {
+                        if cancelled.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        let found =
+                            db.check_trusted_pair(&trusted_owner, &untrusted_owner, &mut cache);
+                        results
+                            .lock()
+                            .expect("A plagiarism check thread panicked!")
+                            .extend(found);
+                        compared.fetch_add(1, Ordering::Relaxed);
+                    }
+                })
+            })
+            .collect();
+        CheckSession {
+            cancelled,
+            compared,
+            total,
+            results,
+            handles,
+        }
+    }
+}
+
+impl CheckSession {
+    /// Stops the check after the owner pairs currently being compared.
+    ///     Results found so far can still be taken.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// If all threads have stopped, because they are done or cancelled
+    pub fn is_finished(&self) -> bool {
+        self.handles.iter().all(JoinHandle::is_finished)
+    }
+
+    /// (owner pairs compared, total owner pairs)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.compared.load(Ordering::Relaxed), self.total)
+    }
+
+    /// Takes the results found since the last call
+    pub fn take_results(&self) -> Vec<PlagiarismResult> {
+        std::mem::take(
+            &mut *self
+                .results
+                .lock()
+                .expect("A plagiarism check thread panicked!"),
+        )
+    }
+
+    /// Waits for all threads to stop and returns the results that weren't
+    ///     taken yet. Only returns all results if the check wasn't cancelled.
+    pub fn wait(self) -> Vec<PlagiarismResult> {
+        for handle in self.handles {
+            handle.join().expect("A plagiarism check thread panicked!");
+        }
+        std::mem::take(
+            &mut *self
+                .results
+                .lock()
+                .expect("A plagiarism check thread panicked!"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::corpus::synthetic;
+    use crate::{Metric, PlagiarismDatabase};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_check_session() {
+        let mut db = PlagiarismDatabase::builder(6, 0, Metric::Equal)
+            .threads(2)
+            .build();
+        synthetic(4, 100, 0.5, 3).add_to(&mut db);
+        let expected = db.check_trusted_plagiarism().len();
+        let db = Arc::new(db);
+
+        let session = Arc::clone(&db).start_check();
+        let mut results = session.take_results();
+        results.extend(session.wait());
+        assert_eq!(results.len(), expected);
+
+        let session = Arc::clone(&db).start_check();
+        session.cancel();
+        assert!(session.is_cancelled());
+        let (_, total) = session.progress();
+        assert_eq!(total, 16);
+        assert!(session.wait().len() <= expected);
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+/// A user-defined comparison of two fragments (e.g. for a domain where some
+///     words are interchangeable), plugged into a database with
+///     `custom_metric` on the builder and run as `Metric::Custom`. Closures
+///     taking both fragments and the similarity threshold implement it too.
+pub trait SimilarityMetric: Send + Sync {
+    /// Whether two fragments (clean words separated by single spaces) match,
+    ///     given the similarity threshold `s` of the database
+    fn is_match(&self, fragment1: &str, fragment2: &str, s: usize) -> bool;
+
+    /// Lengths in bytes of the fragments that can match a fragment of this
+    ///     length, so that the others aren't compared at all. All of them by
+    ///     default.
+    fn candidate_lengths(&self, _length: usize, _s: usize) -> RangeInclusive<usize> {
+        0..=usize::MAX
+    }
+}
+
+impl<F: Fn(&str, &str, usize) -> bool + Send + Sync> SimilarityMetric for F {
+    fn is_match(&self, fragment1: &str, fragment2: &str, s: usize) -> bool {
+        self(fragment1, fragment2, s)
+    }
+}
+
+/// The custom metric of a database, shared by its clones and threads
+#[derive(Clone)]
+pub(crate) struct CustomMetric(pub(crate) Arc<dyn SimilarityMetric>);
+
+impl fmt::Debug for CustomMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CustomMetric")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::{Metric, PlagiarismDatabase};
+
+    /// Fragments match if they have the same words in any order
+    struct SameWords;
+
+    impl SimilarityMetric for SameWords {
+        fn is_match(&self, fragment1: &str, fragment2: &str, _s: usize) -> bool {
+            let mut words1: Vec<&str> = fragment1.split(' ').collect();
+            let mut words2: Vec<&str> = fragment2.split(' ').collect();
+            words1.sort_unstable();
+            words2.sort_unstable();
+            words1 == words2
+        }
+
+        fn candidate_lengths(&self, length: usize, _s: usize) -> RangeInclusive<usize> {
+            length..=length
+        }
+    }
+
+    #[test]
+    fn test_custom_metric() {
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Custom)
+            .custom_metric(SameWords)
+            .build();
+        db.add_trusted_text("source", "the cat sat on the mat");
+        db.add_untrusted_text("essay", "the sat cat on mat the");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].matching_fragments[0],
+            ("the cat sat".to_string(), "the sat cat".to_string())
+        );
+
+        let mut db = PlagiarismDatabase::builder(3, 1, Metric::Custom)
+            .custom_metric(|a: &str, b: &str, s: usize| a.len().abs_diff(b.len()) <= s)
+            .build();
+        db.add_trusted_text("source", "one two three");
+        db.add_untrusted_text("essay", "six ten eight");
+        assert_eq!(db.check_trusted_plagiarism().len(), 1);
+
+        let missing = PlagiarismDatabase::builder(3, 0, Metric::Custom).try_build();
+        assert!(matches!(missing, Err(Error::MissingCustomMetric)));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::error::{Error, Result};
+use crate::plagiarism_database::{ArchivedText, Fragmentation, TextOwnerID};
+use crate::text_utils::FragmentHash;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Keeps trusted texts outside of the database, so that the trusted corpus
+///     can be larger than the memory. See `PlagiarismDatabase::check_against_archive`.
+pub trait ArchiveStore {
+    /// How the archived texts were split into fragments
+    fn fragmentation(&self) -> &Fragmentation;
+
+    /// Adds texts to the archive, replacing archived texts of the same owners
+    fn store(&mut self, texts: Vec<ArchivedText>) -> Result<()>;
+
+    /// Sorted owners of the archived texts containing any of the fragments
+    fn candidates(&self, fragments: &[FragmentHash]) -> Result<Vec<TextOwnerID>>;
+
+    /// Loads the archived text of an owner
+    fn load(&self, owner_id: &str) -> Result<ArchivedText>;
+}
+
+/// Contents of the `archive.json` file of an `FsArchive`
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    fragmentation: Fragmentation,
+    /// Owner of every text file, by file number
+    owners: Vec<TextOwnerID>,
+}
+
+/// An archive in a directory. Every text is a JSON file in `texts/`, and the
+///     index of which texts contain a fragment is split over up to 256 files
+///     in `index/` by the first byte of the fragment hash, so that looking up
+///     candidates only reads the index files of the fragments looked up.
+///     A replaced text stays indexed under its old fragments too, which only
+///     makes it a candidate more often.
+pub struct FsArchive {
+    dir: PathBuf,
+    manifest: Manifest,
+    /// File number of every owner
+    numbers: HashMap<TextOwnerID, usize>,
+}
+
+impl FsArchive {
+    /// Creates an empty archive in a directory, replacing an archive that
+    ///     was there before
+    pub fn create<P: AsRef<Path>>(dir: P, fragmentation: Fragmentation) -> Result<FsArchive> {
+        let dir = dir.as_ref().to_path_buf();
+        for subdir in ["texts", "index"] {
+            let path = dir.join(subdir);
+            if path.exists() {
+                fs::remove_dir_all(&path)?;
+            }
+            fs::create_dir_all(&path)?;
+        }
+        let archive = FsArchive {
+            dir,
+            manifest: Manifest {
+                fragmentation,
+                owners: Vec::new(),
+            },
+            numbers: HashMap::new(),
+        };
+        archive.save_manifest()?;
+        Ok(archive)
+    }
+
+    /// Opens an archive written by `create` and `store`
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<FsArchive> {
+        let dir = dir.as_ref().to_path_buf();
+        let file = File::open(dir.join("archive.json"))?;
+        let manifest: Manifest = serde_json::from_reader(BufReader::new(file))?;
+        let numbers = manifest
+            .owners
+            .iter()
+            .enumerate()
+            .map(|(number, owner)| (owner.clone(), number))
+            .collect();
+        Ok(FsArchive {
+            dir,
+            manifest,
+            numbers,
+        })
+    }
+
+    fn save_manifest(&self) -> Result<()> {
+        let file = File::create(self.dir.join("archive.json"))?;
+        serde_json::to_writer(BufWriter::new(file), &self.manifest)?;
+        Ok(())
+    }
+
+    fn text_path(&self, number: usize) -> PathBuf {
+        self.dir.join("texts").join(format!("{}.json", number))
+    }
+
+    fn shard_path(&self, shard: u8) -> PathBuf {
+        self.dir.join("index").join(format!("{:02x}.json", shard))
+    }
+
+    /// Reads one index file, mapping fragments to the numbers of the texts
+    ///     containing them
+    fn read_shard(&self, shard: u8) -> Result<HashMap<FragmentHash, Vec<usize>>> {
+        let path = self.shard_path(shard);
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+/// Index file a fragment is listed in
+fn shard(fragment: FragmentHash) -> u8 {
+    (fragment >> 56) as u8
+}
+
+impl ArchiveStore for FsArchive {
+    fn fragmentation(&self) -> &Fragmentation {
+        &self.manifest.fragmentation
+    }
+
+    fn store(&mut self, texts: Vec<ArchivedText>) -> Result<()> {
+        let mut additions: BTreeMap<u8, Vec<(FragmentHash, usize)>> = BTreeMap::new();
+        for text in texts {
+            let number = match self.numbers.get(text.owner()) {
+                Some(&number) => number,
+                None => {
+                    let number = self.manifest.owners.len();
+                    self.manifest.owners.push(text.owner().to_string());
+                    self.numbers.insert(text.owner().to_string(), number);
+                    number
+                }
+            };
+            let file = File::create(self.text_path(number))?;
+            serde_json::to_writer(BufWriter::new(file), &text)?;
+            for fragment in text.fragments() {
+                additions
+                    .entry(shard(fragment))
+                    .or_default()
+                    .push((fragment, number));
+            }
+        }
+        // Every index file is only rewritten once per call
+        for (shard, fragments) in additions {
+            let mut index = self.read_shard(shard)?;
+            for (fragment, number) in fragments {
+                let numbers = index.entry(fragment).or_default();
+                if !numbers.contains(&number) {
+                    numbers.push(number);
+                }
+            }
+            let file = File::create(self.shard_path(shard))?;
+            serde_json::to_writer(BufWriter::new(file), &index)?;
+        }
+        self.save_manifest()
+    }
+
+    fn candidates(&self, fragments: &[FragmentHash]) -> Result<Vec<TextOwnerID>> {
+        let mut by_shard: BTreeMap<u8, Vec<FragmentHash>> = BTreeMap::new();
+        for &fragment in fragments {
+            by_shard.entry(shard(fragment)).or_default().push(fragment);
+        }
+        let mut numbers: BTreeSet<usize> = BTreeSet::new();
+        for (shard, fragments) in by_shard {
+            let index = self.read_shard(shard)?;
+            for fragment in fragments {
+                numbers.extend(index.get(&fragment).into_iter().flatten());
+            }
+        }
+        let mut owners: Vec<TextOwnerID> = numbers
+            .into_iter()
+            .filter_map(|number| self.manifest.owners.get(number).cloned())
+            .collect();
+        owners.sort();
+        Ok(owners)
+    }
+
+    fn load(&self, owner_id: &str) -> Result<ArchivedText> {
+        let number = self
+            .numbers
+            .get(owner_id)
+            .copied()
+            .ok_or_else(|| Error::UnknownOwner {
+                owner_id: owner_id.to_string(),
+            })?;
+        let file = File::open(self.text_path(number))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_fs_archive() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text(
+            "cs101/book",
+            "the mitochondria is the powerhouse of the cell",
+        );
+        db.add_trusted_text("unrelated", "nothing in common with any essay");
+        let dir = std::env::temp_dir().join("plagiarismbasic_test_archive");
+        let mut archive = FsArchive::create(&dir, db.fragmentation()).unwrap();
+        db.archive_trusted_texts(&mut archive).unwrap();
+
+        let archive = FsArchive::open(&dir).unwrap();
+        let mut query = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        query.add_untrusted_text("essay", "we know the powerhouse of the cell well");
+        let results = query.check_against_archive(&archive).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id1, "cs101/book");
+        assert_eq!(results[0].matching_fragments.len(), 3);
+        assert!(archive.load("missing").is_err());
+
+        let wrong_n = PlagiarismDatabase::new(4, 0, Metric::Equal, Vec::new());
+        assert!(wrong_n.check_against_archive(&archive).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
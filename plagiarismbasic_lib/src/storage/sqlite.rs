@@ -0,0 +1,195 @@
+use crate::plagiarism_database::{PlagiarismDatabase, PlagiarismResult};
+use rusqlite::{params, Connection, Result};
+use std::path::Path;
+
+/// Schema changes, applied in order. `PRAGMA user_version` holds the number
+///     of migrations a database file has seen, so new ones can be appended
+///     without breaking files written by older versions.
+const MIGRATIONS: [&str; 4] = [
+    "
+    CREATE TABLE runs (
+        id INTEGER PRIMARY KEY,
+        label TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE owners (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        owner_id TEXT NOT NULL,
+        trusted INTEGER NOT NULL,
+        language TEXT,
+        PRIMARY KEY (run_id, owner_id, trusted)
+    );
+    CREATE TABLE results (
+        id INTEGER PRIMARY KEY,
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        owner_id1 TEXT NOT NULL,
+        trusted_owner1 INTEGER NOT NULL,
+        owner_id2 TEXT NOT NULL,
+        trusted_owner2 INTEGER NOT NULL,
+        equal_fragments INTEGER NOT NULL
+    );
+    CREATE TABLE fragments (
+        result_id INTEGER NOT NULL REFERENCES results(id),
+        fragment1 TEXT NOT NULL,
+        fragment2 TEXT NOT NULL,
+        locations1 TEXT NOT NULL,
+        locations2 TEXT NOT NULL
+    );
+    CREATE INDEX results_by_owner1 ON results (owner_id1);
+    CREATE INDEX results_by_owner2 ON results (owner_id2);
+    ",
+    // Ngram size of each result
+    "ALTER TABLE results ADD COLUMN n INTEGER;",
+    // Metadata of each owner, as a JSON object
+    "ALTER TABLE owners ADD COLUMN metadata TEXT;",
+    // Fraction of each owner's fragments that match
+    "
+    ALTER TABLE results ADD COLUMN coverage_owner1 REAL;
+    ALTER TABLE results ADD COLUMN coverage_owner2 REAL;
+    ",
+];
+
+/// An SQLite database that keeps the owners and results of many runs (e.g.
+///     one per assignment and semester), to be queried with plain SQL.
+///     Fragment locations are stored as JSON arrays of [start, end] word ranges.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    /// Opens (or creates) a database file and brings its schema up to date
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteStore> {
+        SqliteStore::from_connection(Connection::open(path)?)
+    }
+
+    /// A database that only lives in memory, mostly for tests
+    pub fn open_in_memory() -> Result<SqliteStore> {
+        SqliteStore::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<SqliteStore> {
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let tx = conn.transaction()?;
+        for migration in MIGRATIONS.iter().skip(version) {
+            tx.execute_batch(migration)?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+        tx.commit()?;
+        Ok(SqliteStore { conn })
+    }
+
+    /// The underlying connection, to run queries on
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Writes all owners of the database and the results of a check on it
+    ///     as a new run, returning the ID of the run
+    pub fn record_run(
+        &mut self,
+        label: &str,
+        db: &PlagiarismDatabase,
+        results: &[PlagiarismResult],
+    ) -> Result<i64> {
+        let tx = self.conn.transaction()?;
+        tx.execute("INSERT INTO runs (label) VALUES (?1)", params![label])?;
+        let run_id = tx.last_insert_rowid();
+        for trusted in [true, false] {
+            for owner in db.get_owners(trusted) {
+                tx.execute(
+                    "INSERT INTO owners (run_id, owner_id, trusted, language, metadata)
+                    VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        run_id,
+                        owner,
+                        trusted,
+                        db.get_language(owner),
+                        serde_json::to_string(&db.get_metadata(owner, trusted))
+                            .expect("Metadata is always valid JSON")
+                    ],
+                )?;
+            }
+        }
+        for result in results {
+            tx.execute(
+                "INSERT INTO results (run_id, owner_id1, trusted_owner1, owner_id2,
+                    trusted_owner2, equal_fragments, n, coverage_owner1, coverage_owner2)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    run_id,
+                    result.owner_id1,
+                    result.trusted_owner1,
+                    result.owner_id2,
+                    result.trusted_owner2,
+                    result.equal_fragments,
+                    result.n as i64,
+                    result.coverage_owner1,
+                    result.coverage_owner2
+                ],
+            )?;
+            let result_id = tx.last_insert_rowid();
+            for ((fragment1, fragment2), (locations1, locations2)) in result
+                .matching_fragments
+                .iter()
+                .zip(&result.matching_fragments_locations)
+            {
+                tx.execute(
+                    "INSERT INTO fragments (result_id, fragment1, fragment2, locations1, locations2)
+                    VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        result_id,
+                        fragment1,
+                        fragment2,
+                        serde_json::to_string(locations1).expect("Locations are always valid JSON"),
+                        serde_json::to_string(locations2).expect("Locations are always valid JSON")
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(run_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Metric;
+
+    #[test]
+    fn test_record_run() {
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("source", "the mitochondria is the powerhouse of the cell");
+        db.add_untrusted_text("essay", "we know the powerhouse of the cell well");
+        db.add_untrusted_text("other", "nothing to see here at all");
+        let results = db.check_trusted_plagiarism();
+
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        let first = store.record_run("fall", &db, &results).unwrap();
+        let second = store.record_run("spring", &db, &results).unwrap();
+        assert_ne!(first, second);
+        let conn = store.connection();
+        let owners: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM owners WHERE run_id = ?1",
+                [first],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(owners, 3);
+        let fragments: Vec<String> = conn
+            .prepare(
+                "SELECT fragment1 FROM fragments JOIN results ON results.id = result_id
+                WHERE run_id = ?1 AND owner_id2 = 'essay' ORDER BY fragment1",
+            )
+            .unwrap()
+            .query_map([second], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            fragments,
+            vec!["of the cell", "powerhouse of the", "the powerhouse of"]
+        );
+    }
+}
@@ -1,10 +1,25 @@
 use crate::Metric;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use strsim::levenshtein;
 
 pub fn is_plagiarised(s1: &str, s2: &str, metric: Metric, cutoff: usize) -> bool {
     match metric {
         Metric::Equal => check_equal(s1, s2),
         Metric::Lev => check_lev(s1, s2, cutoff),
+        Metric::SimHash => check_simhash(simhash(s1), simhash(s2), cutoff),
+        // Needs the word vectors of the database, see `check_plagiarism_embedding`
+        Metric::Embedding => {
+            panic!("Embedding similarity cannot be computed from the strings alone!")
+        }
+        // Compares whole texts, see `check_plagiarism_lcs`
+        Metric::Lcs => panic!("The LCS metric does not compare single fragments!"),
+        // Needs the metric of the database, see `check_plagiarism_other`
+        Metric::Custom => {
+            panic!("A custom metric cannot be computed without its SimilarityMetric!")
+        }
     }
 }
 
@@ -19,10 +34,345 @@ fn check_lev(s1: &str, s2: &str, cutoff: usize) -> bool {
     levenshtein(s1, s2) <= cutoff
 }
 
+/// A fragment preprocessed for Myers' bit-parallel Levenshtein distance,
+///     which computes 64 cells of the distance matrix per machine word
+///     instead of one, so that one source fragment is compared with many
+///     target fragments quickly. Fragments longer than 64 characters are
+///     split into blocks of 64.
+#[derive(Debug, Clone)]
+pub struct LevenshteinPattern {
+    /// Length of the pattern in characters
+    len: usize,
+    /// Number of 64-bit blocks
+    blocks: usize,
+    /// Bits of the positions of every ASCII character, `blocks` words each
+    ascii: Vec<u64>,
+    /// Bits of the positions of the other characters
+    other: HashMap<char, Vec<u64>>,
+}
+
+impl LevenshteinPattern {
+    pub fn new(pattern: &str) -> LevenshteinPattern {
+        let chars: Vec<char> = pattern.chars().collect();
+        let blocks = chars.len().div_ceil(64).max(1);
+        let mut ascii = vec![0u64; 128 * blocks];
+        let mut other: HashMap<char, Vec<u64>> = HashMap::new();
+        for (idx, &c) in chars.iter().enumerate() {
+            let bit = 1u64 << (idx % 64);
+            if c.is_ascii() {
+                ascii[c as usize * blocks + idx / 64] |= bit;
+            } else {
+                other.entry(c).or_insert_with(|| vec![0; blocks])[idx / 64] |= bit;
+            }
+        }
+        LevenshteinPattern {
+            len: chars.len(),
+            blocks,
+            ascii,
+            other,
+        }
+    }
+
+    /// Positions of a character in block `block` of the pattern
+    #[inline]
+    fn positions(&self, c: char, block: usize) -> u64 {
+        if c.is_ascii() {
+            self.ascii[c as usize * self.blocks + block]
+        } else {
+            self.other.get(&c).map_or(0, |positions| positions[block])
+        }
+    }
+
+    /// Levenshtein distance (in characters) of the pattern and a text
+    pub fn distance(&self, text: &str) -> usize {
+        self.distance_within(text, usize::MAX)
+            .expect("Every distance is within usize::MAX")
+    }
+
+    /// Levenshtein distance of the pattern and a text if it is at most
+    ///     `cutoff`, stopping as soon as it is certainly larger
+    pub fn distance_within(&self, text: &str, cutoff: usize) -> Option<usize> {
+        if self.len == 0 {
+            let len = text.chars().count();
+            return (len <= cutoff).then_some(len);
+        }
+        // Vertical deltas of every block, all +1 in the first column
+        let mut positive = vec![u64::MAX; self.blocks];
+        let mut negative = vec![0u64; self.blocks];
+        let last_bit = 1u64 << ((self.len - 1) % 64);
+        let mut score = self.len;
+        let mut remaining = text.chars().count();
+        for c in text.chars() {
+            // The distance grows by one per text character in the first row
+            let mut carry: i8 = 1;
+            for block in 0..self.blocks {
+                let high_bit = if block + 1 == self.blocks {
+                    last_bit
+                } else {
+                    1 << 63
+                };
+                carry = self.advance_block(
+                    &mut positive[block],
+                    &mut negative[block],
+                    self.positions(c, block),
+                    carry,
+                    high_bit,
+                );
+            }
+            score = score.wrapping_add_signed(carry as isize);
+            remaining -= 1;
+            // Every remaining character lowers the distance by one at most
+            if score.saturating_sub(remaining) > cutoff {
+                return None;
+            }
+        }
+        (score <= cutoff).then_some(score)
+    }
+
+    /// Advances the vertical deltas of one block by a text character with
+    ///     the horizontal delta entering its top, returning the horizontal
+    ///     delta leaving its bottom (Hyyrö's block-based Myers algorithm)
+    #[inline]
+    fn advance_block(
+        &self,
+        positive: &mut u64,
+        negative: &mut u64,
+        mut equal: u64,
+        carry: i8,
+        high_bit: u64,
+    ) -> i8 {
+        let (pv, mv) = (*positive, *negative);
+        let xv = equal | mv;
+        if carry < 0 {
+            equal |= 1;
+        }
+        let xh = ((equal & pv).wrapping_add(pv) ^ pv) | equal;
+        let mut ph = mv | !(xh | pv);
+        let mut mh = pv & xh;
+        let carry_out = if ph & high_bit != 0 {
+            1
+        } else if mh & high_bit != 0 {
+            -1
+        } else {
+            0
+        };
+        ph <<= 1;
+        mh <<= 1;
+        if carry > 0 {
+            ph |= 1;
+        } else if carry < 0 {
+            mh |= 1;
+        }
+        *positive = mh | !(xv | ph);
+        *negative = ph & xv;
+        carry_out
+    }
+}
+
+/// Levenshtein distances of one source fragment to every target fragment,
+///     with the source preprocessed once (see `LevenshteinPattern`)
+pub fn levenshtein_batch<S: AsRef<str>>(source: &str, targets: &[S]) -> Vec<usize> {
+    let pattern = LevenshteinPattern::new(source);
+    targets
+        .iter()
+        .map(|target| pattern.distance(target.as_ref()))
+        .collect()
+}
+
+/// Computes the 64-bit SimHash of a string from its character trigrams,
+///     so that similar strings get hashes with a small Hamming distance
+pub fn simhash(s: &str) -> u64 {
+    let chars: Vec<char> = s.chars().collect();
+    // Strings shorter than a trigram are a single feature
+    let features: Vec<&[char]> = if chars.len() < 3 {
+        vec![&chars[..]]
+    } else {
+        chars.windows(3).collect()
+    };
+    let mut weights = [0i32; 64];
+    for feature in features {
+        let mut hasher = DefaultHasher::new();
+        feature.hash(&mut hasher);
+        let hash = hasher.finish();
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash >> bit & 1 == 1 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, &weight)| weight > 0)
+        .fold(0, |hash, (bit, _)| hash | 1 << bit)
+}
+
+/// Checks the Hamming distance between two SimHashes against cutoff
+pub fn check_simhash(hash1: u64, hash2: u64, cutoff: usize) -> bool {
+    (hash1 ^ hash2).count_ones() as usize <= cutoff
+}
+
+/// Index pairs of a longest common subsequence of two word lists, in order.
+///     Takes time and memory proportional to the product of the lengths.
+pub fn longest_common_subsequence(words1: &[&str], words2: &[&str]) -> Vec<(usize, usize)> {
+    let width = words2.len() + 1;
+    // lengths[i * width + j] is the LCS length of words1[i..] and words2[j..]
+    let mut lengths = vec![0u32; (words1.len() + 1) * width];
+    for i in (0..words1.len()).rev() {
+        for j in (0..words2.len()).rev() {
+            lengths[i * width + j] = if words1[i] == words2[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < words1.len() && j < words2.len() {
+        if words1[i] == words2[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Splits the pairs of a common subsequence into spans wherever more than
+///     `max_gap` unmatched words lie between two matches in either text,
+///     keeping the spans with at least `min_length` matched words
+pub fn lcs_spans(
+    pairs: &[(usize, usize)],
+    max_gap: usize,
+    min_length: usize,
+) -> Vec<Vec<(usize, usize)>> {
+    let mut spans: Vec<Vec<(usize, usize)>> = Vec::new();
+    let mut span: Vec<(usize, usize)> = Vec::new();
+    for &(i, j) in pairs {
+        if let Some(&(last_i, last_j)) = span.last() {
+            if i - last_i - 1 > max_gap || j - last_j - 1 > max_gap {
+                spans.push(std::mem::take(&mut span));
+            }
+        }
+        span.push((i, j));
+    }
+    spans.push(span);
+    spans.retain(|span| !span.is_empty() && span.len() >= min_length);
+    spans
+}
+
+/// How a word of the first text turns into its aligned word of the second
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditOp {
+    Keep,
+    Substitute,
+    /// Only in the second text
+    Insert,
+    /// Only in the first text
+    Delete,
+}
+
+/// One step of a word alignment, with the indexes of the aligned words
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct AlignedWord {
+    pub op: EditOp,
+    pub word1: Option<usize>,
+    pub word2: Option<usize>,
+}
+
+/// Aligns two word lists with the fewest word edits (word-level Levenshtein),
+///     preferring substitutions over an insertion plus a deletion
+pub fn align_words<S: AsRef<str>>(words1: &[S], words2: &[S]) -> Vec<AlignedWord> {
+    let width = words2.len() + 1;
+    // costs[i * width + j] is the edit distance of words1[i..] and words2[j..]
+    let mut costs = vec![0u32; (words1.len() + 1) * width];
+    for i in (0..=words1.len()).rev() {
+        for j in (0..=words2.len()).rev() {
+            costs[i * width + j] = if i == words1.len() {
+                (words2.len() - j) as u32
+            } else if j == words2.len() {
+                (words1.len() - i) as u32
+            } else {
+                let keep = u32::from(words1[i].as_ref() != words2[j].as_ref());
+                (costs[(i + 1) * width + j + 1] + keep)
+                    .min(costs[(i + 1) * width + j] + 1)
+                    .min(costs[i * width + j + 1] + 1)
+            };
+        }
+    }
+    let mut alignment = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < words1.len() || j < words2.len() {
+        let cost = costs[i * width + j];
+        if i < words1.len() && j < words2.len() {
+            let equal = words1[i].as_ref() == words2[j].as_ref();
+            if cost == costs[(i + 1) * width + j + 1] + u32::from(!equal) {
+                let op = if equal {
+                    EditOp::Keep
+                } else {
+                    EditOp::Substitute
+                };
+                alignment.push(AlignedWord {
+                    op,
+                    word1: Some(i),
+                    word2: Some(j),
+                });
+                i += 1;
+                j += 1;
+                continue;
+            }
+        }
+        if i < words1.len() && (j == words2.len() || cost == costs[(i + 1) * width + j] + 1) {
+            alignment.push(AlignedWord {
+                op: EditOp::Delete,
+                word1: Some(i),
+                word2: None,
+            });
+            i += 1;
+        } else {
+            alignment.push(AlignedWord {
+                op: EditOp::Insert,
+                word1: None,
+                word2: Some(j),
+            });
+            j += 1;
+        }
+    }
+    alignment
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_align_words() {
+        let ops: Vec<EditOp> = align_words(&["the", "big", "red", "dog"], &["the", "large", "dog"])
+            .into_iter()
+            .map(|aligned| aligned.op)
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                EditOp::Keep,
+                EditOp::Substitute,
+                EditOp::Delete,
+                EditOp::Keep
+            ]
+        );
+        let inserted = align_words(&["a"], &["a", "b"]);
+        assert_eq!(inserted[1].word1, None);
+        assert_eq!(inserted[1].word2, Some(1));
+    }
+
     #[test]
     fn test_equal() {
         assert!(is_plagiarised("a", "a", Metric::Equal, 0));
@@ -33,4 +383,54 @@ mod tests {
         assert!(is_plagiarised("abcd", "ac", Metric::Lev, 2));
         assert!(!is_plagiarised("abcd", "ac", Metric::Lev, 1));
     }
+
+    #[test]
+    fn test_levenshtein_batch() {
+        let long1 = "the quick brown fox jumps over the lazy dog ".repeat(4);
+        let long2 = "the quick brown cat jumps over a lazy dog ".repeat(4);
+        let targets = ["kitten", "sitting", "", "kitchen", "çitten", &long1, &long2];
+        for source in targets {
+            let distances = levenshtein_batch(source, &targets);
+            for (target, distance) in targets.iter().zip(distances) {
+                assert_eq!(
+                    distance,
+                    levenshtein(source, target),
+                    "{source:?} {target:?}"
+                );
+            }
+        }
+        let pattern = LevenshteinPattern::new(&long1);
+        assert_eq!(
+            pattern.distance_within(&long2, 100),
+            Some(levenshtein(&long1, &long2))
+        );
+        assert_eq!(pattern.distance_within(&long2, 3), None);
+    }
+
+    #[test]
+    fn test_simhash() {
+        let original = "the inheritance concept was invented in 1967 for simula";
+        let similar = "the inheritance concept was created in 1967 for simula";
+        let different = "a fruit is a generalization of apple orange and mango";
+        let distance = |a: &str, b: &str| (simhash(a) ^ simhash(b)).count_ones();
+        assert_eq!(distance(original, original), 0);
+        assert!(distance(original, similar) < distance(original, different));
+        assert!(distance(original, different) > 16);
+        assert!(is_plagiarised(original, original, Metric::SimHash, 0));
+        assert!(!is_plagiarised(original, different, Metric::SimHash, 8));
+    }
+
+    #[test]
+    fn test_lcs_spans() {
+        let words1: Vec<&str> = "a b c d e f x y z".split(' ').collect();
+        let words2: Vec<&str> = "q a b m c d e r s t x y z".split(' ').collect();
+        let pairs = longest_common_subsequence(&words1, &words2);
+        assert_eq!(pairs.len(), 8);
+        assert_eq!(pairs[0], (0, 1));
+        let spans = lcs_spans(&pairs, 1, 3);
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0], vec![(0, 1), (1, 2), (2, 4), (3, 5), (4, 6)]);
+        assert_eq!(spans[1], vec![(6, 10), (7, 11), (8, 12)]);
+        assert!(lcs_spans(&pairs, 1, 4).len() == 1);
+    }
 }
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Maps every word of a synonym set to one representative of the set, so that
+///     "big house" and "large home" become the same fragment. Applied to the
+///     cleaned words (before stemming) when texts are tokenized.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SynonymNormalizer {
+    /// Word -> representative of its synonym set
+    canonical: HashMap<String, String>,
+}
+
+impl SynonymNormalizer {
+    /// Builds the mapping from synonym sets. The first word of each set is its
+    ///     representative. A word in several sets keeps its first one.
+    pub fn from_sets<I, S>(sets: I) -> SynonymNormalizer
+    where
+        I: IntoIterator<Item = S>,
+        S: IntoIterator<Item = String>,
+    {
+        let mut canonical: HashMap<String, String> = HashMap::new();
+        for set in sets {
+            let mut words = set.into_iter().map(|word| word.trim().to_lowercase());
+            let representative = match words.next() {
+                Some(word) if !word.is_empty() => word,
+                _ => continue,
+            };
+            canonical
+                .entry(representative.clone())
+                .or_insert_with(|| representative.clone());
+            for word in words.filter(|word| !word.is_empty()) {
+                canonical
+                    .entry(word)
+                    .or_insert_with(|| representative.clone());
+            }
+        }
+        SynonymNormalizer { canonical }
+    }
+
+    /// Reads one comma-separated synonym set per line, e.g. "big, large, huge".
+    ///     Empty lines and lines starting with '#' are skipped.
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<SynonymNormalizer> {
+        let mut sets: Vec<Vec<String>> = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            sets.push(line.split(',').map(String::from).collect());
+        }
+        Ok(SynonymNormalizer::from_sets(sets))
+    }
+
+    /// Reads a synonym list file, see `from_reader`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<SynonymNormalizer> {
+        SynonymNormalizer::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// The representative of the word's synonym set, or the word itself.
+    ///     Words are looked up in lowercase.
+    pub fn normalize<'a>(&'a self, word: &'a str) -> &'a str {
+        self.canonical
+            .get(word)
+            .or_else(|| self.canonical.get(&word.to_lowercase()))
+            .map_or(word, String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metric, PlagiarismDatabase};
+
+    #[test]
+    fn test_synonym_normalizer() {
+        let synonyms = SynonymNormalizer::from_reader(
+            "# sizes\nbig, large, huge\n\nhouse, home\nlarge, great\n".as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(synonyms.normalize("huge"), "big");
+        assert_eq!(synonyms.normalize("Home"), "house");
+        assert_eq!(synonyms.normalize("large"), "big");
+        assert_eq!(synonyms.normalize("great"), "large");
+        assert_eq!(synonyms.normalize("garden"), "garden");
+
+        let mut db = PlagiarismDatabase::builder(2, 0, Metric::Equal)
+            .synonyms(synonyms)
+            .build();
+        db.add_trusted_text("t", "a big house");
+        db.add_untrusted_text("u", "the large home");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(
+            results[0].matching_fragments,
+            vec![("big house".to_string(), "big house".to_string())]
+        );
+    }
+}
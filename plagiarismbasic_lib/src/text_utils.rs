@@ -1,48 +1,843 @@
+use crate::cjk::{is_cjk, CjkSegmenter};
 use crate::result_output_html::TextMaybeBold;
+use crate::synonyms::SynonymNormalizer;
 use gcollections::ops::*;
 use interval::interval_set::*;
 use lazy_static::lazy_static;
 use regex::Regex;
+use rust_stemmers::{Algorithm, Stemmer};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Unicode normalization form applied to text before it is split into words
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Normalization {
+    /// Canonical composition only
+    Nfc,
+    /// Compatibility composition, also folds ligatures, full-width forms, etc.
+    Nfkc,
+}
+
+/// Options controlling how raw text is turned into clean words
+#[derive(Clone, Debug)]
+pub struct CleanOptions {
+    /// Normalization form to apply before word segmentation
+    pub normalization: Normalization,
+    /// Removes combining marks so that e.g. "café" and "cafe" are the same word
+    pub strip_diacritics: bool,
+    /// Words inside double quotation marks are not used to build fragments
+    pub exclude_quotes: bool,
+    /// Citation markers like "(Smith, 2020)" or "[3]" are not used to build fragments
+    pub exclude_citations: bool,
+    /// Keeps upper case letters instead of converting everything to lowercase
+    pub preserve_case: bool,
+    /// Keeps digits in words, otherwise they are removed
+    pub keep_numbers: bool,
+    /// Keeps punctuation inside words (apostrophes, periods in abbreviations,
+    ///     hyphens of joined words), otherwise it is removed
+    pub keep_punctuation: bool,
+    /// Treats "well-known" as two words instead of one
+    pub split_hyphens: bool,
+    /// Words left out of the text entirely, compared against the cleaned
+    ///     (e.g. lowercased) words
+    pub stopwords: HashSet<String>,
+    /// Reduces every word to its stem with this Snowball algorithm, so that
+    ///     e.g. "connected" and "connecting" are the same word
+    pub stemmer: Option<Algorithm>,
+    /// Replaces words by the representative of their synonym set (before stemming)
+    pub synonyms: Option<Arc<SynonymNormalizer>>,
+    /// Regions of the original text matching any of these are dropped before
+    ///     tokenization, e.g. `(?s)^References\b.*` for a bibliography
+    pub exclude_patterns: Vec<Regex>,
+    /// Replaces every number (e.g. "3.14" or "1,000") with `<NUM>`, so that
+    ///     lab reports differing only in measured values still match
+    pub normalize_numbers: bool,
+    /// Replaces every date (e.g. "2020-01-31", "31/01/2020" or "January 31,
+    ///     2020") with one `<DATE>` word
+    pub normalize_dates: bool,
+    /// Replaces every run of capitalized words that doesn't start a sentence
+    ///     (usually a name) with one `<ENT>` word, so that changing the names
+    ///     in a copied text doesn't hide it
+    pub normalize_entities: bool,
+    /// Drops invisible characters (e.g. zero-width spaces) and, in texts
+    ///     written mostly in Latin letters, replaces Cyrillic and Greek
+    ///     letters that look like Latin ones, which are used to hide copies
+    pub fold_confusables: bool,
+    /// Splits runs of Chinese and Japanese characters into words with this
+    ///     segmenter instead of making every character a word
+    pub cjk_segmenter: Option<Arc<CjkSegmenter>>,
+}
+
+/// Words that numbers, dates and named entities are replaced with
+pub const NUMBER_TOKEN: &str = "<NUM>";
+pub const DATE_TOKEN: &str = "<DATE>";
+pub const ENTITY_TOKEN: &str = "<ENT>";
+
+impl Default for CleanOptions {
+    fn default() -> CleanOptions {
+        CleanOptions {
+            normalization: Normalization::Nfkc,
+            strip_diacritics: false,
+            exclude_quotes: false,
+            exclude_citations: false,
+            preserve_case: false,
+            keep_numbers: true,
+            keep_punctuation: false,
+            split_hyphens: true,
+            stopwords: HashSet::new(),
+            stemmer: None,
+            synonyms: None,
+            exclude_patterns: Vec::new(),
+            normalize_numbers: false,
+            normalize_dates: false,
+            normalize_entities: false,
+            fold_confusables: true,
+            cjk_segmenter: None,
+        }
+    }
+}
+
+/// Clean words of a text, along with the word ranges that should not
+///     take part in plagiarism checks
+#[derive(Debug, PartialEq)]
+pub struct CleanedText {
+    /// Cleaned words, in the order they appear in the text
+    pub words: Vec<String>,
+    /// Sorted, non-overlapping (start index, end index) word ranges, both inclusive
+    pub excluded: Vec<(usize, usize)>,
+    /// Sorted indices of the words that start a sentence. Empty if the
+    ///     text isn't split into sentences (e.g. in code mode).
+    pub sentence_starts: Vec<usize>,
+    /// (start, end (exclusive)) byte range of each word in the original text
+    pub byte_ranges: Vec<(usize, usize)>,
+    /// Sorted (start, end (exclusive)) byte ranges of the original text that
+    ///     matched an exclusion pattern and were dropped
+    pub stripped: Vec<(usize, usize)>,
+    /// Number of invisible or look-alike characters dropped or replaced by
+    ///     `CleanOptions::fold_confusables`
+    pub suspicious_chars: usize,
+}
+
+impl CleanedText {
+    /// Checks if any word between start and end (both inclusive) is excluded
+    pub fn is_excluded(&self, start: usize, end: usize) -> bool {
+        self.excluded
+            .iter()
+            .any(|&(ex_start, ex_end)| ex_start <= end && start <= ex_end)
+    }
+
+    /// Checks if a sentence starts after start and up to end (inclusive), so
+    ///     that the words between them aren't all in one sentence
+    pub fn crosses_sentence(&self, start: usize, end: usize) -> bool {
+        let next = self.sentence_starts.partition_point(|&idx| idx <= start);
+        self.sentence_starts
+            .get(next)
+            .is_some_and(|&idx| idx <= end)
+    }
+
+    /// (start index, end index) word ranges of all sentences, both inclusive.
+    ///     The whole text is one sentence if it hasn't been split.
+    pub fn sentences(&self) -> Vec<(usize, usize)> {
+        if self.words.is_empty() {
+            return Vec::new();
+        }
+        let mut starts = self.sentence_starts.clone();
+        if starts.first() != Some(&0) {
+            starts.insert(0, 0);
+        }
+        let ends = starts
+            .iter()
+            .skip(1)
+            .map(|start| start - 1)
+            .chain(std::iter::once(self.words.len() - 1));
+        starts.iter().copied().zip(ends).collect()
+    }
+}
+
+/// Hash of a fragment's text, stored instead of the text itself
+pub type FragmentHash = u64;
+
+/// Hasher of the maps and sets keyed by fragment hashes: ahash with the
+///     `fast-hash` feature (on by default), else the standard library hasher,
+///     both with fixed keys, so that iterating them is deterministic between
+///     runs and no random source is needed on wasm32
+#[cfg(feature = "fast-hash")]
+pub type FragmentBuildHasher = ahash::RandomState;
+#[cfg(not(feature = "fast-hash"))]
+pub type FragmentBuildHasher =
+    std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+
+/// Map keyed by fragment hashes, see `FragmentBuildHasher`
+pub type FragmentMap<V> = HashMap<FragmentHash, V, FragmentBuildHasher>;
+
+/// Set of fragment hashes, see `FragmentBuildHasher`
+pub type FragmentSet = HashSet<FragmentHash, FragmentBuildHasher>;
+
+/// Version of the way fragments are hashed, saved with fragments written
+///     to files so that hashes of an older version are never compared
+pub const HASH_VERSION: u32 = 3;
+
+/// Multiplier of the polynomial rolling hash over word hashes
+const ROLLING_BASE: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Spreads the bits of a rolling hash
+fn mix(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^ (hash >> 33)
+}
+
+/// Hashes a word with 64-bit xxh3. Unlike the standard library hasher, it is
+///     guaranteed to stay the same between builds, so hashes can be saved in
+///     index files.
+pub fn hash_word(word: &str) -> u64 {
+    xxhash_rust::xxh3::xxh3_64(word.as_bytes())
+}
+
+/// Hashes a fragment from the hashes of its words, in order
+pub fn hash_word_hashes<I: IntoIterator<Item = u64>>(word_hashes: I) -> FragmentHash {
+    mix(word_hashes.into_iter().fold(0, |hash: u64, word| {
+        hash.wrapping_mul(ROLLING_BASE).wrapping_add(word)
+    }))
+}
+
+/// Hashes a fragment's text (words separated by single spaces). Equal to the
+///     hashes of `rolling_ngram_hashes` for the same words.
+pub fn hash_fragment(fragment: &str) -> FragmentHash {
+    hash_word_hashes(fragment.split(' ').map(hash_word))
+}
+
+/// Hashes every ngram of n consecutive words, by start word (Rabin-Karp):
+///     each hash is updated from the previous one by adding the next word and
+///     removing the first, so no ngram text is ever built
+pub fn rolling_ngram_hashes(word_hashes: &[u64], n: usize) -> Vec<FragmentHash> {
+    if n == 0 || n > word_hashes.len() {
+        return Vec::new();
+    }
+    // Factor of the word leaving the window
+    let first_factor = (1..n).fold(1u64, |factor, _| factor.wrapping_mul(ROLLING_BASE));
+    let mut hash = word_hashes[..n].iter().fold(0u64, |hash, &word| {
+        hash.wrapping_mul(ROLLING_BASE).wrapping_add(word)
+    });
+    let mut hashes = Vec::with_capacity(word_hashes.len() - n + 1);
+    hashes.push(mix(hash));
+    for start in 1..=word_hashes.len() - n {
+        hash = hash
+            .wrapping_sub(word_hashes[start - 1].wrapping_mul(first_factor))
+            .wrapping_mul(ROLLING_BASE)
+            .wrapping_add(word_hashes[start + n - 1]);
+        hashes.push(mix(hash));
+    }
+    hashes
+}
+
+/// Words that are usually followed by a period without ending the sentence
+const ABBREVIATIONS: &[&str] = &[
+    "al", "approx", "ca", "cf", "co", "corp", "dept", "dr", "e.g", "eg", "esp", "est", "etc",
+    "fig", "figs", "i.e", "ie", "inc", "jr", "ltd", "mr", "mrs", "ms", "no", "nos", "p", "pp",
+    "prof", "sr", "st", "vol", "vols", "vs",
+];
 
 /// Extracts lists of consecutive words of list length n from the provided text.
 ///     Cleans the text first.
-pub fn extract_clean_word_ngrams(words: &Vec<String>, n: usize) -> Vec<String> {
+pub fn extract_clean_word_ngrams(words: &[String], n: usize) -> Vec<String> {
+    extract_clean_word_skipgrams(words, n, 0)
+        .into_iter()
+        .map(|(_, ngram)| ngram)
+        .collect()
+}
+
+/// Extracts the k-skip-n-grams of the words: n words in their order, skipping
+///     at most k words in between, so that a filler word inserted into a copied
+///     sentence doesn't break every ngram around it. Returns the index of each
+///     chosen word along with the ngram, by start word and with the fewest
+///     skips first. With k = 0 these are the ngrams of consecutive words.
+pub fn extract_clean_word_skipgrams(
+    words: &[String],
+    n: usize,
+    k: usize,
+) -> Vec<(Vec<usize>, String)> {
+    skipgram_indices(words.len(), n, k)
+        .into_iter()
+        .map(|indices| {
+            let ngram: Vec<&str> = indices.iter().map(|&idx| words[idx].as_str()).collect();
+            let ngram = ngram.join(" ");
+            (indices, ngram)
+        })
+        .collect()
+}
+
+/// The word indices of every k-skip-n-gram of a text of `len` words, in the
+///     order of `extract_clean_word_skipgrams`
+pub fn skipgram_indices(len: usize, n: usize, k: usize) -> Vec<Vec<usize>> {
     let mut output = Vec::new();
     // No way to find plagiarism if chunk size > # words
-    if n > words.len() {
-        return Vec::new();
+    if n == 0 || n > len {
+        return output;
     }
-
-    // Inclusive iterator between these two values
-    for i in 0..=(words.len() - n) {
-        let mut ngram = Vec::new();
-        for j in 0..n {
-            ngram.push(words[i + j].to_string());
-        }
-        output.push(ngram.join(" "));
+    for start in 0..=(len - n) {
+        extend_skipgram(len, n, k, &mut vec![start], &mut output);
     }
     output
 }
 
-/// Removes nonalphanumeric characters, redundant spaces, newlines,
-///     converts to lowecase and trims text
+/// Adds every way to complete a skip-gram of the chosen word indices
+fn extend_skipgram(
+    len: usize,
+    n: usize,
+    k: usize,
+    chosen: &mut Vec<usize>,
+    output: &mut Vec<Vec<usize>>,
+) {
+    if chosen.len() == n {
+        output.push(chosen.clone());
+        return;
+    }
+    let last = chosen[chosen.len() - 1];
+    let skipped = last + 1 - chosen[0] - chosen.len();
+    for next in (last + 1..=last + 1 + k - skipped).take_while(|&next| next < len) {
+        chosen.push(next);
+        extend_skipgram(len, n, k, chosen, output);
+        chosen.pop();
+    }
+}
+
+/// Splits every text added to a database into the words that are compared,
+///     see `tokenizer` on the builder. Users can plug in their own, e.g. to
+///     keep legal citations or chemical formulas as single words.
+pub trait Tokenizer: Send + Sync {
+    /// Splits a text into words, with the byte range of every word in the
+    ///     text. The options are the cleaning settings of the database (with
+    ///     the stopwords and stemmer of the text's language, if detected),
+    ///     which a tokenizer may ignore.
+    fn tokenize(&self, text: &str, options: &CleanOptions) -> CleanedText;
+}
+
+/// The tokenizer of a database unless another one is given, see `tokenize_text`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultTokenizer;
+
+impl Tokenizer for DefaultTokenizer {
+    fn tokenize(&self, text: &str, options: &CleanOptions) -> CleanedText {
+        tokenize_text(text, options)
+    }
+}
+
+/// The tokenizer of a database, shared by its clones and threads
+#[derive(Clone)]
+pub(crate) struct SharedTokenizer(pub(crate) Arc<dyn Tokenizer>);
+
+impl fmt::Debug for SharedTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tokenizer")
+    }
+}
+
+/// Splits text into lowercase words using the default cleaning options
 pub fn clean_text(text: &str) -> Vec<String> {
+    clean_text_with_options(text, &CleanOptions::default())
+}
+
+/// Normalizes the text, splits it on Unicode word boundaries, removes
+///     non-alphanumeric characters from each word and converts to lowercase
+///     (subject to the options)
+pub fn clean_text_with_options(text: &str, options: &CleanOptions) -> Vec<String> {
+    tokenize_text(text, options).words
+}
+
+/// Same as `clean_text_with_options`, but also finds the word ranges that are
+///     quoted or cited (if enabled in the options)
+pub fn tokenize_text(text: &str, options: &CleanOptions) -> CleanedText {
+    let stripped = find_pattern_ranges(text, &options.exclude_patterns);
+    // Normalize one grapheme at a time to remember where each one came from,
+    //     as (byte offset in normalized, byte offset in text) pairs
+    let mut normalized = String::with_capacity(text.len());
+    let mut origins: Vec<(usize, usize)> = Vec::new();
+    let fold_letters = options.fold_confusables && is_mostly_latin(text);
+    let mut suspicious_chars = 0;
+    for (offset, grapheme) in text.grapheme_indices(true) {
+        origins.push((normalized.len(), offset));
+        let mut grapheme: String = match options.normalization {
+            Normalization::Nfc => grapheme.nfc().collect(),
+            Normalization::Nfkc => grapheme.nfkc().collect(),
+        };
+        if options.fold_confusables {
+            let folded: String = grapheme
+                .chars()
+                .filter(|&c| !is_invisible(c))
+                .map(|c| match latin_lookalike(c) {
+                    Some(latin) if fold_letters => latin,
+                    _ => c,
+                })
+                .collect();
+            suspicious_chars += grapheme
+                .chars()
+                .filter(|&c| is_invisible(c) || fold_letters && latin_lookalike(c).is_some())
+                .count();
+            grapheme = folded;
+        }
+        if options.strip_diacritics {
+            normalized.push_str(&strip_diacritics(&grapheme));
+        } else {
+            normalized.push_str(&grapheme);
+        }
+    }
+    // Maps byte ranges of normalized back to the graphemes of text they cover
+    let original_range = |start: usize, end: usize| -> (usize, usize) {
+        let first = origins.partition_point(|&(norm, _)| norm <= start) - 1;
+        let after = origins.partition_point(|&(norm, _)| norm < end);
+        let original_end = origins.get(after).map_or(text.len(), |&(_, orig)| orig);
+        (origins[first].1, original_end)
+    };
+
+    // Byte ranges of the normalized text whose words should be excluded
+    let mut excluded_bytes: Vec<(usize, usize)> = Vec::new();
+    if options.exclude_quotes {
+        excluded_bytes.extend(find_quoted_ranges(&normalized));
+    }
+    if options.exclude_citations {
+        excluded_bytes.extend(find_citation_ranges(&normalized));
+    }
+    let dates = if options.normalize_dates {
+        find_date_ranges(&normalized)
+    } else {
+        Vec::new()
+    };
+
+    let mut words: Vec<String> = Vec::new();
+    let mut excluded: Vec<(usize, usize)> = Vec::new();
+    let mut sentence_starts: Vec<usize> = Vec::new();
+    let mut byte_ranges: Vec<(usize, usize)> = Vec::new();
+    // Byte index just after the last word that was kept, and that word
+    let mut last_word_end: Option<usize> = None;
+    let mut last_raw_word: &str = "";
+    let stemmer = options.stemmer.map(Stemmer::create);
+    // Date range that the last kept word is part of
+    let mut last_date: Option<usize> = None;
+    for (offset, raw_word) in segment_words(&normalized, options) {
+        let date = dates
+            .iter()
+            .position(|&(start, end)| start <= offset && offset < end);
+        let placeholder = if date.is_some() {
+            Some(DATE_TOKEN)
+        } else if options.normalize_numbers && is_number(raw_word) {
+            Some(NUMBER_TOKEN)
+        } else {
+            None
+        };
+        let mut word = match placeholder {
+            Some(placeholder) => placeholder.to_string(),
+            None => clean_word(raw_word, options),
+        };
+        if placeholder.is_none() {
+            if word.is_empty() || options.stopwords.contains(&word) {
+                continue;
+            }
+            if let Some(synonyms) = &options.synonyms {
+                word = synonyms.normalize(&word).to_string();
+            }
+            if let Some(stemmer) = &stemmer {
+                word = stemmer.stem(&word).into_owned();
+            }
+        }
+        // CJK bigrams overlap the previous word
+        let gap = last_word_end.map(|end| &normalized[end.min(offset)..offset]);
+        let follows_hyphen =
+            gap.is_some_and(|gap| gap.chars().count() == 1 && gap.chars().all(is_hyphen));
+        let starts_sentence = gap.is_none_or(|gap| ends_sentence(last_raw_word, gap));
+        if options.normalize_entities
+            && placeholder.is_none()
+            && !starts_sentence
+            && is_capitalized(raw_word)
+        {
+            word = ENTITY_TOKEN.to_string();
+        }
+        last_word_end = Some(offset + raw_word.len());
+        last_raw_word = raw_word;
+        let (start, end) = original_range(offset, offset + raw_word.len());
+        if stripped
+            .iter()
+            .any(|&(strip_start, strip_end)| strip_start <= start && start < strip_end)
+        {
+            continue;
+        }
+        // The words of one date, and of a run of capitalized words separated
+        //     only by spaces, become one word
+        let previous_date = std::mem::replace(&mut last_date, date);
+        let continues_date = date.is_some() && date == previous_date;
+        let continues_entity = word == ENTITY_TOKEN
+            && words
+                .last()
+                .is_some_and(|previous| previous == ENTITY_TOKEN)
+            && gap.is_some_and(|gap| gap.chars().all(char::is_whitespace));
+        if continues_date || continues_entity {
+            if let Some(range) = byte_ranges.last_mut() {
+                range.1 = end;
+            }
+            continue;
+        }
+        if !options.split_hyphens && follows_hyphen {
+            if let Some(previous) = words.last_mut() {
+                if options.keep_punctuation {
+                    previous.push('-');
+                }
+                previous.push_str(&word);
+                if let Some(range) = byte_ranges.last_mut() {
+                    range.1 = end;
+                }
+                continue;
+            }
+        }
+        let idx = words.len();
+        if starts_sentence {
+            sentence_starts.push(idx);
+        }
+        words.push(word);
+        byte_ranges.push((start, end));
+        if excluded_bytes
+            .iter()
+            .any(|&(start, end)| start <= offset && offset < end)
+        {
+            // Extend the previous range if this word directly follows it
+            match excluded.last_mut() {
+                Some((_, end)) if *end + 1 == idx => *end = idx,
+                _ => excluded.push((idx, idx)),
+            }
+        }
+    }
+    CleanedText {
+        words,
+        excluded,
+        sentence_starts,
+        byte_ranges,
+        stripped,
+        suspicious_chars,
+    }
+}
+
+/// Splits a text on Unicode word boundaries, with the byte offset of every
+///     word, and the runs of CJK characters with the CJK segmenter if set
+fn segment_words<'a>(text: &'a str, options: &CleanOptions) -> Vec<(usize, &'a str)> {
+    let segmenter = match &options.cjk_segmenter {
+        Some(segmenter) => segmenter,
+        None => return text.unicode_word_indices().collect(),
+    };
+    let mut words: Vec<(usize, &str)> = Vec::new();
+    // Byte range of the run of CJK words being collected
+    let mut run: Option<(usize, usize)> = None;
+    let flush = |run: &mut Option<(usize, usize)>, words: &mut Vec<(usize, &'a str)>| {
+        if let Some((start, end)) = run.take() {
+            let segmented = segmenter.segment(&text[start..end]);
+            words.extend(
+                segmented
+                    .into_iter()
+                    .map(|(offset, word)| (start + offset, word)),
+            );
+        }
+    };
+    for (offset, word) in text.unicode_word_indices() {
+        if !word.chars().all(is_cjk) {
+            flush(&mut run, &mut words);
+            words.push((offset, word));
+            continue;
+        }
+        match &mut run {
+            Some((_, end)) if *end == offset => *end = offset + word.len(),
+            _ => {
+                flush(&mut run, &mut words);
+                run = Some((offset, offset + word.len()));
+            }
+        }
+    }
+    flush(&mut run, &mut words);
+    words
+}
+
+/// Checks for characters that take no space, like zero-width spaces,
+///     joiners, soft hyphens and direction marks
+fn is_invisible(c: char) -> bool {
+    matches!(c,
+        '\u{AD}' | '\u{34F}' | '\u{61C}' | '\u{115F}' | '\u{1160}' | '\u{17B4}' | '\u{17B5}'
+        | '\u{180E}' | '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}'
+        | '\u{2060}'..='\u{2064}' | '\u{2066}'..='\u{206F}' | '\u{3164}' | '\u{FEFF}'
+        | '\u{FFA0}')
+}
+
+/// The Latin letter that a Cyrillic or Greek letter looks like
+fn latin_lookalike(c: char) -> Option<char> {
+    let latin = match c {
+        // Cyrillic
+        'а' => 'a',
+        'е' => 'e',
+        'о' => 'o',
+        'р' => 'p',
+        'с' => 'c',
+        'у' => 'y',
+        'х' => 'x',
+        'і' => 'i',
+        'ј' => 'j',
+        'ѕ' => 's',
+        'ԁ' => 'd',
+        'һ' => 'h',
+        'ԛ' => 'q',
+        'ԝ' => 'w',
+        'ӏ' => 'l',
+        'А' => 'A',
+        'В' => 'B',
+        'Е' => 'E',
+        'К' => 'K',
+        'М' => 'M',
+        'Н' => 'H',
+        'О' => 'O',
+        'Р' => 'P',
+        'С' => 'C',
+        'Т' => 'T',
+        'Х' => 'X',
+        'У' => 'Y',
+        'І' => 'I',
+        'Ј' => 'J',
+        'Ѕ' => 'S',
+        // Greek
+        'ο' => 'o',
+        'ν' => 'v',
+        'ι' => 'i',
+        'ρ' => 'p',
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Ζ' => 'Z',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Υ' => 'Y',
+        'Χ' => 'X',
+        _ => return None,
+    };
+    Some(latin)
+}
+
+/// Checks if a text has more Latin letters than Cyrillic and Greek ones, so
+///     that look-alike letters in it are folded without mangling Russian or
+///     Greek texts
+fn is_mostly_latin(text: &str) -> bool {
+    let (mut latin, mut other) = (0usize, 0usize);
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        match c {
+            'A'..='Z' | 'a'..='z' | '\u{C0}'..='\u{24F}' => latin += 1,
+            '\u{370}'..='\u{3FF}' | '\u{400}'..='\u{52F}' => other += 1,
+            _ => {}
+        }
+    }
+    latin > other
+}
+
+/// Finds the sorted, merged (start, end) byte ranges matching any of the patterns
+fn find_pattern_ranges(text: &str, patterns: &[Regex]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = patterns
+        .iter()
+        .flat_map(|pattern| pattern.find_iter(text).map(|m| (m.start(), m.end())))
+        .filter(|(start, end)| start < end)
+        .collect();
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Checks if the text between two words ends a sentence: a blank line, or
+///     a terminator that doesn't just end an abbreviation or initial
+fn ends_sentence(previous_word: &str, gap: &str) -> bool {
+    if gap.chars().filter(|&c| c == '\n').count() >= 2 {
+        return true;
+    }
+    if gap
+        .chars()
+        .any(|c| matches!(c, '!' | '?' | '。' | '！' | '？'))
+    {
+        return true;
+    }
+    if !gap.contains('.') {
+        return false;
+    }
+    let previous_word = previous_word.to_lowercase();
+    let is_initial =
+        previous_word.chars().count() == 1 && previous_word.chars().all(char::is_alphabetic);
+    if is_initial {
+        return false;
+    }
+    // Abbreviations like "etc." can still end a sentence with an ellipsis
+    !ABBREVIATIONS.contains(&previous_word.as_str()) || gap.trim() != "."
+}
+
+/// Finds the (start, end) byte ranges of text enclosed in double quotation marks.
+///     Quotes that are never closed are ignored rather than excluding the rest of the text.
+fn find_quoted_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    // Opening quote character and the byte index right after it
+    let mut open: Option<(char, usize)> = None;
+    for (idx, c) in text.char_indices() {
+        match open {
+            None => {
+                if matches!(c, '"' | '“' | '„' | '«') {
+                    open = Some((c, idx + c.len_utf8()));
+                }
+            }
+            Some((opener, start)) => {
+                let closes = match opener {
+                    '«' => c == '»',
+                    '„' => c == '“' || c == '”',
+                    _ => c == '"' || c == '”',
+                };
+                if closes {
+                    ranges.push((start, idx));
+                    open = None;
+                }
+            }
+        }
+    }
+    ranges
+}
+
+/// Finds the (start, end) byte ranges of author-year citations like "(Smith, 2020)"
+///     and numeric citations like "[3]" or "[1, 4-6]"
+fn find_citation_ranges(text: &str) -> Vec<(usize, usize)> {
+    // Compile this only once
+    lazy_static! {
+        static ref CITATION: Regex =
+            Regex::new(r"\([^()]*\b\d{4}[a-z]?\)|\[\d+(?:\s*[,\-–]\s*\d+)*\]")
+                .expect("Regex to find citation markers could not be compiled properly!");
+    }
+    CITATION
+        .find_iter(text)
+        .map(|m| (m.start(), m.end()))
+        .collect()
+}
+
+/// Finds the (start, end) byte ranges of numeric dates ("2020-01-31",
+///     "31/01/2020", "31.01.20") and dates with English month names
+///     ("January 31, 2020", "31st Jan 2020")
+fn find_date_ranges(text: &str) -> Vec<(usize, usize)> {
+    // Compile this only once
+    lazy_static! {
+        static ref DATE: Regex = Regex::new(
+            r"(?i)\b(?:\d{4}-\d{1,2}-\d{1,2}|\d{1,2}[/.]\d{1,2}[/.]\d{2,4}|(?:jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.?\s+\d{1,2}(?:st|nd|rd|th)?,?\s+\d{4}|\d{1,2}(?:st|nd|rd|th)?\s+(?:jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.?,?\s+\d{4})\b"
+        )
+        .expect("Regex to find dates could not be compiled properly!");
+    }
+    DATE.find_iter(text).map(|m| (m.start(), m.end())).collect()
+}
+
+/// Checks if a word is a number, possibly with decimal or thousands separators
+fn is_number(word: &str) -> bool {
+    word.chars().any(char::is_numeric)
+        && word.chars().all(|c| c.is_numeric() || c == '.' || c == ',')
+}
+
+/// Checks if a word starts with an upper case letter, ignoring the pronoun "I"
+fn is_capitalized(word: &str) -> bool {
+    word.chars().next().is_some_and(char::is_uppercase) && word != "I"
+}
+
+/// Removes HTML tags, comments, scripts/styles and entities as well as Markdown
+///     syntax (link targets, images, code fences, headings, emphasis) so that
+///     markup isn't turned into fragments. Link and image texts are kept.
+pub fn strip_markup(text: &str) -> String {
     // Compile this only once
     lazy_static! {
-        static ref REMOVE_NONALPHA: Regex = Regex::new(r"[^A-Za-z0-9 ]")
-            .expect("Regex to remove non-alphanumeric characters could not be compiled properly!");
-        static ref REMOVE_SPACES: Regex = Regex::new(r"\s+")
-            .expect("Regex to remove redundant spaces could not be compiled properly!");
-    }
-    // Remove all newlines and convert to lowercase
-    let mut new_text: String = text.replace('\n', " ").to_lowercase();
-    // Remove all non alphanumeric+space chars and remove redundant spaces
-    new_text = REMOVE_NONALPHA.replace_all(&new_text, " ").to_string();
-    REMOVE_SPACES
-        .replace_all(&new_text, " ")
-        .trim()
-        .split_whitespace()
-        .map(String::from)
+        static ref HTML_BLOCKS: Regex = Regex::new(
+            r"(?is)<!--.*?-->|<script\b.*?</script\s*>|<style\b.*?</style\s*>"
+        )
+        .expect("Regex to remove HTML comments and scripts could not be compiled properly!");
+        static ref HTML_TAGS: Regex = Regex::new(r"</?[A-Za-z][^<>]*>")
+            .expect("Regex to remove HTML tags could not be compiled properly!");
+        static ref HTML_ENTITIES: Regex = Regex::new(r"&(#[0-9]+|#[xX][0-9A-Fa-f]+|[A-Za-z]+);")
+            .expect("Regex to decode HTML entities could not be compiled properly!");
+        static ref MD_LINKS: Regex = Regex::new(r"!?\[([^\]]*)\]\([^)]*\)")
+            .expect("Regex to remove Markdown link targets could not be compiled properly!");
+        static ref MD_LINE_SYNTAX: Regex = Regex::new(
+            r"(?m)^\s*(?:\[[^\]]+\]:\s*\S+.*$|```.*$|~~~.*$|#{1,6}\s+|>\s?|[-*+]\s+|\d+\.\s+)"
+        )
+        .expect("Regex to remove Markdown line syntax could not be compiled properly!");
+        static ref MD_EMPHASIS: Regex = Regex::new(r"[*_~`]+")
+            .expect("Regex to remove Markdown emphasis could not be compiled properly!");
+    }
+    let text = HTML_BLOCKS.replace_all(text, " ");
+    let text = HTML_TAGS.replace_all(&text, " ");
+    let text = HTML_ENTITIES.replace_all(&text, |caps: &regex::Captures| decode_entity(&caps[1]));
+    let text = MD_LINKS.replace_all(&text, "$1");
+    let text = MD_LINE_SYNTAX.replace_all(&text, "");
+    MD_EMPHASIS.replace_all(&text, " ").to_string()
+}
+
+/// Decodes the name or number of a single HTML entity (without & and ;)
+fn decode_entity(entity: &str) -> String {
+    let code = if let Some(hex) = entity
+        .strip_prefix("#x")
+        .or_else(|| entity.strip_prefix("#X"))
+    {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(decimal) = entity.strip_prefix('#') {
+        decimal.parse().ok()
+    } else {
+        None
+    };
+    if let Some(c) = code.and_then(std::char::from_u32) {
+        return c.to_string();
+    }
+    match entity {
+        "amp" => "&",
+        "lt" => "<",
+        "gt" => ">",
+        "quot" => "\"",
+        "apos" => "'",
+        // Anything else (nbsp, mdash, ...) separates words at most
+        _ => " ",
+    }
+    .to_string()
+}
+
+/// Removes the characters of a single word that the options don't keep
+fn clean_word(word: &str, options: &CleanOptions) -> String {
+    let kept = word.chars().filter(|&c| {
+        if c.is_alphabetic() || is_combining_mark(c) {
+            true
+        } else if c.is_numeric() {
+            options.keep_numbers
+        } else {
+            options.keep_punctuation
+        }
+    });
+    if options.preserve_case {
+        kept.collect()
+    } else {
+        kept.flat_map(char::to_lowercase).collect()
+    }
+}
+
+/// Checks for the ASCII hyphen-minus and the Unicode hyphens
+fn is_hyphen(c: char) -> bool {
+    matches!(c, '-' | '\u{2010}' | '\u{2011}')
+}
+
+/// Decomposes the text and drops all combining marks, then recomposes it
+fn strip_diacritics(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .nfc()
         .collect()
 }
 
@@ -53,7 +848,7 @@ pub fn clean_text(text: &str) -> Vec<String> {
 ///     - Since the intervals are sorted, we read each interval one by one from start
 ///     -
 pub fn get_boldtext_segments_from_intervals(
-    words: &Vec<String>,
+    words: &[String],
     text_intervals: &IntervalSet<usize>,
 ) -> Vec<TextMaybeBold> {
     let mut text_segments: Vec<TextMaybeBold> = Vec::new();
@@ -73,8 +868,7 @@ pub fn get_boldtext_segments_from_intervals(
                         is_bold: false,
                     });
                 }
-                cur_words = Vec::new();
-                cur_words.push(word);
+                cur_words = vec![word];
             }
             contains_previously = true;
         } else {
@@ -86,8 +880,7 @@ pub fn get_boldtext_segments_from_intervals(
                         is_bold: true,
                     });
                 }
-                cur_words = Vec::new();
-                cur_words.push(word);
+                cur_words = vec![word];
             } else {
                 cur_words.push(word);
             }
@@ -114,13 +907,59 @@ pub fn get_boldtext_segments_from_intervals(
 mod tests {
     use super::*;
 
+    /// Splits on whitespace only, keeping formulas like "H2O" as they are
+    struct FormulaTokenizer;
+
+    impl Tokenizer for FormulaTokenizer {
+        fn tokenize(&self, text: &str, _options: &CleanOptions) -> CleanedText {
+            let mut words = Vec::new();
+            let mut byte_ranges = Vec::new();
+            for word in text.split_whitespace() {
+                let start = word.as_ptr() as usize - text.as_ptr() as usize;
+                words.push(word.to_string());
+                byte_ranges.push((start, start + word.len()));
+            }
+            CleanedText {
+                words,
+                excluded: Vec::new(),
+                sentence_starts: vec![0],
+                byte_ranges,
+                stripped: Vec::new(),
+                suspicious_chars: 0,
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_tokenizer() {
+        use crate::{Metric, PlagiarismDatabase};
+
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .tokenizer(FormulaTokenizer)
+            .build();
+        db.add_trusted_text("lab", "dissolve NaCl in H2O quickly");
+        db.add_untrusted_text("report", "dissolve NaCl in H2O slowly");
+        db.add_untrusted_text("lowercase", "dissolve nacl in h2o slowly");
+        assert_eq!(
+            db.get_clean_words("report", false),
+            ["dissolve", "NaCl", "in", "H2O", "slowly"]
+        );
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].owner_id2, "report");
+        assert_eq!(results[0].matching_fragments.len(), 2);
+        assert_eq!(
+            DefaultTokenizer
+                .tokenize("H2O", &CleanOptions::default())
+                .words,
+            vec!["h2o"]
+        );
+    }
+
     #[test]
     fn test_ngram() {
         assert_eq!(
-            extract_clean_word_ngrams(
-                &vec!["mary".to_string(), "had".to_string(), "a".to_string()],
-                2
-            ),
+            extract_clean_word_ngrams(&["mary".to_string(), "had".to_string(), "a".to_string()], 2),
             vec!["mary had", "had a"]
         );
 
@@ -130,6 +969,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rolling_hashes() {
+        let words = clean_text("the cat sat on the cat sat");
+        let word_hashes: Vec<u64> = words.iter().map(|word| hash_word(word)).collect();
+        let hashes = rolling_ngram_hashes(&word_hashes, 3);
+        let expected: Vec<FragmentHash> = extract_clean_word_ngrams(&words, 3)
+            .iter()
+            .map(|ngram| hash_fragment(ngram))
+            .collect();
+        assert_eq!(hashes, expected);
+        assert_eq!(hashes[0], hashes[4]);
+        assert_ne!(hashes[0], hashes[1]);
+        assert!(rolling_ngram_hashes(&word_hashes, 8).is_empty());
+    }
+
+    #[test]
+    fn test_skipgrams() {
+        let words = clean_text("mary had a lamb");
+        let skipgrams: Vec<String> = extract_clean_word_skipgrams(&words, 2, 1)
+            .into_iter()
+            .map(|(_, ngram)| ngram)
+            .collect();
+        assert_eq!(
+            skipgrams,
+            vec!["mary had", "mary a", "had a", "had lamb", "a lamb"]
+        );
+        let indices: Vec<Vec<usize>> = extract_clean_word_skipgrams(&words, 3, 1)
+            .into_iter()
+            .map(|(indices, _)| indices)
+            .collect();
+        assert_eq!(
+            indices,
+            vec![vec![0, 1, 2], vec![0, 1, 3], vec![0, 2, 3], vec![1, 2, 3]]
+        );
+    }
+
     #[test]
     fn test_clean() {
         assert_eq!(
@@ -138,9 +1013,271 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_clean_unicode() {
+        assert_eq!(
+            clean_text("Ça VA? Größe—Straße, naïve"),
+            vec!["ça", "va", "größe", "straße", "naïve"]
+        );
+        // Ligatures and full-width characters are folded by NFKC
+        assert_eq!(clean_text("ﬁne ＡＢＣ"), vec!["fine", "abc"]);
+    }
+
+    #[test]
+    fn test_clean_strip_diacritics() {
+        let options = CleanOptions {
+            strip_diacritics: true,
+            ..CleanOptions::default()
+        };
+        assert_eq!(
+            clean_text_with_options("Café crème, déjà vu", &options),
+            vec!["cafe", "creme", "deja", "vu"]
+        );
+    }
+
+    #[test]
+    fn test_clean_case_and_numbers() {
+        let text = "Call 911 in NYC, don't wait";
+        assert_eq!(
+            clean_text(text),
+            vec!["call", "911", "in", "nyc", "dont", "wait"]
+        );
+        let options = CleanOptions {
+            preserve_case: true,
+            keep_numbers: false,
+            keep_punctuation: true,
+            ..CleanOptions::default()
+        };
+        assert_eq!(
+            clean_text_with_options(text, &options),
+            vec!["Call", "in", "NYC", "don't", "wait"]
+        );
+    }
+
+    #[test]
+    fn test_clean_hyphens() {
+        let text = "a well-known state-of-the-art idea - really";
+        assert_eq!(
+            clean_text(text),
+            vec!["a", "well", "known", "state", "of", "the", "art", "idea", "really"]
+        );
+        let mut options = CleanOptions {
+            split_hyphens: false,
+            ..CleanOptions::default()
+        };
+        assert_eq!(
+            clean_text_with_options(text, &options),
+            vec!["a", "wellknown", "stateoftheart", "idea", "really"]
+        );
+        options.keep_punctuation = true;
+        assert_eq!(
+            clean_text_with_options(text, &options),
+            vec!["a", "well-known", "state-of-the-art", "idea", "really"]
+        );
+    }
+
+    #[test]
+    fn test_strip_html() {
+        let html = "<html><head><style>p { color: red; }</style></head>\
+            <body><!-- nav --><p class=\"intro\">Fish &amp; chips&nbsp;are <b>great</b>&#33;</p>\
+            <script>var x = 1;</script></body></html>";
+        assert_eq!(
+            clean_text(&strip_markup(html)),
+            vec!["fish", "chips", "are", "great"]
+        );
+        assert!(strip_markup(html).contains("Fish & chips"));
+    }
+
+    #[test]
+    fn test_strip_markdown() {
+        let markdown = "# Title\n\
+            Some **bold** and _italic_ text, see [the docs](https://example.com/docs).\n\
+            ![a diagram](img/diagram.png)\n\
+            > quoted line\n\
+            - item one\n\
+            ```rust\n\
+            [ref]: https://example.com\n";
+        assert_eq!(
+            clean_text(&strip_markup(markdown)),
+            vec![
+                "title", "some", "bold", "and", "italic", "text", "see", "the", "docs", "a",
+                "diagram", "quoted", "line", "item", "one"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exclude_quotes() {
+        let options = CleanOptions {
+            exclude_quotes: true,
+            ..CleanOptions::default()
+        };
+        let cleaned = tokenize_text(
+            "He said “to be or not” and then \"left\" for \"good",
+            &options,
+        );
+        assert_eq!(
+            cleaned.words,
+            vec!["he", "said", "to", "be", "or", "not", "and", "then", "left", "for", "good"]
+        );
+        // The unterminated quote before "good" is not excluded
+        assert_eq!(cleaned.excluded, vec![(2, 5), (8, 8)]);
+        assert!(cleaned.is_excluded(0, 2));
+        assert!(!cleaned.is_excluded(6, 7));
+    }
+
+    #[test]
+    fn test_exclude_citations() {
+        let options = CleanOptions {
+            exclude_citations: true,
+            ..CleanOptions::default()
+        };
+        let cleaned = tokenize_text(
+            "As shown (Smith et al., 2020a) before [1, 3-4] here",
+            &options,
+        );
+        assert_eq!(
+            cleaned.words,
+            vec!["as", "shown", "smith", "et", "al", "2020a", "before", "1", "3", "4", "here"]
+        );
+        assert_eq!(cleaned.excluded, vec![(2, 5), (7, 9)]);
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let options = CleanOptions {
+            exclude_patterns: vec![
+                Regex::new(r"(?ms)^References$.*").unwrap(),
+                Regex::new(r"```.*?```").unwrap(),
+            ],
+            ..CleanOptions::default()
+        };
+        let text = "Intro text ```let x``` more\nReferences\nSmith 2020";
+        let cleaned = tokenize_text(text, &options);
+        assert_eq!(cleaned.words, vec!["intro", "text", "more"]);
+        assert_eq!(cleaned.stripped, vec![(11, 22), (28, text.len())]);
+        assert_eq!(
+            &text[cleaned.byte_ranges[2].0..cleaned.byte_ranges[2].1],
+            "more"
+        );
+    }
+
+    #[test]
+    fn test_fold_confusables() {
+        // Cyrillic "а" and "о", and a zero-width space inside a word
+        let text = "The c\u{430}t s\u{430}t \u{43E}n th\u{200B}e mat";
+        let cleaned = tokenize_text(text, &CleanOptions::default());
+        assert_eq!(cleaned.words, vec!["the", "cat", "sat", "on", "the", "mat"]);
+        assert_eq!(cleaned.suspicious_chars, 4);
+        assert_eq!(
+            &text[cleaned.byte_ranges[4].0..cleaned.byte_ranges[4].1],
+            "th\u{200B}e"
+        );
+
+        let russian = tokenize_text("Кот сидит на ковре", &CleanOptions::default());
+        assert_eq!(russian.words, vec!["кот", "сидит", "на", "ковре"]);
+        assert_eq!(russian.suspicious_chars, 0);
+        let kept = CleanOptions {
+            fold_confusables: false,
+            ..CleanOptions::default()
+        };
+        assert_eq!(tokenize_text(text, &kept).words[1], "c\u{430}t");
+    }
+
+    #[test]
+    fn test_sentences() {
+        let cleaned = tokenize_text(
+            "Dr. Smith arrived, e.g. at noon. J. R. R. Tolkien wrote it! Did he?\n\nHeading\nThe end",
+            &CleanOptions::default(),
+        );
+        assert_eq!(cleaned.sentence_starts, vec![0, 6, 12, 14]);
+        assert_eq!(
+            cleaned.sentences(),
+            vec![(0, 5), (6, 11), (12, 13), (14, 16)]
+        );
+        let unsplit = CleanedText {
+            words: vec!["a".to_string(), "b".to_string()],
+            excluded: Vec::new(),
+            sentence_starts: Vec::new(),
+            byte_ranges: Vec::new(),
+            stripped: Vec::new(),
+            suspicious_chars: 0,
+        };
+        assert_eq!(unsplit.sentences(), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_byte_ranges() {
+        let options = CleanOptions {
+            strip_diacritics: true,
+            split_hyphens: false,
+            ..CleanOptions::default()
+        };
+        let text = "Ｃafé's ﬁne well-known x";
+        let cleaned = tokenize_text(text, &options);
+        assert_eq!(cleaned.words, vec!["cafes", "fine", "wellknown", "x"]);
+        let originals: Vec<&str> = cleaned
+            .byte_ranges
+            .iter()
+            .map(|&(start, end)| &text[start..end])
+            .collect();
+        assert_eq!(originals, vec!["Ｃafé's", "ﬁne", "well-known", "x"]);
+    }
+
+    #[test]
+    fn test_normalize_numbers_dates_entities() {
+        let options = CleanOptions {
+            normalize_numbers: true,
+            normalize_dates: true,
+            normalize_entities: true,
+            ..CleanOptions::default()
+        };
+        let text = "On January 5, 2021 Marie Curie measured 3.14 grams. Then she left";
+        let cleaned = tokenize_text(text, &options);
+        assert_eq!(
+            cleaned.words,
+            vec!["on", "<DATE>", "<ENT>", "measured", "<NUM>", "grams", "then", "she", "left"]
+        );
+        let originals: Vec<&str> = cleaned
+            .byte_ranges
+            .iter()
+            .map(|&(start, end)| &text[start..end])
+            .collect();
+        assert_eq!(originals[1], "January 5, 2021");
+        assert_eq!(originals[2], "Marie Curie");
+        assert_eq!(
+            clean_text_with_options("Due 2021-01-05 or 5/1/21", &options),
+            vec!["due", "<DATE>", "or", "<DATE>"]
+        );
+    }
+
+    #[test]
+    fn test_stemming() {
+        let options = CleanOptions {
+            stemmer: Some(Algorithm::English),
+            ..CleanOptions::default()
+        };
+        assert_eq!(
+            clean_text_with_options("Connected connecting connections", &options),
+            vec!["connect", "connect", "connect"]
+        );
+    }
+
+    #[test]
+    fn test_stopwords() {
+        let options = CleanOptions {
+            stopwords: ["the", "of"].iter().map(|x| x.to_string()).collect(),
+            ..CleanOptions::default()
+        };
+        assert_eq!(
+            clean_text_with_options("The Lord of THE Rings", &options),
+            vec!["lord", "rings"]
+        );
+    }
+
     #[test]
     fn test_intervals_firstwords_bold() {
-        let words = vec!["a", "b", "c", "d", "e"]
+        let words: Vec<String> = ["a", "b", "c", "d", "e"]
             .iter()
             .map(|x| x.to_string())
             .collect();
@@ -162,7 +1299,7 @@ mod tests {
 
     #[test]
     fn test_intervals_lastwords_bold() {
-        let words = vec!["a", "b", "c", "d", "e"]
+        let words: Vec<String> = ["a", "b", "c", "d", "e"]
             .iter()
             .map(|x| x.to_string())
             .collect();
@@ -184,7 +1321,7 @@ mod tests {
 
     #[test]
     fn test_intervals_no_bold() {
-        let words = vec!["a", "b", "c", "d", "e"]
+        let words: Vec<String> = ["a", "b", "c", "d", "e"]
             .iter()
             .map(|x| x.to_string())
             .collect();
@@ -200,7 +1337,7 @@ mod tests {
 
     #[test]
     fn test_intervals_all_bold() {
-        let words = vec!["a", "b", "c", "d", "e"]
+        let words: Vec<String> = ["a", "b", "c", "d", "e"]
             .iter()
             .map(|x| x.to_string())
             .collect();
@@ -216,7 +1353,7 @@ mod tests {
 
     #[test]
     fn test_intervals_single_letters_bold() {
-        let words = vec!["a", "b", "c", "d", "e"]
+        let words: Vec<String> = ["a", "b", "c", "d", "e"]
             .iter()
             .map(|x| x.to_string())
             .collect();
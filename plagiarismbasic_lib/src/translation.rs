@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Normalizes the words of texts to a pivot language before fragments are
+///     built, so that a text translated from a trusted source still matches
+///     it, see `translator` on the builder. Implementations can look words up
+///     in a dictionary (like `DictionaryTranslator`) or call a translation API.
+pub trait Translator: Send + Sync {
+    /// Translates the clean words of a text in a language (ISO 639-3 code,
+    ///     `None` unless detected with `detect_language`) to the pivot
+    ///     language. Must return exactly one word for every word, so that
+    ///     matches still point at the words of the original text (texts are
+    ///     rejected with `Error::TranslatorMismatch` otherwise). Words without
+    ///     a translation are returned as they are.
+    fn translate(&self, words: &[String], language: Option<&str>) -> Vec<String>;
+
+    /// ISO 639-3 code of the pivot language, whose stemmer is used for the
+    ///     translated words with `stem`
+    fn pivot_language(&self) -> &str {
+        "eng"
+    }
+}
+
+/// Translates word by word with a bilingual dictionary
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DictionaryTranslator {
+    /// Lowercase word -> its translation
+    translations: HashMap<String, String>,
+    /// Only texts detected in this language are translated, if set
+    source_language: Option<String>,
+}
+
+impl DictionaryTranslator {
+    /// Builds the dictionary from (word, translation) pairs. A word listed
+    ///     twice keeps its first translation.
+    pub fn from_pairs<I: IntoIterator<Item = (String, String)>>(pairs: I) -> DictionaryTranslator {
+        let mut translations: HashMap<String, String> = HashMap::new();
+        for (word, translation) in pairs {
+            let (word, translation) = (
+                word.trim().to_lowercase(),
+                translation.trim().to_lowercase(),
+            );
+            if !word.is_empty() && !translation.is_empty() {
+                translations.entry(word).or_insert(translation);
+            }
+        }
+        DictionaryTranslator {
+            translations,
+            source_language: None,
+        }
+    }
+
+    /// Reads one "word, translation" pair per line, e.g. "perro, dog".
+    ///     Empty lines and lines starting with '#' are skipped.
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<DictionaryTranslator> {
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((word, translation)) = line.split_once(',') {
+                pairs.push((word.to_string(), translation.to_string()));
+            }
+        }
+        Ok(DictionaryTranslator::from_pairs(pairs))
+    }
+
+    /// Reads a dictionary file, see `from_reader`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<DictionaryTranslator> {
+        DictionaryTranslator::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Only translates texts detected to be in this language (ISO 639-3
+    ///     code), so that pivot language words that look like words of the
+    ///     dictionary are left alone
+    pub fn with_source_language(mut self, language: &str) -> DictionaryTranslator {
+        self.source_language = Some(language.to_string());
+        self
+    }
+}
+
+impl Translator for DictionaryTranslator {
+    fn translate(&self, words: &[String], language: Option<&str>) -> Vec<String> {
+        if let Some(source_language) = &self.source_language {
+            if language != Some(source_language.as_str()) {
+                return words.to_vec();
+            }
+        }
+        words
+            .iter()
+            .map(|word| {
+                self.translations
+                    .get(word)
+                    .or_else(|| self.translations.get(&word.to_lowercase()))
+                    .unwrap_or(word)
+                    .clone()
+            })
+            .collect()
+    }
+}
+
+/// The translator of a database, shared by its clones and threads
+#[derive(Clone)]
+pub(crate) struct SharedTranslator(pub(crate) Arc<dyn Translator>);
+
+impl fmt::Debug for SharedTranslator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Translator")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, Metric, PlagiarismDatabase};
+
+    /// Drops the last word of every text
+    struct TruncatingTranslator;
+
+    impl Translator for TruncatingTranslator {
+        fn translate(&self, words: &[String], _language: Option<&str>) -> Vec<String> {
+            words[..words.len().saturating_sub(1)].to_vec()
+        }
+    }
+
+    #[test]
+    fn test_translator() {
+        let dictionary = "# es -> en\nel, the\nperro, dog\nnegro, black\ncorre, runs\n";
+        let translator = DictionaryTranslator::from_reader(dictionary.as_bytes()).unwrap();
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .translator(translator.clone())
+            .build();
+        db.add_trusted_text("source", "every day the dog runs home");
+        db.add_untrusted_text("essay", "cada día el perro corre a casa");
+        let results = db.check_trusted_plagiarism();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].matching_fragments,
+            vec![("the dog runs".to_string(), "the dog runs".to_string())]
+        );
+        // Byte ranges still point at the Spanish words
+        assert_eq!(results[0].char_locations[0].1, vec![(10, 24)]);
+
+        let spanish_only = translator.with_source_language("spa");
+        let words = vec!["el".to_string(), "perro".to_string()];
+        assert_eq!(spanish_only.translate(&words, Some("eng")), words);
+        assert_eq!(
+            spanish_only.translate(&words, Some("spa")),
+            vec!["the", "dog"]
+        );
+    }
+
+    #[test]
+    fn test_translator_mismatch() {
+        let mut db = PlagiarismDatabase::builder(3, 0, Metric::Equal)
+            .translator(TruncatingTranslator)
+            .build();
+        assert!(matches!(
+            db.try_add_untrusted_text("essay", "the dog runs home"),
+            Err(Error::TranslatorMismatch { ref owner_id, expected: 4, got: 3 }) if owner_id == "essay"
+        ));
+        assert!(matches!(
+            db.try_set_assignment_prompt("write about the dog"),
+            Err(Error::TranslatorMismatch { .. })
+        ));
+        assert!(matches!(
+            db.try_add_boilerplate_text("the dog runs"),
+            Err(Error::TranslatorMismatch { .. })
+        ));
+        assert!(matches!(
+            db.try_add_baseline_text("essay", "the dog runs home"),
+            Err(Error::TranslatorMismatch { .. })
+        ));
+    }
+}
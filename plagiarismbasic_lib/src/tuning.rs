@@ -0,0 +1,189 @@
+use crate::corpus::SplitMix64;
+use crate::plagiarism_database::{PlagiarismDatabase, TextOwnerID};
+use crate::string_compare::simhash;
+use crate::text_utils::extract_clean_word_ngrams;
+use crate::Metric;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strsim::levenshtein;
+
+/// Fragment pairs compared for every sampled text pair
+const FRAGMENTS_PER_PAIR: usize = 64;
+
+/// Seed of the sampling, so that suggestions are reproducible
+const SEED: u64 = 0x5eed_7e5e;
+
+/// Thresholds suggested by `auto_tune`, from the background similarity of
+///     random pairs of texts that are assumed not to be copied from each other
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TuneSuggestion {
+    pub metric: Metric,
+    pub false_positive_rate: f64,
+    /// Text pairs whose scores were computed
+    pub sampled_pairs: usize,
+    /// Pairs of different n-word fragments whose distance was computed
+    pub sampled_fragments: usize,
+    /// Largest similarity threshold at which at most the false-positive rate
+    ///     of random fragment pairs match. Only for the `lev` and `simhash`
+    ///     metrics, which measure a distance.
+    pub s: Option<usize>,
+    /// Score (see `PlagiarismResult::score`) that at most the false-positive
+    ///     rate of random text pairs exceed with the current settings, e.g. for
+    ///     `--warn-above`
+    pub score_threshold: f32,
+}
+
+impl PlagiarismDatabase {
+    /// Samples up to `samples` random pairs of texts of different owners
+    ///     (trusted with untrusted ones, or untrusted ones with each other
+    ///     without trusted texts) and suggests thresholds that only the
+    ///     `false_positive_rate` (e.g. 0.01) of them would exceed. Most random
+    ///     pairs of a corpus aren't copied, so their scores show how similar
+    ///     unrelated texts on the same topic are. Identical fragments are
+    ///     left out of the fragment distances, since they are copies.
+    pub fn auto_tune(&self, false_positive_rate: f64, samples: usize) -> TuneSuggestion {
+        let (metric, _) = self.metric_settings();
+        let mut rng = SplitMix64(SEED);
+        let trusted_side = !self.get_owners(true).is_empty();
+        let pairs = self.sample_owner_pairs(trusted_side, samples, &mut rng);
+
+        let mut scores: HashMap<(&str, &str), f32> = pairs
+            .iter()
+            .map(|(a, b)| ((a.as_str(), b.as_str()), 0.0))
+            .collect();
+        // Samples aren't results of a check, so they aren't notified
+        let results = self.check_owner_pairs(&pairs, (trusted_side, false), false);
+        for result in &results {
+            let mut key = (result.owner_id1.as_str(), result.owner_id2.as_str());
+            if !scores.contains_key(&key) {
+                key = (key.1, key.0);
+            }
+            if let Some(score) = scores.get_mut(&key) {
+                *score = score.max(result.score());
+            }
+        }
+        let mut scores: Vec<f32> = scores.into_values().collect();
+        scores.sort_by(|a, b| a.total_cmp(b));
+        let score_threshold = match scores.len() {
+            0 => 0.0,
+            len => {
+                let allowed = (false_positive_rate * len as f64).floor() as usize;
+                scores[len - 1 - allowed.min(len - 1)]
+            }
+        };
+
+        let mut distances: Vec<usize> = Vec::new();
+        if matches!(metric, Metric::Lev | Metric::SimHash) {
+            let n = self.fragmentation().n;
+            for (owner1, owner2) in &pairs {
+                let fragments1 =
+                    extract_clean_word_ngrams(self.get_clean_words(owner1, trusted_side), n);
+                let fragments2 = extract_clean_word_ngrams(self.get_clean_words(owner2, false), n);
+                if fragments1.is_empty() || fragments2.is_empty() {
+                    continue;
+                }
+                for _ in 0..FRAGMENTS_PER_PAIR {
+                    let fragment1 = &fragments1[rng.below(fragments1.len())];
+                    let fragment2 = &fragments2[rng.below(fragments2.len())];
+                    let distance = match metric {
+                        Metric::Lev => levenshtein(fragment1, fragment2),
+                        _ => (simhash(fragment1) ^ simhash(fragment2)).count_ones() as usize,
+                    };
+                    if distance > 0 {
+                        distances.push(distance);
+                    }
+                }
+            }
+            distances.sort_unstable();
+        }
+        // The n-th smallest distance is the first that more than n fragment
+        //     pairs are at or below
+        let allowed = (false_positive_rate * distances.len() as f64).floor() as usize;
+        let s = match metric {
+            Metric::Lev | Metric::SimHash => Some(match distances.get(allowed) {
+                Some(&distance) => distance - 1,
+                None => distances.last().copied().unwrap_or(0),
+            }),
+            _ => None,
+        };
+        TuneSuggestion {
+            metric,
+            false_positive_rate,
+            sampled_pairs: pairs.len(),
+            sampled_fragments: distances.len(),
+            s,
+            score_threshold,
+        }
+    }
+
+    /// All (trusted or untrusted owner, untrusted owner) pairs of different
+    ///     owners if there are at most `samples` of them, else `samples`
+    ///     random ones
+    fn sample_owner_pairs(
+        &self,
+        trusted_side: bool,
+        samples: usize,
+        rng: &mut SplitMix64,
+    ) -> Vec<(TextOwnerID, TextOwnerID)> {
+        let mut owners1 = self.get_owners(trusted_side);
+        let mut owners2 = self.get_owners(false);
+        owners1.sort();
+        owners2.sort();
+        let mut all: Vec<(TextOwnerID, TextOwnerID)> = owners1
+            .iter()
+            .flat_map(|&owner1| {
+                owners2
+                    .iter()
+                    // Untrusted pairs once each, like `check_untrusted_plagiarism`
+                    .filter(move |&&owner2| {
+                        if trusted_side {
+                            owner1 != owner2
+                        } else {
+                            owner1 < owner2
+                        }
+                    })
+                    .map(move |&owner2| (owner1.clone(), owner2.clone()))
+            })
+            .collect();
+        // Partial Fisher-Yates shuffle, so no pair is sampled twice
+        for idx in 0..samples.min(all.len()) {
+            let other = idx + rng.below(all.len() - idx);
+            all.swap(idx, other);
+        }
+        all.truncate(samples);
+        all
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus;
+
+    #[test]
+    fn test_auto_tune() {
+        let corpus = corpus::synthetic(12, 100, 0.0, 7);
+        let mut db = PlagiarismDatabase::new(4, 5, Metric::Lev, Vec::new());
+        for (owner, text) in &corpus.untrusted {
+            db.add_untrusted_text(owner, text);
+        }
+        let suggestion = db.auto_tune(0.01, 40);
+        assert_eq!(suggestion.sampled_pairs, 40);
+        assert_eq!(suggestion.sampled_fragments, 40 * FRAGMENTS_PER_PAIR);
+        let s = suggestion.s.unwrap();
+        // Random fragments are far apart, and the suggestion is reproducible
+        assert!(s > 0);
+        assert_eq!(db.auto_tune(0.01, 40), suggestion);
+        assert!(db.auto_tune(0.2, 40).s.unwrap() >= s);
+        assert_eq!(suggestion.score_threshold, 0.0);
+
+        let mut db = PlagiarismDatabase::new(3, 0, Metric::Equal, Vec::new());
+        db.add_trusted_text("book", "the mitochondria is the powerhouse of the cell");
+        db.add_untrusted_text("alice", "we know the powerhouse of the cell well");
+        db.add_untrusted_text("bob", "a text about something else entirely");
+        let suggestion = db.auto_tune(0.0, 100);
+        assert_eq!(suggestion.sampled_pairs, 2);
+        assert_eq!(suggestion.s, None);
+        assert!(suggestion.score_threshold > 0.0);
+    }
+}
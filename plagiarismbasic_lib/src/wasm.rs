@@ -0,0 +1,87 @@
+use crate::plagiarism_database::{sort_results, PlagiarismDatabase, PlagiarismResult};
+use crate::Metric;
+use wasm_bindgen::prelude::*;
+
+/// `PlagiarismDatabase` as a JavaScript class, e.g.
+///     `new PlagiarismDatabase(8, 0, "equal")`
+#[wasm_bindgen(js_name = PlagiarismDatabase)]
+pub struct WasmPlagiarismDatabase {
+    db: PlagiarismDatabase,
+}
+
+#[wasm_bindgen(js_class = PlagiarismDatabase)]
+impl WasmPlagiarismDatabase {
+    /// Creates a database with ngram size n, metric cutoff s and
+    ///     the metric "equal", "lev" or "simhash"
+    #[wasm_bindgen(constructor)]
+    pub fn new(n: usize, s: usize, metric: &str) -> Result<WasmPlagiarismDatabase, JsError> {
+        let metric: Metric = metric.parse().map_err(|err: String| JsError::new(&err))?;
+        if metric == Metric::Embedding {
+            return Err(JsError::new(
+                "The embedding metric is not available in the browser",
+            ));
+        }
+        Ok(WasmPlagiarismDatabase {
+            db: PlagiarismDatabase::new(n, s, metric, Vec::new()),
+        })
+    }
+
+    /// Throws if the text has no words
+    #[wasm_bindgen(js_name = addTrustedText)]
+    pub fn add_trusted_text(&mut self, owner_id: &str, text: &str) -> Result<(), JsError> {
+        self.db
+            .try_add_trusted_text(owner_id, text)
+            .map_err(|err| JsError::new(&err.to_string()))
+    }
+
+    /// Throws if the text has no words
+    #[wasm_bindgen(js_name = addUntrustedText)]
+    pub fn add_untrusted_text(&mut self, owner_id: &str, text: &str) -> Result<(), JsError> {
+        self.db
+            .try_add_untrusted_text(owner_id, text)
+            .map_err(|err| JsError::new(&err.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = addBoilerplateText)]
+    pub fn add_boilerplate_text(&mut self, text: &str) -> Result<(), JsError> {
+        self.db
+            .try_add_boilerplate_text(text)
+            .map_err(|err| JsError::new(&err.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = setAssignmentPrompt)]
+    pub fn set_assignment_prompt(&mut self, text: &str) -> Result<(), JsError> {
+        self.db
+            .try_set_assignment_prompt(text)
+            .map_err(|err| JsError::new(&err.to_string()))
+    }
+
+    /// Checks the untrusted texts against the trusted ones, returning the
+    ///     results as a JSON array (most matches first)
+    #[wasm_bindgen(js_name = checkTrustedPlagiarism)]
+    pub fn check_trusted_plagiarism(&self) -> Result<String, JsError> {
+        let mut results: Vec<PlagiarismResult> = self.db.check_trusted_plagiarism();
+        sort_results(&mut results);
+        serde_json::to_string(&results).map_err(|err| JsError::new(&err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_returns_json() {
+        let mut db = WasmPlagiarismDatabase::new(3, 0, "equal").unwrap();
+        assert!(db
+            .add_trusted_text("source", "the quick brown fox jumps")
+            .is_ok());
+        assert!(db
+            .add_untrusted_text("student", "a quick brown fox appears")
+            .is_ok());
+        let json: serde_json::Value =
+            serde_json::from_str(&db.check_trusted_plagiarism().unwrap()).unwrap();
+        assert_eq!(json[0]["owner_id2"], "student");
+        assert_eq!(json[0]["matching_fragments"][0][0], "quick brown fox");
+    }
+}
@@ -1,2 +1,3 @@
 #!/bin/bash
-cargo run --release -- -t plagiarismbasic_lib/testfiles/t/ -u plagiarismbasic_lib/testfiles/ut/ -m equal -n 5 -s 0 --openhtml --cli --html
\ No newline at end of file
+cargo run --release -- check -t plagiarismbasic_lib/testfiles/t/ -u plagiarismbasic_lib/testfiles/ut/ -m equal -n 5 -s 0
+cargo run --release -- report html --open -t plagiarismbasic_lib/testfiles/t/ -u plagiarismbasic_lib/testfiles/ut/ -m equal -n 5 -s 0
\ No newline at end of file